@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 /// Volume as a normalized value in [0.0, 1.0].
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -19,24 +20,39 @@ impl Volume {
     pub fn get(self) -> f64 {
         self.0
     }
+
+    /// Round to the nearest multiple of `1 / steps`, so 0 and 1 stay exact.
+    /// `0` steps leaves the volume unchanged.
+    #[must_use]
+    pub fn quantized(self, steps: u32) -> Self {
+        if steps == 0 {
+            return self;
+        }
+        let steps = f64::from(steps);
+        Volume::new((self.0 * steps).round() / steps)
+    }
 }
 
-/// Parameterized power curve for mapping hardware values to volume.
+/// Curve mapping hardware values to volume.
 ///
-/// `volume = (hw_value / 255) ^ exponent`
+/// Usually a power curve, `volume = (hw_value / 255) ^ exponent`.
 ///
 /// Default exponent: 1.0 (linear). PulseAudio's volume scale already applies
 /// perceptual (cubic) weighting, so a linear mapping here means slider
 /// position corresponds directly to perceived volume percentage. An exponent
 /// >1.0 adds extra resolution at the quiet end on top of PA's curve.
-#[derive(Debug, Clone, Copy)]
-pub struct VolumeCurve {
-    exponent: f64,
+#[derive(Debug, Clone, PartialEq)]
+pub enum VolumeCurve {
+    /// `volume = (hw_value / 255) ^ exponent`. Build with [`VolumeCurve::new`].
+    Power { exponent: f64 },
+    /// Volume for each hardware value, indexed directly. Build with
+    /// [`VolumeCurve::from_table`].
+    Table(Box<[f64; 256]>),
 }
 
 impl Default for VolumeCurve {
     fn default() -> Self {
-        VolumeCurve { exponent: 1.0 }
+        VolumeCurve::Power { exponent: 1.0 }
     }
 }
 
@@ -56,28 +72,134 @@ impl VolumeCurve {
     /// - 3.0: strong quiet-end bias
     #[must_use]
     pub fn new(exponent: f64) -> Self {
-        VolumeCurve {
+        VolumeCurve::Power {
             exponent: exponent.max(Self::MIN_EXPONENT),
         }
     }
 
+    /// Create a lookup-table curve. With 256 entries, entry `n` is the volume
+    /// for hardware value `n`. Shorter tables are spread evenly across the
+    /// hardware range and interpolated linearly, so `[0.0, 1.0]` is linear.
+    /// Entries are clamped to [0, 1] and need not be monotonic.
+    ///
+    /// Returns `None` unless the table has 2 to 256 entries.
+    #[must_use]
+    pub fn from_table(entries: &[f64]) -> Option<Self> {
+        if !(2..=256).contains(&entries.len()) {
+            return None;
+        }
+        let last = (entries.len() - 1) as f64;
+        let table = std::array::from_fn(|hw| {
+            let pos = hw as f64 * last / 255.0;
+            let lower = pos.floor() as usize;
+            let upper = (lower + 1).min(entries.len() - 1);
+            let frac = pos - lower as f64;
+            let (a, b) = (
+                entries[lower].clamp(0.0, 1.0),
+                entries[upper].clamp(0.0, 1.0),
+            );
+            a + (b - a) * frac
+        });
+        Some(VolumeCurve::Table(Box::new(table)))
+    }
+
     /// Map a hardware value (0-255) to a normalized volume.
     #[must_use]
     pub fn hw_to_volume(&self, hw_value: u8) -> Volume {
-        let normalized = f64::from(hw_value) / 255.0;
-        Volume::new(normalized.powf(self.exponent))
+        match self {
+            VolumeCurve::Power { exponent } => {
+                let normalized = f64::from(hw_value) / 255.0;
+                Volume::new(normalized.powf(*exponent))
+            }
+            VolumeCurve::Table(table) => Volume::new(table[usize::from(hw_value)]),
+        }
     }
 
     /// Map a normalized volume back to the nearest hardware value (0-255).
+    /// For a table, the first hardware value whose volume is closest.
     #[must_use]
     pub fn volume_to_hw(&self, volume: Volume) -> u8 {
-        let normalized = volume.get().powf(1.0 / self.exponent);
-        (normalized * 255.0).round().clamp(0.0, 255.0) as u8
+        match self {
+            VolumeCurve::Power { exponent } => {
+                let normalized = volume.get().powf(1.0 / exponent);
+                (normalized * 255.0).round().clamp(0.0, 255.0) as u8
+            }
+            VolumeCurve::Table(table) => {
+                let distance = |hw: &u8| (table[usize::from(*hw)] - volume.get()).abs();
+                (0..=255u8)
+                    .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+                    .unwrap_or(0)
+            }
+        }
     }
 
+    /// The power curve exponent, `None` for a table.
     #[must_use]
-    pub fn exponent(&self) -> f64 {
-        self.exponent
+    pub fn exponent(&self) -> Option<f64> {
+        match self {
+            VolumeCurve::Power { exponent } => Some(*exponent),
+            VolumeCurve::Table(_) => None,
+        }
+    }
+}
+
+/// Why a list of curve points can't be used as a [`PiecewiseCurve`].
+#[derive(Error, Debug, Clone, Copy, PartialEq)]
+pub enum CurvePointsError {
+    #[error("needs at least 2 points")]
+    TooFewPoints,
+    #[error("point {index} is outside [0, 1]")]
+    OutOfRange { index: usize },
+    #[error("point {index} does not increase in x")]
+    NotIncreasing { index: usize },
+}
+
+/// Piecewise-linear mapping from control travel to volume.
+///
+/// Each point is `(travel, volume)`, both normalized to [0, 1]. Travel
+/// between two points interpolates linearly; travel before the first point
+/// or after the last holds that point's volume. E.g. `[(0, 0), (0.2, 0.5),
+/// (1, 1)]` spends the first 20% of travel on the bottom half of the volume
+/// range.
+#[derive(Debug, Clone, Copy)]
+pub struct PiecewiseCurve<'a> {
+    points: &'a [(f64, f64)],
+}
+
+impl<'a> PiecewiseCurve<'a> {
+    /// Use `points` as a curve. They must be within [0, 1] and strictly
+    /// increasing in travel.
+    pub fn new(points: &'a [(f64, f64)]) -> Result<Self, CurvePointsError> {
+        if points.len() < 2 {
+            return Err(CurvePointsError::TooFewPoints);
+        }
+        let in_range = |v: f64| (0.0..=1.0).contains(&v);
+        for (index, &(x, y)) in points.iter().enumerate() {
+            if !in_range(x) || !in_range(y) {
+                return Err(CurvePointsError::OutOfRange { index });
+            }
+            if index > 0 && x <= points[index - 1].0 {
+                return Err(CurvePointsError::NotIncreasing { index });
+            }
+        }
+        Ok(PiecewiseCurve { points })
+    }
+
+    /// Map a hardware value (0-255) to a normalized volume.
+    #[must_use]
+    pub fn hw_to_volume(&self, hw_value: u8) -> Volume {
+        let travel = f64::from(hw_value) / 255.0;
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if travel <= first.0 {
+            return Volume::new(first.1);
+        }
+        if travel >= last.0 {
+            return Volume::new(last.1);
+        }
+        let upper = self.points.partition_point(|&(x, _)| x < travel);
+        let (x0, y0) = self.points[upper - 1];
+        let (x1, y1) = self.points[upper];
+        Volume::new(y0 + (travel - x0) / (x1 - x0) * (y1 - y0))
     }
 }
 
@@ -90,6 +212,28 @@ pub struct SinkInfo {
     pub volume: Volume,
     pub muted: bool,
     pub channels: u8,
+    /// Index of the sink's monitor source, used for peak metering.
+    #[serde(default)]
+    pub monitor_source: Option<u32>,
+    /// Output ports of the sink (e.g. speakers and headphones).
+    #[serde(default)]
+    pub ports: Vec<PortInfo>,
+    /// Name of the active port, if the sink has ports.
+    #[serde(default)]
+    pub active_port: Option<String>,
+    /// Whether the sink is suspended.
+    #[serde(default)]
+    pub suspended: bool,
+}
+
+/// A port of a PulseAudio sink.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortInfo {
+    pub name: String,
+    pub description: String,
+    /// `false` when PulseAudio knows nothing is plugged in (e.g. an empty
+    /// headphone jack).
+    pub available: bool,
 }
 
 /// Information about a PulseAudio source (input device).
@@ -103,6 +247,15 @@ pub struct SourceInfo {
     pub channels: u8,
 }
 
+impl SourceInfo {
+    /// Whether this is the monitor of an output rather than a real input.
+    /// PulseAudio names those `<sink name>.monitor`.
+    #[must_use]
+    pub fn is_monitor(&self) -> bool {
+        self.name.ends_with(".monitor")
+    }
+}
+
 /// Information about a PulseAudio sink-input (application audio stream).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SinkInputInfo {
@@ -112,12 +265,33 @@ pub struct SinkInputInfo {
     pub flatpak_id: Option<String>,
     #[serde(default)]
     pub pid: Option<u32>,
+    /// systemd scope unit the process runs in (e.g. `app-flatpak-org.mozilla.firefox-1234.scope`).
+    #[serde(default)]
+    pub cgroup: Option<String>,
+    /// The stream's `media.name`, e.g. `Loopback from Built-in Audio` for a
+    /// `module-loopback` stream.
+    #[serde(default)]
+    pub media_name: Option<String>,
     pub sink_index: u32,
     pub volume: Volume,
     pub muted: bool,
     pub channels: u8,
 }
 
+/// Extract the systemd `app-*.scope` unit from the contents of `/proc/<pid>/cgroup`.
+///
+/// Desktop launchers and Flatpak place each app in its own scope, so the unit
+/// name identifies the app even when the binary is a generic wrapper like `bwrap`.
+#[must_use]
+pub fn cgroup_scope(contents: &str) -> Option<&str> {
+    contents
+        .lines()
+        // cgroup v1 lines are `id:controllers:path`, v2 is `0::path`.
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .flat_map(|path| path.rsplit('/'))
+        .find(|unit| unit.starts_with("app-") && unit.ends_with(".scope"))
+}
+
 /// Full audio state snapshot from the PulseAudio thread.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AudioState {
@@ -151,6 +325,20 @@ pub struct DeviceInfo {
 mod tests {
     use super::*;
 
+    #[test]
+    fn monitor_sources_are_recognized_by_name() {
+        let source = |name: &str| SourceInfo {
+            index: 0,
+            name: name.into(),
+            description: String::new(),
+            volume: Volume::new(1.0),
+            muted: false,
+            channels: 2,
+        };
+        assert!(source("alsa_output.pci-0000_00_1f.3.analog-stereo.monitor").is_monitor());
+        assert!(!source("alsa_input.pci-0000_00_1f.3.analog-stereo").is_monitor());
+    }
+
     #[test]
     fn volume_clamped_to_valid_range() {
         assert_eq!(Volume::new(-0.5).get(), 0.0);
@@ -160,6 +348,18 @@ mod tests {
         assert_eq!(Volume::new(1.5).get(), 1.0);
     }
 
+    #[test]
+    fn quantized_snaps_to_the_grid_but_keeps_endpoints() {
+        assert_eq!(Volume::new(0.0).quantized(20).get(), 0.0);
+        assert_eq!(Volume::new(1.0).quantized(20).get(), 1.0);
+        assert_eq!(Volume::new(0.51).quantized(20).get(), 0.5);
+        assert_eq!(Volume::new(0.53).quantized(20).get(), 0.55);
+        assert_eq!(Volume::new(0.02).quantized(20).get(), 0.0);
+        assert_eq!(Volume::new(0.98).quantized(20).get(), 1.0);
+        assert_eq!(Volume::new(0.3).quantized(1).get(), 0.0);
+        assert_eq!(Volume::new(0.37).quantized(0).get(), 0.37);
+    }
+
     #[test]
     fn default_curve_endpoints() {
         let curve = VolumeCurve::default();
@@ -167,6 +367,45 @@ mod tests {
         assert!((curve.hw_to_volume(255).get() - 1.0).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn piecewise_curve_interpolates_between_points() {
+        let points = [(0.0, 0.0), (0.2, 0.5), (1.0, 1.0)];
+        let curve = PiecewiseCurve::new(&points).unwrap();
+        assert_eq!(curve.hw_to_volume(0).get(), 0.0);
+        assert!((curve.hw_to_volume(51).get() - 0.5).abs() < 1e-9);
+        // Halfway through the first segment.
+        assert!((curve.hw_to_volume(25).get() - 25.0 / 51.0 * 0.5).abs() < 1e-9);
+        // Halfway through the second segment (travel 0.6).
+        assert!((curve.hw_to_volume(153).get() - 0.75).abs() < 1e-9);
+        assert!((curve.hw_to_volume(255).get() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn piecewise_curve_holds_endpoints_outside_points() {
+        let points = [(0.2, 0.3), (0.8, 0.6)];
+        let curve = PiecewiseCurve::new(&points).unwrap();
+        assert_eq!(curve.hw_to_volume(0).get(), 0.3);
+        assert_eq!(curve.hw_to_volume(51).get(), 0.3);
+        assert_eq!(curve.hw_to_volume(204).get(), 0.6);
+        assert_eq!(curve.hw_to_volume(255).get(), 0.6);
+    }
+
+    #[test]
+    fn piecewise_curve_rejects_bad_points() {
+        assert_eq!(
+            PiecewiseCurve::new(&[(0.0, 0.0)]).unwrap_err(),
+            CurvePointsError::TooFewPoints
+        );
+        assert_eq!(
+            PiecewiseCurve::new(&[(0.0, 0.0), (1.0, 1.5)]).unwrap_err(),
+            CurvePointsError::OutOfRange { index: 1 }
+        );
+        assert_eq!(
+            PiecewiseCurve::new(&[(0.0, 0.0), (0.5, 0.5), (0.5, 1.0)]).unwrap_err(),
+            CurvePointsError::NotIncreasing { index: 2 }
+        );
+    }
+
     #[test]
     fn default_curve_midpoint_is_half() {
         let curve = VolumeCurve::default();
@@ -230,13 +469,56 @@ mod tests {
     #[test]
     fn volume_curve_clamps_zero_exponent() {
         let curve = VolumeCurve::new(0.0);
-        assert_eq!(curve.exponent(), VolumeCurve::MIN_EXPONENT);
+        assert_eq!(curve.exponent(), Some(VolumeCurve::MIN_EXPONENT));
     }
 
     #[test]
     fn volume_curve_clamps_negative_exponent() {
         let curve = VolumeCurve::new(-1.0);
-        assert_eq!(curve.exponent(), VolumeCurve::MIN_EXPONENT);
+        assert_eq!(curve.exponent(), Some(VolumeCurve::MIN_EXPONENT));
+    }
+
+    #[test]
+    fn table_curve_indexes_full_table() {
+        let entries: Vec<f64> = (0..256).map(|i| f64::from(255 - i) / 255.0).collect();
+        let curve = VolumeCurve::from_table(&entries).unwrap();
+        assert_eq!(curve.hw_to_volume(0).get(), 1.0);
+        assert_eq!(curve.hw_to_volume(255).get(), 0.0);
+        assert_eq!(curve.hw_to_volume(55).get(), 200.0 / 255.0);
+        assert_eq!(curve.volume_to_hw(Volume::new(200.0 / 255.0)), 55);
+    }
+
+    #[test]
+    fn table_curve_interpolates_sparse_table() {
+        let curve = VolumeCurve::from_table(&[0.0, 1.0]).unwrap();
+        for hw in 0..=255u8 {
+            let linear = f64::from(hw) / 255.0;
+            assert!((curve.hw_to_volume(hw).get() - linear).abs() < 1e-9);
+        }
+        // Three entries: hw 0, 127.5, 255.
+        let curve = VolumeCurve::from_table(&[0.0, 0.8, 1.0]).unwrap();
+        assert!((curve.hw_to_volume(51).get() - 0.32).abs() < 1e-9);
+    }
+
+    #[test]
+    fn table_curve_clamps_range_without_requiring_monotonicity() {
+        let curve = VolumeCurve::from_table(&[-0.5, 1.0, 0.2, 2.0]).unwrap();
+        assert_eq!(curve.hw_to_volume(0).get(), 0.0);
+        assert_eq!(curve.hw_to_volume(85).get(), 1.0);
+        assert!((curve.hw_to_volume(170).get() - 0.2).abs() < 1e-9);
+        assert_eq!(curve.hw_to_volume(255).get(), 1.0);
+        for hw in 0..=255u8 {
+            let vol = curve.hw_to_volume(hw).get();
+            assert!((0.0..=1.0).contains(&vol), "hw={hw}: vol={vol}");
+        }
+    }
+
+    #[test]
+    fn table_curve_rejects_bad_lengths() {
+        assert_eq!(VolumeCurve::from_table(&[]), None);
+        assert_eq!(VolumeCurve::from_table(&[0.5]), None);
+        assert_eq!(VolumeCurve::from_table(&[0.5; 257]), None);
+        assert!(VolumeCurve::from_table(&[0.5; 256]).is_some());
     }
 
     #[test]
@@ -268,6 +550,8 @@ mod tests {
             binary: Some("test".into()),
             flatpak_id: None,
             pid: Some(12345),
+            cgroup: None,
+            media_name: None,
             sink_index: 0,
             volume: Volume::new(0.5),
             muted: false,
@@ -292,5 +576,38 @@ mod tests {
         }"#;
         let parsed: SinkInputInfo = serde_json::from_str(json).unwrap();
         assert_eq!(parsed.pid, None);
+        assert_eq!(parsed.cgroup, None);
+    }
+
+    #[test]
+    fn cgroup_scope_from_v2_flatpak() {
+        let contents = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/\
+                        app-flatpak-org.mozilla.firefox-41234.scope\n";
+        assert_eq!(
+            cgroup_scope(contents),
+            Some("app-flatpak-org.mozilla.firefox-41234.scope")
+        );
+    }
+
+    #[test]
+    fn cgroup_scope_from_v1_hierarchy() {
+        let contents = "12:cpuset:/\n\
+                        1:name=systemd:/user.slice/user-1000.slice/user@1000.service/\
+                        app.slice/app-gnome-spotify-5678.scope\n\
+                        0::/user.slice/user-1000.slice/user@1000.service/app.slice\n";
+        assert_eq!(cgroup_scope(contents), Some("app-gnome-spotify-5678.scope"));
+    }
+
+    #[test]
+    fn cgroup_scope_ignores_non_app_units() {
+        assert_eq!(
+            cgroup_scope("0::/user.slice/user-1000.slice/session-2.scope\n"),
+            None
+        );
+        assert_eq!(
+            cgroup_scope("0::/user.slice/user-1000.slice/user@1000.service/app.slice/app.slice\n"),
+            None
+        );
+        assert_eq!(cgroup_scope(""), None);
     }
 }
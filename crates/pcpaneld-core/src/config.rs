@@ -1,11 +1,16 @@
 use std::collections::HashMap;
 use std::io;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::control::{ControlConfig, ControlId};
+use crate::audio::{PiecewiseCurve, SinkInputInfo, Volume, VolumeCurve};
+use crate::control::{
+    AudioTarget, ButtonAction, ControlConfig, ControlId, ControlPart, DialAction,
+};
+use crate::ipc::{self, IpcEndpoint, IpcRequest};
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -18,6 +23,8 @@ pub enum ConfigError {
         path: PathBuf,
         source: toml_edit::de::Error,
     },
+    #[error("invalid config: {source}")]
+    Invalid { source: toml_edit::de::Error },
     #[error("failed to serialize config: {source}")]
     Serialize { source: toml_edit::ser::Error },
     #[error("failed to create config directory {path}: {source}")]
@@ -25,11 +32,62 @@ pub enum ConfigError {
 }
 
 /// Device-specific configuration.
-#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DeviceConfig {
     /// Optional serial number to lock to a specific device.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub serial: Option<String>,
+    /// When `serial` is unset and exactly one device is connected, write that
+    /// device's serial into the config on first connect.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_lock: bool,
+    /// How long to discard position reports after the device is
+    /// initialized, in milliseconds.
+    #[serde(default = "default_drain_ms")]
+    pub drain_ms: u64,
+    /// Longest wait for each report while draining, in milliseconds. A
+    /// device that stays quiet this long ends the drain early.
+    #[serde(default = "default_drain_read_timeout_ms")]
+    pub drain_read_timeout_ms: u64,
+    /// Longest wait for each report once the device is running, in
+    /// milliseconds. Queued LED writes also wait for it to expire.
+    #[serde(default = "default_read_timeout_ms")]
+    pub read_timeout_ms: u64,
+}
+
+/// Shortest `read_timeout_ms` used; anything lower busy-loops the HID thread.
+pub const MIN_READ_TIMEOUT_MS: u64 = 10;
+
+fn default_drain_ms() -> u64 {
+    500
+}
+
+fn default_drain_read_timeout_ms() -> u64 {
+    50
+}
+
+fn default_read_timeout_ms() -> u64 {
+    100
+}
+
+impl DeviceConfig {
+    /// `read_timeout_ms`, at least [`MIN_READ_TIMEOUT_MS`], as `hidapi` takes it.
+    #[must_use]
+    pub fn read_timeout_ms(&self) -> i32 {
+        i32::try_from(self.read_timeout_ms.max(MIN_READ_TIMEOUT_MS)).unwrap_or(i32::MAX)
+    }
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        DeviceConfig {
+            serial: None,
+            auto_lock: false,
+            drain_ms: default_drain_ms(),
+            drain_read_timeout_ms: default_drain_read_timeout_ms(),
+            read_timeout_ms: default_read_timeout_ms(),
+        }
+    }
 }
 
 /// Signal processing parameters.
@@ -49,6 +107,22 @@ pub struct SignalConfig {
     pub knob_debounce_ms: u64,
     #[serde(default = "default_volume_exponent")]
     pub volume_exponent: f64,
+    /// Volume for each hardware value, used instead of `volume_exponent`.
+    /// See [`VolumeCurve::from_table`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub curve_table: Vec<f64>,
+    /// Power curve exponent for dials driving an input (microphone). When
+    /// unset, inputs use the same curve as outputs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mic_volume_exponent: Option<f64>,
+    /// Longest gap between two hits of the same endpoint that still counts
+    /// as a slam gesture (see `slam_action`).
+    #[serde(default = "default_slam_window_ms")]
+    pub slam_window_ms: u64,
+    /// Snap dial volumes to this many equal steps, e.g. 20 for 5%
+    /// increments. Full resolution when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume_steps: Option<u32>,
 }
 
 fn default_slider_rolling_average() -> usize {
@@ -72,16 +146,255 @@ fn default_knob_debounce_ms() -> u64 {
 fn default_volume_exponent() -> f64 {
     1.0
 }
+fn default_slam_window_ms() -> u64 {
+    400
+}
 
 fn default_true() -> bool {
     true
 }
 
-/// LED zone enable/disable configuration.
+/// An RGB color. The config accepts `[r, g, b]`, `"#rrggbb"`, one of the names
+/// in [`COLOR_NAMES`], or an HSV table `{ h = 210, s = 1.0, v = 0.8 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+/// Color names accepted in the config.
+pub const COLOR_NAMES: &[(&str, Color)] = &[
+    ("black", Color(0, 0, 0)),
+    ("white", Color(255, 255, 255)),
+    ("red", Color(255, 0, 0)),
+    ("green", Color(0, 255, 0)),
+    ("blue", Color(0, 0, 255)),
+    ("yellow", Color(255, 255, 0)),
+    ("cyan", Color(0, 255, 255)),
+    ("magenta", Color(255, 0, 255)),
+    ("orange", Color(255, 140, 0)),
+    ("purple", Color(160, 0, 255)),
+    ("pink", Color(255, 105, 180)),
+    ("gray", Color(128, 128, 128)),
+];
+
+impl Color {
+    /// Convert hue (degrees, wrapped into [0, 360)), saturation, and value
+    /// (both clamped to [0.0, 1.0]) to RGB.
+    #[must_use]
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Color {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+        let chroma = v * s;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = v - chroma;
+        let channel = |c: f64| ((c + m) * 255.0).round() as u8;
+        Color(channel(r), channel(g), channel(b))
+    }
+}
+
+/// HSV form of a [`Color`] in the config.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Hsv {
+    h: f64,
+    s: f64,
+    v: f64,
+}
+
+impl std::str::FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("invalid hex color \"{s}\", expected \"#rrggbb\""));
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+            return Ok(Color(channel(0), channel(2), channel(4)));
+        }
+        let name = s.to_ascii_lowercase();
+        COLOR_NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|&(_, color)| color)
+            .ok_or_else(|| {
+                let names: Vec<&str> = COLOR_NAMES.iter().map(|(n, _)| *n).collect();
+                format!(
+                    "unknown color \"{s}\", expected \"#rrggbb\", [r, g, b], or one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ColorVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a color as \"#rrggbb\", a color name, [r, g, b], or { h, s, v }")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Color, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Color, A::Error> {
+                use serde::de::Error;
+
+                let mut rgb = [0u8; 3];
+                for (i, channel) in rgb.iter_mut().enumerate() {
+                    *channel = seq
+                        .next_element()?
+                        .ok_or_else(|| A::Error::invalid_length(i, &"[r, g, b]"))?;
+                }
+                if seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+                    return Err(A::Error::invalid_length(4, &"[r, g, b]"));
+                }
+                Ok(Color(rgb[0], rgb[1], rgb[2]))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Color, A::Error> {
+                use serde::de::Error;
+
+                let Hsv { h, s, v } =
+                    Hsv::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                if !(0.0..=360.0).contains(&h) {
+                    return Err(A::Error::custom(format!(
+                        "hue {h} out of range, expected 0 to 360"
+                    )));
+                }
+                if !(0.0..=1.0).contains(&s) || !(0.0..=1.0).contains(&v) {
+                    return Err(A::Error::custom(format!(
+                        "saturation {s} and value {v} must be between 0.0 and 1.0"
+                    )));
+                }
+                Ok(Color::from_hsv(h, s, v))
+            }
+        }
+
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
+
+/// Colors used by `color_by_action`, one per kind of action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedPalette {
+    /// Volume or mute of the default output/input device.
+    #[serde(default = "default_palette_device")]
+    pub device: Color,
+    /// Volume or mute of a specific app.
+    #[serde(default = "default_palette_app")]
+    pub app: Color,
+    /// Volume or mute of the focused app.
+    #[serde(default = "default_palette_focused")]
+    pub focused: Color,
+    /// MPRIS media button.
+    #[serde(default = "default_palette_media")]
+    pub media: Color,
+    /// Shell command button.
+    #[serde(default = "default_palette_exec")]
+    pub exec: Color,
+    /// Control with no action.
+    #[serde(default = "default_palette_unassigned")]
+    pub unassigned: Color,
+}
+
+fn default_palette_device() -> Color {
+    Color(0, 100, 255)
+}
+fn default_palette_app() -> Color {
+    Color(255, 140, 0)
+}
+fn default_palette_focused() -> Color {
+    Color(0, 255, 200)
+}
+fn default_palette_media() -> Color {
+    Color(0, 255, 0)
+}
+fn default_palette_exec() -> Color {
+    Color(160, 0, 255)
+}
+fn default_palette_unassigned() -> Color {
+    Color(40, 40, 40)
+}
+
+impl Default for LedPalette {
+    fn default() -> Self {
+        LedPalette {
+            device: default_palette_device(),
+            app: default_palette_app(),
+            focused: default_palette_focused(),
+            media: default_palette_media(),
+            exec: default_palette_exec(),
+            unassigned: default_palette_unassigned(),
+        }
+    }
+}
+
+impl LedPalette {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn target_color(&self, target: &AudioTarget) -> Color {
+        match target {
+            AudioTarget::DefaultOutput | AudioTarget::DefaultInput => self.device,
+            AudioTarget::App { .. } => self.app,
+            AudioTarget::FocusedApp => self.focused,
+        }
+    }
+
+    /// Pick the color for a control from what it is mapped to.
+    ///
+    /// Media and exec buttons win over the dial because they are the more
+    /// distinctive action; otherwise the dial's (first) target decides, then a
+    /// mute button's target.
+    #[must_use]
+    pub fn color_for(&self, control: Option<&ControlConfig>) -> Color {
+        let Some(control) = control else {
+            return self.unassigned;
+        };
+        match (&control.dial, &control.button) {
+            (_, Some(ButtonAction::Media { .. })) => self.media,
+            (_, Some(ButtonAction::Exec { .. })) => self.exec,
+            (Some(dial), _) => dial
+                .targets()
+                .first()
+                .map_or(self.unassigned, |target| self.target_color(target)),
+            (None, Some(ButtonAction::Mute { target })) => self.target_color(target),
+            (None, Some(ButtonAction::MuteDefaultInput)) => {
+                self.target_color(&AudioTarget::DefaultInput)
+            }
+            (None, Some(ButtonAction::VolumeStep { target, .. })) => self.target_color(target),
+            (None, Some(ButtonAction::SetPort { .. })) => self.device,
+            (None, Some(ButtonAction::ToggleOutputs { .. })) => self.device,
+            (None, Some(ButtonAction::ToggleSinkSuspend { .. })) => self.device,
+            (None, Some(ButtonAction::MoveAppToSink { .. })) => self.app,
+            (None, Some(ButtonAction::ToggleLeds)) => self.unassigned,
+            (None, None) => self.unassigned,
+        }
+    }
+}
+
+/// LED zone configuration.
 ///
 /// Controls which LED zones are active on the device. Disabled zones are sent
 /// all-off commands. Default is all enabled (backward compatible).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct LedConfig {
     #[serde(default = "default_true")]
     pub knobs: bool,
@@ -91,6 +404,59 @@ pub struct LedConfig {
     pub slider_labels: bool,
     #[serde(default = "default_true")]
     pub logo: bool,
+    /// Dim the LEDs after this many milliseconds without knob, slider, or
+    /// button input. 0 disables idle dimming.
+    #[serde(default)]
+    pub idle_timeout_ms: u64,
+    /// LED brightness while idle, in percent of normal (0 = off).
+    #[serde(default)]
+    pub idle_brightness: u8,
+    /// Color each knob ring and slider by the kind of action it is mapped to,
+    /// using `palette`, instead of the uniform default colors.
+    #[serde(default)]
+    pub color_by_action: bool,
+    #[serde(default, skip_serializing_if = "LedPalette::is_default")]
+    pub palette: LedPalette,
+    /// Gamma applied to every LED color before it is sent, so brightness
+    /// ramps look even. 1.0 sends colors unchanged; 2.2 suits most LEDs.
+    #[serde(default = "default_gamma")]
+    pub gamma: f64,
+    /// Make a `toggle_leds` button flip `off` in the config file instead of
+    /// switching the LEDs off for the current session only.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub persist_toggle: bool,
+    /// Switch every zone off while leaving the zone flags above as they are.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub off: bool,
+    /// Switch the LEDs off while the screen is locked.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub off_on_lock: bool,
+    /// Sweep a light across the knob rings when a device connects, before
+    /// showing the configured LEDs.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub startup_animation: bool,
+    /// Switch every zone off before sending the LEDs of a reloaded config,
+    /// so no zone keeps its old color on firmware that is slow to follow.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clear_on_reload: bool,
+}
+
+fn default_gamma() -> f64 {
+    1.0
+}
+
+impl LedConfig {
+    /// Whether any of the knob, slider, slider label, or logo zones is on.
+    #[must_use]
+    pub fn any_zone_enabled(&self) -> bool {
+        self.knobs || self.sliders || self.slider_labels || self.logo
+    }
+
+    /// Idle brightness as a factor in [0.0, 1.0].
+    #[must_use]
+    pub fn idle_brightness_factor(&self) -> f64 {
+        f64::from(self.idle_brightness.min(100)) / 100.0
+    }
 }
 
 impl Default for LedConfig {
@@ -100,6 +466,16 @@ impl Default for LedConfig {
             sliders: true,
             slider_labels: true,
             logo: true,
+            idle_timeout_ms: 0,
+            idle_brightness: 0,
+            color_by_action: false,
+            palette: LedPalette::default(),
+            gamma: default_gamma(),
+            persist_toggle: false,
+            off: false,
+            off_on_lock: false,
+            startup_animation: false,
+            clear_on_reload: false,
         }
     }
 }
@@ -114,13 +490,199 @@ impl Default for SignalConfig {
             knob_delta_threshold: default_knob_delta_threshold(),
             knob_debounce_ms: default_knob_debounce_ms(),
             volume_exponent: default_volume_exponent(),
+            curve_table: Vec::new(),
+            mic_volume_exponent: None,
+            slam_window_ms: default_slam_window_ms(),
+            volume_steps: None,
+        }
+    }
+}
+
+impl SignalConfig {
+    /// The volume curve for controls without `curve_points`: `curve_table`
+    /// if set and usable, otherwise the `volume_exponent` power curve.
+    #[must_use]
+    pub fn volume_curve(&self) -> VolumeCurve {
+        if self.curve_table.is_empty() {
+            return VolumeCurve::new(self.volume_exponent);
+        }
+        VolumeCurve::from_table(&self.curve_table)
+            .unwrap_or_else(|| VolumeCurve::new(self.volume_exponent))
+    }
+
+    /// The volume curve for dials whose target resolves to an input:
+    /// `mic_volume_exponent` if set, otherwise [`Self::volume_curve`].
+    #[must_use]
+    pub fn mic_volume_curve(&self) -> VolumeCurve {
+        match self.mic_volume_exponent {
+            Some(exponent) => VolumeCurve::new(exponent),
+            None => self.volume_curve(),
+        }
+    }
+
+    /// `volume` snapped to `volume_steps`, if set.
+    #[must_use]
+    pub fn quantize(&self, volume: Volume) -> Volume {
+        volume.quantized(self.volume_steps.unwrap_or(0))
+    }
+}
+
+/// Limits on `exec` button commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecConfig {
+    /// Commands allowed to run at once; presses beyond this are dropped.
+    #[serde(default = "default_exec_max_concurrent")]
+    pub max_concurrent: usize,
+    /// Seconds before a running command is killed.
+    #[serde(default = "default_exec_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Capture each command's stdout and stderr and log them at debug level,
+    /// instead of discarding stdout and passing stderr through.
+    #[serde(default)]
+    pub capture_output: bool,
+}
+
+fn default_exec_max_concurrent() -> usize {
+    8
+}
+fn default_exec_timeout_secs() -> u64 {
+    30
+}
+
+impl ExecConfig {
+    /// `max_concurrent`, at least 1.
+    #[must_use]
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent.max(1)
+    }
+
+    /// `timeout_secs` as a duration, at least one second.
+    #[must_use]
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout_secs.max(1))
+    }
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        ExecConfig {
+            max_concurrent: default_exec_max_concurrent(),
+            timeout_secs: default_exec_timeout_secs(),
+            capture_output: false,
+        }
+    }
+}
+
+/// Daemon process settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    /// Write the daemon's PID to this file while it runs. Off when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pidfile: Option<PathBuf>,
+    /// Copy the config file to `<file>.bak.<unix time>` before each save,
+    /// keeping this many of the newest copies. `0` keeps none.
+    #[serde(default)]
+    pub config_backups: usize,
+    /// Make the tray's Quit item quit at once instead of opening a
+    /// confirmation submenu.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tray_direct_quit: bool,
+    /// Show a system tray icon. Off for sessions without a StatusNotifier
+    /// host, where registering it only fails.
+    #[serde(default = "default_true")]
+    pub tray: bool,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        DaemonConfig {
+            pidfile: None,
+            config_backups: 0,
+            tray_direct_quit: false,
+            tray: true,
+        }
+    }
+}
+
+/// Daemon logging. Console logging is always on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Also write logs to this file, rotated daily. Each day's log is
+    /// `<file>.<YYYY-MM-DD>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+/// Prometheus metrics endpoint. Only served when the daemon is built with
+/// the `metrics` cargo feature.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// Address to serve `/metrics` on, e.g. `127.0.0.1:9185`. Off when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub listen: Option<SocketAddr>,
+}
+
+/// IPC socket settings. Read by the daemon at startup and by every CLI command.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpcConfig {
+    /// Listen on the abstract-namespace socket `@pcpaneld` instead of a file
+    /// in `$XDG_RUNTIME_DIR`, for sandboxes where that directory isn't
+    /// writable.
+    #[serde(
+        rename = "abstract",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub abstract_socket: bool,
+}
+
+impl IpcConfig {
+    /// The endpoint the daemon listens on and the CLI connects to. An
+    /// explicit socket path (`--socket`, else `PCPANELD_SOCKET`) wins over
+    /// `abstract`.
+    #[must_use]
+    pub fn endpoint(&self, socket: Option<PathBuf>) -> IpcEndpoint {
+        match socket.or_else(ipc::env_socket_path) {
+            Some(path) => IpcEndpoint::Path(path),
+            None if self.abstract_socket => {
+                IpcEndpoint::Abstract(ipc::ABSTRACT_SOCKET_NAME.to_string())
+            }
+            None => IpcEndpoint::Path(ipc::default_socket_path()),
         }
     }
 }
 
+/// App matching rules shared by every app and focused-app target.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchingConfig {
+    /// Sink-inputs whose binary or name equals one of these (case-insensitive)
+    /// are never matched, e.g. `["pipewire", "speech-dispatcher"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// A `focused_app` target controls the default output instead when the
+    /// focused app's streams are the only ones playing on it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub focused_alone_controls_output: bool,
+}
+
+impl MatchingConfig {
+    /// Whether `si` is excluded from app and focused-app matching.
+    #[must_use]
+    pub fn is_ignored(&self, si: &SinkInputInfo) -> bool {
+        self.ignore.iter().any(|entry| {
+            si.name.eq_ignore_ascii_case(entry)
+                || si
+                    .binary
+                    .as_deref()
+                    .is_some_and(|bin| bin.eq_ignore_ascii_case(entry))
+        })
+    }
+}
+
 /// Top-level configuration.
 ///
-/// Forward-compatible: unknown fields are silently ignored (no `deny_unknown_fields`).
+/// Forward-compatible: unknown fields are ignored (no `deny_unknown_fields`).
+/// [`unknown_keys`] reports them as warnings.
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -130,6 +692,18 @@ pub struct Config {
     #[serde(default)]
     pub leds: LedConfig,
     #[serde(default)]
+    pub exec: ExecConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub matching: MatchingConfig,
+    #[serde(default)]
+    pub ipc: IpcConfig,
+    #[serde(default)]
     pub controls: HashMap<String, ControlConfig>,
 }
 
@@ -150,6 +724,193 @@ impl Config {
         self.controls.remove(&id.config_key())
     }
 
+    /// Apply an assign or unassign request without saving it. Returns
+    /// whether `request` was one.
+    pub fn apply_mapping_change(&mut self, request: &IpcRequest) -> bool {
+        match request {
+            IpcRequest::AssignDial { control, action } => {
+                self.controls.entry(control.config_key()).or_default().dial = Some(action.clone());
+            }
+            IpcRequest::AssignButton { control, action } => {
+                self.controls
+                    .entry(control.config_key())
+                    .or_default()
+                    .button = Some(action.clone());
+            }
+            IpcRequest::Unassign {
+                control,
+                part: None,
+            } => {
+                self.remove_control(*control);
+            }
+            IpcRequest::Unassign {
+                control,
+                part: Some(part),
+            } => {
+                let key = control.config_key();
+                if let Some(entry) = self.controls.get_mut(&key) {
+                    match part {
+                        ControlPart::Dial => entry.dial = None,
+                        ControlPart::Button => entry.button = None,
+                    }
+                    if entry.dial.is_none() && entry.button.is_none() {
+                        self.controls.remove(&key);
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Check the control mappings for mistakes that parse fine but can't do
+    /// what was meant. Returns one human-readable warning per problem, sorted
+    /// by control; the config is still usable as-is.
+    ///
+    /// Flags control keys that name no control, button actions on sliders,
+    /// app targets without any matcher field, and the same action mapped to
+    /// several controls. Several controls on a default device are fine.
+    #[must_use]
+    pub fn validate(&self) -> Vec<String> {
+        let mut keys: Vec<&String> = self.controls.keys().collect();
+        keys.sort();
+
+        let mut warnings = Vec::new();
+        let table_len = self.signal.curve_table.len();
+        if table_len == 1 || table_len > 256 {
+            warnings.push(format!(
+                "signal.curve_table: has {table_len} entries, needs 2 to 256, using volume_exponent"
+            ));
+        }
+        if self
+            .signal
+            .curve_table
+            .iter()
+            .any(|v| !(0.0..=1.0).contains(v))
+        {
+            warnings.push("signal.curve_table: entries outside [0, 1] are clamped".to_string());
+        }
+        if self.signal.volume_steps == Some(0) {
+            warnings.push("signal.volume_steps: must be at least 1, ignored".to_string());
+        }
+        if self.device.read_timeout_ms < MIN_READ_TIMEOUT_MS {
+            warnings.push(format!(
+                "device.read_timeout_ms: must be at least {MIN_READ_TIMEOUT_MS}, using {MIN_READ_TIMEOUT_MS}"
+            ));
+        }
+        if self.exec.max_concurrent == 0 {
+            warnings.push("exec.max_concurrent: must be at least 1, using 1".to_string());
+        }
+        if self.exec.timeout_secs == 0 {
+            warnings.push("exec.timeout_secs: must be at least 1, using 1".to_string());
+        }
+        let mut dials: Vec<(&str, &DialAction)> = Vec::new();
+        let mut buttons: Vec<(&str, &ButtonAction)> = Vec::new();
+        for key in keys {
+            let control = &self.controls[key];
+            let Some(id) = ControlId::from_config_key(key) else {
+                warnings.push(format!(
+                    "controls.{key}: unknown control, ignored (expected knob1-knob5 or slider1-slider4)"
+                ));
+                continue;
+            };
+            if let Some(dial) = &control.dial {
+                if dial.targets().iter().any(is_empty_app_target) {
+                    warnings.push(format!(
+                        "controls.{key}.dial: app target has no binary, name, flatpak_id, cgroup, or media_name and never matches"
+                    ));
+                }
+                if let Some((other, _)) = dials.iter().find(|(_, d)| *d == dial) {
+                    if !dial.targets().iter().all(is_default_device) {
+                        warnings.push(format!(
+                            "controls.{key}.dial: same action as controls.{other}.dial"
+                        ));
+                    }
+                }
+                dials.push((key, dial));
+            }
+            if !control.curve_points.is_empty() {
+                if let Err(e) = PiecewiseCurve::new(&control.curve_points) {
+                    warnings.push(format!(
+                        "controls.{key}.curve_points: {e}, using the [signal] curve instead"
+                    ));
+                }
+            }
+            if let Some(button) = &control.button {
+                if id.is_slider() {
+                    warnings.push(format!(
+                        "controls.{key}.button: sliders have no button, ignored"
+                    ));
+                    continue;
+                }
+                let empty_matcher = match button {
+                    ButtonAction::Mute { target } | ButtonAction::VolumeStep { target, .. } => {
+                        is_empty_app_target(target)
+                    }
+                    ButtonAction::MoveAppToSink { matcher, .. } => !matcher.is_valid(),
+                    _ => false,
+                };
+                if empty_matcher {
+                    warnings.push(format!(
+                        "controls.{key}.button: app matcher has no binary, name, flatpak_id, cgroup, or media_name and never matches"
+                    ));
+                }
+                if let ButtonAction::VolumeStep { delta, .. } = button {
+                    if !delta.is_finite() || *delta == 0.0 {
+                        warnings.push(format!(
+                            "controls.{key}.button: volume_step delta {delta} changes nothing"
+                        ));
+                    }
+                }
+                if let Some((other, _)) = buttons.iter().find(|(_, b)| *b == button) {
+                    let on_default_device = matches!(
+                        button,
+                        ButtonAction::Mute { target } if is_default_device(target)
+                    ) || *button == ButtonAction::MuteDefaultInput;
+                    if !on_default_device {
+                        warnings.push(format!(
+                            "controls.{key}.button: same action as controls.{other}.button"
+                        ));
+                    }
+                }
+                buttons.push((key, button));
+            }
+        }
+        warnings
+    }
+
+    /// Names of the sections and controls that differ between `self` and
+    /// `other`, e.g. `["signal", "controls.knob1"]`, sorted within controls.
+    #[must_use]
+    pub fn differences(&self, other: &Config) -> Vec<String> {
+        let sections = [
+            ("device", self.device != other.device),
+            ("signal", self.signal != other.signal),
+            ("leds", self.leds != other.leds),
+            ("exec", self.exec != other.exec),
+            ("daemon", self.daemon != other.daemon),
+            ("logging", self.logging != other.logging),
+            ("metrics", self.metrics != other.metrics),
+            ("matching", self.matching != other.matching),
+            ("ipc", self.ipc != other.ipc),
+        ];
+        let mut changes: Vec<String> = sections
+            .into_iter()
+            .filter(|&(_, differs)| differs)
+            .map(|(name, _)| name.to_string())
+            .collect();
+
+        let mut keys: Vec<&String> = self.controls.keys().chain(other.controls.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        changes.extend(
+            keys.into_iter()
+                .filter(|key| self.controls.get(*key) != other.controls.get(*key))
+                .map(|key| format!("controls.{key}")),
+        );
+        changes
+    }
+
     /// Load config from a TOML file. Returns default config if file doesn't exist.
     pub fn load(path: &Path) -> Result<Self, ConfigError> {
         match std::fs::read_to_string(path) {
@@ -167,12 +928,21 @@ impl Config {
         }
     }
 
+    /// Parse config from a TOML string, e.g. one received over IPC.
+    pub fn from_toml(contents: &str) -> Result<Self, ConfigError> {
+        toml_edit::de::from_str(contents).map_err(|source| ConfigError::Invalid { source })
+    }
+
     /// Serialize this config to a TOML string.
     ///
     /// Uses `toml_edit` to produce clean output with dotted keys for control
     /// sub-tables (e.g., `dial.type = "volume"`) instead of verbose nested
     /// table headers.
     pub fn to_toml(&self) -> Result<String, ConfigError> {
+        Ok(self.to_document()?.to_string())
+    }
+
+    fn to_document(&self) -> Result<toml_edit::DocumentMut, ConfigError> {
         let mut doc = toml_edit::ser::to_document(self)
             .map_err(|source| ConfigError::Serialize { source })?;
 
@@ -188,12 +958,15 @@ impl Config {
         // Flatten nested control tables into dotted keys
         flatten_control_tables(&mut doc);
 
-        Ok(doc.to_string())
+        Ok(doc)
     }
 
     /// Save config to a file, creating parent directories if needed.
     ///
+    /// If the file already holds valid TOML, only the entries that changed
+    /// are rewritten, so the user's comments and layout survive CLI edits.
     /// Uses atomic write: writes to `path.tmp` then renames over `path`.
+    /// With `[daemon] config_backups` set, the old file is backed up first.
     pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent).map_err(|source| ConfigError::CreateDir {
@@ -202,7 +975,20 @@ impl Config {
             })?;
         }
 
-        let contents = self.to_toml()?;
+        let existing = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| text.parse::<toml_edit::DocumentMut>().ok());
+        let contents = match existing {
+            Some(mut doc) => {
+                merge_table(doc.as_table_mut(), self.to_document()?.as_table(), "");
+                doc.to_string()
+            }
+            None => self.to_toml()?,
+        };
+
+        if self.daemon.config_backups > 0 && path.exists() {
+            backup_config(path, self.daemon.config_backups)?;
+        }
 
         let tmp_path = path.with_extension("toml.tmp");
         std::fs::write(&tmp_path, &contents).map_err(|source| ConfigError::Write {
@@ -230,6 +1016,261 @@ impl Config {
     }
 }
 
+const TOP_LEVEL_KEYS: &[&str] = &[
+    "device", "signal", "leds", "exec", "daemon", "logging", "metrics", "matching", "ipc",
+    "controls",
+];
+const DEVICE_KEYS: &[&str] = &[
+    "serial",
+    "auto_lock",
+    "drain_ms",
+    "drain_read_timeout_ms",
+    "read_timeout_ms",
+];
+const SIGNAL_KEYS: &[&str] = &[
+    "slider_rolling_average",
+    "slider_delta_threshold",
+    "slider_debounce_ms",
+    "knob_rolling_average",
+    "knob_delta_threshold",
+    "knob_debounce_ms",
+    "volume_exponent",
+    "curve_table",
+    "mic_volume_exponent",
+    "slam_window_ms",
+    "volume_steps",
+];
+const LED_KEYS: &[&str] = &[
+    "knobs",
+    "sliders",
+    "slider_labels",
+    "logo",
+    "idle_timeout_ms",
+    "idle_brightness",
+    "color_by_action",
+    "palette",
+    "gamma",
+    "persist_toggle",
+    "off",
+    "off_on_lock",
+    "startup_animation",
+    "clear_on_reload",
+];
+const PALETTE_KEYS: &[&str] = &["device", "app", "focused", "media", "exec", "unassigned"];
+const CONTROL_KEYS: &[&str] = &[
+    "dial",
+    "button",
+    "led_effect",
+    "reverse_travel",
+    "curve_points",
+    "slam_action",
+    "disabled",
+];
+
+/// Find keys in a config file that no setting reads, such as a misspelled
+/// `volume_exponet`. `Config` ignores them so newer files still load; this
+/// turns them into warnings. Covers the top level, `[device]`, `[signal]`,
+/// `[leds]` and its palette, and each control table. Returns nothing if
+/// `contents` isn't valid TOML, since loading reports that already.
+#[must_use]
+pub fn unknown_keys(contents: &str) -> Vec<String> {
+    let Ok(doc) = contents.parse::<toml_edit::DocumentMut>() else {
+        return Vec::new();
+    };
+    let mut warnings = Vec::new();
+    check_keys(&mut warnings, "", doc.as_table(), TOP_LEVEL_KEYS);
+
+    let section = |name: &str| doc.get(name).and_then(toml_edit::Item::as_table_like);
+    if let Some(device) = section("device") {
+        check_keys(&mut warnings, "device.", device, DEVICE_KEYS);
+    }
+    if let Some(signal) = section("signal") {
+        check_keys(&mut warnings, "signal.", signal, SIGNAL_KEYS);
+    }
+    if let Some(leds) = section("leds") {
+        check_keys(&mut warnings, "leds.", leds, LED_KEYS);
+        if let Some(palette) = leds.get("palette").and_then(toml_edit::Item::as_table_like) {
+            check_keys(&mut warnings, "leds.palette.", palette, PALETTE_KEYS);
+        }
+    }
+    if let Some(controls) = section("controls") {
+        for (name, item) in controls.iter() {
+            if let Some(control) = item.as_table_like() {
+                check_keys(
+                    &mut warnings,
+                    &format!("controls.{name}."),
+                    control,
+                    CONTROL_KEYS,
+                );
+            }
+        }
+    }
+    warnings
+}
+
+fn check_keys(
+    warnings: &mut Vec<String>,
+    prefix: &str,
+    table: &dyn toml_edit::TableLike,
+    known: &[&str],
+) {
+    for (key, _) in table.iter() {
+        if !known.contains(&key) {
+            warnings.push(format!("{prefix}{key}: unknown key, ignored"));
+        }
+    }
+}
+
+/// The keys [`unknown_keys`] knows for the table at dotted `path` ("" for
+/// the top level), or `None` for a table it doesn't check.
+fn known_keys(path: &str) -> Option<&'static [&'static str]> {
+    match path {
+        "" => Some(TOP_LEVEL_KEYS),
+        "device" => Some(DEVICE_KEYS),
+        "signal" => Some(SIGNAL_KEYS),
+        "leds" => Some(LED_KEYS),
+        "leds.palette" => Some(PALETTE_KEYS),
+        _ => match path.strip_prefix("controls.") {
+            Some(control) if !control.contains('.') => Some(CONTROL_KEYS),
+            _ => None,
+        },
+    }
+}
+
+/// Update `existing`, the table at dotted `path`, in place to hold the same
+/// data as `fresh`, keeping the comments and layout of every entry that
+/// didn't change. Entries missing from `fresh` are removed, except keys
+/// `Config` doesn't know, which a newer version may still read; new ones
+/// are appended.
+fn merge_table(
+    existing: &mut dyn toml_edit::TableLike,
+    fresh: &dyn toml_edit::TableLike,
+    path: &str,
+) {
+    let known = known_keys(path);
+    let stale: Vec<String> = existing
+        .iter()
+        .filter(|(key, _)| !fresh.contains_key(key))
+        .filter(|(key, _)| known.is_none_or(|known| known.contains(key)))
+        .map(|(key, _)| key.to_owned())
+        .collect();
+    for key in stale {
+        existing.remove(&key);
+    }
+
+    for (key, fresh_item) in fresh.iter() {
+        let child = if path.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{path}.{key}")
+        };
+        match existing.get_mut(key) {
+            Some(item) => merge_item(item, fresh_item, &child),
+            None => {
+                existing.insert(key, fresh_item.clone());
+            }
+        }
+    }
+}
+
+fn merge_item(existing: &mut toml_edit::Item, fresh: &toml_edit::Item, path: &str) {
+    if existing.is_table_like() && fresh.is_table_like() {
+        if let (Some(existing), Some(fresh)) = (existing.as_table_like_mut(), fresh.as_table_like())
+        {
+            merge_table(existing, fresh, path);
+        }
+        return;
+    }
+    match (existing.as_value_mut(), fresh.as_value()) {
+        (Some(old), Some(new)) if same_value(old, new) => {}
+        (Some(old), Some(new)) => {
+            // Keep the spacing and any trailing comment of the old value.
+            let decor = old.decor().clone();
+            *old = new.clone();
+            *old.decor_mut() = decor;
+        }
+        _ => *existing = fresh.clone(),
+    }
+}
+
+/// Whether two values hold the same data, ignoring how they're written
+/// (quoting, spacing, comments).
+fn same_value(a: &toml_edit::Value, b: &toml_edit::Value) -> bool {
+    use toml_edit::Value;
+
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same_value(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| same_value(a, b)))
+        }
+        _ => false,
+    }
+}
+
+/// Copy `path` to `<path>.bak.<unix seconds>`, then delete all but the newest
+/// `keep` backups. A second save within the same second keeps the first
+/// backup, since it holds the older contents.
+fn backup_config(path: &Path, keep: usize) -> Result<(), ConfigError> {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let prefix = format!(
+        "{}.bak.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let backup_path = path.with_file_name(format!("{prefix}{secs}"));
+    if !backup_path.exists() {
+        std::fs::copy(path, &backup_path).map_err(|source| ConfigError::Write {
+            path: backup_path.clone(),
+            source,
+        })?;
+    }
+
+    // Pruning is best effort: a leftover backup is harmless.
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+    let Ok(entries) = std::fs::read_dir(dir.unwrap_or(Path::new("."))) else {
+        return Ok(());
+    };
+    let mut backups: Vec<(u64, PathBuf)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let stamp = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix(&prefix)?
+                .parse()
+                .ok()?;
+            Some((stamp, entry.path()))
+        })
+        .collect();
+    backups.sort_unstable();
+    let excess = backups.len().saturating_sub(keep);
+    for (_, old) in backups.drain(..excess) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(())
+}
+
+fn is_empty_app_target(target: &AudioTarget) -> bool {
+    matches!(target, AudioTarget::App { matcher } if !matcher.is_valid())
+}
+
+fn is_default_device(target: &AudioTarget) -> bool {
+    matches!(
+        target,
+        AudioTarget::DefaultOutput | AudioTarget::DefaultInput
+    )
+}
+
 /// Convert top-level `InlineTable` values into proper `Table` entries so they
 /// render as `[section]` headers instead of `section = { ... }` one-liners.
 fn expand_top_level_tables(doc: &mut toml_edit::DocumentMut) {
@@ -341,6 +1382,48 @@ const EXAMPLE: &str = "\n\
 # sliders = false
 # slider_labels = false
 # logo = false
+# idle_timeout_ms = 60000  # dim after a minute without input (0 = never)
+# idle_brightness = 10     # percent of normal while idle (0 = off)
+# color_by_action = true   # color each control by what it is mapped to
+# gamma = 2.2              # even out perceived brightness (1.0 = off)
+
+# Example: allow longer or more parallel exec commands
+# [exec]
+# max_concurrent = 8  # commands running at once; extra presses are dropped
+# timeout_secs = 30   # kill a command after this long
+
+# Example: also write logs to a daily-rotated file (applies on daemon restart)
+# [logging]
+# file = \"/home/you/.local/state/pcpaneld/pcpaneld.log\"
+
+# Example: use an abstract socket when $XDG_RUNTIME_DIR isn't writable
+# [ipc]
+# abstract = true
+
+# Example: never match these apps, even when they hold focus
+# [matching]
+# ignore = [\"pipewire\", \"speech-dispatcher\"]
+
+# Example: write the daemon's PID for init scripts and monitoring, keep
+# backups of this file before pcpaneld rewrites it, and skip the tray icon on
+# desktops without a StatusNotifier host
+# [daemon]
+# pidfile = \"/run/user/1000/pcpaneld.pid\"
+# config_backups = 3
+# tray = false
+
+# Example: serve Prometheus metrics (needs the `metrics` build feature)
+# [metrics]
+# listen = \"127.0.0.1:9185\"
+
+# Example: override colors used by color_by_action ([r, g, b])
+# [leds.palette]
+# device = [0, 100, 255]
+# app = [255, 140, 0]
+# focused = [0, 255, 200]
+# media = [0, 255, 0]
+# exec = [160, 0, 255]
+# unassigned = [40, 40, 40]
 ";
 
 /// Generate the default config file content for new users.
@@ -350,8 +1433,6 @@ const EXAMPLE: &str = "\n\
 /// generated from the same serialization path as `Config::save()`, so defaults
 /// can never drift from the code.
 pub fn default_config_content() -> Result<String, ConfigError> {
-    use crate::control::{AudioTarget, ButtonAction, DialAction};
-
     let mut config = Config::default();
     config.set_control(
         ControlId::Knob(0),
@@ -362,6 +1443,11 @@ pub fn default_config_content() -> Result<String, ConfigError> {
             button: Some(ButtonAction::Mute {
                 target: AudioTarget::DefaultOutput,
             }),
+            led_effect: None,
+            reverse_travel: false,
+            curve_points: Vec::new(),
+            slam_action: None,
+            disabled: false,
         },
     );
     config.set_control(
@@ -373,6 +1459,11 @@ pub fn default_config_content() -> Result<String, ConfigError> {
             button: Some(ButtonAction::Mute {
                 target: AudioTarget::DefaultInput,
             }),
+            led_effect: None,
+            reverse_travel: false,
+            curve_points: Vec::new(),
+            slam_action: None,
+            disabled: false,
         },
     );
 
@@ -467,6 +1558,17 @@ mod tests {
         assert_eq!(config.device.serial.as_deref(), Some("ABC123"));
     }
 
+    #[test]
+    fn device_auto_lock_defaults_off_and_is_omitted() {
+        let config: Config = toml_edit::de::from_str("[device]\n").unwrap();
+        assert!(!config.device.auto_lock);
+        assert!(!config.to_toml().unwrap().contains("auto_lock"));
+
+        let config: Config = toml_edit::de::from_str("[device]\nauto_lock = true\n").unwrap();
+        assert!(config.device.auto_lock);
+        assert!(config.to_toml().unwrap().contains("auto_lock = true"));
+    }
+
     #[test]
     fn control_config_round_trip() {
         let mut config = Config::default();
@@ -479,6 +1581,11 @@ mod tests {
                 button: Some(ButtonAction::Mute {
                     target: AudioTarget::DefaultOutput,
                 }),
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         config.set_control(
@@ -493,6 +1600,11 @@ mod tests {
                     },
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
@@ -526,6 +1638,21 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn from_toml_round_trips_to_toml() {
+        let content = default_config_content().unwrap();
+        let config = Config::from_toml(&content).unwrap();
+        let again = Config::from_toml(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(config, again);
+    }
+
+    #[test]
+    fn from_toml_rejects_invalid_document() {
+        let err = Config::from_toml("[signal]\nvolume_exponent = \"steep\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { .. }));
+        assert!(err.to_string().starts_with("invalid config:"));
+    }
+
     #[test]
     fn load_nonexistent_returns_defaults() {
         let config = Config::load(Path::new("/nonexistent/path/config.toml")).unwrap();
@@ -533,8 +1660,112 @@ mod tests {
     }
 
     #[test]
-    fn set_and_remove_control() {
-        let mut config = Config::default();
+    fn unknown_keys_flags_typos_in_checked_sections() {
+        let warnings = unknown_keys(
+            r#"
+[signal]
+volume_exponet = 2.0
+
+[leds]
+logo = false
+palette = { exec = "red", exce = "blue" }
+
+[lights]
+logo = false
+
+[controls]
+knob1.dial = { type = "volume", target = { type = "default_output" } }
+knob1.reverse = true
+"#,
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                "lights: unknown key, ignored",
+                "signal.volume_exponet: unknown key, ignored",
+                "leds.palette.exce: unknown key, ignored",
+                "controls.knob1.reverse: unknown key, ignored",
+            ]
+        );
+        assert!(unknown_keys("not [valid").is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_knows_every_field() {
+        let mut config = Config::default();
+        config.device.serial = Some("ABC".into());
+        config.device.auto_lock = true;
+        config.signal.curve_table = vec![0.0, 1.0];
+        config.signal.mic_volume_exponent = Some(2.0);
+        config.leds.persist_toggle = true;
+        config.leds.off = true;
+        config.leds.off_on_lock = true;
+        config.leds.palette.exec = Color(1, 2, 3);
+        config.set_control(
+            ControlId::Knob(0),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultOutput,
+                }),
+                button: Some(ButtonAction::Mute {
+                    target: AudioTarget::DefaultOutput,
+                }),
+                led_effect: Some(LedEffect::Level),
+                reverse_travel: true,
+                curve_points: vec![(0.0, 0.0), (1.0, 1.0)],
+                slam_action: Some(ButtonAction::ToggleLeds),
+                disabled: false,
+            },
+        );
+        assert_eq!(
+            unknown_keys(&config.to_toml().unwrap()),
+            Vec::<String>::new()
+        );
+        assert!(unknown_keys(&default_config_content().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn differences_names_changed_sections_and_controls() {
+        let running = Config::default();
+        assert!(running.differences(&running.clone()).is_empty());
+
+        let mut on_disk = running.clone();
+        on_disk.signal.volume_exponent = 2.0;
+        on_disk.set_control(
+            ControlId::Slider(0),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultInput,
+                }),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            running.differences(&on_disk),
+            vec!["signal", "controls.slider1"]
+        );
+    }
+
+    #[test]
+    fn read_timeout_has_a_floor() {
+        let mut config = Config::default();
+        assert_eq!(config.device.read_timeout_ms(), 100);
+        assert!(config.validate().is_empty());
+
+        config.device.read_timeout_ms = 0;
+        assert_eq!(config.device.read_timeout_ms(), 10);
+        assert_eq!(
+            config.validate(),
+            vec!["device.read_timeout_ms: must be at least 10, using 10"]
+        );
+
+        config.device.read_timeout_ms = u64::MAX;
+        assert_eq!(config.device.read_timeout_ms(), i32::MAX);
+    }
+
+    #[test]
+    fn set_and_remove_control() {
+        let mut config = Config::default();
         let id = ControlId::Knob(2);
         assert!(config.get_control(id).is_none());
 
@@ -545,6 +1776,11 @@ mod tests {
                     target: AudioTarget::DefaultOutput,
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         assert!(config.get_control(id).is_some());
@@ -620,6 +1856,11 @@ mod tests {
                     target: AudioTarget::DefaultOutput,
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
@@ -629,6 +1870,153 @@ mod tests {
         assert_eq!(config, loaded);
     }
 
+    #[test]
+    fn save_preserves_comments_of_unchanged_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let mut config = Config::default();
+        config.set_control(
+            ControlId::Knob(0),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultOutput,
+                }),
+                button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
+            },
+        );
+        let original = format!(
+            "# My panel, hand-tuned\n{}\n# Ideas for later:\n# [controls.knob5]\n",
+            config
+                .to_toml()
+                .unwrap()
+                .replace("[signal]\n", "[signal]\n# keep it snappy\n")
+                .replace(
+                    "volume_exponent = 1.0\n",
+                    "volume_exponent = 1.0 # matches my speakers\n"
+                )
+        );
+        std::fs::write(&path, &original).unwrap();
+
+        // Saving the same config leaves the file byte-for-byte intact.
+        config.save(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+
+        // Changing one value and adding a control keeps every comment.
+        config.signal.volume_exponent = 2.0;
+        config.set_control(
+            ControlId::Slider(0),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultInput,
+                }),
+                button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
+            },
+        );
+        config.save(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        for comment in [
+            "# My panel, hand-tuned\n",
+            "# keep it snappy\n",
+            "volume_exponent = 2.0 # matches my speakers\n",
+            "# Ideas for later:\n# [controls.knob5]\n",
+        ] {
+            assert!(saved.contains(comment), "lost {comment:?} in:\n{saved}");
+        }
+        assert_eq!(Config::load(&path).unwrap(), config);
+
+        // Removed controls disappear from the file.
+        config.remove_control(ControlId::Knob(0));
+        config.save(&path).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("knob1"));
+        assert_eq!(Config::load(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn save_keeps_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let mut config = Config::default();
+        config.device.serial = Some("ABC".into());
+        let original = config
+            .to_toml()
+            .unwrap()
+            .replace("[signal]\n", "[signal]\nfuture_setting = 3\n")
+            .replace("[leds]\n", "[leds]\nkonbs = false\n");
+        let original = format!("future_section = true\n{original}");
+        std::fs::write(&path, &original).unwrap();
+
+        // Known fields that went away are still removed.
+        config.device.serial = None;
+        config.signal.volume_exponent = 2.0;
+        config.save(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        for key in [
+            "future_section = true",
+            "future_setting = 3",
+            "konbs = false",
+        ] {
+            assert!(saved.contains(key), "lost {key:?} in:\n{saved}");
+        }
+        assert!(!saved.contains("serial"), "kept serial in:\n{saved}");
+        assert_eq!(Config::load(&path).unwrap(), config);
+    }
+
+    #[test]
+    fn save_keeps_configured_number_of_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "# hand-edited\n").unwrap();
+        let backups = || {
+            let mut names: Vec<String> = std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|e| e.unwrap().file_name().into_string().unwrap())
+                .filter(|name| name.starts_with("config.toml.bak."))
+                .collect();
+            names.sort();
+            names
+        };
+
+        Config::default().save(&path).unwrap();
+        assert!(backups().is_empty(), "backups are off by default");
+
+        let mut config = Config::default();
+        config.daemon.config_backups = 2;
+        std::fs::write(&path, "# hand-edited\n").unwrap();
+        config.save(&path).unwrap();
+        let names = backups();
+        assert_eq!(names.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(&names[0])).unwrap(),
+            "# hand-edited\n"
+        );
+        let first = names[0].clone();
+
+        // Older backups beyond the limit are pruned. The save may land in a
+        // new second and add a backup of its own, so only the oldest two are
+        // sure to go.
+        for stamp in ["1000000000", "1000000001", "1000000002"] {
+            std::fs::write(dir.path().join(format!("config.toml.bak.{stamp}")), "").unwrap();
+        }
+        config.save(&path).unwrap();
+        let names = backups();
+        assert_eq!(names.len(), 2);
+        assert!(!names.contains(&"config.toml.bak.1000000000".to_string()));
+        assert!(!names.contains(&"config.toml.bak.1000000001".to_string()));
+        assert!(names.contains(&first));
+    }
+
     #[test]
     fn full_example_config_parses() {
         let toml_str = r#"
@@ -684,6 +2072,11 @@ dial = { type = "volume", target = { type = "app", matcher = { name = "Steam" }
                 button: Some(ButtonAction::Mute {
                     target: AudioTarget::DefaultOutput,
                 }),
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
@@ -746,6 +2139,11 @@ dial = { type = "volume", target = { type = "app", matcher = { name = "Steam" }
                     },
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
@@ -773,6 +2171,11 @@ dial = { type = "volume", target = { type = "app", matcher = { name = "Steam" }
                     target: AudioTarget::DefaultOutput,
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         config.set_control(
@@ -782,6 +2185,11 @@ dial = { type = "volume", target = { type = "app", matcher = { name = "Steam" }
                     target: AudioTarget::DefaultOutput,
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         config.set_control(
@@ -791,6 +2199,11 @@ dial = { type = "volume", target = { type = "app", matcher = { name = "Steam" }
                     target: AudioTarget::DefaultInput,
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
@@ -876,7 +2289,13 @@ dial = { type = "volume", target = { type = "app", matcher = { name = "Steam" }
                 dial: None,
                 button: Some(ButtonAction::Exec {
                     command: "notify-send hello".into(),
+                    repeat_ms: None,
                 }),
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
@@ -910,6 +2329,11 @@ dial = { type = "volume", target = { type = "app", matcher = { name = "Steam" }
                 button: Some(ButtonAction::Media {
                     command: MediaCommand::PlayPause,
                 }),
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
@@ -930,4 +2354,551 @@ dial = { type = "volume", target = { type = "app", matcher = { name = "Steam" }
             parsed.get_control(ControlId::Knob(3))
         );
     }
+
+    #[test]
+    fn level_led_effect_parses() {
+        let config: Config = toml_edit::de::from_str(
+            r#"
+            [controls.knob1]
+            dial = { type = "volume", target = { type = "default_output" } }
+            led_effect = "level"
+            "#,
+        )
+        .unwrap();
+        let control = config.get_control(ControlId::Knob(0)).unwrap();
+        assert_eq!(control.led_effect, Some(LedEffect::Level));
+    }
+
+    #[test]
+    fn peak_meter_led_effect_round_trip() {
+        let config: Config = toml_edit::de::from_str(
+            r#"
+            [controls.slider1]
+            dial = { type = "volume", target = { type = "default_output" } }
+            led_effect = "peak_meter"
+            "#,
+        )
+        .unwrap();
+        let control = config.get_control(ControlId::Slider(0)).unwrap();
+        assert_eq!(control.led_effect, Some(LedEffect::PeakMeter));
+
+        let output = config.to_toml().unwrap();
+        let parsed: Config = toml_edit::de::from_str(&output).unwrap();
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn idle_dimming_defaults_to_disabled() {
+        let config: Config = toml_edit::de::from_str("[leds]\nknobs = true\n").unwrap();
+        assert_eq!(config.leds.idle_timeout_ms, 0);
+        assert_eq!(config.leds.idle_brightness_factor(), 0.0);
+    }
+
+    #[test]
+    fn idle_brightness_factor_is_clamped() {
+        for (percent, expected) in [(0u8, 0.0), (50, 0.5), (100, 1.0), (255, 1.0)] {
+            let leds = LedConfig {
+                idle_brightness: percent,
+                ..LedConfig::default()
+            };
+            assert_eq!(leds.idle_brightness_factor(), expected, "percent={percent}");
+        }
+    }
+
+    #[test]
+    fn palette_partial_override_keeps_other_defaults() {
+        let config: Config = toml_edit::de::from_str(
+            r#"
+            [leds]
+            color_by_action = true
+            [leds.palette]
+            media = [255, 0, 0]
+            "#,
+        )
+        .unwrap();
+        assert!(config.leds.color_by_action);
+        assert_eq!(config.leds.palette.media, Color(255, 0, 0));
+        assert_eq!(config.leds.palette.exec, LedPalette::default().exec);
+
+        let output = config.to_toml().unwrap();
+        let parsed: Config = toml_edit::de::from_str(&output).unwrap();
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn color_parses_hex_names_and_arrays() {
+        let config: Config = toml_edit::de::from_str(
+            r##"
+            [leds.palette]
+            media = "#ff0000"
+            exec = "Cyan"
+            app = "#0A141e"
+            focused = [1, 2, 3]
+            "##,
+        )
+        .unwrap();
+        let palette = config.leds.palette;
+        assert_eq!(palette.media, Color(255, 0, 0));
+        assert_eq!(palette.exec, Color(0, 255, 255));
+        assert_eq!(palette.app, Color(10, 20, 30));
+        assert_eq!(palette.focused, Color(1, 2, 3));
+        assert_eq!("red".parse(), Ok(Color(255, 0, 0)));
+    }
+
+    #[test]
+    fn hsv_converts_known_values() {
+        let cases = [
+            ((0.0, 1.0, 1.0), Color(255, 0, 0)),
+            ((120.0, 1.0, 1.0), Color(0, 255, 0)),
+            ((240.0, 1.0, 1.0), Color(0, 0, 255)),
+            ((60.0, 1.0, 1.0), Color(255, 255, 0)),
+            ((180.0, 1.0, 1.0), Color(0, 255, 255)),
+            ((300.0, 1.0, 1.0), Color(255, 0, 255)),
+            ((360.0, 1.0, 1.0), Color(255, 0, 0)),
+            ((210.0, 1.0, 0.8), Color(0, 102, 204)),
+            ((30.0, 0.5, 1.0), Color(255, 191, 128)),
+            ((0.0, 0.0, 0.5), Color(128, 128, 128)),
+            ((123.0, 1.0, 0.0), Color(0, 0, 0)),
+        ];
+        for ((h, s, v), expected) in cases {
+            assert_eq!(Color::from_hsv(h, s, v), expected, "h={h} s={s} v={v}");
+        }
+    }
+
+    #[test]
+    fn color_parses_hsv_table() {
+        let config: Config = toml_edit::de::from_str(
+            r#"
+            [leds.palette]
+            media = { h = 210, s = 1.0, v = 0.8 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.leds.palette.media, Color(0, 102, 204));
+
+        for bad in [
+            "{ h = 400, s = 1.0, v = 1.0 }",
+            "{ h = 0, s = 1.5, v = 1.0 }",
+            "{ h = 0, s = 1.0 }",
+            "{ h = 0, s = 1.0, v = 1.0, a = 1.0 }",
+        ] {
+            let toml = format!("[leds.palette]\nmedia = {bad}\n");
+            assert!(
+                toml_edit::de::from_str::<Config>(&toml).is_err(),
+                "{bad} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn color_rejects_invalid_input() {
+        for bad in [
+            "#ff00",
+            "#ff00000",
+            "#gg0000",
+            "ff0000",
+            "#ff\u{e9}000",
+            "#+f0000",
+        ] {
+            assert!(bad.parse::<Color>().is_err(), "{bad} should be rejected");
+        }
+
+        let err = toml_edit::de::from_str::<Config>(
+            r#"
+            [leds.palette]
+            media = "chartreuse"
+            "#,
+        )
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("unknown color \"chartreuse\""),
+            "got: {message}"
+        );
+        assert!(message.contains("orange"), "got: {message}");
+
+        for bad in ["[1, 2]", "[1, 2, 3, 4]", "[256, 0, 0]", "7"] {
+            let toml = format!("[leds.palette]\nmedia = {bad}\n");
+            assert!(
+                toml_edit::de::from_str::<Config>(&toml).is_err(),
+                "{bad} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn default_palette_is_not_serialized() {
+        let output = Config::default().to_toml().unwrap();
+        assert!(!output.contains("palette"), "got:\n{output}");
+    }
+
+    #[test]
+    fn palette_buttons_win_over_dial() {
+        let palette = LedPalette::default();
+        let dial = Some(DialAction::Volume {
+            target: AudioTarget::DefaultOutput,
+        });
+        let media = ControlConfig {
+            dial: dial.clone(),
+            button: Some(ButtonAction::Media {
+                command: MediaCommand::Next,
+            }),
+            led_effect: None,
+            reverse_travel: false,
+            curve_points: Vec::new(),
+            slam_action: None,
+            disabled: false,
+        };
+        let mute = ControlConfig {
+            dial,
+            button: Some(ButtonAction::Mute {
+                target: AudioTarget::FocusedApp,
+            }),
+            led_effect: None,
+            reverse_travel: false,
+            curve_points: Vec::new(),
+            slam_action: None,
+            disabled: false,
+        };
+        assert_eq!(palette.color_for(Some(&media)), palette.media);
+        // A mute button doesn't override the dial's target
+        assert_eq!(palette.color_for(Some(&mute)), palette.device);
+        assert_eq!(palette.color_for(None), palette.unassigned);
+        assert_eq!(
+            palette.color_for(Some(&ControlConfig::default())),
+            palette.unassigned
+        );
+    }
+
+    #[test]
+    fn default_config_has_no_warnings() {
+        let config = Config::from_toml(&default_config_content().unwrap()).unwrap();
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn curve_points_round_trip_and_validate() {
+        let config = Config::from_toml(
+            "[controls.slider1]\ncurve_points = [[0.0, 0.0], [0.2, 0.5], [1.0, 1.0]]\n",
+        )
+        .unwrap();
+        let slider = config.get_control(ControlId::Slider(0)).unwrap();
+        assert_eq!(
+            slider.curve_points,
+            vec![(0.0, 0.0), (0.2, 0.5), (1.0, 1.0)]
+        );
+        assert!(config.validate().is_empty());
+        assert_eq!(
+            Config::from_toml(&config.to_toml().unwrap()).unwrap(),
+            config
+        );
+
+        let config =
+            Config::from_toml("[controls.slider1]\ncurve_points = [[0.5, 0.0], [0.2, 1.0]]\n")
+                .unwrap();
+        assert_eq!(
+            config.validate(),
+            vec![
+                "controls.slider1.curve_points: point 1 does not increase in x, using the [signal] curve instead"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn curve_table_replaces_power_curve() {
+        let config = Config::from_toml("[signal]\nvolume_exponent = 2.0\n").unwrap();
+        assert_eq!(config.signal.volume_curve(), VolumeCurve::new(2.0));
+
+        let config = Config::from_toml("[signal]\ncurve_table = [1.0, 0.0]\n").unwrap();
+        assert!(config.validate().is_empty());
+        assert_eq!(config.signal.volume_curve().hw_to_volume(0).get(), 1.0);
+        assert_eq!(
+            Config::from_toml(&config.to_toml().unwrap()).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn mic_curve_defaults_to_main_curve() {
+        let config = Config::from_toml("[signal]\nvolume_exponent = 2.0\n").unwrap();
+        assert_eq!(config.signal.mic_volume_curve(), VolumeCurve::new(2.0));
+
+        let config =
+            Config::from_toml("[signal]\nvolume_exponent = 2.0\nmic_volume_exponent = 1.0\n")
+                .unwrap();
+        assert_eq!(config.signal.mic_volume_curve(), VolumeCurve::new(1.0));
+        assert_eq!(config.signal.volume_curve(), VolumeCurve::new(2.0));
+    }
+
+    #[test]
+    fn invalid_curve_table_falls_back_with_warning() {
+        let config =
+            Config::from_toml("[signal]\nvolume_exponent = 2.0\ncurve_table = [0.5]\n").unwrap();
+        assert_eq!(config.signal.volume_curve(), VolumeCurve::new(2.0));
+        assert_eq!(
+            config.validate(),
+            vec!["signal.curve_table: has 1 entries, needs 2 to 256, using volume_exponent"]
+        );
+
+        let config = Config::from_toml("[signal]\ncurve_table = [0.0, 1.5]\n").unwrap();
+        assert_eq!(
+            config.validate(),
+            vec!["signal.curve_table: entries outside [0, 1] are clamped"]
+        );
+    }
+
+    #[test]
+    fn metrics_listen_is_off_by_default() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.metrics.listen, None);
+
+        let config = Config::from_toml("[metrics]\nlisten = \"127.0.0.1:9185\"\n").unwrap();
+        assert_eq!(
+            config.metrics.listen,
+            Some(SocketAddr::from(([127, 0, 0, 1], 9185)))
+        );
+        assert_eq!(
+            Config::from_toml(&config.to_toml().unwrap()).unwrap(),
+            config
+        );
+        assert!(Config::from_toml("[metrics]\nlisten = \"localhost\"\n").is_err());
+    }
+
+    #[test]
+    fn matching_ignore_compares_binary_and_name_case_insensitively() {
+        let config =
+            Config::from_toml("[matching]\nignore = [\"speech-dispatcher\", \"PipeWire\"]\n")
+                .unwrap();
+        let si = |name: &str, binary: Option<&str>| SinkInputInfo {
+            index: 1,
+            name: name.to_string(),
+            binary: binary.map(String::from),
+            flatpak_id: None,
+            pid: None,
+            cgroup: None,
+            media_name: None,
+            sink_index: 0,
+            volume: crate::audio::Volume::new(0.5),
+            muted: false,
+            channels: 2,
+        };
+        assert!(config
+            .matching
+            .is_ignored(&si("playback", Some("speech-dispatcher"))));
+        assert!(config.matching.is_ignored(&si("pipewire", None)));
+        assert!(!config.matching.is_ignored(&si("Firefox", Some("firefox"))));
+        assert!(!Config::default()
+            .matching
+            .is_ignored(&si("pipewire", Some("pipewire"))));
+    }
+
+    #[test]
+    fn ipc_abstract_socket_is_opt_in() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(
+            config.ipc.endpoint(None),
+            IpcEndpoint::Path(ipc::default_socket_path())
+        );
+
+        let config = Config::from_toml("[ipc]\nabstract = true\n").unwrap();
+        assert_eq!(
+            config.ipc.endpoint(None),
+            IpcEndpoint::Abstract("pcpaneld".to_string())
+        );
+        assert_eq!(
+            config.ipc.endpoint(Some("/tmp/panel-a.sock".into())),
+            IpcEndpoint::Path("/tmp/panel-a.sock".into())
+        );
+        assert_eq!(
+            Config::from_toml(&config.to_toml().unwrap()).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn pidfile_is_off_by_default() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.daemon.pidfile, None);
+
+        let config = Config::from_toml("[daemon]\npidfile = \"/tmp/pcpaneld.pid\"\n").unwrap();
+        assert_eq!(
+            config.daemon.pidfile.as_deref(),
+            Some(Path::new("/tmp/pcpaneld.pid"))
+        );
+    }
+
+    #[test]
+    fn apply_mapping_change_edits_controls() {
+        let knob = ControlId::Knob(0);
+        let mut config = Config::default();
+        assert!(config.apply_mapping_change(&IpcRequest::AssignButton {
+            control: knob,
+            action: ButtonAction::Mute {
+                target: AudioTarget::DefaultOutput,
+            },
+        }));
+        assert!(config.get_control(knob).unwrap().button.is_some());
+        assert!(config.get_control(knob).unwrap().dial.is_none());
+
+        // Removing the last half removes the control.
+        assert!(config.apply_mapping_change(&IpcRequest::Unassign {
+            control: knob,
+            part: Some(ControlPart::Button),
+        }));
+        assert!(config.get_control(knob).is_none());
+
+        assert!(!config.apply_mapping_change(&IpcRequest::GetConfig));
+    }
+
+    #[test]
+    fn tray_is_on_unless_disabled() {
+        assert!(Config::from_toml("").unwrap().daemon.tray);
+        assert!(
+            Config::from_toml("[daemon]\npidfile = \"/tmp/p\"\n")
+                .unwrap()
+                .daemon
+                .tray
+        );
+
+        let config = Config::from_toml("[daemon]\ntray = false\n").unwrap();
+        assert!(!config.daemon.tray);
+        let reparsed = Config::from_toml(&config.to_toml().unwrap()).unwrap();
+        assert!(!reparsed.daemon.tray);
+    }
+
+    #[test]
+    fn volume_steps_quantize_dial_volumes() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.signal.quantize(Volume::new(0.37)).get(), 0.37);
+
+        let config = Config::from_toml("[signal]\nvolume_steps = 20\n").unwrap();
+        assert_eq!(config.signal.quantize(Volume::new(0.37)).get(), 0.35);
+        assert_eq!(config.signal.quantize(Volume::MAX).get(), 1.0);
+        assert!(config.validate().is_empty());
+
+        let config = Config::from_toml("[signal]\nvolume_steps = 0\n").unwrap();
+        assert_eq!(config.signal.quantize(Volume::new(0.37)).get(), 0.37);
+        assert_eq!(
+            config.validate(),
+            vec!["signal.volume_steps: must be at least 1, ignored"]
+        );
+    }
+
+    #[test]
+    fn logging_defaults_to_console_only() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.logging.file, None);
+
+        let config = Config::from_toml("[logging]\nfile = \"/tmp/pcpaneld.log\"\n").unwrap();
+        assert_eq!(
+            config.logging.file.as_deref(),
+            Some(Path::new("/tmp/pcpaneld.log"))
+        );
+        assert_eq!(
+            Config::from_toml(&config.to_toml().unwrap()).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn exec_limits_default_and_clamp() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.exec.max_concurrent(), 8);
+        assert_eq!(config.exec.timeout(), std::time::Duration::from_secs(30));
+
+        let config = Config::from_toml("[exec]\nmax_concurrent = 0\ntimeout_secs = 0\n").unwrap();
+        assert_eq!(config.exec.max_concurrent(), 1);
+        assert_eq!(config.exec.timeout(), std::time::Duration::from_secs(1));
+        assert_eq!(
+            config.validate(),
+            [
+                "exec.max_concurrent: must be at least 1, using 1",
+                "exec.timeout_secs: must be at least 1, using 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_flags_unknown_keys_slider_buttons_and_empty_matchers() {
+        let config = Config::from_toml(
+            r#"
+            [controls.knob9]
+            dial = { type = "volume", target = { type = "default_output" } }
+
+            [controls.slider1]
+            button = { type = "media", command = "next" }
+
+            [controls.knob2]
+            dial = { type = "volume", target = { type = "app", matcher = {} } }
+            button = { type = "move_app_to_sink", matcher = {}, sink_name = "headset" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.validate(),
+            [
+                "controls.knob2.dial: app target has no binary, name, flatpak_id, cgroup, or media_name and never matches",
+                "controls.knob2.button: app matcher has no binary, name, flatpak_id, cgroup, or media_name and never matches",
+                "controls.knob9: unknown control, ignored (expected knob1-knob5 or slider1-slider4)",
+                "controls.slider1.button: sliders have no button, ignored",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_flags_volume_steps_that_change_nothing() {
+        let config = Config::from_toml(
+            r#"
+            [controls.knob1]
+            button = { type = "volume_step", target = { type = "default_output" }, delta = 0.0 }
+
+            [controls.knob2]
+            button = { type = "volume_step", target = { type = "default_output" }, delta = 0.05 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.validate(),
+            ["controls.knob1.button: volume_step delta 0 changes nothing"]
+        );
+    }
+
+    #[test]
+    fn validate_flags_duplicates_except_default_devices() {
+        let config = Config::from_toml(
+            r#"
+            [controls.knob1]
+            dial = { type = "volume", target = { type = "default_output" } }
+            button = { type = "mute", target = { type = "default_output" } }
+
+            [controls.knob2]
+            dial = { type = "volume", target = { type = "default_output" } }
+            button = { type = "mute", target = { type = "default_output" } }
+
+            [controls.knob3]
+            button = { type = "media", command = "play_pause" }
+
+            [controls.knob4]
+            button = { type = "media", command = "play_pause" }
+
+            [controls.slider1]
+            dial = { type = "volume", target = { type = "app", matcher = { binary = "spotify" } } }
+
+            [controls.slider2]
+            dial = { type = "volume", target = { type = "app", matcher = { binary = "spotify" } } }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.validate(),
+            [
+                "controls.knob4.button: same action as controls.knob3.button",
+                "controls.slider2.dial: same action as controls.slider1.dial",
+            ]
+        );
+    }
 }
@@ -2,10 +2,83 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
-/// Identifies a physical control on the PCPanel Pro.
+/// PCPanel hardware model, which determines the control layout.
 ///
-/// Knobs 0-4 (rotary encoders with buttons), Sliders 0-3 (linear faders).
+/// HID analog IDs number the knobs first, then the sliders. Button IDs
+/// match the knob numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceModel {
+    /// 5 knobs with buttons, 4 sliders.
+    #[default]
+    Pro,
+    /// 4 knobs with buttons, no sliders.
+    Mini,
+}
+
+impl DeviceModel {
+    /// Every supported model.
+    pub const ALL: [DeviceModel; 2] = [DeviceModel::Pro, DeviceModel::Mini];
+
+    /// Human-readable product name.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceModel::Pro => "PCPanel Pro",
+            DeviceModel::Mini => "PCPanel Mini",
+        }
+    }
+
+    /// Number of knobs on this model.
+    #[must_use]
+    pub const fn num_knobs(self) -> u8 {
+        match self {
+            DeviceModel::Pro => 5,
+            DeviceModel::Mini => 4,
+        }
+    }
+
+    /// Number of sliders on this model.
+    #[must_use]
+    pub const fn num_sliders(self) -> u8 {
+        match self {
+            DeviceModel::Pro => 4,
+            DeviceModel::Mini => 0,
+        }
+    }
+
+    /// Total number of analog controls.
+    #[must_use]
+    pub const fn num_analog(self) -> u8 {
+        self.num_knobs() + self.num_sliders()
+    }
+
+    /// All controls on this model, in HID analog ID order.
+    pub fn controls(self) -> impl Iterator<Item = ControlId> {
+        (0..self.num_analog()).filter_map(move |id| ControlId::from_analog_id(id, self))
+    }
+
+    /// Returns true if this model has the given control.
+    #[must_use]
+    pub fn has_control(self, control: ControlId) -> bool {
+        match control {
+            ControlId::Knob(n) => n < self.num_knobs(),
+            ControlId::Slider(n) => n < self.num_sliders(),
+        }
+    }
+}
+
+impl fmt::Display for DeviceModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Identifies a physical control on a PCPanel.
+///
+/// Knobs are rotary encoders with buttons, sliders are linear faders.
 /// Each knob has both an analog dial and a digital button; sliders are analog only.
+/// How many of each exist depends on the [`DeviceModel`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ControlId {
     Knob(u8),
@@ -13,40 +86,33 @@ pub enum ControlId {
 }
 
 impl ControlId {
-    /// Number of knobs on the PCPanel Pro.
-    pub const NUM_KNOBS: u8 = 5;
-    /// Number of sliders on the PCPanel Pro.
-    pub const NUM_SLIDERS: u8 = 4;
-    /// Total number of analog controls.
-    pub const NUM_ANALOG: u8 = Self::NUM_KNOBS + Self::NUM_SLIDERS;
-
-    /// Convert a HID analog control ID (0-8) to a `ControlId`.
-    /// IDs 0-4 are knobs, 5-8 are sliders.
+    /// Convert a HID analog control ID to a `ControlId` for the given model.
+    /// IDs below the model's knob count are knobs, the rest are sliders.
     #[must_use]
-    pub fn from_analog_id(id: u8) -> Option<Self> {
-        if id < Self::NUM_KNOBS {
+    pub fn from_analog_id(id: u8, model: DeviceModel) -> Option<Self> {
+        if id < model.num_knobs() {
             Some(ControlId::Knob(id))
-        } else if id < Self::NUM_ANALOG {
-            Some(ControlId::Slider(id - Self::NUM_KNOBS))
+        } else if id < model.num_analog() {
+            Some(ControlId::Slider(id - model.num_knobs()))
         } else {
             None
         }
     }
 
-    /// Convert back to a HID analog control ID (0-8).
+    /// Convert back to a HID analog control ID for the given model.
     #[must_use]
-    pub fn to_analog_id(self) -> u8 {
+    pub fn to_analog_id(self, model: DeviceModel) -> u8 {
         match self {
             ControlId::Knob(n) => n,
-            ControlId::Slider(n) => Self::NUM_KNOBS + n,
+            ControlId::Slider(n) => model.num_knobs() + n,
         }
     }
 
-    /// Convert a HID button ID (0-4) to a `ControlId`.
-    /// Only knobs have buttons on the PCPanel Pro.
+    /// Convert a HID button ID to a `ControlId` for the given model.
+    /// Only knobs have buttons.
     #[must_use]
-    pub fn from_button_id(id: u8) -> Option<Self> {
-        if id < Self::NUM_KNOBS {
+    pub fn from_button_id(id: u8, model: DeviceModel) -> Option<Self> {
+        if id < model.num_knobs() {
             Some(ControlId::Knob(id))
         } else {
             None
@@ -64,21 +130,23 @@ impl ControlId {
     }
 
     /// Parse a config key name back to a `ControlId`.
-    /// Accepts "knob1"-"knob5" and "slider1"-"slider4" (1-based).
+    /// Accepts any control that exists on some supported model, i.e.
+    /// "knob1"-"knob5" and "slider1"-"slider4" (1-based).
     #[must_use]
     pub fn from_config_key(key: &str) -> Option<Self> {
-        if let Some(n) = key.strip_prefix("knob") {
+        let control = if let Some(n) = key.strip_prefix("knob") {
             let n: u8 = n.parse().ok()?;
-            if (1..=Self::NUM_KNOBS).contains(&n) {
-                return Some(ControlId::Knob(n - 1));
-            }
+            ControlId::Knob(n.checked_sub(1)?)
         } else if let Some(n) = key.strip_prefix("slider") {
             let n: u8 = n.parse().ok()?;
-            if (1..=Self::NUM_SLIDERS).contains(&n) {
-                return Some(ControlId::Slider(n - 1));
-            }
-        }
-        None
+            ControlId::Slider(n.checked_sub(1)?)
+        } else {
+            return None;
+        };
+        DeviceModel::ALL
+            .iter()
+            .any(|model| model.has_control(control))
+            .then_some(control)
     }
 
     /// Returns true if this control is a knob (has both dial and button).
@@ -109,13 +177,23 @@ pub struct AppMatcher {
     /// Match against `application.flatpak.id`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub flatpak_id: Option<String>,
+    /// Match against the systemd scope unit from the process cgroup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<String>,
+    /// Match against the stream's `media.name`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_name: Option<String>,
 }
 
 impl AppMatcher {
     /// Returns true if this matcher has at least one field set.
     #[must_use]
     pub fn is_valid(&self) -> bool {
-        self.binary.is_some() || self.name.is_some() || self.flatpak_id.is_some()
+        self.binary.is_some()
+            || self.name.is_some()
+            || self.flatpak_id.is_some()
+            || self.cgroup.is_some()
+            || self.media_name.is_some()
     }
 
     /// Check if a sink-input's properties match this matcher.
@@ -139,6 +217,8 @@ impl AppMatcher {
         check(&self.binary, &props.binary)
             && check(&self.name, &props.name)
             && check(&self.flatpak_id, &props.flatpak_id)
+            && check(&self.cgroup, &props.cgroup)
+            && check(&self.media_name, &props.media_name)
     }
 }
 
@@ -148,6 +228,8 @@ pub struct AppProperties {
     pub binary: Option<String>,
     pub name: Option<String>,
     pub flatpak_id: Option<String>,
+    pub cgroup: Option<String>,
+    pub media_name: Option<String>,
 }
 
 impl From<&crate::audio::SinkInputInfo> for AppProperties {
@@ -156,6 +238,8 @@ impl From<&crate::audio::SinkInputInfo> for AppProperties {
             binary: si.binary.clone(),
             name: Some(si.name.clone()),
             flatpak_id: si.flatpak_id.clone(),
+            cgroup: si.cgroup.clone(),
+            media_name: si.media_name.clone(),
         }
     }
 }
@@ -209,6 +293,12 @@ impl fmt::Display for AudioTarget {
                 if let Some(fid) = &matcher.flatpak_id {
                     parts.push(format!("flatpak={fid}"));
                 }
+                if let Some(cg) = &matcher.cgroup {
+                    parts.push(format!("cgroup={cg}"));
+                }
+                if let Some(media) = &matcher.media_name {
+                    parts.push(format!("media={media}"));
+                }
                 write!(f, "app({})", parts.join(", "))
             }
             AudioTarget::FocusedApp => f.write_str("focused"),
@@ -220,7 +310,33 @@ impl fmt::Display for AudioTarget {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum DialAction {
-    Volume { target: AudioTarget },
+    Volume {
+        target: AudioTarget,
+    },
+    /// Volume of the first target in the list that currently resolves,
+    /// e.g. the focused app, else the default output.
+    VolumeChain {
+        targets: Vec<AudioTarget>,
+    },
+}
+
+impl DialAction {
+    /// The audio targets this action may control, in order of preference.
+    #[must_use]
+    pub fn targets(&self) -> &[AudioTarget] {
+        match self {
+            DialAction::Volume { target } => std::slice::from_ref(target),
+            DialAction::VolumeChain { targets } => targets,
+        }
+    }
+}
+
+/// One half of a knob: its dial or its button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlPart {
+    Dial,
+    Button,
 }
 
 /// MPRIS media player command.
@@ -251,21 +367,132 @@ impl MediaCommand {
 }
 
 /// Action for a button press.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ButtonAction {
-    Mute { target: AudioTarget },
-    Media { command: MediaCommand },
-    Exec { command: String },
+    Mute {
+        target: AudioTarget,
+    },
+    /// Toggle mute on the default input, like `Mute` with a `DefaultInput`
+    /// target, and show the mute on the knob's ring.
+    MuteDefaultInput,
+    /// Nudge the target's volume by `delta`, e.g. 0.05 for up 5% or -0.05
+    /// for down, clamped to 0.0-1.0. Streams of an app target all move to
+    /// the loudest one's volume plus `delta`.
+    VolumeStep {
+        target: AudioTarget,
+        delta: f64,
+        /// While the button is held, step again every this many milliseconds.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        repeat_ms: Option<u64>,
+    },
+    Media {
+        command: MediaCommand,
+    },
+    Exec {
+        command: String,
+        /// While the button is held, run the command again every this many
+        /// milliseconds, e.g. for a volume step.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        repeat_ms: Option<u64>,
+    },
+    /// Switch a sink to one of its ports, e.g. speakers vs headphones on
+    /// the same card. `sink_name` and `port` are PulseAudio names, as listed
+    /// by `pcpaneld ports`.
+    SetPort {
+        sink_name: String,
+        port: String,
+    },
+    /// Move every stream of the matching app to another sink, e.g. send the
+    /// browser to the headphones. `sink_name` is a PulseAudio sink name, as
+    /// listed by `pcpaneld ports`.
+    MoveAppToSink {
+        matcher: AppMatcher,
+        sink_name: String,
+    },
+    /// Make `a` the default sink, or `b` if `a` already is, e.g. to flip
+    /// between speakers and headphones. Both are PulseAudio sink names, as
+    /// listed by `pcpaneld ports`.
+    ToggleOutputs {
+        a: String,
+        b: String,
+    },
+    /// Suspend the named sink, or resume it if it is already suspended,
+    /// e.g. to power down an unused output or silence a misbehaving one.
+    ToggleSinkSuspend {
+        sink_name: String,
+    },
+    /// Switch every LED zone off, or back on. For this session only unless
+    /// `[leds] persist_toggle` is set.
+    ToggleLeds,
+}
+
+/// Shortest repeat interval for a held button, so a tiny `repeat_ms` can't
+/// flood the system with commands.
+pub const MIN_REPEAT_MS: u64 = 50;
+
+impl ButtonAction {
+    /// How often the action repeats while its button is held, `None` if it
+    /// only fires on press.
+    #[must_use]
+    pub fn repeat_interval(&self) -> Option<std::time::Duration> {
+        match self {
+            ButtonAction::Exec {
+                repeat_ms: Some(ms),
+                ..
+            }
+            | ButtonAction::VolumeStep {
+                repeat_ms: Some(ms),
+                ..
+            } => Some(std::time::Duration::from_millis((*ms).max(MIN_REPEAT_MS))),
+            _ => None,
+        }
+    }
+}
+
+/// Daemon-driven LED effect for a control's LED zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedEffect {
+    /// Slider LED brightness follows the peak level of the dial's target
+    /// (the default output's monitor, or the default input). Opt-in because
+    /// it opens a PulseAudio record stream per metered device.
+    PeakMeter,
+    /// Knob ring brightness follows the volume of the dial's target. The
+    /// ring is a single LED slot, so the level is shown as brightness rather
+    /// than as a partly lit arc.
+    Level,
 }
 
 /// Configuration for a single physical control.
-#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ControlConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dial: Option<DialAction>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub button: Option<ButtonAction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub led_effect: Option<LedEffect>,
+    /// The control is mounted upside down: its physical travel is flipped
+    /// end for end before any signal processing.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reverse_travel: bool,
+    /// Piecewise-linear `(travel, volume)` mapping used for the dial instead
+    /// of `[signal] volume_exponent`. See [`PiecewiseCurve`].
+    ///
+    /// [`PiecewiseCurve`]: crate::audio::PiecewiseCurve
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub curve_points: Vec<(f64, f64)>,
+    /// Action run when the control is slammed to the same end of its travel
+    /// twice within `[signal] slam_window_ms`. Gives sliders, which have no
+    /// button, a button-like action.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slam_action: Option<ButtonAction>,
+    /// The control is inert: its dial, button, and slam action are ignored
+    /// and its LEDs stay off, whatever else is mapped. For a broken or
+    /// unused control.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disabled: bool,
 }
 
 #[cfg(test)]
@@ -274,36 +501,39 @@ mod tests {
 
     #[test]
     fn analog_id_round_trip() {
-        for id in 0..ControlId::NUM_ANALOG {
-            let control = ControlId::from_analog_id(id).unwrap();
-            assert_eq!(control.to_analog_id(), id);
+        for model in DeviceModel::ALL {
+            for id in 0..model.num_analog() {
+                let control = ControlId::from_analog_id(id, model).unwrap();
+                assert_eq!(control.to_analog_id(model), id);
+            }
         }
     }
 
     #[test]
     fn analog_id_out_of_range() {
-        assert_eq!(ControlId::from_analog_id(9), None);
-        assert_eq!(ControlId::from_analog_id(255), None);
+        assert_eq!(ControlId::from_analog_id(9, DeviceModel::Pro), None);
+        assert_eq!(ControlId::from_analog_id(255, DeviceModel::Pro), None);
+        assert_eq!(ControlId::from_analog_id(4, DeviceModel::Mini), None);
     }
 
     #[test]
     fn button_id_valid_range() {
         for id in 0..5 {
-            let control = ControlId::from_button_id(id).unwrap();
+            let control = ControlId::from_button_id(id, DeviceModel::Pro).unwrap();
             assert!(control.is_knob());
         }
     }
 
     #[test]
     fn button_id_out_of_range() {
-        assert_eq!(ControlId::from_button_id(5), None);
-        assert_eq!(ControlId::from_button_id(255), None);
+        assert_eq!(ControlId::from_button_id(5, DeviceModel::Pro), None);
+        assert_eq!(ControlId::from_button_id(255, DeviceModel::Pro), None);
+        assert_eq!(ControlId::from_button_id(4, DeviceModel::Mini), None);
     }
 
     #[test]
     fn config_key_round_trip() {
-        for id in 0..ControlId::NUM_ANALOG {
-            let control = ControlId::from_analog_id(id).unwrap();
+        for control in DeviceModel::Pro.controls() {
             let key = control.config_key();
             let parsed = ControlId::from_config_key(&key).unwrap();
             assert_eq!(control, parsed);
@@ -324,7 +554,7 @@ mod tests {
     #[test]
     fn knob_ids_are_0_to_4() {
         for id in 0..5u8 {
-            let control = ControlId::from_analog_id(id).unwrap();
+            let control = ControlId::from_analog_id(id, DeviceModel::Pro).unwrap();
             assert_eq!(control, ControlId::Knob(id));
             assert!(control.is_knob());
             assert!(!control.is_slider());
@@ -334,13 +564,22 @@ mod tests {
     #[test]
     fn slider_ids_are_5_to_8() {
         for id in 5..9u8 {
-            let control = ControlId::from_analog_id(id).unwrap();
+            let control = ControlId::from_analog_id(id, DeviceModel::Pro).unwrap();
             assert_eq!(control, ControlId::Slider(id - 5));
             assert!(control.is_slider());
             assert!(!control.is_knob());
         }
     }
 
+    #[test]
+    fn mini_has_four_knobs_and_no_sliders() {
+        let model = DeviceModel::Mini;
+        let controls: Vec<_> = model.controls().collect();
+        assert_eq!(controls, (0..4).map(ControlId::Knob).collect::<Vec<_>>());
+        assert!(!model.has_control(ControlId::Knob(4)));
+        assert!(!model.has_control(ControlId::Slider(0)));
+    }
+
     #[test]
     fn app_properties_from_sink_input_info() {
         use crate::audio::{SinkInputInfo, Volume};
@@ -350,6 +589,8 @@ mod tests {
             binary: Some("firefox".into()),
             flatpak_id: Some("org.mozilla.firefox".into()),
             pid: Some(1234),
+            cgroup: Some("app-flatpak-org.mozilla.firefox-1234.scope".into()),
+            media_name: Some("Playback".into()),
             sink_index: 0,
             volume: Volume::new(0.5),
             muted: false,
@@ -359,6 +600,11 @@ mod tests {
         assert_eq!(props.binary, Some("firefox".into()));
         assert_eq!(props.name, Some("Firefox".into()));
         assert_eq!(props.flatpak_id, Some("org.mozilla.firefox".into()));
+        assert_eq!(
+            props.cgroup,
+            Some("app-flatpak-org.mozilla.firefox-1234.scope".into())
+        );
+        assert_eq!(props.media_name, Some("Playback".into()));
     }
 
     #[test]
@@ -368,6 +614,8 @@ mod tests {
             binary: Some("firefox".into()),
             name: Some("Firefox".into()),
             flatpak_id: None,
+            cgroup: None,
+            media_name: None,
         };
         assert!(!matcher.matches(&props));
         assert!(!matcher.is_valid());
@@ -452,6 +700,54 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn cgroup_match_finds_wrapped_flatpak() {
+        let matcher = AppMatcher {
+            cgroup: Some("org.mozilla.firefox".into()),
+            ..Default::default()
+        };
+        assert!(matcher.is_valid());
+        assert!(matcher.matches(&AppProperties {
+            binary: Some("bwrap".into()),
+            cgroup: Some("app-flatpak-org.mozilla.firefox-41234.scope".into()),
+            ..Default::default()
+        }));
+        assert!(!matcher.matches(&AppProperties {
+            binary: Some("bwrap".into()),
+            cgroup: None,
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn media_name_match_picks_out_loopback() {
+        use crate::audio::{SinkInputInfo, Volume};
+        let matcher = AppMatcher {
+            media_name: Some("loopback".into()),
+            ..Default::default()
+        };
+        assert!(matcher.is_valid());
+        let loopback = SinkInputInfo {
+            index: 7,
+            name: "Loopback from Built-in Audio".into(),
+            binary: None,
+            flatpak_id: None,
+            pid: None,
+            cgroup: None,
+            media_name: Some("Loopback from Built-in Audio".into()),
+            sink_index: 0,
+            volume: Volume::new(1.0),
+            muted: false,
+            channels: 2,
+        };
+        assert!(matcher.matches(&AppProperties::from(&loopback)));
+        assert!(!matcher.matches(&AppProperties {
+            binary: Some("firefox".into()),
+            media_name: Some("Playback".into()),
+            ..Default::default()
+        }));
+    }
+
     #[test]
     fn name_match_case_insensitive_substring() {
         let matcher = AppMatcher {
@@ -462,11 +758,15 @@ mod tests {
             binary: Some("firefox".into()),
             name: Some("Firefox".into()),
             flatpak_id: None,
+            cgroup: None,
+            media_name: None,
         };
         let no = AppProperties {
             binary: Some("chrome".into()),
             name: Some("Chrome".into()),
             flatpak_id: None,
+            cgroup: None,
+            media_name: None,
         };
         assert!(matcher.matches(&yes));
         assert!(!matcher.matches(&no));
@@ -518,12 +818,54 @@ mod tests {
             button: Some(ButtonAction::Mute {
                 target: AudioTarget::DefaultOutput,
             }),
+            led_effect: None,
+            reverse_travel: false,
+            curve_points: Vec::new(),
+            slam_action: None,
+            disabled: false,
         };
         let json = serde_json::to_string(&config).unwrap();
         let parsed: ControlConfig = serde_json::from_str(&json).unwrap();
         assert_eq!(config, parsed);
     }
 
+    #[test]
+    fn reverse_travel_defaults_off_and_is_omitted() {
+        let config: ControlConfig = toml_edit::de::from_str("").unwrap();
+        assert!(!config.reverse_travel);
+        assert!(!toml_edit::ser::to_string(&config)
+            .unwrap()
+            .contains("reverse_travel"));
+
+        let config: ControlConfig = toml_edit::de::from_str("reverse_travel = true").unwrap();
+        assert!(config.reverse_travel);
+    }
+
+    #[test]
+    fn volume_chain_toml_round_trip() {
+        let toml_str = r#"
+        dial = { type = "volume_chain", targets = [{ type = "focused_app" }, { type = "default_output" }] }
+        "#;
+        let config: ControlConfig = toml_edit::de::from_str(toml_str).unwrap();
+        let dial = config.dial.as_ref().unwrap();
+        assert_eq!(
+            dial.targets(),
+            &[AudioTarget::FocusedApp, AudioTarget::DefaultOutput]
+        );
+
+        let serialized = toml_edit::ser::to_string(&config).unwrap();
+        let parsed: ControlConfig = toml_edit::de::from_str(&serialized).unwrap();
+        assert_eq!(config, parsed);
+    }
+
+    #[test]
+    fn single_volume_target_is_a_one_element_chain() {
+        let action = DialAction::Volume {
+            target: AudioTarget::DefaultInput,
+        };
+        assert_eq!(action.targets(), &[AudioTarget::DefaultInput]);
+    }
+
     #[test]
     fn button_action_media_json_round_trip() {
         let action = ButtonAction::Media {
@@ -538,12 +880,150 @@ mod tests {
     fn button_action_exec_json_round_trip() {
         let action = ButtonAction::Exec {
             command: "notify-send 'hello world'".into(),
+            repeat_ms: None,
         };
         let json = serde_json::to_string(&action).unwrap();
         let parsed: ButtonAction = serde_json::from_str(&json).unwrap();
         assert_eq!(action, parsed);
     }
 
+    #[test]
+    fn volume_step_parses_and_repeats_when_asked() {
+        let action: ButtonAction = toml_edit::de::from_str(
+            r#"
+            type = "volume_step"
+            target = { type = "default_output" }
+            delta = -0.05
+            repeat_ms = 120
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            action,
+            ButtonAction::VolumeStep {
+                target: AudioTarget::DefaultOutput,
+                delta: -0.05,
+                repeat_ms: Some(120),
+            }
+        );
+        assert_eq!(
+            action.repeat_interval(),
+            Some(std::time::Duration::from_millis(120))
+        );
+    }
+
+    #[test]
+    fn exec_repeat_is_opt_in_and_clamped() {
+        let parse = |json: &str| serde_json::from_str::<ButtonAction>(json).unwrap();
+        assert_eq!(
+            parse(r#"{"type": "exec", "command": "true"}"#).repeat_interval(),
+            None
+        );
+        assert_eq!(
+            parse(r#"{"type": "exec", "command": "true", "repeat_ms": 150}"#).repeat_interval(),
+            Some(std::time::Duration::from_millis(150))
+        );
+        assert_eq!(
+            parse(r#"{"type": "exec", "command": "true", "repeat_ms": 0}"#).repeat_interval(),
+            Some(std::time::Duration::from_millis(MIN_REPEAT_MS))
+        );
+        assert_eq!(ButtonAction::ToggleLeds.repeat_interval(), None);
+    }
+
+    #[test]
+    fn button_action_set_port_toml_round_trip() {
+        let toml_str = r#"
+        button = { type = "set_port", sink_name = "alsa_output.pci-0000_00_1f.3.analog-stereo", port = "analog-output-headphones" }
+        "#;
+        let config: ControlConfig = toml_edit::de::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.button,
+            Some(ButtonAction::SetPort {
+                sink_name: "alsa_output.pci-0000_00_1f.3.analog-stereo".into(),
+                port: "analog-output-headphones".into(),
+            })
+        );
+        let serialized = toml_edit::ser::to_string(&config).unwrap();
+        let parsed: ControlConfig = toml_edit::de::from_str(&serialized).unwrap();
+        assert_eq!(config, parsed);
+    }
+
+    #[test]
+    fn button_action_move_app_to_sink_toml_round_trip() {
+        let toml_str = r#"
+        button = { type = "move_app_to_sink", matcher = { binary = "firefox" }, sink_name = "alsa_output.usb-headset" }
+        "#;
+        let config: ControlConfig = toml_edit::de::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.button,
+            Some(ButtonAction::MoveAppToSink {
+                matcher: AppMatcher {
+                    binary: Some("firefox".into()),
+                    ..Default::default()
+                },
+                sink_name: "alsa_output.usb-headset".into(),
+            })
+        );
+        let serialized = toml_edit::ser::to_string(&config).unwrap();
+        let parsed: ControlConfig = toml_edit::de::from_str(&serialized).unwrap();
+        assert_eq!(config, parsed);
+    }
+
+    #[test]
+    fn button_action_toggle_outputs_toml_round_trip() {
+        let toml_str = r#"
+        button = { type = "toggle_outputs", a = "speakers", b = "headphones" }
+        "#;
+        let config: ControlConfig = toml_edit::de::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.button,
+            Some(ButtonAction::ToggleOutputs {
+                a: "speakers".into(),
+                b: "headphones".into(),
+            })
+        );
+        let serialized = toml_edit::ser::to_string(&config).unwrap();
+        let parsed: ControlConfig = toml_edit::de::from_str(&serialized).unwrap();
+        assert_eq!(config, parsed);
+    }
+
+    #[test]
+    fn button_action_toggle_sink_suspend_toml_round_trip() {
+        let toml_str = r#"
+        button = { type = "toggle_sink_suspend", sink_name = "alsa_output.usb-headset" }
+        "#;
+        let config: ControlConfig = toml_edit::de::from_str(toml_str).unwrap();
+        assert_eq!(
+            config.button,
+            Some(ButtonAction::ToggleSinkSuspend {
+                sink_name: "alsa_output.usb-headset".into(),
+            })
+        );
+        let serialized = toml_edit::ser::to_string(&config).unwrap();
+        let parsed: ControlConfig = toml_edit::de::from_str(&serialized).unwrap();
+        assert_eq!(config, parsed);
+    }
+
+    #[test]
+    fn button_action_mute_default_input_toml_round_trip() {
+        let config: ControlConfig =
+            toml_edit::de::from_str(r#"button = { type = "mute_default_input" }"#).unwrap();
+        assert_eq!(config.button, Some(ButtonAction::MuteDefaultInput));
+        let serialized = toml_edit::ser::to_string(&config).unwrap();
+        let parsed: ControlConfig = toml_edit::de::from_str(&serialized).unwrap();
+        assert_eq!(config, parsed);
+    }
+
+    #[test]
+    fn button_action_toggle_leds_toml_round_trip() {
+        let config: ControlConfig =
+            toml_edit::de::from_str(r#"button = { type = "toggle_leds" }"#).unwrap();
+        assert_eq!(config.button, Some(ButtonAction::ToggleLeds));
+        let serialized = toml_edit::ser::to_string(&config).unwrap();
+        let parsed: ControlConfig = toml_edit::de::from_str(&serialized).unwrap();
+        assert_eq!(config, parsed);
+    }
+
     #[test]
     fn media_command_all_variants_round_trip() {
         let variants = [
@@ -598,6 +1078,7 @@ mod tests {
         let w = Wrapper {
             button: ButtonAction::Exec {
                 command: "notify-send 'Button pressed!'".into(),
+                repeat_ms: None,
             },
         };
         let toml_str = toml_edit::ser::to_string(&w).unwrap();
@@ -1,24 +1,90 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-/// PCPanel Pro USB identifiers.
+use crate::control::DeviceModel;
+
+/// USB vendor ID shared by all PCPanel models.
 pub const VENDOR_ID: u16 = 0x0483;
-pub const PRODUCT_ID: u16 = 0xA3C5;
+/// PCPanel Pro USB product ID.
+pub const PRO_PRODUCT_ID: u16 = 0xA3C5;
+/// PCPanel Mini USB product ID.
+pub const MINI_PRODUCT_ID: u16 = 0xA3C4;
+
+/// Known PCPanel USB product IDs and the model each one identifies.
+pub const KNOWN_PRODUCTS: [(u16, DeviceModel); 2] = [
+    (PRO_PRODUCT_ID, DeviceModel::Pro),
+    (MINI_PRODUCT_ID, DeviceModel::Mini),
+];
+
+/// Map a USB product ID to the PCPanel model it identifies.
+#[must_use]
+pub fn model_for_product_id(product_id: u16) -> Option<DeviceModel> {
+    KNOWN_PRODUCTS
+        .iter()
+        .find(|(pid, _)| *pid == product_id)
+        .map(|(_, model)| *model)
+}
+
+/// Returns true if the USB vendor/product ID pair belongs to a known PCPanel.
+#[must_use]
+pub fn is_known_device(vendor_id: u16, product_id: u16) -> bool {
+    vendor_id == VENDOR_ID && model_for_product_id(product_id).is_some()
+}
+
+/// The udev rule that gives the logged-in user access to every known
+/// PCPanel's hidraw node, as installed to `/etc/udev/rules.d/70-pcpanel.rules`.
+#[must_use]
+pub fn udev_rule() -> String {
+    let mut product_ids: Vec<String> = KNOWN_PRODUCTS
+        .iter()
+        .map(|(pid, _)| format!("{pid:04x}"))
+        .collect();
+    product_ids.sort();
+    format!(
+        r#"KERNEL=="hidraw*", ATTRS{{idVendor}}=="{VENDOR_ID:04x}", ATTRS{{idProduct}}=="{}", MODE="0660", TAG+="uaccess""#,
+        product_ids.join("|")
+    )
+}
 
 /// HID report payload size (excluding Report ID byte).
 pub const REPORT_SIZE: usize = 64;
 
+/// Report ID of the feature report carrying the firmware version.
+pub const FIRMWARE_REPORT_ID: u8 = 0x00;
+
 #[derive(Error, Debug)]
 pub enum HidError {
     #[error("HID device I/O error: {0}")]
     Io(String),
-    #[error("HID device not found (VID={vid:#06x}, PID={pid:#06x})")]
-    DeviceNotFound { vid: u16, pid: u16 },
+    #[error("no known PCPanel device found (VID={vid:#06x})")]
+    DeviceNotFound { vid: u16 },
+    /// The device is present but its hidraw node isn't accessible, which
+    /// almost always means the udev rule is missing.
+    #[error("permission denied opening the PCPanel: {0}")]
+    PermissionDenied(String),
     #[error("invalid HID report: {0}")]
     InvalidReport(String),
     #[error("HID write failed: expected {expected} bytes, wrote {actual}")]
     ShortWrite { expected: usize, actual: usize },
 }
 
+impl HidError {
+    /// Classify the message of a failed device open: no access to the device
+    /// node becomes [`HidError::PermissionDenied`], anything else
+    /// [`HidError::Io`].
+    #[must_use]
+    pub fn from_open_error(message: String) -> Self {
+        // hidraw reports errno text; the libusb backend its error names.
+        const MARKERS: [&str; 3] = ["permission denied", "eacces", "libusb_error_access"];
+        let lower = message.to_lowercase();
+        if MARKERS.iter().any(|marker| lower.contains(marker)) {
+            HidError::PermissionDenied(message)
+        } else {
+            HidError::Io(message)
+        }
+    }
+}
+
 /// A parsed HID input event from the PCPanel Pro.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HidEvent {
@@ -84,7 +150,8 @@ impl HidEvent {
 }
 
 /// LED mode for a single element (knob ring or slider).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum LedMode {
     Off = 0,
@@ -94,7 +161,7 @@ pub enum LedMode {
 }
 
 /// LED configuration for a single element (7-byte slot).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LedSlot {
     pub mode: LedMode,
     pub r1: u8,
@@ -121,7 +188,7 @@ impl LedSlot {
     };
 
     #[must_use]
-    pub fn static_color(r: u8, g: u8, b: u8) -> Self {
+    pub const fn static_color(r: u8, g: u8, b: u8) -> Self {
         LedSlot {
             mode: LedMode::Static,
             r1: r,
@@ -133,6 +200,40 @@ impl LedSlot {
         }
     }
 
+    /// Scale both colors of this slot by `factor`, clamped to [0.0, 1.0].
+    ///
+    /// The mode is preserved, so a scaled-down static slot stays static (and
+    /// therefore visibly dark at 0.0 rather than "no change" to the firmware).
+    #[must_use]
+    pub fn scaled(self, factor: f64) -> Self {
+        let factor = factor.clamp(0.0, 1.0);
+        let scale = |c: u8| (f64::from(c) * factor).round() as u8;
+        LedSlot {
+            mode: self.mode,
+            r1: scale(self.r1),
+            g1: scale(self.g1),
+            b1: scale(self.b1),
+            r2: scale(self.r2),
+            g2: scale(self.g2),
+            b2: scale(self.b2),
+        }
+    }
+
+    /// Apply [`gamma_correct`] to both colors of this slot.
+    #[must_use]
+    pub fn gamma_corrected(self, gamma: f64) -> Self {
+        let correct = |c: u8| gamma_correct(c, gamma);
+        LedSlot {
+            mode: self.mode,
+            r1: correct(self.r1),
+            g1: correct(self.g1),
+            b1: correct(self.b1),
+            r2: correct(self.r2),
+            g2: correct(self.g2),
+            b2: correct(self.b2),
+        }
+    }
+
     fn encode_to(&self, buf: &mut [u8]) {
         debug_assert!(buf.len() >= 7);
         buf[0] = self.mode as u8;
@@ -165,7 +266,8 @@ impl LedSlot {
 }
 
 /// Logo LED mode.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum LogoMode {
     Off = 0,
@@ -265,6 +367,36 @@ impl HidCommand {
     }
 }
 
+/// Parse a firmware version feature report as returned by hidapi (Report ID
+/// byte first).
+///
+/// The version is a NUL-terminated ASCII string after the Report ID. Firmware
+/// without the query answers with zeros or binary data, which yields `None`.
+#[must_use]
+pub fn parse_firmware_version(report: &[u8]) -> Option<String> {
+    let payload = report.get(1..)?;
+    let end = payload
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(payload.len());
+    let version = std::str::from_utf8(&payload[..end]).ok()?.trim();
+    if version.is_empty() || !version.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return None;
+    }
+    Some(version.to_string())
+}
+
+/// Map a linear color channel through `gamma` so equal steps look equally
+/// bright. 1.0 leaves the channel unchanged; larger values darken midtones.
+/// Non-positive or non-finite values are treated as 1.0.
+#[must_use]
+pub fn gamma_correct(channel: u8, gamma: f64) -> u8 {
+    if gamma == 1.0 || gamma <= 0.0 || !gamma.is_finite() {
+        return channel;
+    }
+    ((f64::from(channel) / 255.0).powf(gamma) * 255.0).round() as u8
+}
+
 /// Decode a knob LED command payload back into LED slots.
 pub fn decode_knob_leds(payload: &[u8; REPORT_SIZE]) -> Option<[LedSlot; 5]> {
     if payload[0] != 0x05 || payload[1] != 0x02 {
@@ -293,6 +425,76 @@ pub fn decode_slider_leds(payload: &[u8; REPORT_SIZE]) -> Option<[LedSlot; 4]> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn open_errors_tell_permission_from_other_failures() {
+        let denied = HidError::from_open_error(
+            "Failed to open a device with path '/dev/hidraw3': Permission denied".into(),
+        );
+        assert!(matches!(denied, HidError::PermissionDenied(_)));
+        let libusb = HidError::from_open_error("hid_open_path: LIBUSB_ERROR_ACCESS".into());
+        assert!(matches!(libusb, HidError::PermissionDenied(_)));
+        let busy = HidError::from_open_error("Device or resource busy".into());
+        assert!(matches!(busy, HidError::Io(_)));
+    }
+
+    #[test]
+    fn udev_rule_matches_the_shipped_rule() {
+        let shipped = include_str!("../../../dist/70-pcpanel.rules");
+        assert!(
+            shipped.lines().any(|line| line == udev_rule()),
+            "{}",
+            udev_rule()
+        );
+    }
+
+    #[test]
+    fn gamma_correct_darkens_midtones() {
+        assert_eq!(gamma_correct(128, 2.2), 56);
+        assert!(gamma_correct(64, 2.2) < 64);
+        // Endpoints are fixed
+        assert_eq!(gamma_correct(0, 2.2), 0);
+        assert_eq!(gamma_correct(255, 2.2), 255);
+    }
+
+    #[test]
+    fn gamma_correct_identity_and_invalid_values() {
+        for gamma in [1.0, 0.0, -2.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(gamma_correct(128, gamma), 128, "gamma={gamma}");
+        }
+    }
+
+    #[test]
+    fn gamma_corrected_keeps_mode() {
+        let slot = LedSlot::static_color(128, 255, 0).gamma_corrected(2.2);
+        assert_eq!(slot, LedSlot::static_color(56, 255, 0));
+    }
+
+    #[test]
+    fn parse_firmware_version_reads_ascii() {
+        let mut report = [0u8; REPORT_SIZE + 1];
+        report[1..6].copy_from_slice(b"1.2.3");
+        assert_eq!(parse_firmware_version(&report).as_deref(), Some("1.2.3"));
+    }
+
+    #[test]
+    fn parse_firmware_version_rejects_unsupported_replies() {
+        assert_eq!(parse_firmware_version(&[]), None);
+        assert_eq!(parse_firmware_version(&[0u8; REPORT_SIZE + 1]), None);
+        assert_eq!(parse_firmware_version(&[0x00, 0x01, 0xFF, 0x80]), None);
+    }
+
+    #[test]
+    fn known_products_map_to_models() {
+        assert_eq!(model_for_product_id(PRO_PRODUCT_ID), Some(DeviceModel::Pro));
+        assert_eq!(
+            model_for_product_id(MINI_PRODUCT_ID),
+            Some(DeviceModel::Mini)
+        );
+        assert_eq!(model_for_product_id(0x0001), None);
+        assert!(is_known_device(VENDOR_ID, MINI_PRODUCT_ID));
+        assert!(!is_known_device(0x1234, PRO_PRODUCT_ID));
+    }
+
     #[test]
     fn parse_position_event_knob() {
         let report = [0x01, 0x02, 0x80, 0, 0]; // knob 2, value 128
@@ -483,6 +685,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn scaled_slot_is_bounded_and_monotonic() {
+        let base = LedSlot::static_color(0, 100, 255);
+        assert_eq!(base.scaled(1.0), base);
+        assert_eq!(base.scaled(0.0), LedSlot::OFF);
+        // Out-of-range factors clamp instead of overflowing or wrapping
+        assert_eq!(base.scaled(2.5), base);
+        assert_eq!(base.scaled(-1.0), LedSlot::OFF);
+
+        let mut prev = base.scaled(0.0);
+        for step in 1..=100 {
+            let slot = base.scaled(f64::from(step) / 100.0);
+            assert!(slot.b1 >= prev.b1 && slot.g1 >= prev.g1);
+            assert_eq!(slot.mode, LedMode::Static);
+            prev = slot;
+        }
+    }
+
     #[test]
     fn parse_all_valid_position_reports() {
         for control_id in 0..=8u8 {
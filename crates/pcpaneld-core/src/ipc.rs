@@ -2,11 +2,16 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::audio::{DeviceInfo, SinkInfo, SinkInputInfo, SourceInfo};
-use crate::control::{ButtonAction, ControlId, DialAction};
+use crate::control::{ButtonAction, ControlId, ControlPart, DeviceModel, DialAction};
+use crate::hid::{LedSlot, LogoMode};
 
 /// Maximum IPC message size (1 MB).
 pub const MAX_MESSAGE_SIZE: u32 = 1024 * 1024;
 
+/// Version of the request/response schema. Bump it whenever a change would
+/// make an older CLI or daemon mis-read the other side.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 #[derive(Error, Debug)]
 pub enum IpcError {
     #[error("IPC I/O error: {0}")]
@@ -23,9 +28,22 @@ pub enum IpcError {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum IpcRequest {
+    /// Sent first on a connection so both sides can check they speak the same
+    /// [`PROTOCOL_VERSION`]. Answered with [`IpcResponse::Hello`].
+    Hello {
+        client_version: u32,
+    },
     GetStatus,
+    /// Identity of the connected device. Answered with
+    /// [`IpcResponse::DeviceInfo`].
+    GetDeviceInfo,
     ListApps,
-    ListDevices,
+    /// Outputs and inputs. Monitors of outputs are left out unless
+    /// `monitors` is set.
+    ListDevices {
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        monitors: bool,
+    },
     /// Legacy: list output devices only
     #[serde(alias = "list_sinks")]
     ListOutputs,
@@ -40,12 +58,55 @@ pub enum IpcRequest {
         control: ControlId,
         action: ButtonAction,
     },
+    /// Remove a control's mapping. With `part`, only its dial or button is
+    /// cleared, and the control goes once neither is left.
     Unassign {
         control: ControlId,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        part: Option<ControlPart>,
     },
     GetConfig,
+    /// Replace the whole config with the given TOML document.
+    SetConfig {
+        toml: String,
+    },
     ReloadConfig,
+    /// Make an output device the default. `device` is an index, as listed by
+    /// `pcpaneld devices`, or a case-insensitive substring of its name or
+    /// description.
+    SetDefaultSink {
+        device: String,
+    },
+    /// Make an input device the default. `device` is matched like
+    /// [`IpcRequest::SetDefaultSink`].
+    SetDefaultSource {
+        device: String,
+    },
+    /// Peak levels of the default output and input. Meters on them open
+    /// with the first request and close a few seconds after the last, so
+    /// a client polls this to show live levels. Answered with
+    /// [`IpcResponse::Levels`].
+    GetLevels,
+    /// Resend the configured LEDs, dropping session-only changes such as a
+    /// `toggle_leds` press.
+    ResetLeds,
+    /// The LED colors last sent to the device. Answered with
+    /// [`IpcResponse::Leds`].
+    GetLeds,
+    /// What the daemon believes has focus, and which apps that matches.
+    /// Answered with [`IpcResponse::Focused`].
+    GetFocused,
+    /// Whether the config file differs from the running config. Answered
+    /// with [`IpcResponse::ConfigDirty`].
+    ConfigDirty,
     Shutdown,
+    /// Several requests handled in order on one connection. Answered with
+    /// [`IpcResponse::Batch`], one response per request. Assignments in the
+    /// batch are saved to disk once, after the last of them. Batches can't
+    /// be nested.
+    Batch {
+        requests: Vec<IpcRequest>,
+    },
 }
 
 /// Device connection status reported via IPC.
@@ -53,6 +114,53 @@ pub enum IpcRequest {
 pub struct DeviceStatus {
     pub connected: bool,
     pub serial: Option<String>,
+    /// Model of the connected device, `None` while disconnected.
+    #[serde(default)]
+    pub model: Option<DeviceModel>,
+    /// Why the device is disconnected, if not simply unplugged: e.g. it is
+    /// present but can't be opened, which usually means missing udev rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Identity of a connected PCPanel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareInfo {
+    pub model: DeviceModel,
+    /// USB serial number, if the device reports one.
+    pub serial: Option<String>,
+    /// Firmware version, if the device answers the version query.
+    pub firmware_version: Option<String>,
+}
+
+/// LEDs as last sent to the device. A zone is `None` until the daemon has
+/// written it since the device connected, and all are `None` while no device
+/// is connected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedState {
+    pub knobs: Option<[LedSlot; 5]>,
+    pub slider_labels: Option<[LedSlot; 4]>,
+    pub sliders: Option<[LedSlot; 4]>,
+    pub logo: Option<LogoLed>,
+}
+
+/// The logo LED's setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogoLed {
+    pub mode: LogoMode,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub speed: u8,
+}
+
+/// The focused window as reported by the compositor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FocusedWindow {
+    pub desktop_file: Option<String>,
+    pub resource_name: Option<String>,
+    pub resource_class: Option<String>,
+    pub pid: Option<u32>,
 }
 
 /// A single control mapping for status display.
@@ -63,18 +171,66 @@ pub struct MappingInfo {
     pub button: Option<String>,
 }
 
+/// Daemon runtime counters for status display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeStats {
+    /// Seconds since the daemon started.
+    pub uptime_secs: u64,
+    /// Knob/slider position changes and button events handled since start.
+    pub events_processed: u64,
+    /// PulseAudio reconnections after the first connection.
+    pub pulse_reconnects: u64,
+}
+
+/// Machine-readable category of an [`IpcResponse::Error`], so clients can
+/// react without parsing the message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request couldn't be parsed, e.g. an unknown request type.
+    InvalidRequest,
+    /// A config given with the request, or the config file, doesn't parse.
+    InvalidConfig,
+    /// Reading, writing, or serializing the config failed.
+    ConfigIo,
+    /// No audio device matches the request.
+    DeviceNotFound,
+    /// More than one audio device matches the request.
+    AmbiguousDevice,
+    /// The daemon is shutting down and dropped the request.
+    ShuttingDown,
+    /// The connection sent requests faster than the daemon accepts them.
+    RateLimited,
+    /// Not otherwise classified. Also assumed for errors from daemons that
+    /// predate error codes.
+    #[default]
+    Other,
+}
+
 /// Responses from the daemon to pcpaneld CLI.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum IpcResponse {
     Ok,
+    Hello {
+        protocol_version: u32,
+    },
     Error {
+        #[serde(default)]
+        code: ErrorCode,
         message: String,
     },
     Status {
         device: DeviceStatus,
         pulse_connected: bool,
         mappings: Vec<MappingInfo>,
+        /// Zero from daemons that predate runtime stats.
+        #[serde(default)]
+        stats: RuntimeStats,
+    },
+    /// `None` while no device is connected.
+    DeviceInfo {
+        device: Option<HardwareInfo>,
     },
     Apps {
         apps: Vec<SinkInputInfo>,
@@ -97,6 +253,34 @@ pub enum IpcResponse {
     Config {
         toml: String,
     },
+    /// Answer to [`IpcRequest::GetLevels`]: the latest peaks, 0.0-1.0.
+    /// `None` if there is no such device or its meter hasn't reported.
+    Levels {
+        output: Option<f32>,
+        input: Option<f32>,
+    },
+    /// Answer to [`IpcRequest::GetFocused`]. `tracking` is false while no
+    /// compositor backend reports focus changes; `matches` are the apps a
+    /// `focused_app` target would act on now.
+    Focused {
+        tracking: bool,
+        window: Option<FocusedWindow>,
+        matches: Vec<SinkInputInfo>,
+    },
+    /// Answer to [`IpcRequest::GetLeds`].
+    Leds {
+        leds: LedState,
+    },
+    /// Answer to [`IpcRequest::ConfigDirty`]. `changes` names the sections
+    /// and controls that differ, or why the file couldn't be read.
+    ConfigDirty {
+        dirty: bool,
+        changes: Vec<String>,
+    },
+    /// Answer to [`IpcRequest::Batch`], in request order.
+    Batch {
+        responses: Vec<IpcResponse>,
+    },
 }
 
 /// Encode a message with a 4-byte little-endian length prefix.
@@ -143,24 +327,107 @@ pub fn xdg_runtime_dir() -> std::path::PathBuf {
     std::path::PathBuf::from(dir)
 }
 
-/// Returns the default IPC socket path.
+/// Per-user stand-in for a missing or read-only runtime directory:
+/// `pcpaneld-{uid}` in the system temp directory.
+#[must_use]
+pub fn fallback_runtime_dir() -> std::path::PathBuf {
+    let uid = unsafe { libc::getuid() };
+    std::env::temp_dir().join(format!("pcpaneld-{uid}"))
+}
+
+/// Returns the runtime directory if we can create files in it, else
+/// [`fallback_runtime_dir`]. Daemon and CLI both resolve the socket through
+/// this, so they agree on where it is.
+#[must_use]
+pub fn usable_runtime_dir() -> std::path::PathBuf {
+    let dir = xdg_runtime_dir();
+    if is_writable_dir(&dir) {
+        dir
+    } else {
+        fallback_runtime_dir()
+    }
+}
+
+/// Whether `dir` is a directory we may create files in.
+#[must_use]
+pub fn is_writable_dir(dir: &std::path::Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    dir.is_dir() && unsafe { libc::access(c_path.as_ptr(), libc::W_OK | libc::X_OK) } == 0
+}
+
+/// Environment variable that overrides the IPC socket path.
+pub const SOCKET_ENV: &str = "PCPANELD_SOCKET";
+
+/// Returns the socket path set in `PCPANELD_SOCKET`, if any.
+#[must_use]
+pub fn env_socket_path() -> Option<std::path::PathBuf> {
+    non_empty_path(std::env::var_os(SOCKET_ENV))
+}
+
+/// Returns the default IPC socket path: `PCPANELD_SOCKET` if set, else
+/// `pcpaneld.sock` in the runtime directory.
 #[must_use]
 pub fn default_socket_path() -> std::path::PathBuf {
-    xdg_runtime_dir().join("pcpaneld.sock")
+    resolve_socket_path(std::env::var_os(SOCKET_ENV), &usable_runtime_dir())
+}
+
+fn resolve_socket_path(
+    env: Option<std::ffi::OsString>,
+    runtime_dir: &std::path::Path,
+) -> std::path::PathBuf {
+    non_empty_path(env).unwrap_or_else(|| runtime_dir.join("pcpaneld.sock"))
+}
+
+/// An empty variable counts as unset, like `PCPANELD_SOCKET= pcpaneld info`.
+fn non_empty_path(value: Option<std::ffi::OsString>) -> Option<std::path::PathBuf> {
+    value
+        .filter(|v| !v.is_empty())
+        .map(std::path::PathBuf::from)
+}
+
+/// Name of the abstract-namespace socket used with `[ipc] abstract = true`.
+pub const ABSTRACT_SOCKET_NAME: &str = "pcpaneld";
+
+/// Where the daemon listens for IPC connections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpcEndpoint {
+    /// A socket file, normally [`default_socket_path`].
+    Path(std::path::PathBuf),
+    /// A Linux abstract-namespace socket. It has no file, so there is nothing
+    /// to clean up after a crash.
+    Abstract(String),
+}
+
+impl std::fmt::Display for IpcEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpcEndpoint::Path(path) => write!(f, "{}", path.display()),
+            IpcEndpoint::Abstract(name) => write!(f, "@{name}"),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::audio::Volume;
+    use crate::audio::{PortInfo, Volume};
     use crate::control::{AppMatcher, AudioTarget, MediaCommand};
 
     #[test]
     fn request_serde_round_trip_all_variants() {
         let requests = vec![
+            IpcRequest::Hello {
+                client_version: PROTOCOL_VERSION,
+            },
             IpcRequest::GetStatus,
+            IpcRequest::GetDeviceInfo,
             IpcRequest::ListApps,
-            IpcRequest::ListDevices,
+            IpcRequest::ListDevices { monitors: false },
+            IpcRequest::ListDevices { monitors: true },
             IpcRequest::ListOutputs,
             IpcRequest::ListInputs,
             IpcRequest::AssignDial {
@@ -177,10 +444,36 @@ mod tests {
             },
             IpcRequest::Unassign {
                 control: ControlId::Slider(2),
+                part: None,
+            },
+            IpcRequest::Unassign {
+                control: ControlId::Knob(2),
+                part: Some(ControlPart::Button),
             },
+            IpcRequest::SetDefaultSink {
+                device: "headphones".into(),
+            },
+            IpcRequest::SetDefaultSource { device: "9".into() },
             IpcRequest::GetConfig,
+            IpcRequest::SetConfig {
+                toml: "[leds]\nknobs = false\n".into(),
+            },
             IpcRequest::ReloadConfig,
+            IpcRequest::GetLevels,
+            IpcRequest::ResetLeds,
+            IpcRequest::GetLeds,
+            IpcRequest::GetFocused,
+            IpcRequest::ConfigDirty,
             IpcRequest::Shutdown,
+            IpcRequest::Batch {
+                requests: vec![
+                    IpcRequest::Unassign {
+                        control: ControlId::Knob(1),
+                        part: None,
+                    },
+                    IpcRequest::GetConfig,
+                ],
+            },
             IpcRequest::AssignDial {
                 control: ControlId::Knob(2),
                 action: DialAction::Volume {
@@ -208,6 +501,14 @@ mod tests {
                 control: ControlId::Knob(4),
                 action: ButtonAction::Exec {
                     command: "notify-send 'hello'".into(),
+                    repeat_ms: None,
+                },
+            },
+            IpcRequest::AssignButton {
+                control: ControlId::Knob(0),
+                action: ButtonAction::SetPort {
+                    sink_name: "alsa_output.pci".into(),
+                    port: "analog-output-speaker".into(),
                 },
             },
         ];
@@ -220,17 +521,118 @@ mod tests {
         }
     }
 
+    #[test]
+    fn led_state_round_trips() {
+        let leds = LedState {
+            knobs: Some([LedSlot::static_color(1, 2, 3); 5]),
+            slider_labels: Some([LedSlot::OFF; 4]),
+            sliders: None,
+            logo: Some(LogoLed {
+                mode: LogoMode::Static,
+                r: 4,
+                g: 5,
+                b: 6,
+                speed: 0,
+            }),
+        };
+        let json = serde_json::to_string(&leds).unwrap();
+        assert!(json.contains(r#""mode":"static""#), "got: {json}");
+        assert_eq!(serde_json::from_str::<LedState>(&json).unwrap(), leds);
+    }
+
+    #[test]
+    fn list_devices_without_monitors_field_hides_monitors() {
+        match serde_json::from_str(r#"{"type":"list_devices"}"#).unwrap() {
+            IpcRequest::ListDevices { monitors } => assert!(!monitors),
+            other => panic!("expected ListDevices, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unassign_part_is_optional_on_the_wire() {
+        let json = r#"{"type":"unassign","control":{"Knob":2}}"#;
+        match serde_json::from_str(json).unwrap() {
+            IpcRequest::Unassign { control, part } => {
+                assert_eq!(control, ControlId::Knob(2));
+                assert_eq!(part, None);
+            }
+            other => panic!("expected Unassign, got {other:?}"),
+        }
+
+        let req = IpcRequest::Unassign {
+            control: ControlId::Knob(2),
+            part: Some(ControlPart::Dial),
+        };
+        assert_eq!(
+            serde_json::to_string(&req).unwrap(),
+            r#"{"type":"unassign","control":{"Knob":2},"part":"dial"}"#
+        );
+    }
+
+    #[test]
+    fn error_code_serializes_as_snake_case() {
+        let resp = IpcResponse::Error {
+            code: ErrorCode::InvalidConfig,
+            message: "bad".into(),
+        };
+        let json = serde_json::to_string(&resp).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"error","code":"invalid_config","message":"bad"}"#
+        );
+        match serde_json::from_str(&json).unwrap() {
+            IpcResponse::Error { code, message } => {
+                assert_eq!(code, ErrorCode::InvalidConfig);
+                assert_eq!(message, "bad");
+            }
+            other => panic!("expected Error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn error_without_code_defaults_to_other() {
+        let json = r#"{"type":"error","message":"from an older daemon"}"#;
+        match serde_json::from_str(json).unwrap() {
+            IpcResponse::Error { code, .. } => assert_eq!(code, ErrorCode::Other),
+            other => panic!("expected Error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn status_without_stats_defaults_to_zero() {
+        let json = r#"{"type":"status","device":{"connected":false,"serial":null},"pulse_connected":true,"mappings":[]}"#;
+        match serde_json::from_str(json).unwrap() {
+            IpcResponse::Status { stats, .. } => assert_eq!(stats, RuntimeStats::default()),
+            other => panic!("expected Status, got {other:?}"),
+        }
+    }
+
     #[test]
     fn response_serde_round_trip_all_variants() {
         let responses = vec![
             IpcResponse::Ok,
+            IpcResponse::Batch {
+                responses: vec![
+                    IpcResponse::Ok,
+                    IpcResponse::Error {
+                        code: ErrorCode::InvalidRequest,
+                        message: "batches can't be nested".into(),
+                    },
+                ],
+            },
+            IpcResponse::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            },
             IpcResponse::Error {
+                code: ErrorCode::DeviceNotFound,
                 message: "something went wrong".into(),
             },
             IpcResponse::Status {
                 device: DeviceStatus {
                     connected: true,
                     serial: Some("ABC123".into()),
+                    model: Some(DeviceModel::Mini),
+                    error: None,
                 },
                 pulse_connected: true,
                 mappings: vec![MappingInfo {
@@ -238,6 +640,59 @@ mod tests {
                     dial: Some("volume default-output".into()),
                     button: Some("mute default-output".into()),
                 }],
+                stats: RuntimeStats {
+                    uptime_secs: 3600,
+                    events_processed: 1234,
+                    pulse_reconnects: 2,
+                },
+            },
+            IpcResponse::DeviceInfo {
+                device: Some(HardwareInfo {
+                    model: DeviceModel::Pro,
+                    serial: Some("ABC123".into()),
+                    firmware_version: None,
+                }),
+            },
+            IpcResponse::DeviceInfo { device: None },
+            IpcResponse::Levels {
+                output: Some(0.5),
+                input: None,
+            },
+            IpcResponse::Focused {
+                tracking: true,
+                window: Some(FocusedWindow {
+                    desktop_file: Some("firefox".into()),
+                    resource_name: Some("firefox".into()),
+                    resource_class: None,
+                    pid: Some(1234),
+                }),
+                matches: vec![],
+            },
+            IpcResponse::Focused {
+                tracking: false,
+                window: None,
+                matches: vec![],
+            },
+            IpcResponse::Leds {
+                leds: LedState::default(),
+            },
+            IpcResponse::Leds {
+                leds: LedState {
+                    knobs: Some([LedSlot::static_color(255, 0, 0); 5]),
+                    slider_labels: None,
+                    sliders: Some([LedSlot::OFF; 4]),
+                    logo: Some(LogoLed {
+                        mode: LogoMode::Breathing,
+                        r: 0,
+                        g: 0,
+                        b: 255,
+                        speed: 10,
+                    }),
+                },
+            },
+            IpcResponse::ConfigDirty {
+                dirty: true,
+                changes: vec!["signal".into(), "controls.knob1".into()],
             },
             IpcResponse::Apps {
                 apps: vec![SinkInputInfo {
@@ -246,6 +701,8 @@ mod tests {
                     binary: Some("firefox".into()),
                     flatpak_id: Some("org.mozilla.firefox".into()),
                     pid: Some(1234),
+                    cgroup: None,
+                    media_name: None,
                     sink_index: 0,
                     volume: Volume::new(0.75),
                     muted: false,
@@ -260,6 +717,14 @@ mod tests {
                     volume: Volume::new(1.0),
                     muted: false,
                     channels: 2,
+                    monitor_source: Some(1),
+                    ports: vec![PortInfo {
+                        name: "analog-output-headphones".into(),
+                        description: "Headphones".into(),
+                        available: true,
+                    }],
+                    active_port: Some("analog-output-headphones".into()),
+                    suspended: false,
                 }],
             },
             IpcResponse::Inputs {
@@ -335,6 +800,8 @@ mod tests {
                         binary: Some("firefox".into()),
                         name: Some("Firefox".into()),
                         flatpak_id: None,
+                        cgroup: None,
+                        media_name: None,
                     },
                 },
             },
@@ -371,11 +838,23 @@ mod tests {
                     binary: Some("firefox".into()),
                     name: Some("Firefox".into()),
                     flatpak_id: None,
+                    cgroup: None,
+                    media_name: None,
                 }
             }
             .to_string(),
             "app(binary=firefox, name=Firefox)"
         );
+        assert_eq!(
+            AudioTarget::App {
+                matcher: AppMatcher {
+                    cgroup: Some("steam".into()),
+                    ..Default::default()
+                }
+            }
+            .to_string(),
+            "app(cgroup=steam)"
+        );
         assert_eq!(AudioTarget::FocusedApp.to_string(), "focused");
     }
 
@@ -388,6 +867,8 @@ mod tests {
                 binary: Some("müsik".into()),
                 flatpak_id: Some("org.example.müsik".into()),
                 pid: None,
+                cgroup: None,
+                media_name: None,
                 sink_index: 0,
                 volume: Volume::new(0.5),
                 muted: false,
@@ -400,6 +881,42 @@ mod tests {
         assert_eq!(json, json2);
     }
 
+    #[test]
+    fn socket_env_var_overrides_runtime_dir() {
+        let runtime_dir = std::path::Path::new("/run/user/1000");
+        assert_eq!(
+            resolve_socket_path(None, runtime_dir),
+            runtime_dir.join("pcpaneld.sock")
+        );
+        assert_eq!(
+            resolve_socket_path(Some("/tmp/panel-a.sock".into()), runtime_dir),
+            std::path::PathBuf::from("/tmp/panel-a.sock")
+        );
+        assert_eq!(
+            resolve_socket_path(Some("".into()), runtime_dir),
+            runtime_dir.join("pcpaneld.sock")
+        );
+    }
+
+    #[test]
+    fn writable_dir_requires_an_existing_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(is_writable_dir(dir.path()));
+        assert!(!is_writable_dir(&dir.path().join("missing")));
+
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        assert!(!is_writable_dir(&file));
+    }
+
+    #[test]
+    fn abstract_endpoint_displays_with_at_prefix() {
+        let endpoint = IpcEndpoint::Abstract(ABSTRACT_SOCKET_NAME.to_string());
+        assert_eq!(endpoint.to_string(), "@pcpaneld");
+        let endpoint = IpcEndpoint::Path("/run/user/1000/pcpaneld.sock".into());
+        assert_eq!(endpoint.to_string(), "/run/user/1000/pcpaneld.sock");
+    }
+
     #[test]
     fn socket_path_is_deterministic() {
         let path = default_socket_path();
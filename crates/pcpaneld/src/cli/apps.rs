@@ -1,26 +1,63 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use pcpaneld_core::audio::SinkInputInfo;
+use pcpaneld_core::config::Config;
+use pcpaneld_core::control::{AppProperties, AudioTarget, ButtonAction, DeviceModel};
 use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
 
-use super::{check_response, send_request, truncate};
+use super::{check_response, paint, printable, send_request, truncate, Style};
+use crate::AppSort;
 
-pub async fn run() -> Result<()> {
+pub async fn run(sort: Option<AppSort>, filter: Option<String>, mappings: bool) -> Result<()> {
+    let config = if mappings {
+        let resp = check_response(send_request(IpcRequest::GetConfig).await?)?;
+        let IpcResponse::Config { toml } = resp else {
+            anyhow::bail!("unexpected response");
+        };
+        Some(Config::from_toml(&toml).context("daemon sent an unparseable config")?)
+    } else {
+        None
+    };
     let resp = check_response(send_request(IpcRequest::ListApps).await?)?;
     match resp {
         IpcResponse::Apps { apps } => {
+            let apps = select_apps(apps, sort, filter.as_deref());
             if apps.is_empty() {
-                println!("No audio apps running.");
+                match filter {
+                    Some(filter) => println!("No audio apps match \"{filter}\"."),
+                    None => println!("No audio apps running."),
+                }
             } else {
                 println!(
-                    "{:<6} {:<30} {:<20} {:<30} {:<8} {:<8} {:<6}",
-                    "INDEX", "NAME", "BINARY", "FLATPAK ID", "PID", "VOLUME", "MUTED"
+                    "{:<6} {:<30} {:<20} {:<30} {:<8} {:<8} {:<6} {}SCOPE",
+                    "INDEX",
+                    "NAME",
+                    "BINARY",
+                    "FLATPAK ID",
+                    "PID",
+                    "VOLUME",
+                    "MUTED",
+                    if config.is_some() {
+                        format!("{:<20} ", "CONTROLS")
+                    } else {
+                        String::new()
+                    },
                 );
                 for app in &apps {
                     let pid_str = app
                         .pid
                         .map(|p| p.to_string())
                         .unwrap_or_else(|| "-".to_string());
-                    println!(
-                        "{:<6} {:<30} {:<20} {:<30} {:<8} {:<8.0}% {:<6}",
+                    let controls = config.as_ref().map_or_else(String::new, |config| {
+                        let names = mapped_controls(config, app);
+                        let names = if names.is_empty() {
+                            "-".to_string()
+                        } else {
+                            names.join(",")
+                        };
+                        format!("{:<20} ", truncate(&names, 19))
+                    });
+                    let row = format!(
+                        "{:<6} {:<30} {:<20} {:<30} {:<8} {:<8.0}% {:<6} {controls}{}",
                         app.index,
                         truncate(&app.name, 29),
                         truncate(app.binary.as_deref().unwrap_or("-"), 19),
@@ -28,7 +65,13 @@ pub async fn run() -> Result<()> {
                         pid_str,
                         app.volume.get() * 100.0,
                         if app.muted { "yes" } else { "no" },
+                        printable(app.cgroup.as_deref().unwrap_or("-")),
                     );
+                    if app.muted {
+                        println!("{}", paint(&row, Style::Dim));
+                    } else {
+                        println!("{row}");
+                    }
                 }
             }
         }
@@ -36,3 +79,148 @@ pub async fn run() -> Result<()> {
     }
     Ok(())
 }
+
+/// Controls with an `app` target matching `app`, by dial, mute button, or
+/// volume step button. Disabled controls don't act on it, so they're left out.
+/// Focused-app targets aren't listed: which app has focus is up to the
+/// desktop, not the config.
+fn mapped_controls(config: &Config, app: &SinkInputInfo) -> Vec<String> {
+    if config.matching.is_ignored(app) {
+        return Vec::new();
+    }
+    let props = AppProperties::from(app);
+    let matches = |target: &AudioTarget| match target {
+        AudioTarget::App { matcher } => matcher.matches(&props),
+        _ => false,
+    };
+    DeviceModel::Pro
+        .controls()
+        .filter(|id| {
+            config.get_control(*id).is_some_and(|control| {
+                if control.disabled {
+                    return false;
+                }
+                let dial = control
+                    .dial
+                    .as_ref()
+                    .is_some_and(|dial| dial.targets().iter().any(matches));
+                let button = match &control.button {
+                    Some(
+                        ButtonAction::Mute { target } | ButtonAction::VolumeStep { target, .. },
+                    ) => matches(target),
+                    _ => false,
+                };
+                dial || button
+            })
+        })
+        .map(|id| id.config_key())
+        .collect()
+}
+
+/// Keep the apps matching `filter`, in `sort` order or the daemon's order.
+fn select_apps(
+    mut apps: Vec<SinkInputInfo>,
+    sort: Option<AppSort>,
+    filter: Option<&str>,
+) -> Vec<SinkInputInfo> {
+    if let Some(filter) = filter {
+        let filter = filter.to_lowercase();
+        apps.retain(|app| {
+            [
+                Some(app.name.as_str()),
+                app.binary.as_deref(),
+                app.flatpak_id.as_deref(),
+                app.cgroup.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|field| field.to_lowercase().contains(&filter))
+        });
+    }
+    match sort {
+        None => {}
+        Some(AppSort::Name) => apps.sort_by_key(|app| app.name.to_lowercase()),
+        Some(AppSort::Volume) => {
+            apps.sort_by(|a, b| b.volume.get().total_cmp(&a.volume.get()));
+        }
+        Some(AppSort::Index) => apps.sort_by_key(|app| app.index),
+    }
+    apps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcpaneld_core::audio::Volume;
+
+    fn app(index: u32, name: &str, binary: &str, volume: f64) -> SinkInputInfo {
+        SinkInputInfo {
+            index,
+            name: name.into(),
+            binary: Some(binary.into()),
+            flatpak_id: None,
+            pid: None,
+            cgroup: None,
+            media_name: None,
+            sink_index: 0,
+            volume: Volume::new(volume),
+            muted: false,
+            channels: 2,
+        }
+    }
+
+    fn indices(apps: &[SinkInputInfo]) -> Vec<u32> {
+        apps.iter().map(|a| a.index).collect()
+    }
+
+    #[test]
+    fn select_apps_keeps_daemon_order_by_default() {
+        let apps = vec![app(7, "mpv", "mpv", 0.5), app(3, "Firefox", "firefox", 0.9)];
+        assert_eq!(indices(&select_apps(apps, None, None)), [7, 3]);
+    }
+
+    #[test]
+    fn select_apps_sorts() {
+        let apps = vec![
+            app(7, "mpv", "mpv", 0.5),
+            app(3, "Firefox", "firefox", 0.9),
+            app(5, "discord", "Discord", 0.2),
+        ];
+        let sorted = |sort| indices(&select_apps(apps.clone(), Some(sort), None));
+        assert_eq!(sorted(AppSort::Name), [5, 3, 7]);
+        assert_eq!(sorted(AppSort::Volume), [3, 7, 5]);
+        assert_eq!(sorted(AppSort::Index), [3, 5, 7]);
+    }
+
+    #[test]
+    fn mapped_controls_lists_dials_and_mute_buttons() {
+        let config = Config::from_toml(
+            r#"
+            [controls.knob1]
+            dial = { type = "volume", target = { type = "app", matcher = { binary = "firefox" } } }
+            [controls.knob3]
+            button = { type = "mute", target = { type = "app", matcher = { binary = "firefox" } } }
+            [controls.knob4]
+            dial = { type = "volume", target = { type = "app", matcher = { binary = "firefox" } } }
+            disabled = true
+            [controls.slider2]
+            dial = { type = "volume", target = { type = "app", matcher = { binary = "spotify" } } }
+            "#,
+        )
+        .unwrap();
+        let firefox = app(3, "Firefox", "firefox", 0.9);
+        assert_eq!(mapped_controls(&config, &firefox), ["knob1", "knob3"]);
+        assert!(mapped_controls(&config, &app(7, "mpv", "mpv", 0.5)).is_empty());
+    }
+
+    #[test]
+    fn select_apps_filters_case_insensitively() {
+        let apps = vec![
+            app(7, "mpv", "mpv", 0.5),
+            app(3, "Firefox", "firefox", 0.9),
+            app(5, "WEBRTC VoiceEngine", "Discord", 0.2),
+        ];
+        assert_eq!(indices(&select_apps(apps.clone(), None, Some("FIRE"))), [3]);
+        assert_eq!(indices(&select_apps(apps, None, Some("discord"))), [5]);
+    }
+}
@@ -1,98 +1,327 @@
+use std::io::{BufRead, Write};
+
 use anyhow::{Context, Result};
+use pcpaneld_core::audio::{DeviceType, SinkInputInfo};
+use pcpaneld_core::config::Config;
 use pcpaneld_core::control::{
-    AppMatcher, AudioTarget, ButtonAction, ControlId, DialAction, MediaCommand,
+    AppMatcher, AudioTarget, ButtonAction, ControlId, ControlPart, DeviceModel, DialAction,
+    MediaCommand,
 };
-use pcpaneld_core::ipc::IpcRequest;
+use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
+
+use super::{check_response, printable, send_request};
+
+/// What `assign` maps, before it is tied to a control.
+enum Mapping {
+    Dial(DialAction),
+    Button(ButtonAction),
+}
+
+impl Mapping {
+    fn request(&self, control: ControlId) -> IpcRequest {
+        match self {
+            Mapping::Dial(action) => IpcRequest::AssignDial {
+                control,
+                action: action.clone(),
+            },
+            Mapping::Button(action) => IpcRequest::AssignButton {
+                control,
+                action: action.clone(),
+            },
+        }
+    }
 
-use super::{check_response, send_request};
+    /// Sliders have no button, so only dial mappings fit them.
+    fn check(&self, control: ControlId) -> Result<()> {
+        if matches!(self, Mapping::Button(_)) && control.is_slider() {
+            anyhow::bail!(
+                "{} is a slider and has no button; button actions only go on knobs",
+                control.config_key()
+            );
+        }
+        Ok(())
+    }
+}
 
+/// Assign one action to each of `controls`. Several controls are sent as
+/// one batch, and each is reported on its own. With `dry_run`, only print
+/// the resulting config change.
 pub async fn run_assign(
-    control: String,
+    controls: Vec<String>,
     action: String,
     value: String,
-    binary: Option<String>,
-    name: Option<String>,
-    flatpak_id: Option<String>,
+    matcher: AppMatcher,
+    sink: Option<String>,
+    dry_run: bool,
 ) -> Result<()> {
-    let control_id = ControlId::from_config_key(&control)
-        .with_context(|| format!("invalid control name: {control}"))?;
+    let control_ids = controls
+        .iter()
+        .map(|control| {
+            ControlId::from_config_key(control)
+                .with_context(|| format!("invalid control name: {control}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    let has_audio_flags = binary.is_some() || name.is_some() || flatpak_id.is_some();
+    let has_audio_flags = matcher.is_valid();
 
-    let request = match action.as_str() {
+    let mapping = match action.as_str() {
         "volume" => {
-            let audio_target = parse_target(&value, binary, name, flatpak_id)?;
-            IpcRequest::AssignDial {
-                control: control_id,
-                action: DialAction::Volume {
-                    target: audio_target,
-                },
-            }
+            let audio_target = parse_target(&value, matcher)?;
+            Mapping::Dial(DialAction::Volume {
+                target: audio_target,
+            })
         }
         "mute" => {
-            let audio_target = parse_target(&value, binary, name, flatpak_id)?;
-            IpcRequest::AssignButton {
-                control: control_id,
-                action: ButtonAction::Mute {
-                    target: audio_target,
-                },
-            }
+            let audio_target = parse_target(&value, matcher)?;
+            Mapping::Button(ButtonAction::Mute {
+                target: audio_target,
+            })
+        }
+        "volume-step" => {
+            let delta = parse_step(&value)?;
+            let target = if has_audio_flags {
+                AudioTarget::App { matcher }
+            } else {
+                AudioTarget::DefaultOutput
+            };
+            Mapping::Button(ButtonAction::VolumeStep {
+                target,
+                delta,
+                repeat_ms: None,
+            })
         }
         "media" => {
-            if has_audio_flags {
-                anyhow::bail!(
-                    "--binary, --name, and --flatpak-id are only valid for volume/mute actions"
-                );
-            }
+            reject_audio_flags(has_audio_flags)?;
             let command = parse_media_command(&value)?;
-            IpcRequest::AssignButton {
-                control: control_id,
-                action: ButtonAction::Media { command },
-            }
+            Mapping::Button(ButtonAction::Media { command })
         }
         "exec" => {
-            if has_audio_flags {
+            reject_audio_flags(has_audio_flags)?;
+            Mapping::Button(ButtonAction::Exec {
+                command: value.clone(),
+                repeat_ms: None,
+            })
+        }
+        "port" => {
+            reject_audio_flags(has_audio_flags)?;
+            let sink_name = sink
+                .context("port action requires --sink <name> (see `pcpaneld ports` for names)")?;
+            Mapping::Button(ButtonAction::SetPort {
+                sink_name,
+                port: value.clone(),
+            })
+        }
+        "move" => {
+            if !has_audio_flags {
                 anyhow::bail!(
-                    "--binary, --name, and --flatpak-id are only valid for volume/mute actions"
+                    "move action requires at least one of --binary, --name, --flatpak-id, --cgroup, or --media-name"
                 );
             }
-            IpcRequest::AssignButton {
-                control: control_id,
-                action: ButtonAction::Exec {
-                    command: value.clone(),
-                },
-            }
+            Mapping::Button(ButtonAction::MoveAppToSink {
+                matcher,
+                sink_name: value.clone(),
+            })
         }
         _ => anyhow::bail!(
-            "unknown action: {action} (expected 'volume', 'mute', 'media', or 'exec')"
+            "unknown action: {action} (expected 'volume', 'mute', 'volume-step', 'media', 'exec', 'port', or 'move')"
         ),
     };
+    for &control_id in &control_ids {
+        mapping.check(control_id)?;
+    }
+    if dry_run {
+        let requests = control_ids.iter().map(|&id| mapping.request(id)).collect();
+        return preview(requests).await;
+    }
 
-    check_response(send_request(request).await?)?;
-    println!("Assigned {control} {action} -> {value}");
+    if let [control_id] = control_ids[..] {
+        check_response(send_request(mapping.request(control_id)).await?)?;
+        println!("Assigned {} {action} -> {value}", controls[0]);
+        return Ok(());
+    }
+
+    let requests = control_ids.iter().map(|&id| mapping.request(id)).collect();
+    let resp = check_response(send_request(IpcRequest::Batch { requests }).await?)?;
+    let IpcResponse::Batch { responses } = resp else {
+        anyhow::bail!("unexpected response");
+    };
+    let mut failed = 0;
+    for (control, resp) in controls.iter().zip(&responses) {
+        match resp {
+            IpcResponse::Error { message, .. } => {
+                failed += 1;
+                println!("{control}: {message}");
+            }
+            _ => println!("Assigned {control} {action} -> {value}"),
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{failed} of {} assignments failed", controls.len());
+    }
     Ok(())
 }
 
-pub async fn run_unassign(control: String) -> Result<()> {
+/// Make `control`'s dial set the volume of `target` and its button mute it,
+/// sent as one batch.
+pub async fn run_assign_knob(control: String, target: String, matcher: AppMatcher) -> Result<()> {
     let control_id = ControlId::from_config_key(&control)
         .with_context(|| format!("invalid control name: {control}"))?;
+    let requests = knob_requests(control_id, parse_target(&target, matcher)?)?;
 
-    check_response(
-        send_request(IpcRequest::Unassign {
-            control: control_id,
+    let resp = check_response(send_request(IpcRequest::Batch { requests }).await?)?;
+    let IpcResponse::Batch { responses } = resp else {
+        anyhow::bail!("unexpected response");
+    };
+    let errors: Vec<&str> = responses
+        .iter()
+        .filter_map(|resp| match resp {
+            IpcResponse::Error { message, .. } => Some(message.as_str()),
+            _ => None,
         })
-        .await?,
-    )?;
-    println!("Unassigned {control}");
+        .collect();
+    if !errors.is_empty() {
+        anyhow::bail!("failed to assign {control}: {}", errors.join("; "));
+    }
+    println!("Assigned {control} volume and mute -> {target}");
     Ok(())
 }
 
-fn parse_target(
-    target: &str,
-    binary: Option<String>,
-    name: Option<String>,
-    flatpak_id: Option<String>,
-) -> Result<AudioTarget> {
+/// The dial volume and button mute requests `assign-knob` sends.
+fn knob_requests(control: ControlId, target: AudioTarget) -> Result<Vec<IpcRequest>> {
+    let mappings = [
+        Mapping::Dial(DialAction::Volume {
+            target: target.clone(),
+        }),
+        Mapping::Button(ButtonAction::Mute { target }),
+    ];
+    mappings
+        .iter()
+        .map(|mapping| {
+            mapping.check(control)?;
+            Ok(mapping.request(control))
+        })
+        .collect()
+}
+
+pub async fn run_unassign(control: String, part: Option<ControlPart>, dry_run: bool) -> Result<()> {
+    let control_id = ControlId::from_config_key(&control)
+        .with_context(|| format!("invalid control name: {control}"))?;
+    let request = IpcRequest::Unassign {
+        control: control_id,
+        part,
+    };
+    if dry_run {
+        return preview(vec![request]).await;
+    }
+
+    check_response(send_request(request).await?)?;
+    match part {
+        None => println!("Unassigned {control}"),
+        Some(ControlPart::Dial) => println!("Unassigned {control} dial"),
+        Some(ControlPart::Button) => println!("Unassigned {control} button"),
+    }
+    Ok(())
+}
+
+/// Print how `requests` would change the daemon's config, without sending
+/// them.
+async fn preview(requests: Vec<IpcRequest>) -> Result<()> {
+    let resp = check_response(send_request(IpcRequest::GetConfig).await?)?;
+    let IpcResponse::Config { toml } = resp else {
+        anyhow::bail!("unexpected response");
+    };
+    let mut config = Config::from_toml(&toml).context("daemon sent an unparseable config")?;
+    let before = config.to_toml().context("failed to serialize config")?;
+    for request in &requests {
+        config.apply_mapping_change(request);
+    }
+    let after = config.to_toml().context("failed to serialize config")?;
+
+    let diff = toml_diff(&before, &after);
+    if diff.is_empty() {
+        println!("Dry run: the config would not change");
+    } else {
+        println!("Dry run: the config would change like this:");
+        print!("{diff}");
+    }
+    Ok(())
+}
+
+/// How `new` differs from `old`, table by table: changed lines marked `-`
+/// and `+` under their `[table]` header, and whole tables that were added or
+/// removed.
+fn toml_diff(old: &str, new: &str) -> String {
+    let old = tables(old);
+    let new = tables(new);
+    let mut out = String::new();
+    let mut push = |mark: char, line: &str| out.push_str(&format!("{mark} {line}\n"));
+    for (header, lines) in &old {
+        let Some(new_lines) = table_lines(&new, header) else {
+            push('-', header);
+            lines.iter().for_each(|line| push('-', line));
+            continue;
+        };
+        let removed: Vec<&str> = lines
+            .iter()
+            .copied()
+            .filter(|line| !new_lines.contains(line))
+            .collect();
+        let added: Vec<&str> = new_lines
+            .iter()
+            .copied()
+            .filter(|line| !lines.contains(line))
+            .collect();
+        if removed.is_empty() && added.is_empty() {
+            continue;
+        }
+        if !header.is_empty() {
+            push(' ', header);
+        }
+        removed.iter().for_each(|line| push('-', line));
+        added.iter().for_each(|line| push('+', line));
+    }
+    for (header, lines) in &new {
+        if table_lines(&old, header).is_none() {
+            push('+', header);
+            lines.iter().for_each(|line| push('+', line));
+        }
+    }
+    out
+}
+
+/// A TOML table's header line (empty for the top level) and the non-blank
+/// lines under it.
+type Table<'a> = (&'a str, Vec<&'a str>);
+
+fn tables(toml: &str) -> Vec<Table<'_>> {
+    let mut tables = vec![("", Vec::new())];
+    for line in toml.lines().filter(|line| !line.trim().is_empty()) {
+        match tables.last_mut() {
+            Some((_, lines)) if !line.starts_with('[') => lines.push(line),
+            _ => tables.push((line, Vec::new())),
+        }
+    }
+    tables
+}
+
+fn table_lines<'a>(tables: &'a [Table<'a>], header: &str) -> Option<&'a [&'a str]> {
+    tables
+        .iter()
+        .find(|(h, _)| *h == header)
+        .map(|(_, lines)| lines.as_slice())
+}
+
+/// Fail if app-matching flags were given to an action without an app target.
+fn reject_audio_flags(has_audio_flags: bool) -> Result<()> {
+    if has_audio_flags {
+        anyhow::bail!(
+            "--binary, --name, --flatpak-id, --cgroup, and --media-name are only valid for volume/mute/volume-step/move actions"
+        );
+    }
+    Ok(())
+}
+
+fn parse_target(target: &str, matcher: AppMatcher) -> Result<AudioTarget> {
     match target {
         "default-output" => Ok(AudioTarget::DefaultOutput),
         "default-input" => Ok(AudioTarget::DefaultInput),
@@ -100,14 +329,9 @@ fn parse_target(
         "default-sink" => Ok(AudioTarget::DefaultOutput),
         "default-source" => Ok(AudioTarget::DefaultInput),
         "app" => {
-            let matcher = AppMatcher {
-                binary,
-                name,
-                flatpak_id,
-            };
             if !matcher.is_valid() {
                 anyhow::bail!(
-                    "app target requires at least one of --binary, --name, or --flatpak-id"
+                    "app target requires at least one of --binary, --name, --flatpak-id, --cgroup, or --media-name"
                 );
             }
             Ok(AudioTarget::App { matcher })
@@ -119,16 +343,495 @@ fn parse_target(
     }
 }
 
+/// A volume step: a fraction of full volume, e.g. 0.05 or -0.05.
+fn parse_step(value: &str) -> Result<f64> {
+    let delta: f64 = value
+        .parse()
+        .with_context(|| format!("invalid volume step: {value} (expected e.g. 0.05 or -0.05)"))?;
+    if delta == 0.0 || !(-1.0..=1.0).contains(&delta) {
+        anyhow::bail!("volume step must be between -1.0 and 1.0 and not 0, got {value}");
+    }
+    Ok(delta)
+}
+
+const MEDIA_COMMANDS: [(&str, MediaCommand); 6] = [
+    ("play_pause", MediaCommand::PlayPause),
+    ("play", MediaCommand::Play),
+    ("pause", MediaCommand::Pause),
+    ("next", MediaCommand::Next),
+    ("previous", MediaCommand::Previous),
+    ("stop", MediaCommand::Stop),
+];
+
 fn parse_media_command(value: &str) -> Result<MediaCommand> {
-    match value {
-        "play_pause" => Ok(MediaCommand::PlayPause),
-        "play" => Ok(MediaCommand::Play),
-        "pause" => Ok(MediaCommand::Pause),
-        "next" => Ok(MediaCommand::Next),
-        "previous" => Ok(MediaCommand::Previous),
-        "stop" => Ok(MediaCommand::Stop),
-        _ => anyhow::bail!(
-            "unknown media command: {value} (expected 'play_pause', 'play', 'pause', 'next', 'previous', or 'stop')"
-        ),
+    MEDIA_COMMANDS
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, command)| *command)
+        .with_context(|| {
+            format!(
+                "unknown media command: {value} (expected 'play_pause', 'play', 'pause', 'next', 'previous', or 'stop')"
+            )
+        })
+}
+
+/// Walk the user through an assignment with numbered menus, filled from the
+/// daemon's live app and device lists.
+pub async fn run_interactive() -> Result<()> {
+    let mut prompt = Prompt {
+        input: std::io::stdin().lock(),
+        output: std::io::stdout(),
+    };
+
+    // The daemon may not have a device yet, so offer the largest layout.
+    let controls: Vec<ControlId> = DeviceModel::Pro.controls().collect();
+    let labels: Vec<String> = controls
+        .iter()
+        .map(|c| {
+            if c.is_knob() {
+                format!("{} (turn and press)", c.config_key())
+            } else {
+                c.config_key()
+            }
+        })
+        .collect();
+    let control = controls[prompt.choose("Which control?", &labels)?];
+
+    let actions: &[(&str, &str)] = if control.is_knob() {
+        &[
+            ("volume", "turn to set volume"),
+            ("mute", "press to toggle mute"),
+            ("volume-step", "press to step the volume up or down"),
+            ("media", "press to control the media player"),
+            ("exec", "press to run a command"),
+            ("port", "press to switch an output's port"),
+            ("move", "press to move an app to another output"),
+        ]
+    } else {
+        &[("volume", "slide to set volume")]
+    };
+    let action = if actions.len() == 1 {
+        actions[0].0
+    } else {
+        let labels: Vec<String> = actions
+            .iter()
+            .map(|(name, help)| format!("{name} -- {help}"))
+            .collect();
+        actions[prompt.choose("Which action?", &labels)?].0
+    };
+
+    let request = match action {
+        "volume" => IpcRequest::AssignDial {
+            control,
+            action: DialAction::Volume {
+                target: choose_target(&mut prompt).await?,
+            },
+        },
+        "mute" => IpcRequest::AssignButton {
+            control,
+            action: ButtonAction::Mute {
+                target: choose_target(&mut prompt).await?,
+            },
+        },
+        "volume-step" => {
+            let delta = parse_step(&prompt.line("Step, e.g. 0.05 for up or -0.05 for down: ")?)?;
+            IpcRequest::AssignButton {
+                control,
+                action: ButtonAction::VolumeStep {
+                    target: choose_target(&mut prompt).await?,
+                    delta,
+                    repeat_ms: None,
+                },
+            }
+        }
+        "media" => {
+            let labels: Vec<String> = MEDIA_COMMANDS
+                .iter()
+                .map(|(name, _)| (*name).to_owned())
+                .collect();
+            let (_, command) = MEDIA_COMMANDS[prompt.choose("Which media command?", &labels)?];
+            IpcRequest::AssignButton {
+                control,
+                action: ButtonAction::Media { command },
+            }
+        }
+        "exec" => IpcRequest::AssignButton {
+            control,
+            action: ButtonAction::Exec {
+                command: prompt.line("Command to run: ")?,
+                repeat_ms: None,
+            },
+        },
+        "port" => {
+            let IpcResponse::Outputs { outputs } =
+                check_response(send_request(IpcRequest::ListOutputs).await?)?
+            else {
+                anyhow::bail!("unexpected response");
+            };
+            let ports: Vec<(String, String, String)> = outputs
+                .iter()
+                .flat_map(|sink| {
+                    sink.ports.iter().map(|port| {
+                        (
+                            format!("{}: {}", sink.description, port.description),
+                            sink.name.clone(),
+                            port.name.clone(),
+                        )
+                    })
+                })
+                .collect();
+            if ports.is_empty() {
+                anyhow::bail!("no output device has ports to switch between");
+            }
+            let labels: Vec<String> = ports.iter().map(|(label, _, _)| label.clone()).collect();
+            let (_, sink_name, port) = ports[prompt.choose("Which port?", &labels)?].clone();
+            IpcRequest::AssignButton {
+                control,
+                action: ButtonAction::SetPort { sink_name, port },
+            }
+        }
+        "move" => {
+            let apps = list_app_choices().await?;
+            if apps.is_empty() {
+                anyhow::bail!("no audio apps are playing; start the app and try again");
+            }
+            let labels: Vec<String> = apps.iter().map(|(label, _)| label.clone()).collect();
+            let matcher = apps[prompt.choose("Which app?", &labels)?].1.clone();
+
+            let IpcResponse::Devices { devices } =
+                check_response(send_request(IpcRequest::ListDevices { monitors: false }).await?)?
+            else {
+                anyhow::bail!("unexpected response");
+            };
+            let outputs: Vec<_> = devices
+                .into_iter()
+                .filter(|d| d.device_type == DeviceType::Output)
+                .collect();
+            if outputs.is_empty() {
+                anyhow::bail!("no output devices found");
+            }
+            let labels: Vec<String> = outputs.iter().map(|d| d.description.clone()).collect();
+            let sink_name = outputs[prompt.choose("Move it to which output?", &labels)?]
+                .name
+                .clone();
+            IpcRequest::AssignButton {
+                control,
+                action: ButtonAction::MoveAppToSink { matcher, sink_name },
+            }
+        }
+        _ => unreachable!("action comes from the menu"),
+    };
+
+    check_response(send_request(request).await?)?;
+    println!("Assigned {} {action}", control.config_key());
+    Ok(())
+}
+
+/// Offer the fixed targets plus every app currently playing audio.
+async fn choose_target<R: BufRead, W: Write>(prompt: &mut Prompt<R, W>) -> Result<AudioTarget> {
+    let apps = list_app_choices().await?;
+    let mut labels = vec![
+        "default output".to_owned(),
+        "default input".to_owned(),
+        "focused app (KDE Plasma)".to_owned(),
+    ];
+    labels.extend(apps.iter().map(|(label, _)| label.clone()));
+    if apps.is_empty() {
+        writeln!(
+            prompt.output,
+            "(No apps are playing audio; start one and rerun to pick it.)"
+        )?;
+    }
+    Ok(match prompt.choose("Which target?", &labels)? {
+        0 => AudioTarget::DefaultOutput,
+        1 => AudioTarget::DefaultInput,
+        2 => AudioTarget::FocusedApp,
+        n => AudioTarget::App {
+            matcher: apps[n - 3].1.clone(),
+        },
+    })
+}
+
+async fn list_app_choices() -> Result<Vec<(String, AppMatcher)>> {
+    let IpcResponse::Apps { apps } = check_response(send_request(IpcRequest::ListApps).await?)?
+    else {
+        anyhow::bail!("unexpected response");
+    };
+    Ok(app_choices(&apps))
+}
+
+/// One menu entry per distinct app, with the matcher `assign` would build
+/// for it: the Flatpak ID if there is one, else the binary, else the name.
+/// Apps with several streams appear once.
+fn app_choices(apps: &[SinkInputInfo]) -> Vec<(String, AppMatcher)> {
+    let mut choices: Vec<(String, AppMatcher)> = Vec::new();
+    for app in apps {
+        let (label, matcher) = if let Some(flatpak_id) = &app.flatpak_id {
+            (
+                format!("{} (flatpak {flatpak_id})", app.name),
+                AppMatcher {
+                    flatpak_id: Some(flatpak_id.clone()),
+                    ..Default::default()
+                },
+            )
+        } else if let Some(binary) = &app.binary {
+            (
+                format!("{} ({binary})", app.name),
+                AppMatcher {
+                    binary: Some(binary.clone()),
+                    ..Default::default()
+                },
+            )
+        } else {
+            (
+                app.name.clone(),
+                AppMatcher {
+                    name: Some(app.name.clone()),
+                    ..Default::default()
+                },
+            )
+        };
+        if !choices.iter().any(|(_, m)| *m == matcher) {
+            choices.push((label, matcher));
+        }
+    }
+    choices
+}
+
+/// Numbered-menu prompts over any reader/writer, so answers can be fed in
+/// tests without a terminal.
+struct Prompt<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    /// Show `options` as a numbered menu and return the index picked.
+    /// Asks again until the answer is one of the listed numbers. Options
+    /// are escaped, since app and device names come from PulseAudio.
+    fn choose(&mut self, title: &str, options: &[String]) -> Result<usize> {
+        writeln!(self.output, "{title}")?;
+        for (i, option) in options.iter().enumerate() {
+            writeln!(self.output, "  {}) {}", i + 1, printable(option))?;
+        }
+        loop {
+            match self.line("> ")?.parse::<usize>() {
+                Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+                _ => writeln!(self.output, "Enter a number from 1 to {}.", options.len())?,
+            }
+        }
+    }
+
+    /// Read the next non-empty line, trimmed. End of input aborts.
+    fn line(&mut self, prompt: &str) -> Result<String> {
+        loop {
+            write!(self.output, "{prompt}")?;
+            self.output.flush()?;
+            let mut buf = String::new();
+            if self.input.read_line(&mut buf)? == 0 {
+                anyhow::bail!("aborted");
+            }
+            let answer = buf.trim();
+            if !answer.is_empty() {
+                return Ok(answer.to_owned());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcpaneld_core::audio::Volume;
+
+    fn prompt(input: &str) -> Prompt<&[u8], Vec<u8>> {
+        Prompt {
+            input: input.as_bytes(),
+            output: Vec::new(),
+        }
+    }
+
+    fn app(name: &str, binary: Option<&str>, flatpak_id: Option<&str>) -> SinkInputInfo {
+        SinkInputInfo {
+            index: 0,
+            name: name.into(),
+            binary: binary.map(String::from),
+            flatpak_id: flatpak_id.map(String::from),
+            pid: None,
+            cgroup: None,
+            media_name: None,
+            sink_index: 0,
+            volume: Volume::new(0.5),
+            muted: false,
+            channels: 2,
+        }
+    }
+
+    #[test]
+    fn choose_reasks_until_valid_number() {
+        let options = vec!["a".to_owned(), "b".to_owned()];
+        let mut p = prompt(
+            "x
+
+0
+3
+2
+",
+        );
+        assert_eq!(p.choose("Pick", &options).unwrap(), 1);
+        let shown = String::from_utf8(p.output).unwrap();
+        assert!(shown.contains(
+            "  1) a
+  2) b
+"
+        ));
+        assert_eq!(shown.matches("Enter a number from 1 to 2.").count(), 3);
+    }
+
+    #[test]
+    fn choose_escapes_options() {
+        let options = vec!["evil\n  2) app".to_owned()];
+        let mut p = prompt("1\n");
+        assert_eq!(p.choose("Which app?", &options).unwrap(), 0);
+        let shown = String::from_utf8(p.output).unwrap();
+        assert!(shown.contains("  1) evil\\n  2) app\n"), "{shown:?}");
+    }
+
+    #[test]
+    fn mapping_request_targets_each_control() {
+        let mapping = Mapping::Button(ButtonAction::Mute {
+            target: AudioTarget::DefaultOutput,
+        });
+        for id in [ControlId::Knob(0), ControlId::Knob(4)] {
+            match mapping.request(id) {
+                IpcRequest::AssignButton { control, action } => {
+                    assert_eq!(control, id);
+                    assert_eq!(
+                        action,
+                        ButtonAction::Mute {
+                            target: AudioTarget::DefaultOutput
+                        }
+                    );
+                }
+                other => panic!("expected AssignButton, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn button_mapping_is_rejected_for_sliders() {
+        let button = Mapping::Button(ButtonAction::Media {
+            command: MediaCommand::PlayPause,
+        });
+        assert!(button.check(ControlId::Knob(0)).is_ok());
+        let err = button.check(ControlId::Slider(0)).unwrap_err();
+        assert!(err.to_string().contains("slider1"), "{err}");
+
+        let dial = Mapping::Dial(DialAction::Volume {
+            target: AudioTarget::DefaultOutput,
+        });
+        assert!(dial.check(ControlId::Slider(0)).is_ok());
+    }
+
+    #[test]
+    fn knob_requests_pair_volume_and_mute() {
+        let requests = knob_requests(ControlId::Knob(1), AudioTarget::DefaultInput).unwrap();
+        assert!(
+            matches!(
+                &requests[..],
+                [
+                    IpcRequest::AssignDial {
+                        control: ControlId::Knob(1),
+                        action: DialAction::Volume {
+                            target: AudioTarget::DefaultInput
+                        },
+                    },
+                    IpcRequest::AssignButton {
+                        control: ControlId::Knob(1),
+                        action: ButtonAction::Mute {
+                            target: AudioTarget::DefaultInput
+                        },
+                    },
+                ]
+            ),
+            "{requests:?}"
+        );
+
+        let err = knob_requests(ControlId::Slider(0), AudioTarget::DefaultOutput).unwrap_err();
+        assert!(err.to_string().contains("slider1"), "{err}");
+    }
+
+    #[test]
+    fn toml_diff_shows_changes_by_table() {
+        let old = "[signal]\nvolume_exponent = 1.0\n\n[controls.knob1]\ndial.type = \"volume\"\nbutton.type = \"mute\"\n";
+        let new = "[signal]\nvolume_exponent = 1.0\n\n[controls.knob1]\ndial.type = \"volume\"\n\n[controls.knob2]\nbutton.type = \"mute\"\n";
+        assert_eq!(
+            toml_diff(old, new),
+            "  [controls.knob1]\n- button.type = \"mute\"\n+ [controls.knob2]\n+ button.type = \"mute\"\n"
+        );
+        assert_eq!(
+            toml_diff(new, old),
+            "  [controls.knob1]\n+ button.type = \"mute\"\n- [controls.knob2]\n- button.type = \"mute\"\n"
+        );
+        assert_eq!(toml_diff(old, old), "");
+    }
+
+    #[test]
+    fn parse_step_accepts_nonzero_fractions() {
+        assert_eq!(parse_step("0.05").unwrap(), 0.05);
+        assert_eq!(parse_step("-0.1").unwrap(), -0.1);
+        for bad in ["0", "1.5", "-2", "loud", "NaN"] {
+            assert!(parse_step(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn choose_aborts_on_end_of_input() {
+        let options = vec!["a".to_owned()];
+        assert!(prompt("").choose("Pick", &options).is_err());
+    }
+
+    #[test]
+    fn line_skips_blank_lines_and_trims() {
+        assert_eq!(
+            prompt(
+                "
+  notify-send hi  
+"
+            )
+            .line("> ")
+            .unwrap(),
+            "notify-send hi"
+        );
+    }
+
+    #[test]
+    fn app_choices_prefer_flatpak_then_binary_then_name_and_dedupe() {
+        let apps = [
+            app("Firefox", Some("firefox"), Some("org.mozilla.firefox")),
+            app("Firefox", Some("firefox"), Some("org.mozilla.firefox")),
+            app("Spotify", Some("spotify"), None),
+            app("Game", None, None),
+        ];
+        let choices = app_choices(&apps);
+        let matchers: Vec<&AppMatcher> = choices.iter().map(|(_, m)| m).collect();
+        assert_eq!(
+            matchers,
+            [
+                &AppMatcher {
+                    flatpak_id: Some("org.mozilla.firefox".into()),
+                    ..Default::default()
+                },
+                &AppMatcher {
+                    binary: Some("spotify".into()),
+                    ..Default::default()
+                },
+                &AppMatcher {
+                    name: Some("Game".into()),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(choices[0].0, "Firefox (flatpak org.mozilla.firefox)");
     }
 }
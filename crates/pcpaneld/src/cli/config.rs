@@ -1,11 +1,14 @@
-use anyhow::Result;
-use pcpaneld_core::config::Config;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use pcpaneld_core::config::{self, Config};
 use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
 
 use super::{check_response, send_request};
 use crate::ConfigCommands;
 
-pub async fn run(command: ConfigCommands) -> Result<()> {
+/// `config_path` is the file given with `--config`, else the default one.
+pub async fn run(command: ConfigCommands, config_path: Option<PathBuf>) -> Result<()> {
     match command {
         ConfigCommands::Show => {
             let resp = check_response(send_request(IpcRequest::GetConfig).await?)?;
@@ -19,9 +22,29 @@ pub async fn run(command: ConfigCommands) -> Result<()> {
             println!("Config reloaded.");
         }
         ConfigCommands::Dir => {
-            let dir = Config::default_dir().expect("failed to resolve XDG config directory");
+            let path = config_path.context("failed to resolve XDG config directory")?;
+            let path = std::path::absolute(&path)?;
+            let dir = path.parent().unwrap_or(&path);
             println!("{}", dir.display());
         }
+        ConfigCommands::Validate { path } => {
+            let path = match path {
+                Some(path) => path,
+                None => config_path.context("failed to resolve XDG config directory")?,
+            };
+            let config = Config::load(&path)?;
+            let mut warnings = config.validate();
+            let text = std::fs::read_to_string(&path).unwrap_or_default();
+            warnings.extend(config::unknown_keys(&text));
+            for warning in &warnings {
+                println!("warning: {warning}");
+            }
+            if warnings.is_empty() {
+                println!("{} is valid.", path.display());
+            } else {
+                println!("{}: {} warning(s)", path.display(), warnings.len());
+            }
+        }
     }
     Ok(())
 }
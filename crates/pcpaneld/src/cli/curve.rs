@@ -0,0 +1,101 @@
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+use pcpaneld_core::audio::{PiecewiseCurve, Volume};
+use pcpaneld_core::config::Config;
+use pcpaneld_core::control::ControlId;
+use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
+
+use super::{check_response, send_request};
+
+/// Hardware values shown in the preview.
+const SAMPLES: [u8; 9] = [0, 32, 64, 96, 128, 160, 192, 224, 255];
+
+/// Width of the bar for full volume.
+const BAR_WIDTH: usize = 40;
+
+/// Print the daemon's active volume mapping, for `control` if given.
+pub async fn run(control: Option<String>) -> Result<()> {
+    let resp = check_response(send_request(IpcRequest::GetConfig).await?)?;
+    let IpcResponse::Config { toml } = resp else {
+        anyhow::bail!("unexpected response");
+    };
+    let config = Config::from_toml(&toml).context("daemon sent an unparseable config")?;
+
+    let points = match &control {
+        Some(name) => {
+            let id = ControlId::from_config_key(name)
+                .with_context(|| format!("unknown control: {name}"))?;
+            config
+                .get_control(id)
+                .map(|c| c.curve_points.as_slice())
+                .unwrap_or_default()
+        }
+        None => &[],
+    };
+    match PiecewiseCurve::new(points) {
+        Ok(curve) => {
+            println!("Curve: {} curve points", points.len());
+            print_steps(&config);
+            print!(
+                "{}",
+                render_curve(|hw| config.signal.quantize(curve.hw_to_volume(hw)))
+            );
+            return Ok(());
+        }
+        Err(e) if !points.is_empty() => println!("curve_points ignored: {e}"),
+        Err(_) => {}
+    }
+
+    let curve = config.signal.volume_curve();
+    match curve.exponent() {
+        Some(exponent) => println!("Curve: power, volume_exponent = {exponent}"),
+        None => println!(
+            "Curve: lookup table, {} entries",
+            config.signal.curve_table.len()
+        ),
+    }
+    print_steps(&config);
+    print!(
+        "{}",
+        render_curve(|hw| config.signal.quantize(curve.hw_to_volume(hw)))
+    );
+    Ok(())
+}
+
+fn print_steps(config: &Config) {
+    if let Some(steps @ 1..) = config.signal.volume_steps {
+        println!("Snapped to {steps} steps (volume_steps)");
+    }
+}
+
+/// One row per sample: hardware value, volume percentage, and a bar.
+fn render_curve(hw_to_volume: impl Fn(u8) -> Volume) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{:>8}  {:>6}", "Hardware", "Volume");
+    for hw in SAMPLES {
+        let volume = hw_to_volume(hw).get();
+        let bar = "#".repeat((volume * BAR_WIDTH as f64).round() as usize);
+        let _ = writeln!(out, "{hw:>8}  {:>5.1}%  {bar}", volume * 100.0);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcpaneld_core::audio::VolumeCurve;
+
+    #[test]
+    fn render_curve_shows_percentages_and_bars() {
+        let out = render_curve(|hw| VolumeCurve::new(1.0).hw_to_volume(hw));
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), SAMPLES.len() + 1);
+        assert_eq!(lines[1], "       0    0.0%  ");
+        assert_eq!(
+            lines[9],
+            format!("     255  100.0%  {}", "#".repeat(BAR_WIDTH))
+        );
+        assert!(lines[5].starts_with("     128   50.2%  "));
+    }
+}
@@ -1,10 +1,11 @@
 use anyhow::Result;
 use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
 
-use super::{check_response, send_request, truncate};
+use super::{check_response, paint, send_request, truncate, Style};
+use crate::SetDefaultCommands;
 
-pub async fn run() -> Result<()> {
-    let resp = check_response(send_request(IpcRequest::ListDevices).await?)?;
+pub async fn run(monitors: bool) -> Result<()> {
+    let resp = check_response(send_request(IpcRequest::ListDevices { monitors }).await?)?;
     match resp {
         IpcResponse::Devices { devices } => {
             if devices.is_empty() {
@@ -19,7 +20,7 @@ pub async fn run() -> Result<()> {
                         pcpaneld_core::audio::DeviceType::Output => "output",
                         pcpaneld_core::audio::DeviceType::Input => "input",
                     };
-                    println!(
+                    let row = format!(
                         "{:<7} {:<6} {:<40} {:<8.0}% {:<6}",
                         type_str,
                         dev.index,
@@ -27,6 +28,11 @@ pub async fn run() -> Result<()> {
                         dev.volume.get() * 100.0,
                         if dev.muted { "yes" } else { "no" },
                     );
+                    if dev.muted {
+                        println!("{}", paint(&row, Style::Dim));
+                    } else {
+                        println!("{row}");
+                    }
                 }
             }
         }
@@ -34,3 +40,25 @@ pub async fn run() -> Result<()> {
     }
     Ok(())
 }
+
+pub async fn run_set_default(command: SetDefaultCommands) -> Result<()> {
+    let (request, kind, device) = match command {
+        SetDefaultCommands::Output { device } => (
+            IpcRequest::SetDefaultSink {
+                device: device.clone(),
+            },
+            "output",
+            device,
+        ),
+        SetDefaultCommands::Input { device } => (
+            IpcRequest::SetDefaultSource {
+                device: device.clone(),
+            },
+            "input",
+            device,
+        ),
+    };
+    check_response(send_request(request).await?)?;
+    println!("Default {kind} set to {device}");
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use pcpaneld_core::config::Config;
+use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
+
+use super::{check_response, send_request};
+
+pub async fn run_export(path: PathBuf) -> Result<()> {
+    let resp = check_response(send_request(IpcRequest::GetConfig).await?)?;
+    let IpcResponse::Config { toml } = resp else {
+        anyhow::bail!("unexpected response");
+    };
+    std::fs::write(&path, toml).with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Config exported to {}", path.display());
+    Ok(())
+}
+
+pub async fn run_import(path: PathBuf) -> Result<()> {
+    let toml = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    // Catch mistakes locally so the error names the file; the daemon
+    // validates again before replacing anything.
+    Config::from_toml(&toml)
+        .with_context(|| format!("{} is not a valid config", path.display()))?;
+    check_response(send_request(IpcRequest::SetConfig { toml }).await?)?;
+    println!("Config imported from {}", path.display());
+    Ok(())
+}
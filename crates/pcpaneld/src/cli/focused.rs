@@ -0,0 +1,53 @@
+use anyhow::Result;
+use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
+
+use super::{check_response, printable, send_request};
+
+pub async fn run() -> Result<()> {
+    let resp = check_response(send_request(IpcRequest::GetFocused).await?)?;
+    let IpcResponse::Focused {
+        tracking,
+        window,
+        matches,
+    } = resp
+    else {
+        anyhow::bail!("unexpected response");
+    };
+
+    if !tracking {
+        println!("Focused window tracking is not active (it needs KDE Plasma's KWin).");
+        println!("focused_app targets have nothing to match.");
+        return Ok(());
+    }
+    let Some(window) = window else {
+        println!("No focused window reported yet.");
+        return Ok(());
+    };
+
+    let field = |value: &Option<String>| value.as_deref().unwrap_or("-").to_string();
+    println!("Focused window:");
+    println!("  Desktop file:   {}", field(&window.desktop_file));
+    println!("  Resource name:  {}", field(&window.resource_name));
+    println!("  Resource class: {}", field(&window.resource_class));
+    println!(
+        "  PID:            {}",
+        window
+            .pid
+            .map_or_else(|| "-".to_string(), |p| p.to_string())
+    );
+
+    if matches.is_empty() {
+        println!("No audio apps match it. Compare with `pcpaneld apps`.");
+    } else {
+        println!("Matching audio apps:");
+        for app in &matches {
+            println!(
+                "  {} {} ({})",
+                app.index,
+                printable(&app.name),
+                printable(app.binary.as_deref().unwrap_or("-"))
+            );
+        }
+    }
+    Ok(())
+}
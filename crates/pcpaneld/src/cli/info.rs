@@ -1,7 +1,9 @@
 use anyhow::Result;
-use pcpaneld_core::ipc::{DeviceStatus, IpcRequest, IpcResponse, MappingInfo};
+use pcpaneld_core::ipc::{
+    DeviceStatus, HardwareInfo, IpcRequest, IpcResponse, MappingInfo, RuntimeStats,
+};
 
-use super::{check_response, send_request};
+use super::{check_response, paint, send_request, Style};
 
 pub async fn run() -> Result<()> {
     let resp = check_response(send_request(IpcRequest::GetStatus).await?)?;
@@ -10,34 +12,108 @@ pub async fn run() -> Result<()> {
             device,
             pulse_connected,
             mappings,
+            stats,
         } => {
-            print_status(&device, pulse_connected, &mappings);
+            let hardware = if device.connected {
+                hardware_info().await
+            } else {
+                None
+            };
+            let config = config_state().await;
+            print_status(
+                &device,
+                hardware.as_ref(),
+                pulse_connected,
+                config.as_deref(),
+                &mappings,
+                &stats,
+            );
         }
         _ => anyhow::bail!("unexpected response"),
     }
     Ok(())
 }
 
-fn print_status(device: &DeviceStatus, pulse_connected: bool, mappings: &[MappingInfo]) {
+/// Best-effort device identity; `info` still prints without it.
+async fn hardware_info() -> Option<HardwareInfo> {
+    match check_response(send_request(IpcRequest::GetDeviceInfo).await.ok()?).ok()? {
+        IpcResponse::DeviceInfo { device } => device,
+        _ => None,
+    }
+}
+
+/// Whether the config file matches the running config, for daemons that
+/// can answer.
+async fn config_state() -> Option<String> {
+    match check_response(send_request(IpcRequest::ConfigDirty).await.ok()?).ok()? {
+        IpcResponse::ConfigDirty { dirty, changes } => {
+            let line = config_state_line(dirty, &changes);
+            Some(if dirty {
+                paint(&line, Style::Yellow)
+            } else {
+                line
+            })
+        }
+        _ => None,
+    }
+}
+
+fn config_state_line(dirty: bool, changes: &[String]) -> String {
+    if !dirty {
+        "Config: in sync".to_string()
+    } else if changes.is_empty() {
+        "Config: modified on disk".to_string()
+    } else {
+        format!("Config: modified on disk ({})", changes.join(", "))
+    }
+}
+
+fn print_status(
+    device: &DeviceStatus,
+    hardware: Option<&HardwareInfo>,
+    pulse_connected: bool,
+    config: Option<&str>,
+    mappings: &[MappingInfo],
+    stats: &RuntimeStats,
+) {
     println!("Device:");
     if device.connected {
         println!(
-            "  Connected (serial: {})",
+            "  {}: {} (serial: {})",
+            paint("Connected", Style::Green),
+            device.model.unwrap_or_default(),
             device.serial.as_deref().unwrap_or("unknown")
         );
+        println!(
+            "  Firmware: {}",
+            hardware
+                .and_then(|h| h.firmware_version.as_deref())
+                .unwrap_or("unknown")
+        );
+    } else if let Some(error) = &device.error {
+        println!("  {}: {error}", paint("Device error", Style::Red));
     } else {
-        println!("  Disconnected");
+        println!("  {}", paint("Disconnected", Style::Red));
     }
 
     println!(
         "PulseAudio: {}",
         if pulse_connected {
-            "connected"
+            paint("connected", Style::Green)
         } else {
-            "disconnected"
+            paint("disconnected", Style::Red)
         }
     );
 
+    if let Some(config) = config {
+        println!("{config}");
+    }
+
+    println!("Stats:");
+    println!("  Uptime: {}", format_uptime(stats.uptime_secs));
+    println!("  Events processed: {}", stats.events_processed);
+    println!("  PulseAudio reconnects: {}", stats.pulse_reconnects);
+
     if mappings.is_empty() {
         println!("Mappings: none");
     } else {
@@ -49,3 +125,43 @@ fn print_status(device: &DeviceStatus, pulse_connected: bool, mappings: &[Mappin
         }
     }
 }
+
+/// Render seconds as e.g. `2d 3h 4m 5s`, omitting leading zero units.
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, mins, secs) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{days}d {hours}h {mins}m {secs}s")
+    } else if hours > 0 {
+        format!("{hours}h {mins}m {secs}s")
+    } else if mins > 0 {
+        format!("{mins}m {secs}s")
+    } else {
+        format!("{secs}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_uptime_omits_leading_zero_units() {
+        assert_eq!(format_uptime(0), "0s");
+        assert_eq!(format_uptime(59), "59s");
+        assert_eq!(format_uptime(61), "1m 1s");
+        assert_eq!(format_uptime(3600), "1h 0m 0s");
+        assert_eq!(
+            format_uptime(2 * 86400 + 3 * 3600 + 4 * 60 + 5),
+            "2d 3h 4m 5s"
+        );
+    }
+
+    #[test]
+    fn config_state_line_lists_changes() {
+        assert_eq!(config_state_line(false, &[]), "Config: in sync");
+        assert_eq!(
+            config_state_line(true, &["signal".into(), "controls.knob1".into()]),
+            "Config: modified on disk (signal, controls.knob1)"
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+use pcpaneld_core::ipc::IpcRequest;
+
+use super::{check_response, send_request};
+use crate::LedsCommands;
+
+pub async fn run(command: LedsCommands) -> Result<()> {
+    match command {
+        LedsCommands::Reset => {
+            check_response(send_request(IpcRequest::ResetLeds).await?)?;
+            println!("LEDs reset.");
+        }
+    }
+    Ok(())
+}
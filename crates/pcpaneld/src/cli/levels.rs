@@ -0,0 +1,89 @@
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::Result;
+use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
+
+use super::{check_response, send_request};
+
+/// Time between `--meter` polls. Well inside how long the daemon keeps the
+/// meters open after a request.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Width of a level bar, in characters.
+const BAR_WIDTH: usize = 20;
+
+/// Peak levels of the default output and input, 0.0-1.0.
+type Levels = (Option<f32>, Option<f32>);
+
+pub async fn run(meter: bool) -> Result<()> {
+    if !meter {
+        println!("{}", render_levels(get_levels().await?));
+        return Ok(());
+    }
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                print!("\r{}", render_levels(get_levels().await?));
+                std::io::stdout().flush()?;
+            }
+            _ = &mut ctrl_c => {
+                println!();
+                return Ok(());
+            }
+        }
+    }
+}
+
+async fn get_levels() -> Result<Levels> {
+    match check_response(send_request(IpcRequest::GetLevels).await?)? {
+        IpcResponse::Levels { output, input } => Ok((output, input)),
+        _ => anyhow::bail!("unexpected response"),
+    }
+}
+
+/// Both levels on one line, so `--meter` can redraw it in place.
+fn render_levels((output, input): Levels) -> String {
+    format!("Output {}   Input {}", bar(output), bar(input))
+}
+
+/// A fixed-width bar and percentage, or a blank bar and `-` without a
+/// reading.
+fn bar(level: Option<f32>) -> String {
+    let Some(level) = level else {
+        return format!("[{}]    -", " ".repeat(BAR_WIDTH));
+    };
+    let level = level.clamp(0.0, 1.0);
+    let filled = (level * BAR_WIDTH as f32).round() as usize;
+    format!(
+        "[{}{}] {:>3.0}%",
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        level * 100.0
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_levels_draws_fixed_width_bars() {
+        let line = render_levels((Some(0.5), None));
+        assert_eq!(
+            line,
+            format!(
+                "Output [{}{}]  50%   Input [{}]    -",
+                "#".repeat(10),
+                " ".repeat(10),
+                " ".repeat(BAR_WIDTH)
+            )
+        );
+        // Same width whatever the level, so a redraw covers the last one.
+        assert_eq!(render_levels((Some(1.0), Some(0.0))).len(), line.len());
+    }
+}
@@ -1,22 +1,52 @@
 mod apps;
 mod assign;
 mod config;
+mod curve;
 mod devices;
+mod export;
+mod focused;
 mod info;
+mod leds;
+mod levels;
+mod ports;
+mod udev;
+
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::os::linux::net::SocketAddrExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use anyhow::{Context, Result};
-use pcpaneld_core::ipc::{self, IpcRequest, IpcResponse};
+use pcpaneld_core::config::Config;
+use pcpaneld_core::control::{AppMatcher, ControlPart};
+use pcpaneld_core::ipc::{self, ErrorCode, IpcEndpoint, IpcRequest, IpcResponse};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::UnixStream;
 
 use crate::Commands;
 
-/// Run a CLI command by sending an IPC request to the daemon.
-pub async fn run(cmd: Commands) -> Result<()> {
+/// Where `send_request` connects, resolved once per invocation.
+static ENDPOINT: OnceLock<IpcEndpoint> = OnceLock::new();
+
+/// Run a CLI command by sending an IPC request to the daemon on `socket`, or
+/// the endpoint configured in `config` (default: the usual config file) if
+/// not given.
+pub async fn run(cmd: Commands, config: Option<PathBuf>, socket: Option<PathBuf>) -> Result<()> {
+    let config_path = config.or_else(Config::default_path);
+    let _ = ENDPOINT.set(ipc_endpoint(config_path.as_deref(), socket));
     match cmd {
         Commands::Info => info::run().await,
-        Commands::Apps => apps::run().await,
-        Commands::Devices => devices::run().await,
+        Commands::Apps {
+            sort,
+            filter,
+            mappings,
+        } => apps::run(sort, filter, mappings).await,
+        Commands::Focused => focused::run().await,
+        Commands::Devices { monitors } => devices::run(monitors).await,
+        Commands::Levels { meter } => levels::run(meter).await,
+        Commands::Ports => ports::run().await,
+        Commands::SetDefault { command } => devices::run_set_default(command).await,
         Commands::Assign {
             control,
             action,
@@ -24,24 +54,109 @@ pub async fn run(cmd: Commands) -> Result<()> {
             binary,
             name,
             flatpak_id,
-        } => assign::run_assign(control, action, value, binary, name, flatpak_id).await,
-        Commands::Unassign { control } => assign::run_unassign(control).await,
-        Commands::Config { command } => config::run(command).await,
-        Commands::Daemon { .. } => unreachable!("daemon command handled in main"),
+            cgroup,
+            media_name,
+            sink,
+            interactive,
+            dry_run,
+        } => {
+            if interactive {
+                return assign::run_interactive().await;
+            }
+            // clap requires all three unless --interactive is given.
+            let (false, Some(action), Some(value)) = (control.is_empty(), action, value) else {
+                anyhow::bail!("assign needs <CONTROL> <ACTION> <VALUE>, or --interactive");
+            };
+            let matcher = AppMatcher {
+                binary,
+                name,
+                flatpak_id,
+                cgroup,
+                media_name,
+            };
+            assign::run_assign(control, action, value, matcher, sink, dry_run).await
+        }
+        Commands::AssignKnob {
+            control,
+            target,
+            binary,
+            name,
+            flatpak_id,
+            cgroup,
+            media_name,
+        } => {
+            let matcher = AppMatcher {
+                binary,
+                name,
+                flatpak_id,
+                cgroup,
+                media_name,
+            };
+            assign::run_assign_knob(control, target, matcher).await
+        }
+        Commands::Unassign {
+            control,
+            dial,
+            button,
+            dry_run,
+        } => {
+            let part = if dial {
+                Some(ControlPart::Dial)
+            } else if button {
+                Some(ControlPart::Button)
+            } else {
+                None
+            };
+            assign::run_unassign(control, part, dry_run).await
+        }
+        Commands::Export { path } => export::run_export(path).await,
+        Commands::Import { path } => export::run_import(path).await,
+        Commands::Config { command } => config::run(command, config_path).await,
+        Commands::Leds { command } => leds::run(command).await,
+        Commands::Curve { control } => curve::run(control).await,
+        Commands::UdevRule => {
+            udev::run();
+            Ok(())
+        }
+        Commands::Daemon { .. } | Commands::Completions { .. } => {
+            unreachable!("handled in main")
+        }
     }
 }
 
 fn check_response(resp: IpcResponse) -> Result<IpcResponse> {
     match resp {
-        IpcResponse::Error { message } => anyhow::bail!("{message}"),
+        IpcResponse::Error { message, .. } => anyhow::bail!("{message}"),
         other => Ok(other),
     }
 }
 
-async fn send_request(request: IpcRequest) -> Result<IpcResponse> {
-    let socket_path = ipc::default_socket_path();
+/// The daemon's endpoint: `socket` if given, else per `[ipc]` in the config
+/// file. An unreadable config falls back to the socket file, as the daemon
+/// can't start on it anyway.
+fn ipc_endpoint(config_path: Option<&Path>, socket: Option<PathBuf>) -> IpcEndpoint {
+    config_path
+        .and_then(|path| Config::load(path).ok())
+        .unwrap_or_default()
+        .ipc
+        .endpoint(socket)
+}
 
-    let mut stream = UnixStream::connect(&socket_path).await.map_err(|e| {
+async fn connect(endpoint: &IpcEndpoint) -> std::io::Result<UnixStream> {
+    match endpoint {
+        IpcEndpoint::Path(path) => UnixStream::connect(path).await,
+        IpcEndpoint::Abstract(name) => {
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+            stream.set_nonblocking(true)?;
+            UnixStream::from_std(stream)
+        }
+    }
+}
+
+async fn send_request(request: IpcRequest) -> Result<IpcResponse> {
+    let endpoint = ENDPOINT.get_or_init(|| ipc_endpoint(Config::default_path().as_deref(), None));
+    let mut stream = connect(endpoint).await.map_err(|e| {
         if e.kind() == std::io::ErrorKind::ConnectionRefused
             || e.kind() == std::io::ErrorKind::NotFound
         {
@@ -54,8 +169,23 @@ async fn send_request(request: IpcRequest) -> Result<IpcResponse> {
         }
     })?;
 
+    // Daemons that predate the handshake hang up on the unknown request.
+    let hello = round_trip(
+        &mut stream,
+        &IpcRequest::Hello {
+            client_version: ipc::PROTOCOL_VERSION,
+        },
+    )
+    .await
+    .ok();
+    check_protocol(hello)?;
+
+    round_trip(&mut stream, &request).await
+}
+
+async fn round_trip(stream: &mut UnixStream, request: &IpcRequest) -> Result<IpcResponse> {
     // Send request
-    let encoded = ipc::encode_request(&request)?;
+    let encoded = ipc::encode_request(request)?;
     stream.write_all(&encoded).await?;
     stream.flush().await?;
 
@@ -73,18 +203,157 @@ async fn send_request(request: IpcRequest) -> Result<IpcResponse> {
     Ok(response)
 }
 
-fn truncate(s: &str, max_len: usize) -> &str {
-    if s.len() <= max_len {
-        s
+/// Check the daemon's reply to `Hello`, `None` if it hung up instead.
+/// Fails when the CLI and daemon don't speak the same protocol, which
+/// usually means only one of them was upgraded.
+fn check_protocol(hello: Option<IpcResponse>) -> Result<()> {
+    const RESTART: &str = "Restart it after upgrading: systemctl --user restart pcpaneld.service";
+    match hello {
+        Some(IpcResponse::Hello { protocol_version }) if protocol_version == ipc::PROTOCOL_VERSION => {
+            Ok(())
+        }
+        Some(IpcResponse::Hello { protocol_version }) => anyhow::bail!(
+            "The running daemon speaks IPC protocol v{protocol_version}, this CLI speaks v{}.\n{RESTART}",
+            ipc::PROTOCOL_VERSION
+        ),
+        None
+        | Some(IpcResponse::Error {
+            code: ErrorCode::InvalidRequest,
+            ..
+        }) => anyhow::bail!("The running daemon is older than this CLI.\n{RESTART}"),
+        Some(other) => anyhow::bail!("unexpected handshake response: {other:?}"),
+    }
+}
+
+/// Text styles for table output, by ANSI SGR code.
+#[derive(Debug, Clone, Copy)]
+enum Style {
+    Dim = 2,
+    Red = 31,
+    Green = 32,
+    Yellow = 33,
+}
+
+/// Whether output is colored: only when stdout is a terminal and `NO_COLOR`
+/// is unset or empty (see <https://no-color.org>).
+fn color_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        use std::io::IsTerminal;
+        use_color(
+            std::env::var_os("NO_COLOR").as_deref(),
+            std::io::stdout().is_terminal(),
+        )
+    })
+}
+
+fn use_color(no_color: Option<&OsStr>, is_terminal: bool) -> bool {
+    is_terminal && no_color.is_none_or(OsStr::is_empty)
+}
+
+/// `text` in `style`, or as is when color is off. Pad before painting:
+/// the escape codes count toward format widths.
+fn paint(text: &str, style: Style) -> String {
+    styled(text, style, color_enabled())
+}
+
+fn styled(text: &str, style: Style, color: bool) -> String {
+    if color {
+        format!("\x1b[{}m{text}\x1b[0m", style as u8)
     } else {
-        &s[..s.floor_char_boundary(max_len)]
+        text.to_owned()
+    }
+}
+
+/// `s` made printable, then cut to at most `max_len` bytes on a char
+/// boundary. An escape is kept whole or dropped, never split.
+fn truncate(s: &str, max_len: usize) -> Cow<'_, str> {
+    let mut len = 0;
+    let end = s
+        .char_indices()
+        .find(|&(_, c)| {
+            len += if needs_escape(c) {
+                c.escape_default().count()
+            } else {
+                c.len_utf8()
+            };
+            len > max_len
+        })
+        .map_or(s.len(), |(i, _)| i);
+    printable(&s[..end])
+}
+
+/// `s` with control characters and bidi overrides escaped, so a name coming
+/// from an app (e.g. one containing a newline) can't break a table row.
+fn printable(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(needs_escape) {
+        return Cow::Borrowed(s);
+    }
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if needs_escape(c) {
+            escaped.extend(c.escape_default());
+        } else {
+            escaped.push(c);
+        }
     }
+    Cow::Owned(escaped)
+}
+
+fn needs_escape(c: char) -> bool {
+    // Explicit embeddings, overrides, and isolates: they reorder the rest of
+    // the line.
+    c.is_control() || matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn check_protocol_accepts_same_version() {
+        let hello = IpcResponse::Hello {
+            protocol_version: ipc::PROTOCOL_VERSION,
+        };
+        assert!(check_protocol(Some(hello)).is_ok());
+    }
+
+    #[test]
+    fn check_protocol_rejects_other_version() {
+        let hello = IpcResponse::Hello {
+            protocol_version: ipc::PROTOCOL_VERSION + 1,
+        };
+        let err = check_protocol(Some(hello)).unwrap_err().to_string();
+        assert!(err.contains(&format!("v{}", ipc::PROTOCOL_VERSION + 1)));
+    }
+
+    #[test]
+    fn check_protocol_rejects_daemon_without_handshake() {
+        let rejected = IpcResponse::Error {
+            code: ErrorCode::InvalidRequest,
+            message: "invalid request: unknown variant `hello`".into(),
+        };
+        for hello in [None, Some(rejected)] {
+            let err = check_protocol(hello).unwrap_err().to_string();
+            assert!(err.contains("older than this CLI"));
+        }
+    }
+
+    #[test]
+    fn color_only_on_a_terminal_without_no_color() {
+        assert!(use_color(None, true));
+        assert!(use_color(Some(OsStr::new("")), true));
+        assert!(!use_color(Some(OsStr::new("1")), true));
+        assert!(!use_color(None, false));
+        assert!(!use_color(Some(OsStr::new("")), false));
+    }
+
+    #[test]
+    fn styled_leaves_text_alone_without_color() {
+        assert_eq!(styled("muted", Style::Dim, false), "muted");
+        assert_eq!(styled("muted", Style::Dim, true), "\x1b[2mmuted\x1b[0m");
+    }
+
     #[test]
     fn truncate_ascii_within_limit() {
         assert_eq!(truncate("hello", 10), "hello");
@@ -122,4 +391,26 @@ mod tests {
     fn truncate_zero_max() {
         assert_eq!(truncate("hello", 0), "");
     }
+
+    #[test]
+    fn truncate_escapes_control_characters() {
+        assert_eq!(truncate("evil\nrow", 20), "evil\\nrow");
+        assert_eq!(truncate("tab\there", 20), "tab\\there");
+        assert_eq!(truncate("a\u{202e}b", 20), "a\\u{202e}b");
+        // The escape counts toward the limit and is never cut in half.
+        assert_eq!(truncate("evil\nrow", 5), "evil");
+        assert_eq!(truncate("evil\nrow", 6), "evil\\n");
+        assert_eq!(truncate("a\u{202e}b", 5), "a");
+        assert!(!truncate("x\r\x1b[2J", 30).chars().any(char::is_control));
+    }
+
+    #[test]
+    fn truncate_keeps_zero_width_joiner_sequences() {
+        // Family emoji: three people joined by U+200D, 18 bytes.
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        assert_eq!(truncate(family, 30), family);
+        // Cut mid-sequence on a char boundary rather than panicking.
+        assert_eq!(truncate(family, 6), "\u{1f468}");
+        assert_eq!(truncate(family, 7), "\u{1f468}\u{200d}");
+    }
 }
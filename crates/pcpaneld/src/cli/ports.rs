@@ -0,0 +1,33 @@
+use anyhow::Result;
+use pcpaneld_core::ipc::{IpcRequest, IpcResponse};
+
+use super::{check_response, send_request};
+
+pub async fn run() -> Result<()> {
+    let resp = check_response(send_request(IpcRequest::ListOutputs).await?)?;
+    match resp {
+        IpcResponse::Outputs { outputs } => {
+            if outputs.is_empty() {
+                println!("No output devices found.");
+            }
+            for sink in &outputs {
+                println!("{} ({})", sink.description, sink.name);
+                if sink.ports.is_empty() {
+                    println!("  (no ports)");
+                }
+                for port in &sink.ports {
+                    let active = sink.active_port.as_deref() == Some(port.name.as_str());
+                    println!(
+                        "  {} {:<40} {}{}",
+                        if active { "*" } else { " " },
+                        port.name,
+                        port.description,
+                        if port.available { "" } else { " (unplugged)" },
+                    );
+                }
+            }
+        }
+        _ => anyhow::bail!("unexpected response"),
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use pcpaneld_core::hid::udev_rule;
+
+pub fn run() {
+    print!("{}", rules_file());
+}
+
+/// The rules file for every known model, with install instructions as comments
+/// so the output can go straight into `/etc/udev/rules.d/`.
+fn rules_file() -> String {
+    format!(
+        "# PCPanel access for the logged-in user. Install with:\n\
+         #   pcpaneld udev-rule | sudo tee /etc/udev/rules.d/70-pcpanel.rules\n\
+         # then reload the rules and replug the device:\n\
+         #   sudo udevadm control --reload-rules && sudo udevadm trigger\n\
+         {}\n",
+        udev_rule()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_file_is_comments_and_the_rule() {
+        let file = rules_file();
+        let (rules, comments): (Vec<&str>, Vec<&str>) =
+            file.lines().partition(|line| !line.starts_with('#'));
+        assert_eq!(rules, [udev_rule()]);
+        assert!(comments.iter().any(|line| line.contains("udevadm")));
+        assert!(file.ends_with('\n'));
+    }
+}
@@ -1,43 +1,45 @@
-use std::path::PathBuf;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+/// Quiet time after the last change before reloading. Editors that save in
+/// several steps produce one reload instead of one per write.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Changes this soon after a save by the daemon are its own write.
+const SELF_WRITE_WINDOW: Duration = Duration::from_millis(100);
 
 /// Config file watcher using notify crate.
 ///
-/// Watches the config directory (not the file) to catch editor rename patterns.
-/// Uses timestamp-based self-write suppression.
+/// Watches the config directory (not the file) to catch editor rename patterns,
+/// including the write-then-rename `Config::save` itself uses. If the config
+/// is a symlink, the directory of the file it points to is watched too, so
+/// editing the target or repointing the link both trigger a reload.
+/// Uses timestamp-based self-write suppression and a trailing debounce.
 pub async fn run(
     config_dir: PathBuf,
     config_filename: String,
     reload_tx: mpsc::Sender<()>,
-    mut self_write_rx: mpsc::Receiver<()>,
+    self_write_rx: mpsc::Receiver<()>,
     cancel: CancellationToken,
 ) {
-    let (tx, mut rx) = mpsc::channel(16);
-    let config_filename_clone = config_filename.clone();
+    let (tx, rx) = mpsc::channel(16);
+    let config_path = config_dir.join(&config_filename);
+    let targets = Arc::new(Mutex::new(config_targets(&config_path)));
+    let callback_targets = Arc::clone(&targets);
 
     let mut watcher = match RecommendedWatcher::new(
         move |res: Result<notify::Event, notify::Error>| {
             if let Ok(event) = res {
-                // Only react to Create/Modify events for the config file
-                let is_write_event =
-                    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
-
-                if is_write_event {
-                    let is_config = event.paths.iter().any(|p| {
-                        p.file_name()
-                            .and_then(|f| f.to_str())
-                            .map(|f| f == config_filename_clone)
-                            .unwrap_or(false)
-                    });
-                    if is_config {
-                        // Channel full means a reload is already pending; drop is intentional.
-                        let _ = tx.blocking_send(());
-                    }
+                let targets = callback_targets.lock().unwrap_or_else(|e| e.into_inner());
+                if is_config_change(&event, &targets) {
+                    // Channel full means a reload is already pending; drop is intentional.
+                    let _ = tx.try_send(());
                 }
             }
         },
@@ -60,10 +62,34 @@ pub async fn run(
 
     info!("watching config directory: {}", config_dir.display());
 
+    // Directory of the symlink target, when it isn't the config dir.
+    let mut target_dir = None;
+    update_target_dir(&mut watcher, &config_dir, &targets, &mut target_dir);
+
+    debounce(rx, self_write_rx, reload_tx, cancel, || {
+        // The symlink may now point somewhere else.
+        *targets.lock().unwrap_or_else(|e| e.into_inner()) = config_targets(&config_path);
+        update_target_dir(&mut watcher, &config_dir, &targets, &mut target_dir);
+    })
+    .await;
+
+    // Drop watcher to stop watching
+    drop(watcher);
+}
+
+/// Turn bursts of `changes` into single reloads on `reload_tx`, once no
+/// change has arrived for [`DEBOUNCE`]. Runs `before_reload` first.
+async fn debounce(
+    mut changes: mpsc::Receiver<()>,
+    mut self_write_rx: mpsc::Receiver<()>,
+    reload_tx: mpsc::Sender<()>,
+    cancel: CancellationToken,
+    mut before_reload: impl FnMut(),
+) {
     // Self-write suppression: track recent writes by the daemon.
     // Updated by the engine when it saves config via IPC assign/unassign.
     let mut last_self_write: Option<Instant> = None;
-    let suppress_window = std::time::Duration::from_millis(100);
+    let mut deadline: Option<tokio::time::Instant> = None;
 
     loop {
         tokio::select! {
@@ -73,25 +99,235 @@ pub async fn run(
             Some(()) = self_write_rx.recv() => {
                 last_self_write = Some(Instant::now());
             }
-            Some(()) = rx.recv() => {
-                // Check self-write suppression
-                if let Some(t) = last_self_write {
-                    if t.elapsed() < suppress_window {
-                        debug!("suppressing self-triggered config reload");
-                        continue;
-                    }
+            Some(()) = changes.recv() => {
+                if last_self_write.is_some_and(|t| t.elapsed() < SELF_WRITE_WINDOW) {
+                    debug!("suppressing self-triggered config reload");
+                    continue;
                 }
-
-                // Debounce: wait a bit for editors that do multiple writes
-                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-
+                // Every change pushes the reload back until writes settle.
+                deadline = Some(tokio::time::Instant::now() + DEBOUNCE);
+            }
+            () = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)),
+                if deadline.is_some() =>
+            {
+                deadline = None;
+                before_reload();
                 debug!("config file changed, triggering reload");
                 // Engine may have shut down; non-critical if dropped.
                 let _ = reload_tx.send(()).await;
             }
         }
     }
+}
 
-    // Drop watcher to stop watching
-    drop(watcher);
+/// Paths whose changes mean the config changed: the config path itself and,
+/// if it is a symlink, the file it resolves to.
+fn config_targets(config_path: &Path) -> Vec<PathBuf> {
+    let mut targets = vec![config_path.to_owned()];
+    if let Ok(resolved) = std::fs::canonicalize(config_path) {
+        if resolved != config_path {
+            targets.push(resolved);
+        }
+    }
+    targets
+}
+
+/// Whether `event` writes, creates, or renames onto one of `targets`.
+///
+/// Removals are ignored: loading a missing file yields the default config,
+/// and the create or rename that usually follows triggers the reload.
+fn is_config_change(event: &notify::Event, targets: &[PathBuf]) -> bool {
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        && event.paths.iter().any(|p| targets.contains(p))
+}
+
+/// Watch the directory of the symlink target, if any, instead of the
+/// previously watched one.
+fn update_target_dir(
+    watcher: &mut RecommendedWatcher,
+    config_dir: &Path,
+    targets: &Mutex<Vec<PathBuf>>,
+    target_dir: &mut Option<PathBuf>,
+) {
+    let wanted = targets
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(1)
+        .and_then(|resolved| resolved.parent())
+        .filter(|dir| *dir != config_dir)
+        .map(Path::to_owned);
+    if wanted == *target_dir {
+        return;
+    }
+
+    if let Some(old) = target_dir.take() {
+        let _ = watcher.unwatch(&old);
+    }
+    if let Some(dir) = wanted {
+        match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                info!(
+                    "watching config symlink target directory: {}",
+                    dir.display()
+                );
+                *target_dir = Some(dir);
+            }
+            Err(e) => warn!("failed to watch {}: {e}", dir.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start the watcher on `dir` and give inotify a moment to register.
+    async fn start(
+        dir: &Path,
+    ) -> (
+        mpsc::Receiver<()>,
+        mpsc::Sender<()>,
+        CancellationToken,
+        tokio::task::JoinHandle<()>,
+    ) {
+        let (reload_tx, reload_rx) = mpsc::channel(4);
+        let (self_write_tx, self_write_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+        let handle = tokio::spawn(run(
+            dir.to_owned(),
+            "config.toml".into(),
+            reload_tx,
+            self_write_rx,
+            cancel.clone(),
+        ));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        (reload_rx, self_write_tx, cancel, handle)
+    }
+
+    async fn expect_reload(reload_rx: &mut mpsc::Receiver<()>) {
+        tokio::time::timeout(Duration::from_secs(2), reload_rx.recv())
+            .await
+            .expect("no reload triggered")
+            .expect("watcher stopped");
+    }
+
+    #[tokio::test]
+    async fn rename_over_config_triggers_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.toml");
+        std::fs::write(&config, "").unwrap();
+        let (mut reload_rx, _self_write_tx, cancel, handle) = start(dir.path()).await;
+
+        let tmp = dir.path().join("config.toml.tmp");
+        std::fs::write(&tmp, "[device]\n").unwrap();
+        std::fs::rename(&tmp, &config).unwrap();
+        expect_reload(&mut reload_rx).await;
+
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn symlink_swap_and_target_edit_trigger_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let dotfiles = tempfile::tempdir().unwrap();
+        let first = dotfiles.path().join("first.toml");
+        let second = dotfiles.path().join("second.toml");
+        std::fs::write(&first, "").unwrap();
+        std::fs::write(&second, "").unwrap();
+        let config = dir.path().join("config.toml");
+        std::os::unix::fs::symlink(&first, &config).unwrap();
+        let (mut reload_rx, _self_write_tx, cancel, handle) = start(dir.path()).await;
+
+        // Editing the file the link points to.
+        std::fs::write(&first, "[device]\n").unwrap();
+        expect_reload(&mut reload_rx).await;
+
+        // Repointing the link the way `ln -sf` does: new link, renamed over.
+        let tmp_link = dir.path().join("config.toml.new");
+        std::os::unix::fs::symlink(&second, &tmp_link).unwrap();
+        std::fs::rename(&tmp_link, &config).unwrap();
+        expect_reload(&mut reload_rx).await;
+
+        // The new target is now the one that counts.
+        while reload_rx.try_recv().is_ok() {}
+        std::fs::write(&second, "[device]\n").unwrap();
+        expect_reload(&mut reload_rx).await;
+
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rapid_changes_produce_one_reload() {
+        let (change_tx, change_rx) = mpsc::channel(16);
+        let (_self_write_tx, self_write_rx) = mpsc::channel(4);
+        let (reload_tx, mut reload_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+        let handle = tokio::spawn(debounce(
+            change_rx,
+            self_write_rx,
+            reload_tx,
+            cancel.clone(),
+            || {},
+        ));
+
+        // An editor writing four times, each well inside the debounce window.
+        for _ in 0..4 {
+            change_tx.send(()).await.unwrap();
+            tokio::time::sleep(DEBOUNCE / 5).await;
+        }
+        expect_reload(&mut reload_rx).await;
+        tokio::time::sleep(DEBOUNCE * 4).await;
+        assert!(reload_rx.try_recv().is_err(), "more than one reload");
+
+        // A later change reloads again.
+        change_tx.send(()).await.unwrap();
+        expect_reload(&mut reload_rx).await;
+
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn self_write_is_not_reloaded() {
+        let (change_tx, change_rx) = mpsc::channel(16);
+        let (self_write_tx, self_write_rx) = mpsc::channel(4);
+        let (reload_tx, mut reload_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+        let handle = tokio::spawn(debounce(
+            change_rx,
+            self_write_rx,
+            reload_tx,
+            cancel.clone(),
+            || {},
+        ));
+
+        self_write_tx.send(()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        change_tx.send(()).await.unwrap();
+        tokio::time::sleep(DEBOUNCE * 3).await;
+        assert!(reload_rx.try_recv().is_err());
+
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+
+    #[test]
+    fn removal_alone_does_not_count_as_change() {
+        let path = PathBuf::from("/cfg/config.toml");
+        let event = notify::Event::new(EventKind::Remove(notify::event::RemoveKind::File))
+            .add_path(path.clone());
+        assert!(!is_config_change(&event, std::slice::from_ref(&path)));
+
+        let event = notify::Event::new(EventKind::Modify(notify::event::ModifyKind::Name(
+            notify::event::RenameMode::To,
+        )))
+        .add_path(path.clone());
+        assert!(is_config_change(&event, std::slice::from_ref(&path)));
+
+        let other = notify::Event::new(EventKind::Create(notify::event::CreateKind::File))
+            .add_path(PathBuf::from("/cfg/config.toml.tmp"));
+        assert!(!is_config_change(&other, &[path]));
+    }
 }
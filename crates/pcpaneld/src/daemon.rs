@@ -1,49 +1,242 @@
-use std::path::PathBuf;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::{Context, Result};
 use pcpaneld_core::config::{self, Config};
-use pcpaneld_core::ipc;
+use pcpaneld_core::ipc::{self, IpcEndpoint};
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 
-use crate::{config_watcher, engine, hid_thread, ipc_server, kwin, pulse, tray};
+use crate::metrics::Metrics;
+use crate::{
+    config_watcher, engine, hid_thread, ipc_server, kwin, pidfile, pulse, screen_lock, tray,
+};
 
-/// Run the daemon with the given log level.
-pub fn run(log_level: &str) -> Result<()> {
-    let filter = tracing_subscriber::EnvFilter::try_new(log_level)
-        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+/// Number of daily log files kept, including the current one.
+const MAX_LOG_FILES: usize = 7;
+
+/// Run the daemon with the given log level, reading `config` (default: the
+/// usual config file) and listening on `socket` if given. `no_tray` hides the
+/// tray icon whatever the config says.
+pub fn run(
+    log_level: &str,
+    no_tray: bool,
+    config: Option<PathBuf>,
+    socket: Option<PathBuf>,
+) -> Result<()> {
+    // The config decides where logs go, so it is loaded before logging is up.
+    let config_path = match config {
+        // Absolute, so the watcher has a directory to watch even for a bare
+        // file name.
+        Some(path) => std::path::absolute(&path)
+            .with_context(|| format!("invalid config path {}", path.display()))?,
+        None => Config::default_path().expect("failed to resolve XDG config directory"),
+    };
+    let (config_dir, config_filename) = watch_target(&config_path)?;
+    let bootstrapped = config::bootstrap_config(&config_path);
+    let config = Config::load(&config_path).context("failed to load config")?;
 
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+    let _log_guard = init_logging(log_level, config.logging.file.as_deref());
 
     info!("pcpaneld v{} starting", env!("CARGO_PKG_VERSION"));
 
-    let config_path = Config::default_path().expect("failed to resolve XDG config directory");
-    match config::bootstrap_config(&config_path) {
+    match bootstrapped {
         Ok(true) => info!("created default config at {}", config_path.display()),
         Ok(false) => {}
         Err(e) => warn!("failed to bootstrap config: {e}"),
     }
-
-    let config = Config::load(&config_path).context("failed to load config")?;
     info!("loaded config from {}", config_path.display());
+    let unknown = std::fs::read_to_string(&config_path)
+        .map(|text| config::unknown_keys(&text))
+        .unwrap_or_default();
+    for warning in config.validate().into_iter().chain(unknown) {
+        warn!("config: {warning}");
+    }
     match config.to_toml() {
         Ok(toml) => info!("active config:\n{toml}"),
         Err(e) => warn!("failed to serialize config for logging: {e}"),
     }
 
     let rt = tokio::runtime::Runtime::new().context("failed to create tokio runtime")?;
-    let result = rt.block_on(async_main(config, config_path));
+    // Kept out of the config, which the engine writes back on `assign`.
+    let tray = config.daemon.tray && !no_tray;
+    let result = rt.block_on(async_main(
+        config,
+        config_path,
+        config_dir,
+        config_filename,
+        socket,
+        tray,
+    ));
     // Explicit shutdown with timeout: HID, PulseAudio, and udev threads use blocking
     // APIs. The default runtime drop would wait for them indefinitely.
     rt.shutdown_timeout(std::time::Duration::from_secs(1));
     result
 }
 
-async fn async_main(config: Config, config_path: PathBuf) -> Result<()> {
+/// Set up console logging, plus a daily-rotated log file when `file` is set.
+///
+/// The returned guard flushes the file writer on drop and must be held for
+/// the daemon's lifetime. A log file that can't be opened is reported and
+/// skipped; console logging always works.
+fn init_logging(log_level: &str, file: Option<&Path>) -> Option<WorkerGuard> {
+    use tracing_subscriber::prelude::*;
+
+    let filter = tracing_subscriber::EnvFilter::try_new(log_level)
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+
+    let appender = file.map(|path| {
+        let prefix = path
+            .file_name()
+            .with_context(|| format!("log file {} has no file name", path.display()))?;
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(prefix.to_string_lossy())
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir.unwrap_or(Path::new(".")))
+            .with_context(|| format!("failed to open log file {}", path.display()))
+    });
+    let (file_layer, guard, file_error) = match appender {
+        Some(Ok(appender)) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer);
+            (Some(layer), Some(guard), None)
+        }
+        Some(Err(e)) => (None, None, Some(e)),
+        None => (None, None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(file_layer)
+        .init();
+
+    match (file_error, file) {
+        (Some(e), _) => warn!("file logging disabled: {e:#}"),
+        (None, Some(path)) => info!("also logging to {}.<date>", path.display()),
+        (None, None) => {}
+    }
+    guard
+}
+
+/// Make sure the default socket's directory exists before binding.
+///
+/// Containers and minimal sessions may have no `$XDG_RUNTIME_DIR`, and the
+/// `/run/user/{uid}` fallback may not exist either. Create the runtime
+/// directory if we're allowed to, otherwise use a private directory under the
+/// system temp dir, which `ipc::usable_runtime_dir` picks for the CLI too.
+fn prepare_runtime_dir() -> Result<()> {
+    let runtime_dir = ipc::xdg_runtime_dir();
+    if !runtime_dir.exists() && create_private_dir(&runtime_dir).is_ok() {
+        info!(
+            "created missing runtime directory {}",
+            runtime_dir.display()
+        );
+    }
+    if ipc::is_writable_dir(&runtime_dir) {
+        return Ok(());
+    }
+
+    let fallback = ipc::fallback_runtime_dir();
+    warn!(
+        "runtime directory {} is missing or not writable, using {} instead",
+        runtime_dir.display(),
+        fallback.display()
+    );
+    create_private_dir(&fallback)
+        .with_context(|| format!("failed to create {}", fallback.display()))?;
+
+    // The temp dir is shared; don't put our socket in someone else's directory.
+    let meta = std::fs::symlink_metadata(&fallback)
+        .with_context(|| format!("failed to inspect {}", fallback.display()))?;
+    if !meta.is_dir() || meta.uid() != unsafe { libc::getuid() } {
+        anyhow::bail!(
+            "{} is not a directory owned by the current user",
+            fallback.display()
+        );
+    }
+    Ok(())
+}
+
+/// Create `dir` and any missing parents with owner-only permissions.
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+}
+
+/// Directory and file name of the config, which the watcher watches. A
+/// `--config` path need not have either (`/`, `/etc/..`), and a file that
+/// doesn't exist yet loads as the defaults, so this is checked up front.
+fn watch_target(config_path: &Path) -> Result<(PathBuf, String)> {
+    let dir = config_path.parent().with_context(|| {
+        format!(
+            "config path {} has no parent directory",
+            config_path.display()
+        )
+    })?;
+    let name = config_path
+        .file_name()
+        .with_context(|| format!("config path {} has no file name", config_path.display()))?;
+    let name = name.to_str().with_context(|| {
+        format!(
+            "config file name {} is not valid UTF-8",
+            name.to_string_lossy()
+        )
+    })?;
+    Ok((dir.to_owned(), name.to_owned()))
+}
+
+async fn async_main(
+    config: Config,
+    config_path: PathBuf,
+    config_dir: PathBuf,
+    config_filename: String,
+    socket: Option<PathBuf>,
+    tray: bool,
+) -> Result<()> {
     let cancel = CancellationToken::new();
 
-    let socket_path = ipc::default_socket_path();
-    ipc_server::cleanup_stale_socket(&socket_path).await?;
+    let explicit_socket = socket.is_some() || ipc::env_socket_path().is_some();
+    if !explicit_socket && !config.ipc.abstract_socket {
+        prepare_runtime_dir()?;
+    }
+
+    // Abstract sockets vanish with their process, so only a file can be stale.
+    // Binding one is atomic, so only a file needs the startup lock too.
+    let endpoint = config.ipc.endpoint(socket);
+    let _socket_lock = match &endpoint {
+        IpcEndpoint::Path(path) => {
+            let lock = ipc_server::lock_socket(path)?;
+            ipc_server::cleanup_stale_socket(path).await?;
+            Some(lock)
+        }
+        IpcEndpoint::Abstract(_) => None,
+    };
+
+    // Past the socket check, so a live PID in the file isn't another daemon.
+    let pidfile_path = config.daemon.pidfile.clone();
+    if let Some(path) = &pidfile_path {
+        match pidfile::live_pid(path) {
+            Some(pid) => warn!(
+                "pidfile {} names running process {pid}, replacing it",
+                path.display()
+            ),
+            None if path.exists() => info!("replacing stale pidfile {}", path.display()),
+            None => {}
+        }
+        if let Err(e) = pidfile::write(path) {
+            warn!("failed to write pidfile {}: {e}", path.display());
+        }
+    }
 
     // Set up channels
     let (hid_position_tx, hid_position_rx) = tokio::sync::watch::channel([0u8; 9]);
@@ -54,7 +247,8 @@ async fn async_main(config: Config, config_path: PathBuf) -> Result<()> {
     let (ipc_request_tx, ipc_request_rx) = tokio::sync::mpsc::channel(8);
     let (tray_action_tx, tray_action_rx) = tokio::sync::mpsc::channel(4);
     let (device_event_tx, device_event_rx) = std::sync::mpsc::sync_channel(4);
-    let (device_connected_tx, device_connected_rx) = tokio::sync::watch::channel(false);
+    let (device_connected_tx, device_connected_rx) = tokio::sync::watch::channel(None);
+    let (device_error_tx, device_error_rx) = tokio::sync::watch::channel(None);
 
     // Start udev monitor (std::thread — MonitorSocket is not Send)
     let udev_cancel = cancel.clone();
@@ -66,18 +260,19 @@ async fn async_main(config: Config, config_path: PathBuf) -> Result<()> {
         .context("failed to spawn udev thread")?;
 
     // Start HID thread (std::thread)
-    let hid_config_serial = config.device.serial.clone();
+    let hid_device_config = config.device.clone();
     let hid_cancel = cancel.clone();
     let hid_join = std::thread::Builder::new()
         .name("hid".into())
         .spawn(move || {
             hid_thread::run(
-                hid_config_serial,
+                hid_device_config,
                 hid_position_tx,
                 hid_button_tx,
                 hid_cmd_rx,
                 device_event_rx,
                 device_connected_tx,
+                device_error_tx,
                 hid_cancel,
             );
         })
@@ -94,40 +289,62 @@ async fn async_main(config: Config, config_path: PathBuf) -> Result<()> {
 
     // Start IPC server (tokio task)
     let ipc_cancel = cancel.clone();
+    let ipc_endpoint = endpoint.clone();
     let ipc_handle = tokio::spawn(async move {
-        if let Err(e) = ipc_server::run(socket_path.clone(), ipc_request_tx, ipc_cancel).await {
+        if let Err(e) = ipc_server::run(ipc_endpoint, ipc_request_tx, ipc_cancel).await {
             error!("IPC server failed: {e}");
         }
     });
 
-    // Start system tray (tokio task)
-    let tray_cancel = cancel.clone();
-    let tray_handle = tokio::spawn(async move {
-        tray::run(tray_action_tx, tray_cancel).await;
-    });
+    // Start system tray (tokio task). Without it the tray channel closes and
+    // the engine just never sees a tray action.
+    let tray_handle = if tray {
+        let tray_cancel = cancel.clone();
+        let tray_direct_quit = config.daemon.tray_direct_quit;
+        Some(tokio::spawn(async move {
+            tray::run(tray_action_tx, tray_direct_quit, tray_cancel).await;
+        }))
+    } else {
+        info!("tray icon disabled");
+        drop(tray_action_tx);
+        None
+    };
 
     // Start KWin focused window tracker (tokio task)
     let (focused_window_tx, focused_window_rx) =
         tokio::sync::watch::channel::<Option<kwin::FocusedWindowInfo>>(None);
+    let (focus_tracking_tx, focus_tracking_rx) = tokio::sync::watch::channel(false);
     let kwin_cancel = cancel.clone();
     tokio::spawn(async move {
-        kwin::run(focused_window_tx, kwin_cancel).await;
+        kwin::run(focused_window_tx, focus_tracking_tx, kwin_cancel).await;
+    });
+
+    // Start screen lock tracker (tokio task)
+    let (screen_locked_tx, screen_locked_rx) = tokio::sync::watch::channel(false);
+    let screen_lock_cancel = cancel.clone();
+    tokio::spawn(async move {
+        screen_lock::run(screen_locked_tx, screen_lock_cancel).await;
     });
 
+    // Start metrics server (tokio task, `metrics` feature only)
+    let metrics = Arc::new(Metrics::default());
+    if let Some(addr) = config.metrics.listen {
+        #[cfg(feature = "metrics")]
+        tokio::spawn(crate::metrics::serve(
+            addr,
+            Arc::clone(&metrics),
+            cancel.clone(),
+        ));
+        #[cfg(not(feature = "metrics"))]
+        warn!("[metrics] listen = {addr} ignored: built without the `metrics` feature");
+    }
+
     // Start config watcher (tokio task)
     let (config_reload_tx, config_reload_rx) = tokio::sync::mpsc::channel(4);
     let (config_self_write_tx, config_self_write_rx) = tokio::sync::mpsc::channel::<()>(4);
+    let sighup_reload_tx = config_reload_tx.clone();
+    let (state_dump_tx, state_dump_rx) = tokio::sync::mpsc::channel(1);
     let watcher_cancel = cancel.clone();
-    let config_dir = config_path
-        .parent()
-        .expect("config path has no parent directory")
-        .to_owned();
-    let config_filename = config_path
-        .file_name()
-        .expect("config path has no file name")
-        .to_str()
-        .expect("config filename is not valid UTF-8")
-        .to_owned();
     let _watcher_handle = tokio::spawn(async move {
         config_watcher::run(
             config_dir,
@@ -146,10 +363,33 @@ async fn async_main(config: Config, config_path: PathBuf) -> Result<()> {
             .expect("failed to register SIGTERM handler");
         let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
             .expect("failed to register SIGINT handler");
+        let mut sighup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+            .expect("failed to register SIGHUP handler");
+        let mut sigusr1 =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1())
+                .expect("failed to register SIGUSR1 handler");
 
-        tokio::select! {
-            _ = sigterm.recv() => info!("received SIGTERM"),
-            _ = sigint.recv() => info!("received SIGINT"),
+        loop {
+            tokio::select! {
+                _ = sigterm.recv() => {
+                    info!("received SIGTERM");
+                    break;
+                }
+                _ = sigint.recv() => {
+                    info!("received SIGINT");
+                    break;
+                }
+                _ = sighup.recv() => {
+                    info!("received SIGHUP, reloading config");
+                    // Same path as a file change; a full queue means a reload
+                    // is already pending.
+                    let _ = sighup_reload_tx.try_send(());
+                }
+                _ = sigusr1.recv() => {
+                    info!("received SIGUSR1, dumping engine state");
+                    let _ = state_dump_tx.try_send(());
+                }
+            }
         }
         signal_cancel.cancel();
     });
@@ -164,15 +404,25 @@ async fn async_main(config: Config, config_path: PathBuf) -> Result<()> {
         ipc_request_rx,
         tray_action_rx,
         config_reload_rx,
+        state_dump_rx,
         focused_window_rx,
+        focus_tracking_rx,
+        screen_locked_rx,
         device_connected_rx,
+        device_error_rx,
         config_self_write_tx,
     };
-    engine::run(config, config_path, channels, cancel.clone()).await;
+    engine::run(config, config_path, channels, metrics, cancel.clone()).await;
 
     info!("engine stopped, shutting down subsystems");
     cancel.cancel();
 
+    if let Some(path) = &pidfile_path {
+        if let Err(e) = pidfile::remove(path) {
+            warn!("failed to remove pidfile {}: {e}", path.display());
+        }
+    }
+
     // Wait for threads with timeout
     let _ = tokio::time::timeout(std::time::Duration::from_millis(500), async {
         let _ = tokio::task::spawn_blocking(move || {
@@ -187,14 +437,39 @@ async fn async_main(config: Config, config_path: PathBuf) -> Result<()> {
     // Wait for tokio tasks
     let _ = tokio::time::timeout(std::time::Duration::from_millis(200), async {
         let _ = ipc_handle.await;
-        let _ = tray_handle.await;
+        if let Some(tray_handle) = tray_handle {
+            let _ = tray_handle.await;
+        }
     })
     .await;
 
     // Clean up socket
-    let socket_path = ipc::default_socket_path();
-    let _ = tokio::fs::remove_file(&socket_path).await;
+    if let IpcEndpoint::Path(path) = &endpoint {
+        let _ = tokio::fs::remove_file(path).await;
+    }
 
     info!("shutdown complete");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn watch_target_splits_config_path() {
+        let (dir, name) = watch_target(Path::new("/home/u/.config/pcpaneld/config.toml")).unwrap();
+        assert_eq!(dir, Path::new("/home/u/.config/pcpaneld"));
+        assert_eq!(name, "config.toml");
+    }
+
+    #[test]
+    fn watch_target_rejects_unwatchable_paths() {
+        let non_utf8 = Path::new("/tmp").join(OsStr::from_bytes(b"config-\xff.toml"));
+        for path in [Path::new("/nonexistent/.."), Path::new("/"), &non_utf8] {
+            assert!(watch_target(path).is_err(), "{} accepted", path.display());
+        }
+    }
+}
@@ -1,21 +1,35 @@
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
 use pcpaneld_core::audio::{
-    AudioState, DeviceInfo, DeviceType, SinkInfo, SinkInputInfo, SourceInfo, Volume, VolumeCurve,
+    AudioState, DeviceInfo, DeviceType, PiecewiseCurve, SinkInfo, SinkInputInfo, SourceInfo,
+    Volume, VolumeCurve,
 };
-use pcpaneld_core::config::{Config, LedConfig};
-use pcpaneld_core::control::{AppProperties, AudioTarget, ButtonAction, ControlId, DialAction};
-use pcpaneld_core::hid::HidCommand;
-use pcpaneld_core::ipc::{DeviceStatus, IpcRequest, IpcResponse, MappingInfo};
-use tokio::sync::{mpsc, oneshot, watch};
+use pcpaneld_core::config::{
+    self, Color, Config, ConfigError, ExecConfig, LedConfig, MatchingConfig,
+};
+use pcpaneld_core::control::{
+    AppMatcher, AppProperties, AudioTarget, ButtonAction, ControlConfig, ControlId, DeviceModel,
+    DialAction, LedEffect,
+};
+use pcpaneld_core::hid::{HidCommand, LedSlot};
+use pcpaneld_core::ipc::{
+    DeviceStatus, ErrorCode, FocusedWindow, HardwareInfo, IpcRequest, IpcResponse, LedState,
+    LogoLed, MappingInfo, RuntimeStats, PROTOCOL_VERSION,
+};
+use tokio::sync::{mpsc, oneshot, watch, Semaphore};
+use tokio::time::Instant;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
-use crate::hid_thread::ButtonEvent;
+use crate::hid_thread::{ButtonEvent, ConnectedDevice};
 use crate::kwin::FocusedWindowInfo;
+use crate::metrics::Metrics;
 use crate::pulse::{AudioCommand, AudioNotification};
-use crate::signal::SignalPipeline;
+use crate::signal::{SignalPipeline, SlamDetector};
 use crate::tray::TrayAction;
 
 /// An IPC request bundled with its reply channel.
@@ -34,8 +48,12 @@ pub struct EngineChannels {
     pub ipc_request_rx: mpsc::Receiver<IpcMessage>,
     pub tray_action_rx: mpsc::Receiver<TrayAction>,
     pub config_reload_rx: mpsc::Receiver<()>,
+    pub state_dump_rx: mpsc::Receiver<()>,
     pub focused_window_rx: watch::Receiver<Option<FocusedWindowInfo>>,
-    pub device_connected_rx: watch::Receiver<bool>,
+    pub focus_tracking_rx: watch::Receiver<bool>,
+    pub screen_locked_rx: watch::Receiver<bool>,
+    pub device_connected_rx: watch::Receiver<Option<ConnectedDevice>>,
+    pub device_error_rx: watch::Receiver<Option<String>>,
     pub config_self_write_tx: mpsc::Sender<()>,
 }
 
@@ -45,32 +63,132 @@ struct EngineState {
     config_path: PathBuf,
     audio_state: AudioState,
     volume_curve: VolumeCurve,
-    device_connected: bool,
+    /// Curve for dials whose target resolves to an input.
+    mic_volume_curve: VolumeCurve,
+    /// The connected device, `None` while disconnected.
+    device: Option<HardwareInfo>,
+    /// The HID thread's last device error, read when status is requested.
+    device_error_rx: watch::Receiver<Option<String>>,
+    /// Layout of the connected device, or of the last one seen while
+    /// disconnected. Assumed to be a Pro until a device reports in.
+    model: DeviceModel,
     pulse_connected: bool,
     pipelines: HashMap<u8, SignalPipeline>,
     last_positions: [u8; 9],
+    /// Controls whose position the connected device has reported, so an
+    /// entry of `last_positions` isn't just the initial zero.
+    positions_seen: [bool; 9],
+    /// Controls whose first position change since the device connected is
+    /// still to come. That change may be a stale report the HID thread's
+    /// drain missed, so it only seeds the pipeline.
+    skip_first_delta: [bool; 9],
     last_applied_volumes: [Option<Volume>; 9],
+    /// Analog control that last set each sink-input's volume, and when, to
+    /// spot two controls fighting over one stream.
+    stream_writers: HashMap<u32, (u8, Instant)>,
+    /// Pairs of analog controls already warned about fighting over a stream.
+    warned_stream_conflicts: HashSet<(u8, u8)>,
     focused_window: Option<FocusedWindowInfo>,
     dbus_session: Option<zbus::Connection>,
+    /// Source indices the PA thread is currently peak-metering (sorted).
+    peak_sources: Vec<u32>,
+    /// Latest peak level per metered source.
+    source_peaks: HashMap<u32, f32>,
+    /// Keep the default devices metered until then, for `GetLevels`
+    /// clients. `None` once nobody has asked for a while.
+    levels_until: Option<Instant>,
+    /// `GetLevels` replies held until the default devices' meters report.
+    levels_waiters: Vec<oneshot::Sender<IpcResponse>>,
+    /// When held `GetLevels` replies are sent anyway, `None` if none are held.
+    levels_wait_until: Option<Instant>,
+    /// Slam gesture state per analog control.
+    slam_detectors: [SlamDetector; 9],
+    /// Latest peak level per slider, for sliders with `LedEffect::PeakMeter`.
+    slider_peaks: [f32; 4],
+    /// Last command sent per LED zone (see [`led_zone`]), so unchanged zones
+    /// aren't rewritten. Cleared when a device connects, since it may show
+    /// anything by then.
+    sent_leds: [Option<HidCommand>; 4],
+    /// When the LEDs dim for inactivity, `None` if idle dimming is disabled
+    /// or the LEDs are already dimmed.
+    idle_deadline: Option<Instant>,
+    /// LEDs are currently dimmed for inactivity.
+    leds_idle: bool,
+    /// LEDs switched off for this session by a `toggle_leds` button.
+    leds_off: bool,
+    /// The startup animation playing on the knob rings, if any.
+    led_animation: Option<tokio::task::JoinHandle<()>>,
+    /// The session's screen is locked.
+    screen_locked: bool,
+    /// A compositor backend is reporting focus changes.
+    focus_tracking: bool,
+    /// When each held button with a repeating action fires next, by button ID.
+    button_repeats: HashMap<u8, Instant>,
+    /// Default sink/source names seen in the last snapshot. Survive PA
+    /// disconnects so a reconnect isn't mistaken for a device switch.
+    last_default_sink: Option<String>,
+    last_default_source: Option<String>,
+    /// PulseAudio dropped since the last snapshot, so the next one is from
+    /// a new session whose devices need the controls' volumes again.
+    reapply_after_reconnect: bool,
+    /// Permits for running `exec` commands, sized from `[exec]
+    /// max_concurrent` at startup.
+    exec_semaphore: Arc<Semaphore>,
+    /// Runtime counters, shared with the metrics server.
+    metrics: Arc<Metrics>,
+    /// When the engine started, for the uptime in `GetStatus`.
+    started_at: Instant,
 }
 
 impl EngineState {
     fn new(config: Config, config_path: PathBuf) -> Self {
-        let volume_curve = VolumeCurve::new(config.signal.volume_exponent);
+        let volume_curve = config.signal.volume_curve();
+        let mic_volume_curve = config.signal.mic_volume_curve();
+        let model = DeviceModel::default();
         let mut pipelines = HashMap::new();
-        rebuild_pipelines(&config, &mut pipelines);
+        rebuild_pipelines(model, &config, &mut pipelines);
+        let exec_permits = config.exec.max_concurrent().min(Semaphore::MAX_PERMITS);
+        let exec_semaphore = Arc::new(Semaphore::new(exec_permits));
         Self {
             config,
             config_path,
             audio_state: AudioState::default(),
             volume_curve,
-            device_connected: false,
+            mic_volume_curve,
+            device: None,
+            device_error_rx: watch::channel(None).1,
+            model,
             pulse_connected: false,
             pipelines,
             last_positions: [0u8; 9],
+            positions_seen: [false; 9],
+            skip_first_delta: [false; 9],
             last_applied_volumes: [None; 9],
             focused_window: None,
             dbus_session: None,
+            peak_sources: Vec::new(),
+            source_peaks: HashMap::new(),
+            levels_until: None,
+            levels_waiters: Vec::new(),
+            levels_wait_until: None,
+            slam_detectors: Default::default(),
+            slider_peaks: [0.0; 4],
+            sent_leds: Default::default(),
+            idle_deadline: None,
+            leds_idle: false,
+            leds_off: false,
+            led_animation: None,
+            screen_locked: false,
+            focus_tracking: false,
+            button_repeats: HashMap::new(),
+            stream_writers: HashMap::new(),
+            warned_stream_conflicts: HashSet::new(),
+            last_default_sink: None,
+            last_default_source: None,
+            reapply_after_reconnect: false,
+            exec_semaphore,
+            metrics: Arc::default(),
+            started_at: Instant::now(),
         }
     }
 }
@@ -92,6 +210,7 @@ pub async fn run(
     config: Config,
     config_path: PathBuf,
     channels: EngineChannels,
+    metrics: Arc<Metrics>,
     cancel: CancellationToken,
 ) {
     let EngineChannels {
@@ -103,11 +222,17 @@ pub async fn run(
         mut ipc_request_rx,
         mut tray_action_rx,
         mut config_reload_rx,
+        mut state_dump_rx,
         mut focused_window_rx,
+        mut focus_tracking_rx,
+        mut screen_locked_rx,
         mut device_connected_rx,
+        device_error_rx,
         config_self_write_tx,
     } = channels;
     let mut state = EngineState::new(config, config_path);
+    state.metrics = metrics;
+    state.device_error_rx = device_error_rx;
 
     info!("engine started");
 
@@ -126,18 +251,34 @@ pub async fn run(
                 }
 
                 let positions = *hid_position_rx.borrow();
+                note_activity(&mut state, &hid_cmd_tx).await;
 
                 // Diff against last known state
                 for i in 0..9u8 {
                     if positions[i as usize] != state.last_positions[i as usize] {
-                        let raw = positions[i as usize];
+                        state.positions_seen[i as usize] = true;
+                        if is_disabled(i, state.model, &state.config) {
+                            continue;
+                        }
+                        let raw = physical_position(
+                            i,
+                            positions[i as usize],
+                            state.model,
+                            &state.config,
+                        );
+                        Metrics::incr(&state.metrics.events_processed);
 
                         // Process through signal pipeline
                         let pipeline = state.pipelines
                             .entry(i)
-                            .or_insert_with(|| make_pipeline(i, &state.config));
+                            .or_insert_with(|| make_pipeline(i, state.model, &state.config));
 
-                        if let Some(processed) = pipeline.process(raw) {
+                        let processed = pipeline.process(raw);
+                        if std::mem::take(&mut state.skip_first_delta[i as usize]) {
+                            debug!("ignoring first position change of analog {i} after connect");
+                            continue;
+                        }
+                        if let Some(processed) = processed {
                             if let Some(vol) = handle_position_change(
                                 i,
                                 processed,
@@ -145,6 +286,16 @@ pub async fn run(
                                 &audio_cmd_tx,
                             ).await {
                                 state.last_applied_volumes[i as usize] = Some(vol);
+                                note_stream_writes(i, Instant::now(), &mut state);
+                            }
+                            if let Some(action) = detect_slam(i, processed, &mut state) {
+                                run_button_action(
+                                    &action,
+                                    &mut state,
+                                    &audio_cmd_tx,
+                                    &hid_cmd_tx,
+                                    &config_self_write_tx,
+                                ).await;
                             }
                         }
                     }
@@ -155,82 +306,77 @@ pub async fn run(
 
             // Button events
             Some(event) = hid_button_rx.recv() => {
+                Metrics::incr(&state.metrics.events_processed);
+                note_activity(&mut state, &hid_cmd_tx).await;
                 if event.pressed {
                     handle_button_press(
                         event.button_id,
                         &mut state,
                         &audio_cmd_tx,
+                        &hid_cmd_tx,
+                        &config_self_write_tx,
                     ).await;
+                    start_button_repeat(event.button_id, Instant::now(), &mut state);
+                } else {
+                    state.button_repeats.remove(&event.button_id);
                 }
             }
 
             // Audio state notifications
             Some(notification) = audio_notify_rx.recv() => {
-                match notification {
-                    AudioNotification::Connected => {
-                        state.pulse_connected = true;
-                        info!("PulseAudio connected");
-                    }
-                    AudioNotification::Disconnected => {
-                        state.pulse_connected = false;
-                        state.audio_state = AudioState::default();
-                        warn!("PulseAudio disconnected");
-                    }
-                    AudioNotification::StateSnapshot(new_audio_state) => {
-                        let old_indices: HashSet<u32> =
-                            state.audio_state.sink_inputs.iter().map(|si| si.index).collect();
-                        let new_sink_inputs: Vec<&SinkInputInfo> = new_audio_state
-                            .sink_inputs
-                            .iter()
-                            .filter(|si| !old_indices.contains(&si.index))
-                            .collect();
-
-                        if !new_sink_inputs.is_empty() {
-                            debug!(
-                                "detected {} new sink-input(s), checking for volume re-apply",
-                                new_sink_inputs.len()
-                            );
-                            reapply_volumes_to_new_sink_inputs(
-                                &new_sink_inputs,
-                                &state.last_applied_volumes,
-                                &state.config,
-                                &audio_cmd_tx,
-                                &state.focused_window,
-                            )
-                            .await;
-                        }
-
-                        state.audio_state = new_audio_state;
-                        debug!("audio state updated: {} sink-inputs", state.audio_state.sink_inputs.len());
-                    }
-                }
+                handle_audio_notification(notification, &mut state, &audio_cmd_tx, &hid_cmd_tx)
+                    .await;
             }
 
             // IPC requests
             Some(msg) = ipc_request_rx.recv() => {
-                let is_reload = matches!(msg.request, IpcRequest::ReloadConfig);
-                let mutates_config = matches!(
-                    msg.request,
+                let is_reload = any_request(&msg.request, |r| matches!(
+                    r,
+                    IpcRequest::ReloadConfig | IpcRequest::SetConfig { .. }
+                ));
+                let is_led_reset =
+                    any_request(&msg.request, |r| matches!(r, IpcRequest::ResetLeds));
+                let mutates_config = any_request(&msg.request, |r| matches!(
+                    r,
                     IpcRequest::AssignDial { .. }
                     | IpcRequest::AssignButton { .. }
                     | IpcRequest::Unassign { .. }
+                    | IpcRequest::SetConfig { .. }
                     | IpcRequest::ReloadConfig
-                );
+                ));
+                let previous = mutates_config.then(|| state.config.clone());
                 let response = handle_ipc_request(
                     msg.request,
                     &mut state,
                     &config_self_write_tx,
+                    &audio_cmd_tx,
                     &cancel,
                 ).await;
-                if mutates_config && matches!(response, IpcResponse::Ok) {
-                    // Clear cached volumes so stale values aren't re-applied to
-                    // sink-inputs that appear after a config change.
-                    state.last_applied_volumes = [None; 9];
+                if matches!(response, IpcResponse::Levels { .. }) && !levels_ready(&state) {
+                    // Meters that just opened haven't reported yet.
+                    state.levels_waiters.push(msg.reply_tx);
+                    state
+                        .levels_wait_until
+                        .get_or_insert_with(|| Instant::now() + LEVELS_WAIT);
+                    continue;
+                }
+                // A batch may have changed the config even if some of its
+                // requests failed.
+                let succeeded = matches!(response, IpcResponse::Ok | IpcResponse::Batch { .. });
+                if let (Some(previous), true) = (&previous, succeeded) {
+                    if is_reload {
+                        apply_config(&mut state, previous, &hid_cmd_tx, &audio_cmd_tx).await;
+                    } else {
+                        reset_changed_controls(&mut state, previous);
+                        if state.config.leds.color_by_action {
+                            // Action colors depend on the mapping that just changed.
+                            refresh_leds(&mut state, &hid_cmd_tx).await;
+                        }
+                        sync_peak_sources(&mut state, &audio_cmd_tx).await;
+                    }
                 }
-                if is_reload && matches!(response, IpcResponse::Ok) {
-                    state.volume_curve = VolumeCurve::new(state.config.signal.volume_exponent);
-                    rebuild_pipelines(&state.config, &mut state.pipelines);
-                    send_initial_leds(&hid_cmd_tx, &state.config.leds).await;
+                if is_led_reset {
+                    refresh_leds(&mut state, &hid_cmd_tx).await;
                 }
                 // Client may have disconnected; reply is best-effort.
                 let _ = msg.reply_tx.send(response);
@@ -252,37 +398,135 @@ pub async fn run(
                 if result.is_ok() {
                     state.focused_window = focused_window_rx.borrow().clone();
                     debug!("focused window changed: {:?}", state.focused_window);
+                    update_knob_levels(&mut state, &hid_cmd_tx).await;
+                }
+            }
+
+            // Focus tracking backend coming up
+            result = focus_tracking_rx.changed() => {
+                if result.is_ok() {
+                    state.focus_tracking = *focus_tracking_rx.borrow();
+                }
+            }
+
+            // Screen lock changes
+            result = screen_locked_rx.changed() => {
+                if result.is_ok() {
+                    let locked = *screen_locked_rx.borrow();
+                    set_screen_locked(&mut state, locked, &hid_cmd_tx).await;
                 }
             }
 
             // Device connection state changes
             result = device_connected_rx.changed() => {
                 if result.is_ok() {
-                    let connected = *device_connected_rx.borrow();
-                    state.device_connected = connected;
-                    if connected {
-                        info!("device connected, sending LED config");
+                    let connected = device_connected_rx.borrow().clone();
+                    state.device = connected.as_ref().map(|c| c.info.clone());
+                    state
+                        .metrics
+                        .device_connected
+                        .store(connected.is_some(), Ordering::Relaxed);
+                    if let Some(connected) = connected {
+                        let model = connected.info.model;
+                        info!("{model} connected, sending LED config");
+                        if model != state.model {
+                            state.model = model;
+                            rebuild_pipelines(model, &state.config, &mut state.pipelines);
+                            // Analog IDs now name different controls.
+                            state.last_applied_volumes = [None; 9];
+                            state.positions_seen = [false; 9];
+                        }
                         for pipeline in state.pipelines.values_mut() {
                             pipeline.reset();
                         }
-                        send_initial_leds(&hid_cmd_tx, &state.config.leds).await;
+                        state.skip_first_delta = [true; 9];
+                        state.sent_leds = Default::default();
+                        refresh_leds(&mut state, &hid_cmd_tx).await;
+                        if let Some(animation) = state.led_animation.take() {
+                            animation.abort();
+                        }
+                        state.led_animation = start_led_animation(&state, &hid_cmd_tx);
+                        auto_lock_serial(&mut state, &connected, &config_self_write_tx).await;
                     } else {
                         info!("device disconnected");
+                        if let Some(animation) = state.led_animation.take() {
+                            animation.abort();
+                        }
+                        // Held buttons won't report their release.
+                        state.button_repeats.clear();
+                        // Controls may move while unplugged.
+                        state.positions_seen = [false; 9];
                     }
                 }
             }
 
+            // Held-button repeats. As below, the fallback instant is never
+            // awaited.
+            _ = tokio::time::sleep_until(
+                state.button_repeats.values().min().copied().unwrap_or_else(Instant::now)
+            ), if !state.button_repeats.is_empty() => {
+                fire_button_repeats(
+                    Instant::now(),
+                    &mut state,
+                    &audio_cmd_tx,
+                    &hid_cmd_tx,
+                    &config_self_write_tx,
+                ).await;
+            }
+
+            // Startup animation finished: the knob rings show its last frame,
+            // so put the configured LEDs back.
+            _ = led_animation_done(&mut state.led_animation), if state.led_animation.is_some() => {
+                state.led_animation = None;
+                state.sent_leds[0] = None;
+                refresh_leds(&mut state, &hid_cmd_tx).await;
+            }
+
+            // LED idle timeout. The fallback instant is never awaited: the
+            // branch is disabled whenever there is no deadline.
+            _ = tokio::time::sleep_until(state.idle_deadline.unwrap_or_else(Instant::now)),
+                if state.idle_deadline.is_some() =>
+            {
+                state.idle_deadline = None;
+                state.leds_idle = true;
+                debug!("no input for {}ms, dimming LEDs", state.config.leds.idle_timeout_ms);
+                let brightness = led_brightness(&state) * state.config.leds.idle_brightness_factor();
+                for cmd in led_commands(
+                    &state.config,
+                    &knob_levels(&state),
+                    mic_muted(&state),
+                    brightness,
+                ) {
+                    send_led(&mut state, &hid_cmd_tx, cmd).await;
+                }
+            }
+
+            // Nobody has asked for levels for a while: close those meters.
+            _ = tokio::time::sleep_until(state.levels_until.unwrap_or_else(Instant::now)),
+                if state.levels_until.is_some() =>
+            {
+                expire_levels(&mut state, &audio_cmd_tx).await;
+            }
+
+            // Meters that never reported: answer held `GetLevels` with what there is.
+            _ = tokio::time::sleep_until(state.levels_wait_until.unwrap_or_else(Instant::now)),
+                if state.levels_wait_until.is_some() =>
+            {
+                answer_levels_waiters(&mut state);
+            }
+
+            // Diagnostic dump request (SIGUSR1)
+            Some(()) = state_dump_rx.recv() => {
+                info!("state dump:\n{}", state_dump(&state));
+            }
+
             // Config reload notification
             Some(()) = config_reload_rx.recv() => {
                 info!("config reload triggered");
                 match Config::load(&state.config_path) {
                     Ok(new_config) => {
-                        state.config = new_config;
-                        state.volume_curve = VolumeCurve::new(state.config.signal.volume_exponent);
-                        rebuild_pipelines(&state.config, &mut state.pipelines);
-                        // Clear cached volumes — control-to-target mappings may have changed.
-                        state.last_applied_volumes = [None; 9];
-                        send_initial_leds(&hid_cmd_tx, &state.config.leds).await;
+                        let previous = std::mem::replace(&mut state.config, new_config);
+                        apply_config(&mut state, &previous, &hid_cmd_tx, &audio_cmd_tx).await;
                         info!("config reloaded successfully");
                         match state.config.to_toml() {
                             Ok(toml) => debug!("active config:\n{toml}"),
@@ -300,15 +544,114 @@ pub async fn run(
     info!("engine stopped");
 }
 
-fn make_pipeline(analog_id: u8, config: &Config) -> SignalPipeline {
-    if analog_id < ControlId::NUM_KNOBS {
-        SignalPipeline::new(
+/// Human-readable snapshot of the engine state for bug reports.
+fn state_dump(state: &EngineState) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    match &state.device {
+        Some(device) => {
+            let _ = writeln!(
+                out,
+                "device: {} (serial: {}, firmware: {})",
+                device.model,
+                device.serial.as_deref().unwrap_or("unknown"),
+                device.firmware_version.as_deref().unwrap_or("unknown")
+            );
+        }
+        None => {
+            let _ = writeln!(out, "device: disconnected (last model: {})", state.model);
+        }
+    }
+    let _ = writeln!(out, "pulse_connected: {}", state.pulse_connected);
+    let _ = writeln!(out, "focused_window: {:?}", state.focused_window);
+    let _ = writeln!(out, "last_positions: {:?}", state.last_positions);
+    let volumes: Vec<String> = state
+        .last_applied_volumes
+        .iter()
+        .map(|v| v.map_or("-".to_string(), |v| format!("{:.3}", v.get())))
+        .collect();
+    let _ = writeln!(out, "last_applied_volumes: [{}]", volumes.join(", "));
+    let _ = writeln!(out, "mappings:");
+    for m in build_mapping_info(state.model, &state.config) {
+        let _ = writeln!(
+            out,
+            "  {}: dial: {}, button: {}",
+            m.control,
+            m.dial.as_deref().unwrap_or("-"),
+            m.button.as_deref().unwrap_or("-")
+        );
+    }
+    out
+}
+
+/// Bring derived state in line with a wholesale config replacement (file
+/// reload, `ReloadConfig`, `SetConfig`). `previous` is the config it replaced.
+async fn apply_config(
+    state: &mut EngineState,
+    previous: &Config,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+) {
+    let unknown = std::fs::read_to_string(&state.config_path)
+        .map(|text| config::unknown_keys(&text))
+        .unwrap_or_default();
+    for warning in state.config.validate().into_iter().chain(unknown) {
+        warn!("config: {warning}");
+    }
+    state.volume_curve = state.config.signal.volume_curve();
+    state.mic_volume_curve = state.config.signal.mic_volume_curve();
+    reset_changed_controls(state, previous);
+    if state.config.leds.clear_on_reload {
+        for cmd in HidCommand::all_off_sequence() {
+            send_hid(hid_cmd_tx, cmd).await;
+        }
+        state.sent_leds = Default::default();
+    }
+    refresh_leds(state, hid_cmd_tx).await;
+    sync_peak_sources(state, audio_cmd_tx).await;
+}
+
+/// Rebuild the pipeline and clear the cached volume of each control whose
+/// mapping or signal parameters differ from `previous`. Untouched controls
+/// keep their smoothing history.
+fn reset_changed_controls(state: &mut EngineState, previous: &Config) {
+    let curve_changed = previous.signal.volume_exponent != state.config.signal.volume_exponent
+        || previous.signal.curve_table != state.config.signal.curve_table
+        || previous.signal.mic_volume_exponent != state.config.signal.mic_volume_exponent
+        || previous.signal.volume_steps != state.config.signal.volume_steps;
+    for analog_id in 0..state.model.num_analog() {
+        let control = ControlId::from_analog_id(analog_id, state.model);
+        let mapping_changed = control.map(|id| previous.get_control(id))
+            != control.map(|id| state.config.get_control(id));
+        let params = pipeline_params(analog_id, state.model, &state.config);
+        if mapping_changed || params != pipeline_params(analog_id, state.model, previous) {
+            debug!("resetting signal pipeline for analog {analog_id}");
+            state.pipelines.insert(
+                analog_id,
+                make_pipeline(analog_id, state.model, &state.config),
+            );
+        }
+        // Clear the cached volume so a stale value isn't re-applied to
+        // sink-inputs of a different target.
+        if mapping_changed || curve_changed {
+            state.last_applied_volumes[analog_id as usize] = None;
+        }
+    }
+    // The new mappings may fight again; say so afresh.
+    state.warned_stream_conflicts.clear();
+}
+
+/// Rolling average window, delta threshold, and debounce for a control.
+fn pipeline_params(analog_id: u8, model: DeviceModel, config: &Config) -> (usize, u8, u64) {
+    if ControlId::from_analog_id(analog_id, model).is_some_and(ControlId::is_knob) {
+        (
             config.signal.knob_rolling_average,
             config.signal.knob_delta_threshold,
             config.signal.knob_debounce_ms,
         )
     } else {
-        SignalPipeline::new(
+        (
             config.signal.slider_rolling_average,
             config.signal.slider_delta_threshold,
             config.signal.slider_debounce_ms,
@@ -316,10 +659,54 @@ fn make_pipeline(analog_id: u8, config: &Config) -> SignalPipeline {
     }
 }
 
-fn rebuild_pipelines(config: &Config, pipelines: &mut HashMap<u8, SignalPipeline>) {
+fn make_pipeline(analog_id: u8, model: DeviceModel, config: &Config) -> SignalPipeline {
+    let (window_size, delta_threshold, debounce_ms) = pipeline_params(analog_id, model, config);
+    SignalPipeline::new(window_size, delta_threshold, debounce_ms)
+}
+
+fn rebuild_pipelines(
+    model: DeviceModel,
+    config: &Config,
+    pipelines: &mut HashMap<u8, SignalPipeline>,
+) {
     pipelines.clear();
-    for i in 0..ControlId::NUM_ANALOG {
-        pipelines.insert(i, make_pipeline(i, config));
+    for i in 0..model.num_analog() {
+        pipelines.insert(i, make_pipeline(i, model, config));
+    }
+}
+
+/// Feed an emitted value to the control's slam detector. Returns the
+/// control's `slam_action` when the value completes a slam gesture.
+fn detect_slam(analog_id: u8, value: u8, state: &mut EngineState) -> Option<ButtonAction> {
+    let action = ControlId::from_analog_id(analog_id, state.model)
+        .and_then(|id| state.config.get_control(id))
+        .and_then(|c| c.slam_action.clone())?;
+    let window = Duration::from_millis(state.config.signal.slam_window_ms);
+    let detector = &mut state.slam_detectors[usize::from(analog_id)];
+    if detector.process_at(value, std::time::Instant::now(), window) {
+        debug!("slam gesture on analog {analog_id}");
+        Some(action)
+    } else {
+        None
+    }
+}
+
+/// Whether the control on analog `analog_id` is marked `disabled`.
+fn is_disabled(analog_id: u8, model: DeviceModel, config: &Config) -> bool {
+    ControlId::from_analog_id(analog_id, model)
+        .and_then(|id| config.get_control(id))
+        .is_some_and(|c| c.disabled)
+}
+
+/// Flip a raw position end for end if its control has `reverse_travel`.
+fn physical_position(analog_id: u8, raw: u8, model: DeviceModel, config: &Config) -> u8 {
+    let reversed = ControlId::from_analog_id(analog_id, model)
+        .and_then(|id| config.get_control(id))
+        .is_some_and(|c| c.reverse_travel);
+    if reversed {
+        u8::MAX - raw
+    } else {
+        raw
     }
 }
 
@@ -329,29 +716,235 @@ async fn handle_position_change(
     state: &EngineState,
     audio_cmd_tx: &mpsc::Sender<AudioCommand>,
 ) -> Option<Volume> {
-    let control_id = ControlId::from_analog_id(analog_id)?;
+    let control_id = ControlId::from_analog_id(analog_id, state.model)?;
     let control_config = state.config.get_control(control_id)?;
     let dial_action = control_config.dial.as_ref()?;
 
     match dial_action {
-        DialAction::Volume { target } => {
-            let volume = state.volume_curve.hw_to_volume(processed_value);
-            send_volume_command(target, volume, state, audio_cmd_tx).await;
+        DialAction::Volume { .. } | DialAction::VolumeChain { .. } => {
+            let volume = dial_volume(control_config, dial_action, processed_value, state);
+            send_volume_command(dial_action.targets(), volume, state, audio_cmd_tx).await;
             Some(volume)
         }
     }
 }
 
+/// Writes to one stream by two controls this close together count as the
+/// controls fighting over it.
+const STREAM_CONFLICT_WINDOW: Duration = Duration::from_secs(2);
+
+/// Record that the control on `analog_id` just set the volume of the
+/// sink-inputs its dial resolves to, warning once per pair of controls if
+/// another control set one of them within [`STREAM_CONFLICT_WINDOW`].
+fn note_stream_writes(analog_id: u8, now: Instant, state: &mut EngineState) {
+    state
+        .stream_writers
+        .retain(|_, (_, at)| now.duration_since(*at) <= STREAM_CONFLICT_WINDOW);
+    let Some(control) = ControlId::from_analog_id(analog_id, state.model) else {
+        return;
+    };
+    let Some(dial_action) = state
+        .config
+        .get_control(control)
+        .and_then(|c| c.dial.as_ref())
+    else {
+        return;
+    };
+    let Some(ResolvedTarget::SinkInputs(inputs)) = resolve_first(
+        dial_action.targets(),
+        &state.audio_state,
+        &state.focused_window,
+        &state.config.matching,
+    ) else {
+        return;
+    };
+    for si in inputs {
+        let previous = state.stream_writers.insert(si.index, (analog_id, now));
+        let Some((other, at)) = previous else {
+            continue;
+        };
+        if other == analog_id || now.duration_since(at) > STREAM_CONFLICT_WINDOW {
+            continue;
+        }
+        let other_name = ControlId::from_analog_id(other, state.model)
+            .map_or_else(|| format!("analog {other}"), |id| id.config_key());
+        let pair = (other.min(analog_id), other.max(analog_id));
+        if state.warned_stream_conflicts.insert(pair) {
+            warn!(
+                "{other_name} and {} both set the volume of {} (index {}); whichever moves last wins",
+                control.config_key(),
+                si.name,
+                si.index
+            );
+        } else {
+            debug!(
+                "{other_name} and {} fighting over sink-input {}",
+                control.config_key(),
+                si.index
+            );
+        }
+    }
+}
+
+/// Volume `value` maps to on a control: its own `curve_points` if valid,
+/// else the `[signal]` curve for its target, snapped to `volume_steps`.
+fn dial_volume(
+    control_config: &ControlConfig,
+    dial_action: &DialAction,
+    value: u8,
+    state: &EngineState,
+) -> Volume {
+    let volume = match PiecewiseCurve::new(&control_config.curve_points) {
+        Ok(curve) => curve.hw_to_volume(value),
+        // Empty or invalid (warned about on load): fall back.
+        Err(_) => signal_curve(dial_action.targets(), state).hw_to_volume(value),
+    };
+    state.config.signal.quantize(volume)
+}
+
+async fn handle_audio_notification(
+    notification: AudioNotification,
+    state: &mut EngineState,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+) {
+    match notification {
+        AudioNotification::Connected => {
+            state.pulse_connected = true;
+            Metrics::incr(&state.metrics.pulse_connections);
+            info!("PulseAudio connected");
+        }
+        AudioNotification::Disconnected => {
+            state.pulse_connected = false;
+            state.audio_state = AudioState::default();
+            // Meters died with the PA session; reopen on the next snapshot.
+            state.peak_sources.clear();
+            state.source_peaks.clear();
+            // A restarted server hands out indices afresh.
+            state.stream_writers.clear();
+            state.reapply_after_reconnect = true;
+            warn!("PulseAudio disconnected");
+        }
+        AudioNotification::StateSnapshot(new_audio_state) => {
+            // The cleared state makes every stream of a new session count
+            // as appeared, so they get their volumes back here.
+            let new_sink_inputs =
+                appeared_sink_inputs(&state.audio_state.sink_inputs, &new_audio_state.sink_inputs);
+
+            if !new_sink_inputs.is_empty() {
+                debug!(
+                    "detected {} new sink-input(s), checking for volume re-apply",
+                    new_sink_inputs.len()
+                );
+                reapply_volumes_to_new_sink_inputs(
+                    &new_sink_inputs,
+                    &reapply_volumes(state),
+                    state.model,
+                    &state.config,
+                    &new_audio_state,
+                    audio_cmd_tx,
+                    &state.focused_window,
+                )
+                .await;
+            }
+
+            state.audio_state = new_audio_state;
+            debug!(
+                "audio state updated: {} sink-inputs",
+                state.audio_state.sink_inputs.len()
+            );
+            let reconnected = std::mem::take(&mut state.reapply_after_reconnect);
+            reapply_on_default_device_change(state, reconnected, audio_cmd_tx).await;
+            sync_peak_sources(state, audio_cmd_tx).await;
+            update_knob_levels(state, hid_cmd_tx).await;
+        }
+        AudioNotification::Level { index, peak } => {
+            state.source_peaks.insert(index, peak);
+            if !state.levels_waiters.is_empty() && levels_ready(state) {
+                answer_levels_waiters(state);
+            }
+            handle_peak(index, peak, state, hid_cmd_tx).await;
+        }
+    }
+}
+
+/// Volumes to re-apply to new sink-inputs, per analog control: the last one
+/// the control sent. A `focused_app` control that hasn't sent one, e.g. one
+/// whose mapping was just loaded and which hasn't moved since, uses the
+/// volume for its current position instead, so an app focused before it
+/// starts playing still gets the slider's level.
+fn reapply_volumes(state: &EngineState) -> [Option<Volume>; 9] {
+    std::array::from_fn(|i| {
+        state.last_applied_volumes[i].or_else(|| {
+            if !state.positions_seen[i] {
+                return None;
+            }
+            let analog_id = i as u8;
+            let control_config = ControlId::from_analog_id(analog_id, state.model)
+                .and_then(|id| state.config.get_control(id))?;
+            let dial_action = control_config.dial.as_ref()?;
+            if !dial_action.targets().contains(&AudioTarget::FocusedApp) {
+                return None;
+            }
+            let raw = physical_position(
+                analog_id,
+                state.last_positions[i],
+                state.model,
+                &state.config,
+            );
+            Some(dial_volume(control_config, dial_action, raw, state))
+        })
+    })
+}
+
+/// The `[signal]` curve for a dial: the mic curve if its targets resolve to
+/// an input, the main curve otherwise (including when nothing resolves).
+fn signal_curve<'a>(targets: &[AudioTarget], state: &'a EngineState) -> &'a VolumeCurve {
+    match resolve_first(
+        targets,
+        &state.audio_state,
+        &state.focused_window,
+        &state.config.matching,
+    ) {
+        Some(ResolvedTarget::Source(_)) => &state.mic_volume_curve,
+        _ => &state.volume_curve,
+    }
+}
+
+/// The sink-inputs in `current` that weren't in `previous`.
+///
+/// A stream is known by its index together with its app name and PID, so a
+/// recycled index on another app's stream still counts as new, while a
+/// stream whose volume or `media.name` changed does not.
+fn appeared_sink_inputs<'a>(
+    previous: &[SinkInputInfo],
+    current: &'a [SinkInputInfo],
+) -> Vec<&'a SinkInputInfo> {
+    let known: HashSet<(u32, &str, Option<u32>)> = previous
+        .iter()
+        .map(|si| (si.index, si.name.as_str(), si.pid))
+        .collect();
+    current
+        .iter()
+        .filter(|si| !known.contains(&(si.index, si.name.as_str(), si.pid)))
+        .collect()
+}
+
 /// Re-apply last-known volumes to newly appeared sink-inputs.
 ///
 /// When a browser (or other app) destroys and recreates a PA sink-input
 /// (e.g., pause/play, tab reload, seek), PA's stream-restore resets volume
 /// to 100%. This function detects the new sink-inputs and re-applies the
 /// volume the user had set via the hardware control.
+///
+/// `audio_state` is the snapshot the new sink-inputs came from; a volume chain
+/// only re-applies through the target that resolves in it.
 async fn reapply_volumes_to_new_sink_inputs(
     new_sink_inputs: &[&SinkInputInfo],
     last_applied_volumes: &[Option<Volume>; 9],
+    model: DeviceModel,
     config: &Config,
+    audio_state: &AudioState,
     audio_cmd_tx: &mpsc::Sender<AudioCommand>,
     focused_window: &Option<FocusedWindowInfo>,
 ) {
@@ -378,13 +971,13 @@ async fn reapply_volumes_to_new_sink_inputs(
             .or_insert((volume, priority, si.channels));
     };
 
-    for analog_id in 0..ControlId::NUM_ANALOG {
+    for analog_id in 0..model.num_analog() {
         let volume = match last_applied_volumes[analog_id as usize] {
             Some(v) => v,
             None => continue,
         };
 
-        let control_id = match ControlId::from_analog_id(analog_id) {
+        let control_id = match ControlId::from_analog_id(analog_id, model) {
             Some(id) => id,
             None => continue,
         };
@@ -399,22 +992,39 @@ async fn reapply_volumes_to_new_sink_inputs(
 
         let target = match dial_action {
             DialAction::Volume { target } => target,
+            DialAction::VolumeChain { targets } => {
+                match active_target(targets, audio_state, focused_window, &config.matching) {
+                    Some(target) => target,
+                    None => continue,
+                }
+            }
         };
 
         let priority = target.priority();
+        let candidates = new_sink_inputs
+            .iter()
+            .filter(|si| !config.matching.is_ignored(si));
 
         match target {
             AudioTarget::App { matcher } => {
-                for si in new_sink_inputs {
+                for si in candidates {
                     if matcher.matches(&AppProperties::from(*si)) {
                         record_match(si, volume, priority);
                     }
                 }
             }
             AudioTarget::FocusedApp => {
+                // The control sets the output device instead; the stream
+                // keeps its own volume.
+                if matches!(
+                    resolve_target(target, audio_state, focused_window, &config.matching),
+                    Some(ResolvedTarget::Sink(_))
+                ) {
+                    continue;
+                }
                 if let Some(focused) = focused_window {
                     let focused_proc = CachedProcInfo::lookup(focused.pid, &RealProc);
-                    for si in new_sink_inputs {
+                    for si in candidates {
                         if sink_input_matches_focused(si, focused, &focused_proc, &RealProc) {
                             record_match(si, volume, priority);
                         }
@@ -445,6 +1055,78 @@ async fn reapply_volumes_to_new_sink_inputs(
     }
 }
 
+/// Re-apply control volumes after the default output or input switched to
+/// another device, so a slider mapped to `DefaultOutput` keeps its level
+/// instead of leaving the new device wherever it was. With `reconnected`,
+/// the first snapshot of a new PulseAudio session, both are re-applied even
+/// if unchanged, since a restarted server brings its devices back at their
+/// saved volumes rather than the controls' levels.
+async fn reapply_on_default_device_change(
+    state: &mut EngineState,
+    reconnected: bool,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+) {
+    if reconnected {
+        debug!("PulseAudio reconnected, re-applying default device volumes");
+    }
+    let sink = state.audio_state.default_sink_name.clone();
+    if reconnected || sink != state.last_default_sink {
+        if sink != state.last_default_sink {
+            debug!(
+                "default sink changed: {:?} -> {sink:?}",
+                state.last_default_sink
+            );
+        }
+        state.last_default_sink = sink;
+        reapply_volume_to_default_device(&AudioTarget::DefaultOutput, state, audio_cmd_tx).await;
+    }
+    let source = state.audio_state.default_source_name.clone();
+    if reconnected || source != state.last_default_source {
+        if source != state.last_default_source {
+            debug!(
+                "default source changed: {:?} -> {source:?}",
+                state.last_default_source
+            );
+        }
+        state.last_default_source = source;
+        reapply_volume_to_default_device(&AudioTarget::DefaultInput, state, audio_cmd_tx).await;
+    }
+}
+
+/// Send the last volume of the first control (in analog order) currently
+/// driving `device` to that device.
+async fn reapply_volume_to_default_device(
+    device: &AudioTarget,
+    state: &EngineState,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+) {
+    for analog_id in 0..state.model.num_analog() {
+        let Some(volume) = state.last_applied_volumes[analog_id as usize] else {
+            continue;
+        };
+        let Some(dial) = ControlId::from_analog_id(analog_id, state.model)
+            .and_then(|id| state.config.get_control(id))
+            .and_then(|cc| cc.dial.as_ref())
+        else {
+            continue;
+        };
+        if active_target(
+            dial.targets(),
+            &state.audio_state,
+            &state.focused_window,
+            &state.config.matching,
+        ) == Some(device)
+        {
+            debug!(
+                "re-applying volume {:.2} to new default device",
+                volume.get()
+            );
+            send_volume_command(std::slice::from_ref(device), volume, state, audio_cmd_tx).await;
+            return;
+        }
+    }
+}
+
 /// Lazily initialize and cache a session D-Bus connection for MPRIS commands.
 ///
 /// The session bus connection is stable for the lifetime of a desktop session,
@@ -471,35 +1153,64 @@ async fn get_dbus_session(cached: &mut Option<zbus::Connection>) -> Option<&zbus
 /// No privilege escalation is possible — any command the user could configure here,
 /// they could also run directly from their shell.
 ///
-/// Limits: at most 8 concurrent exec commands. Each command is killed after 30 seconds.
-fn execute_command(command: &str) {
+/// Limits: at most as many concurrent exec commands as `semaphore` has permits
+/// (`[exec] max_concurrent`). Each command is killed after `[exec] timeout_secs`.
+/// With `[exec] capture_output`, stdout and stderr are read while the command
+/// runs and logged at debug level once it exits. Returns false if the command
+/// was dropped for the concurrency limit.
+fn execute_command(command: &str, semaphore: &Arc<Semaphore>, exec: &ExecConfig) -> bool {
     use std::process::Stdio;
-    use std::time::Duration;
-    use tokio::sync::Semaphore;
+    use tokio::io::AsyncReadExt;
 
-    static EXEC_SEMAPHORE: Semaphore = Semaphore::const_new(8);
+    async fn read_all(pipe: Option<impl tokio::io::AsyncRead + Unpin>) -> Vec<u8> {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf).await;
+        }
+        buf
+    }
 
-    let permit = match EXEC_SEMAPHORE.try_acquire() {
+    let permit = match Arc::clone(semaphore).try_acquire_owned() {
         Ok(permit) => permit,
         Err(_) => {
             warn!("exec command dropped (concurrency limit): {command}");
-            return;
+            return false;
         }
     };
 
     let command = command.to_owned();
+    let timeout = exec.timeout();
+    let capture = exec.capture_output;
+    let (stdout, stderr) = if capture {
+        (Stdio::piped(), Stdio::piped())
+    } else {
+        (Stdio::null(), Stdio::inherit())
+    };
     tokio::spawn(async move {
         let _permit = permit; // held until task completes
         match tokio::process::Command::new("sh")
             .arg("-c")
             .arg(&command)
             .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::inherit())
+            .stdout(stdout)
+            .stderr(stderr)
             .spawn()
         {
             Ok(mut child) => {
-                match tokio::time::timeout(Duration::from_secs(30), child.wait()).await {
+                // Drain the pipes alongside `wait` so a chatty command can't
+                // block on a full pipe.
+                let stdout = child.stdout.take();
+                let stderr = child.stderr.take();
+                let run = async {
+                    let (status, out, err) =
+                        tokio::join!(child.wait(), read_all(stdout), read_all(stderr));
+                    if capture {
+                        log_output(&command, "stdout", &out);
+                        log_output(&command, "stderr", &err);
+                    }
+                    status
+                };
+                match tokio::time::timeout(timeout, run).await {
                     Ok(Ok(status)) if !status.success() => {
                         warn!(
                             "exec command exited with {}: {command}",
@@ -512,7 +1223,10 @@ fn execute_command(command: &str) {
                         warn!("failed to wait on exec command: {e}");
                     }
                     Err(_) => {
-                        warn!("exec command timed out (30s), killing: {command}");
+                        warn!(
+                            "exec command timed out ({}s), killing: {command}",
+                            timeout.as_secs()
+                        );
                         let _ = child.kill().await;
                     }
                     _ => {}
@@ -523,40 +1237,147 @@ fn execute_command(command: &str) {
             }
         }
     });
+    true
+}
+
+/// Log one captured stream of an exec command at debug level, if it said anything.
+fn log_output(command: &str, stream: &str, output: &[u8]) {
+    let text = String::from_utf8_lossy(output);
+    let text = text.trim_end();
+    if !text.is_empty() {
+        debug!("exec command {stream} ({command}):\n{text}");
+    }
 }
 
 async fn handle_button_press(
     button_id: u8,
     state: &mut EngineState,
     audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+    config_self_write_tx: &mpsc::Sender<()>,
 ) {
-    let control_id = match ControlId::from_button_id(button_id) {
-        Some(id) => id,
-        None => return,
+    let Some(action) = button_action(button_id, state) else {
+        return;
     };
+    run_button_action(
+        &action,
+        state,
+        audio_cmd_tx,
+        hid_cmd_tx,
+        config_self_write_tx,
+    )
+    .await;
+}
 
-    let media_command = {
-        let control_config = match state.config.get_control(control_id) {
-            Some(c) => c,
-            None => return,
-        };
-        let button_action = match &control_config.button {
-            Some(action) => action,
-            None => return,
+/// The action mapped to the knob button `button_id`, if any and its control
+/// isn't disabled.
+fn button_action(button_id: u8, state: &EngineState) -> Option<ButtonAction> {
+    ControlId::from_button_id(button_id, state.model)
+        .and_then(|id| state.config.get_control(id))
+        .filter(|c| !c.disabled)
+        .and_then(|c| c.button.clone())
+}
+
+/// Schedule the first repeat of a just-pressed button whose action repeats
+/// while held.
+fn start_button_repeat(button_id: u8, now: Instant, state: &mut EngineState) {
+    if let Some(interval) = button_action(button_id, state).and_then(|a| a.repeat_interval()) {
+        state.button_repeats.insert(button_id, now + interval);
+    }
+}
+
+/// Run the action of every held button whose repeat is due at `now` and
+/// schedule its next one.
+async fn fire_button_repeats(
+    now: Instant,
+    state: &mut EngineState,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+    config_self_write_tx: &mpsc::Sender<()>,
+) {
+    let due: Vec<u8> = state
+        .button_repeats
+        .iter()
+        .filter(|(_, at)| **at <= now)
+        .map(|(button_id, _)| *button_id)
+        .collect();
+    for button_id in due {
+        // The mapping may have changed while the button was held.
+        let action = button_action(button_id, state);
+        let Some((action, interval)) =
+            action.and_then(|a| a.repeat_interval().map(|interval| (a, interval)))
+        else {
+            state.button_repeats.remove(&button_id);
+            continue;
         };
-        match button_action {
-            ButtonAction::Mute { target } => {
-                send_mute_toggle(target, state, audio_cmd_tx).await;
-                return;
-            }
-            ButtonAction::Exec { command } => {
-                execute_command(command);
-                return;
+        run_button_action(
+            &action,
+            state,
+            audio_cmd_tx,
+            hid_cmd_tx,
+            config_self_write_tx,
+        )
+        .await;
+        state.button_repeats.insert(button_id, now + interval);
+    }
+}
+
+/// Run a button action, from a knob press or a slam gesture. `action` is
+/// owned by the caller so `state` can be borrowed mutably.
+async fn run_button_action(
+    action: &ButtonAction,
+    state: &mut EngineState,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+    config_self_write_tx: &mpsc::Sender<()>,
+) {
+    let media_command = match action {
+        ButtonAction::Mute { target } => {
+            send_mute_toggle(std::slice::from_ref(target), state, audio_cmd_tx).await;
+            return;
+        }
+        ButtonAction::MuteDefaultInput => {
+            send_mute_toggle(&[AudioTarget::DefaultInput], state, audio_cmd_tx).await;
+            return;
+        }
+        ButtonAction::VolumeStep { target, delta, .. } => {
+            let targets = std::slice::from_ref(target);
+            if let Some(volume) = stepped_volume(targets, *delta, state) {
+                send_volume_command(targets, volume, state, audio_cmd_tx).await;
             }
-            ButtonAction::Media { command } => *command,
+            return;
         }
+        ButtonAction::Exec { command, .. } => {
+            let started = execute_command(command, &state.exec_semaphore, &state.config.exec);
+            Metrics::incr(if started {
+                &state.metrics.exec_run
+            } else {
+                &state.metrics.exec_dropped
+            });
+            return;
+        }
+        ButtonAction::SetPort { sink_name, port } => {
+            send_set_port(sink_name, port, &state.audio_state, audio_cmd_tx).await;
+            return;
+        }
+        ButtonAction::ToggleOutputs { a, b } => {
+            send_toggle_outputs(a, b, &state.audio_state, audio_cmd_tx).await;
+            return;
+        }
+        ButtonAction::ToggleSinkSuspend { sink_name } => {
+            send_toggle_suspend(sink_name, &state.audio_state, audio_cmd_tx).await;
+            return;
+        }
+        ButtonAction::MoveAppToSink { matcher, sink_name } => {
+            send_move_app(matcher, sink_name, &state.audio_state, audio_cmd_tx).await;
+            return;
+        }
+        ButtonAction::ToggleLeds => {
+            toggle_leds(state, hid_cmd_tx, config_self_write_tx).await;
+            return;
+        }
+        ButtonAction::Media { command } => *command,
     };
-    // Config borrow dropped. Safe to take &mut state.dbus_session.
     if let Some(conn) = get_dbus_session(&mut state.dbus_session).await {
         if let Err(e) = crate::mpris::send_media_command(conn, media_command).await {
             warn!("MPRIS command failed: {e}");
@@ -564,46 +1385,238 @@ async fn handle_button_press(
     }
 }
 
-enum ResolvedTarget<'a> {
-    Sink(&'a SinkInfo),
-    Source(&'a SourceInfo),
-    SinkInputs(Vec<&'a SinkInputInfo>),
-}
-
-fn resolve_target<'a>(
-    target: &AudioTarget,
-    audio_state: &'a AudioState,
-    focused_window: &Option<FocusedWindowInfo>,
-) -> Option<ResolvedTarget<'a>> {
-    match target {
-        AudioTarget::DefaultOutput => find_default_sink(audio_state).map(ResolvedTarget::Sink),
-        AudioTarget::DefaultInput => find_default_source(audio_state).map(ResolvedTarget::Source),
-        AudioTarget::App { matcher } => {
-            let inputs: Vec<_> = audio_state
-                .sink_inputs
-                .iter()
-                .filter(|si| matcher.matches(&AppProperties::from(*si)))
-                .collect();
-            (!inputs.is_empty()).then_some(ResolvedTarget::SinkInputs(inputs))
-        }
-        AudioTarget::FocusedApp => {
-            let focused = focused_window.as_ref()?;
-            let inputs = find_focused_sink_inputs(focused, &audio_state.sink_inputs, &RealProc);
-            (!inputs.is_empty()).then_some(ResolvedTarget::SinkInputs(inputs))
+/// Switch every LED zone off, or back on if they are already off.
+///
+/// Session-only by default, so the config file is left alone and a restart
+/// brings the LEDs back. With `[leds] persist_toggle` it flips `[leds] off`
+/// in the config instead, which keeps the zone flags as the user set them.
+async fn toggle_leds(
+    state: &mut EngineState,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+    config_self_write_tx: &mpsc::Sender<()>,
+) {
+    if state.config.leds.persist_toggle {
+        let mut config = state.config.clone();
+        config.leds.off = !(state.leds_off || config.leds.off);
+        if let IpcResponse::Error { message, .. } =
+            replace_config(state, config, config_self_write_tx).await
+        {
+            warn!("toggle_leds: {message}");
+            return;
         }
+        state.leds_off = false;
+    } else {
+        state.leds_off = !state.leds_off;
     }
+    let lit = !leds_dark(state) && state.config.leds.any_zone_enabled();
+    info!("LEDs toggled {}", if lit { "on" } else { "off" });
+    refresh_leds(state, hid_cmd_tx).await;
 }
 
-async fn send_volume_command(
-    target: &AudioTarget,
-    volume: Volume,
+/// Switch the named sink to `port`. Skipped with a log line when the sink
+/// isn't present or doesn't have that port.
+async fn send_set_port(
+    sink_name: &str,
+    port: &str,
+    audio_state: &AudioState,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+) {
+    let Some(sink) = audio_state.sinks.iter().find(|s| s.name == sink_name) else {
+        debug!("set_port: sink {sink_name} not present");
+        return;
+    };
+    if !sink.ports.iter().any(|p| p.name == port) {
+        warn!("set_port: sink {sink_name} has no port {port}");
+        return;
+    }
+    send_audio(
+        audio_cmd_tx,
+        AudioCommand::SetSinkPort {
+            index: sink.index,
+            port: port.to_owned(),
+        },
+    )
+    .await;
+}
+
+/// Make `a` the default sink, or `b` if `a` already is. When neither is the
+/// default, `a` is preferred and `b` used if `a` isn't present. Skipped with a
+/// log line when the sink to switch to isn't present.
+async fn send_toggle_outputs(
+    a: &str,
+    b: &str,
+    audio_state: &AudioState,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+) {
+    let present = |name: &str| audio_state.sinks.iter().any(|s| s.name == name);
+    let next = match audio_state.default_sink_name.as_deref() {
+        Some(current) if current == a => b,
+        Some(current) if current == b => a,
+        _ if present(a) => a,
+        _ => b,
+    };
+    if !present(next) {
+        debug!("toggle_outputs: sink {next} not present");
+        return;
+    }
+    send_audio(
+        audio_cmd_tx,
+        AudioCommand::SetDefaultSink {
+            name: next.to_owned(),
+        },
+    )
+    .await;
+}
+
+/// Suspend the named sink, or resume it if it is suspended. Skipped with a
+/// log line when the sink isn't present.
+async fn send_toggle_suspend(
+    sink_name: &str,
+    audio_state: &AudioState,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+) {
+    let Some(sink) = audio_state.sinks.iter().find(|s| s.name == sink_name) else {
+        debug!("toggle_sink_suspend: sink {sink_name} not present");
+        return;
+    };
+    send_audio(
+        audio_cmd_tx,
+        AudioCommand::SuspendSink {
+            index: sink.index,
+            suspend: !sink.suspended,
+        },
+    )
+    .await;
+}
+
+/// Move every sink-input matching `matcher` to the named sink. Inputs
+/// already playing there are left alone.
+async fn send_move_app(
+    matcher: &AppMatcher,
+    sink_name: &str,
+    audio_state: &AudioState,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
+) {
+    let Some(sink) = audio_state.sinks.iter().find(|s| s.name == sink_name) else {
+        debug!("move_app_to_sink: sink {sink_name} not present");
+        return;
+    };
+    for si in audio_state
+        .sink_inputs
+        .iter()
+        .filter(|si| si.sink_index != sink.index && matcher.matches(&AppProperties::from(*si)))
+    {
+        send_audio(
+            audio_cmd_tx,
+            AudioCommand::MoveSinkInput {
+                index: si.index,
+                sink_index: sink.index,
+            },
+        )
+        .await;
+    }
+}
+
+enum ResolvedTarget<'a> {
+    Sink(&'a SinkInfo),
+    Source(&'a SourceInfo),
+    SinkInputs(Vec<&'a SinkInputInfo>),
+}
+
+fn resolve_target<'a>(
+    target: &AudioTarget,
+    audio_state: &'a AudioState,
+    focused_window: &Option<FocusedWindowInfo>,
+    matching: &MatchingConfig,
+) -> Option<ResolvedTarget<'a>> {
+    match target {
+        AudioTarget::DefaultOutput => find_default_sink(audio_state).map(ResolvedTarget::Sink),
+        AudioTarget::DefaultInput => find_default_source(audio_state).map(ResolvedTarget::Source),
+        AudioTarget::App { matcher } => {
+            let inputs: Vec<_> = audio_state
+                .sink_inputs
+                .iter()
+                .filter(|si| !matching.is_ignored(si))
+                .filter(|si| matcher.matches(&AppProperties::from(*si)))
+                .collect();
+            (!inputs.is_empty()).then_some(ResolvedTarget::SinkInputs(inputs))
+        }
+        AudioTarget::FocusedApp => {
+            let focused = focused_window.as_ref()?;
+            let inputs =
+                find_focused_sink_inputs(focused, &audio_state.sink_inputs, matching, &RealProc);
+            if inputs.is_empty() {
+                return None;
+            }
+            if matching.focused_alone_controls_output {
+                if let Some(sink) = focused_alone_on_default_sink(&inputs, audio_state, matching) {
+                    return Some(ResolvedTarget::Sink(sink));
+                }
+            }
+            Some(ResolvedTarget::SinkInputs(inputs))
+        }
+    }
+}
+
+/// The default sink, if the focused app's `inputs` all play on it and no
+/// other stream does. Ignored streams don't count.
+fn focused_alone_on_default_sink<'a>(
+    inputs: &[&SinkInputInfo],
+    audio_state: &'a AudioState,
+    matching: &MatchingConfig,
+) -> Option<&'a SinkInfo> {
+    let sink = find_default_sink(audio_state)?;
+    let on_sink = |si: &SinkInputInfo| si.sink_index == sink.index;
+    let alone = inputs.iter().all(|si| on_sink(si))
+        && audio_state
+            .sink_inputs
+            .iter()
+            .filter(|si| on_sink(si) && !matching.is_ignored(si))
+            .all(|si| inputs.iter().any(|input| input.index == si.index));
+    alone.then_some(sink)
+}
+
+/// First target in `targets` that resolves, in order.
+fn active_target<'t>(
+    targets: &'t [AudioTarget],
+    audio_state: &AudioState,
+    focused_window: &Option<FocusedWindowInfo>,
+    matching: &MatchingConfig,
+) -> Option<&'t AudioTarget> {
+    targets
+        .iter()
+        .find(|target| resolve_target(target, audio_state, focused_window, matching).is_some())
+}
+
+/// Resolve the first target in `targets` that currently exists, so a chain
+/// like "focused app, else default output" never leaves the control dead.
+fn resolve_first<'a>(
+    targets: &[AudioTarget],
+    audio_state: &'a AudioState,
+    focused_window: &Option<FocusedWindowInfo>,
+    matching: &MatchingConfig,
+) -> Option<ResolvedTarget<'a>> {
+    targets
+        .iter()
+        .find_map(|target| resolve_target(target, audio_state, focused_window, matching))
+}
+
+async fn send_volume_command(
+    targets: &[AudioTarget],
+    volume: Volume,
     state: &EngineState,
     audio_cmd_tx: &mpsc::Sender<AudioCommand>,
 ) {
-    let resolved = match resolve_target(target, &state.audio_state, &state.focused_window) {
+    let resolved = match resolve_first(
+        targets,
+        &state.audio_state,
+        &state.focused_window,
+        &state.config.matching,
+    ) {
         Some(r) => r,
         None => return,
     };
+    Metrics::incr(&state.metrics.volume_commands);
     match resolved {
         ResolvedTarget::Sink(sink) => {
             send_audio(
@@ -643,12 +1656,39 @@ async fn send_volume_command(
     }
 }
 
+/// Volume of the first of `targets` that resolves, moved by `delta` and
+/// clamped. App targets step from their loudest stream, so streams that
+/// disagree end up in step.
+fn stepped_volume(targets: &[AudioTarget], delta: f64, state: &EngineState) -> Option<Volume> {
+    if !delta.is_finite() {
+        return None;
+    }
+    let current = match resolve_first(
+        targets,
+        &state.audio_state,
+        &state.focused_window,
+        &state.config.matching,
+    )? {
+        ResolvedTarget::Sink(sink) => sink.volume.get(),
+        ResolvedTarget::Source(source) => source.volume.get(),
+        ResolvedTarget::SinkInputs(inputs) => {
+            inputs.iter().map(|si| si.volume.get()).reduce(f64::max)?
+        }
+    };
+    Some(Volume::new(current + delta))
+}
+
 async fn send_mute_toggle(
-    target: &AudioTarget,
+    targets: &[AudioTarget],
     state: &EngineState,
     audio_cmd_tx: &mpsc::Sender<AudioCommand>,
 ) {
-    let resolved = match resolve_target(target, &state.audio_state, &state.focused_window) {
+    let resolved = match resolve_first(
+        targets,
+        &state.audio_state,
+        &state.focused_window,
+        &state.config.matching,
+    ) {
         Some(r) => r,
         None => return,
     };
@@ -674,12 +1714,16 @@ async fn send_mute_toggle(
             .await;
         }
         ResolvedTarget::SinkInputs(inputs) => {
+            // One state for every stream: mute them all if any is audible,
+            // else unmute them all, so streams that disagree end up in step
+            // instead of swapping.
+            let mute = inputs.iter().any(|si| !si.muted);
             for si in inputs {
                 send_audio(
                     audio_cmd_tx,
                     AudioCommand::SinkInputMute {
                         index: si.index,
-                        mute: !si.muted,
+                        mute,
                     },
                 )
                 .await;
@@ -774,9 +1818,31 @@ impl CachedProcInfo {
     }
 }
 
-/// PID-based matching for Wine/Proton games (strategies 6, 6a, 6b).
+/// How many parent levels [`pid_ancestors`] walks. Covers typical
+/// launcher → wrapper → game chains without climbing into the session.
+const MAX_ANCESTOR_DEPTH: usize = 4;
+
+/// Parent chain of `pid`, nearest first. Stops at init, at an unreadable
+/// process, or after [`MAX_ANCESTOR_DEPTH`] levels.
+fn pid_ancestors(pid: u32, proc_info: &impl ProcInfo) -> Vec<u32> {
+    let mut ancestors = Vec::with_capacity(MAX_ANCESTOR_DEPTH);
+    let mut current = pid;
+    while ancestors.len() < MAX_ANCESTOR_DEPTH {
+        match proc_info.parent_pid(current) {
+            Some(parent) if parent > 1 => {
+                ancestors.push(parent);
+                current = parent;
+            }
+            _ => break,
+        }
+    }
+    ancestors
+}
+
+/// PID-based matching for Wine/Proton games (strategies 6, 6a, 6b, 6c).
 ///
-/// Falls through: exact PID → same process group → sibling (same parent).
+/// Falls through: exact PID → same process group → sibling (same parent)
+/// → descendant (stream spawned by the window's process).
 fn pid_matches_focused(
     focused_pid: Option<u32>,
     si_pid: Option<u32>,
@@ -811,6 +1877,13 @@ fn pid_matches_focused(
         }
     }
 
+    // Strategy 6c: descendant — launchers and Electron apps often play audio
+    // from a child or grandchild of the window's process.
+    if pid_ancestors(sp, proc_info).contains(&wp) {
+        debug!("focused match via ancestor: window_pid={wp}, stream_pid={sp}");
+        return true;
+    }
+
     false
 }
 
@@ -825,6 +1898,7 @@ fn pid_matches_focused(
 /// 6. Exact PID match (Wine/Proton with same process)
 ///    6a. Same process group (Wine/Proton with separate processes)
 ///    6b. Sibling match — same parent PID (fallback for non-PGID launchers)
+///    6c. Descendant — stream process is a child of the window's process
 fn sink_input_matches_focused(
     si: &SinkInputInfo,
     focused: &FocusedWindowInfo,
@@ -860,17 +1934,19 @@ fn sink_input_matches_focused(
         (&focused.resource_class, &si.binary),
         (Some(rc), Some(bin)) if eq_ci(rc, bin)
     )
-    // Strategies 6/6a/6b: PID-based matching (Wine/Proton games)
+    // Strategies 6/6a/6b/6c: PID-based matching (Wine/Proton games)
     || pid_matches_focused(focused.pid, si.pid, focused_proc, proc_info)
 }
 
 /// Find sink-inputs that belong to the currently focused window.
 ///
 /// Pre-reads the focused window's `/proc` info once to avoid redundant reads
-/// when matching against multiple sink-inputs. De-duplicates by index.
+/// when matching against multiple sink-inputs. De-duplicates by index and
+/// skips sink-inputs on the `[matching] ignore` list.
 fn find_focused_sink_inputs<'a>(
     focused: &FocusedWindowInfo,
     sink_inputs: &'a [SinkInputInfo],
+    matching: &MatchingConfig,
     proc_info: &impl ProcInfo,
 ) -> Vec<&'a SinkInputInfo> {
     let focused_proc = CachedProcInfo::lookup(focused.pid, proc_info);
@@ -878,7 +1954,7 @@ fn find_focused_sink_inputs<'a>(
     let mut results = Vec::new();
 
     for si in sink_inputs {
-        if seen.contains(&si.index) {
+        if seen.contains(&si.index) || matching.is_ignored(si) {
             continue;
         }
 
@@ -906,8 +1982,31 @@ async fn save_and_notify(
     state: &EngineState,
     config_self_write_tx: &mpsc::Sender<()>,
 ) -> IpcResponse {
-    if let Err(e) = state.config.save(&state.config_path) {
+    save_config(&state.config, &state.config_path, config_self_write_tx).await
+}
+
+/// Save `config` and only then make it the running config, so a failed save
+/// leaves the daemon matching the file.
+async fn replace_config(
+    state: &mut EngineState,
+    config: Config,
+    config_self_write_tx: &mpsc::Sender<()>,
+) -> IpcResponse {
+    let response = save_config(&config, &state.config_path, config_self_write_tx).await;
+    if matches!(response, IpcResponse::Ok) {
+        state.config = config;
+    }
+    response
+}
+
+async fn save_config(
+    config: &Config,
+    path: &Path,
+    config_self_write_tx: &mpsc::Sender<()>,
+) -> IpcResponse {
+    if let Err(e) = config.save(path) {
         return IpcResponse::Error {
+            code: ErrorCode::ConfigIo,
             message: format!("failed to save config: {e}"),
         };
     }
@@ -917,28 +2016,87 @@ async fn save_and_notify(
     IpcResponse::Ok
 }
 
+/// Write the connected device's serial into the config when `[device]
+/// auto_lock` is on and no serial is set yet.
+///
+/// Skipped while several PCPanels are present, since there is no telling which
+/// one the user meant to lock to.
+async fn auto_lock_serial(
+    state: &mut EngineState,
+    device: &ConnectedDevice,
+    config_self_write_tx: &mpsc::Sender<()>,
+) {
+    if !state.config.device.auto_lock || state.config.device.serial.is_some() {
+        return;
+    }
+    if !device.only_device {
+        info!("multiple devices connected, not auto-locking serial");
+        return;
+    }
+    let Some(serial) = device.info.serial.clone() else {
+        warn!("device reports no serial, cannot auto-lock");
+        return;
+    };
+
+    state.config.device.serial = Some(serial.clone());
+    if let IpcResponse::Error { message, .. } = save_and_notify(state, config_self_write_tx).await {
+        warn!("auto-lock: {message}");
+        // Retry on the next connect rather than claim a lock that isn't on disk.
+        state.config.device.serial = None;
+        return;
+    }
+    info!("locked config to device serial {serial}");
+}
+
 async fn handle_ipc_request(
     request: IpcRequest,
     state: &mut EngineState,
     config_self_write_tx: &mpsc::Sender<()>,
+    audio_cmd_tx: &mpsc::Sender<AudioCommand>,
     cancel: &CancellationToken,
 ) -> IpcResponse {
     match request {
+        IpcRequest::Hello { client_version } => {
+            if client_version != PROTOCOL_VERSION {
+                debug!("IPC client speaks protocol v{client_version}, daemon v{PROTOCOL_VERSION}");
+            }
+            IpcResponse::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            }
+        }
         IpcRequest::GetStatus => {
-            let mappings = build_mapping_info(&state.config);
+            let mappings = build_mapping_info(state.model, &state.config);
             IpcResponse::Status {
                 device: DeviceStatus {
-                    connected: state.device_connected,
-                    serial: state.config.device.serial.clone(),
+                    connected: state.device.is_some(),
+                    serial: state
+                        .device
+                        .as_ref()
+                        .and_then(|d| d.serial.clone())
+                        .or_else(|| state.config.device.serial.clone()),
+                    model: state.device.as_ref().map(|d| d.model),
+                    error: if state.device.is_some() {
+                        None
+                    } else {
+                        state.device_error_rx.borrow().clone()
+                    },
                 },
                 pulse_connected: state.pulse_connected,
                 mappings,
+                stats: RuntimeStats {
+                    uptime_secs: state.started_at.elapsed().as_secs(),
+                    events_processed: state.metrics.events_processed.load(Ordering::Relaxed),
+                    pulse_reconnects: state.metrics.pulse_reconnects(),
+                },
             }
         }
+        IpcRequest::GetDeviceInfo => IpcResponse::DeviceInfo {
+            device: state.device.clone(),
+        },
         IpcRequest::ListApps => IpcResponse::Apps {
             apps: state.audio_state.sink_inputs.clone(),
         },
-        IpcRequest::ListDevices => {
+        IpcRequest::ListDevices { monitors } => {
             let devices: Vec<DeviceInfo> = state
                 .audio_state
                 .sinks
@@ -951,14 +2109,21 @@ async fn handle_ipc_request(
                     volume: s.volume,
                     muted: s.muted,
                 })
-                .chain(state.audio_state.sources.iter().map(|s| DeviceInfo {
-                    device_type: DeviceType::Input,
-                    index: s.index,
-                    name: s.name.clone(),
-                    description: s.description.clone(),
-                    volume: s.volume,
-                    muted: s.muted,
-                }))
+                .chain(
+                    state
+                        .audio_state
+                        .sources
+                        .iter()
+                        .filter(|s| monitors || !s.is_monitor())
+                        .map(|s| DeviceInfo {
+                            device_type: DeviceType::Input,
+                            index: s.index,
+                            name: s.name.clone(),
+                            description: s.description.clone(),
+                            volume: s.volume,
+                            muted: s.muted,
+                        }),
+                )
                 .collect();
             IpcResponse::Devices { devices }
         }
@@ -968,34 +2133,41 @@ async fn handle_ipc_request(
         IpcRequest::ListInputs => IpcResponse::Inputs {
             inputs: state.audio_state.sources.clone(),
         },
-        IpcRequest::AssignDial { control, action } => {
-            let entry = state
-                .config
-                .controls
-                .entry(control.config_key())
-                .or_default();
-            entry.dial = Some(action);
-            save_and_notify(state, config_self_write_tx).await
-        }
-        IpcRequest::AssignButton { control, action } => {
-            let entry = state
-                .config
-                .controls
-                .entry(control.config_key())
-                .or_default();
-            entry.button = Some(action);
-            save_and_notify(state, config_self_write_tx).await
-        }
-        IpcRequest::Unassign { control } => {
-            state.config.remove_control(control);
-            save_and_notify(state, config_self_write_tx).await
+        IpcRequest::AssignButton { control, .. } if control.is_slider() => {
+            slider_button_error(control)
+        }
+        request @ (IpcRequest::AssignDial { .. }
+        | IpcRequest::AssignButton { .. }
+        | IpcRequest::Unassign { .. }) => {
+            let mut config = state.config.clone();
+            config.apply_mapping_change(&request);
+            replace_config(state, config, config_self_write_tx).await
         }
         IpcRequest::GetConfig => match state.config.to_toml() {
             Ok(toml) => IpcResponse::Config { toml },
             Err(e) => IpcResponse::Error {
+                code: ErrorCode::ConfigIo,
                 message: format!("failed to serialize config: {e}"),
             },
         },
+        IpcRequest::SetConfig { toml } => {
+            // Validate before touching the live config; a rejected import
+            // leaves both memory and disk as they were.
+            let new_config = match Config::from_toml(&toml) {
+                Ok(c) => c,
+                Err(e) => {
+                    return IpcResponse::Error {
+                        code: ErrorCode::InvalidConfig,
+                        message: e.to_string(),
+                    };
+                }
+            };
+            let response = replace_config(state, new_config, config_self_write_tx).await;
+            if matches!(response, IpcResponse::Ok) {
+                info!("config replaced via IPC");
+            }
+            response
+        }
         IpcRequest::ReloadConfig => match Config::load(&state.config_path) {
             Ok(new_config) => {
                 state.config = new_config;
@@ -1006,33 +2178,293 @@ async fn handle_ipc_request(
                 IpcResponse::Ok
             }
             Err(e) => IpcResponse::Error {
+                code: config_error_code(&e),
                 message: format!("failed to reload config: {e}"),
             },
         },
+        IpcRequest::SetDefaultSink { device } => {
+            let sinks = state
+                .audio_state
+                .sinks
+                .iter()
+                .map(|s| (s.index, s.name.as_str(), s.description.as_str()));
+            match resolve_device(&device, sinks, "output") {
+                Ok(name) => {
+                    let name = name.to_owned();
+                    send_audio(audio_cmd_tx, AudioCommand::SetDefaultSink { name }).await;
+                    IpcResponse::Ok
+                }
+                Err((code, message)) => IpcResponse::Error { code, message },
+            }
+        }
+        IpcRequest::SetDefaultSource { device } => {
+            let sources = state
+                .audio_state
+                .sources
+                .iter()
+                .map(|s| (s.index, s.name.as_str(), s.description.as_str()));
+            match resolve_device(&device, sources, "input") {
+                Ok(name) => {
+                    let name = name.to_owned();
+                    send_audio(audio_cmd_tx, AudioCommand::SetDefaultSource { name }).await;
+                    IpcResponse::Ok
+                }
+                Err((code, message)) => IpcResponse::Error { code, message },
+            }
+        }
+        IpcRequest::GetLevels => {
+            state.levels_until = Some(Instant::now() + LEVELS_HOLD);
+            sync_peak_sources(state, audio_cmd_tx).await;
+            levels(state)
+        }
+        IpcRequest::ResetLeds => {
+            // The engine loop resends the LEDs afterwards, all of them in
+            // case the device lost its state.
+            state.leds_off = false;
+            state.sent_leds = Default::default();
+            info!("LEDs reset to the configured colors");
+            IpcResponse::Ok
+        }
+        IpcRequest::GetLeds => IpcResponse::Leds {
+            leds: match state.device {
+                Some(_) => led_state(&state.sent_leds),
+                None => LedState::default(),
+            },
+        },
+        IpcRequest::ConfigDirty => match Config::load(&state.config_path) {
+            Ok(on_disk) => {
+                let changes = state.config.differences(&on_disk);
+                IpcResponse::ConfigDirty {
+                    dirty: !changes.is_empty(),
+                    changes,
+                }
+            }
+            Err(e) => IpcResponse::ConfigDirty {
+                dirty: true,
+                changes: vec![e.to_string()],
+            },
+        },
+        IpcRequest::GetFocused => IpcResponse::Focused {
+            tracking: state.focus_tracking,
+            window: state.focused_window.as_ref().map(|w| FocusedWindow {
+                desktop_file: w.desktop_file.clone(),
+                resource_name: w.resource_name.clone(),
+                resource_class: w.resource_class.clone(),
+                pid: w.pid,
+            }),
+            matches: state
+                .focused_window
+                .as_ref()
+                .map(|focused| {
+                    find_focused_sink_inputs(
+                        focused,
+                        &state.audio_state.sink_inputs,
+                        &state.config.matching,
+                        &RealProc,
+                    )
+                    .into_iter()
+                    .cloned()
+                    .collect()
+                })
+                .unwrap_or_default(),
+        },
         IpcRequest::Shutdown => {
             cancel.cancel();
             IpcResponse::Ok
         }
+        IpcRequest::Batch { requests } => {
+            // Config changes build up in `staged` and are saved once at the
+            // end. Other requests run with it swapped in, so they see the
+            // changes made before them.
+            let mut staged = state.config.clone();
+            // What the file holds, once a `ReloadConfig` has read it.
+            let mut on_disk = None;
+            // Responses to the changes the final save has to persist.
+            let mut unsaved = Vec::new();
+            let mut responses = Vec::with_capacity(requests.len());
+            for request in requests {
+                let response = match request {
+                    IpcRequest::Batch { .. } => IpcResponse::Error {
+                        code: ErrorCode::InvalidRequest,
+                        message: "batches can't be nested".into(),
+                    },
+                    IpcRequest::AssignButton { control, .. } if control.is_slider() => {
+                        slider_button_error(control)
+                    }
+                    IpcRequest::SetConfig { toml } => match Config::from_toml(&toml) {
+                        Ok(config) => {
+                            staged = config;
+                            unsaved.push(responses.len());
+                            IpcResponse::Ok
+                        }
+                        Err(e) => IpcResponse::Error {
+                            code: ErrorCode::InvalidConfig,
+                            message: e.to_string(),
+                        },
+                    },
+                    IpcRequest::ReloadConfig => match Config::load(&state.config_path) {
+                        Ok(config) => {
+                            // Replaces everything staged so far, which then
+                            // needs no saving.
+                            staged = config.clone();
+                            on_disk = Some(config);
+                            unsaved.clear();
+                            IpcResponse::Ok
+                        }
+                        Err(e) => IpcResponse::Error {
+                            code: config_error_code(&e),
+                            message: format!("failed to reload config: {e}"),
+                        },
+                    },
+                    request => {
+                        if staged.apply_mapping_change(&request) {
+                            unsaved.push(responses.len());
+                            IpcResponse::Ok
+                        } else {
+                            std::mem::swap(&mut state.config, &mut staged);
+                            let response = Box::pin(handle_ipc_request(
+                                request,
+                                state,
+                                config_self_write_tx,
+                                audio_cmd_tx,
+                                cancel,
+                            ))
+                            .await;
+                            std::mem::swap(&mut state.config, &mut staged);
+                            response
+                        }
+                    }
+                };
+                responses.push(response);
+            }
+            if !unsaved.is_empty() {
+                let saved = save_config(&staged, &state.config_path, config_self_write_tx).await;
+                if let IpcResponse::Error { .. } = saved {
+                    for i in unsaved {
+                        responses[i] = saved.clone();
+                    }
+                    // Keep matching the file.
+                    staged = on_disk.unwrap_or_else(|| state.config.clone());
+                }
+            }
+            state.config = staged;
+            IpcResponse::Batch { responses }
+        }
+    }
+}
+
+/// Sliders have no button, so a button mapping on one could never fire.
+fn slider_button_error(control: ControlId) -> IpcResponse {
+    IpcResponse::Error {
+        code: ErrorCode::InvalidRequest,
+        message: format!(
+            "{} is a slider and has no button; button actions only go on knobs",
+            control.config_key()
+        ),
+    }
+}
+
+/// Whether `request`, or any request in it if it is a batch, satisfies `pred`.
+fn any_request(request: &IpcRequest, pred: impl Fn(&IpcRequest) -> bool) -> bool {
+    match request {
+        IpcRequest::Batch { requests } => requests.iter().any(pred),
+        request => pred(request),
+    }
+}
+
+/// A config file that doesn't parse is the user's to fix; anything else is I/O.
+fn config_error_code(e: &ConfigError) -> ErrorCode {
+    match e {
+        ConfigError::Parse { .. } | ConfigError::Invalid { .. } => ErrorCode::InvalidConfig,
+        _ => ErrorCode::ConfigIo,
+    }
+}
+
+/// Resolve a device given on the command line as `(index, name, description)`
+/// candidates: an exact index or name wins, otherwise a case-insensitive
+/// substring of the name or description must match exactly one device.
+fn resolve_device<'a>(
+    query: &str,
+    devices: impl IntoIterator<Item = (u32, &'a str, &'a str)>,
+    kind: &str,
+) -> Result<&'a str, (ErrorCode, String)> {
+    let devices: Vec<_> = devices.into_iter().collect();
+    if let Ok(index) = query.parse::<u32>() {
+        if let Some(device) = devices.iter().find(|d| d.0 == index) {
+            return Ok(device.1);
+        }
+    }
+    if let Some(device) = devices.iter().find(|d| d.1 == query) {
+        return Ok(device.1);
+    }
+    let needle = query.to_lowercase();
+    let matches: Vec<_> = devices
+        .iter()
+        .filter(|d| d.1.to_lowercase().contains(&needle) || d.2.to_lowercase().contains(&needle))
+        .collect();
+    match matches.as_slice() {
+        [only] => Ok(only.1),
+        [] => Err((
+            ErrorCode::DeviceNotFound,
+            format!("no {kind} device matches '{query}'"),
+        )),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(|d| d.2).collect();
+            Err((
+                ErrorCode::AmbiguousDevice,
+                format!(
+                    "'{query}' matches several {kind} devices: {}",
+                    names.join(", ")
+                ),
+            ))
+        }
     }
 }
 
-fn build_mapping_info(config: &Config) -> Vec<MappingInfo> {
+fn build_mapping_info(model: DeviceModel, config: &Config) -> Vec<MappingInfo> {
     let mut mappings = Vec::new();
 
-    for analog_id in 0..ControlId::NUM_ANALOG {
-        let Some(control_id) = ControlId::from_analog_id(analog_id) else {
-            continue;
-        };
+    for control_id in model.controls() {
         if let Some(cc) = config.get_control(control_id) {
             let dial = cc.dial.as_ref().map(|d| match d {
                 DialAction::Volume { target } => format!("volume {target}"),
+                DialAction::VolumeChain { targets } => {
+                    let chain: Vec<String> = targets.iter().map(ToString::to_string).collect();
+                    format!("volume chain({})", chain.join(", "))
+                }
             });
             let button = cc.button.as_ref().map(|b| match b {
                 ButtonAction::Mute { target } => format!("mute {target}"),
+                ButtonAction::MuteDefaultInput => {
+                    format!("mute {} (red while muted)", AudioTarget::DefaultInput)
+                }
+                ButtonAction::VolumeStep { target, delta, .. } => {
+                    format!("volume step {delta:+} {target}")
+                }
                 ButtonAction::Media { command } => format!("media {command:?}"),
-                ButtonAction::Exec { command } => format!("exec {command}"),
+                ButtonAction::Exec { command, .. } => format!("exec {command}"),
+                ButtonAction::SetPort { sink_name, port } => format!("port {port} on {sink_name}"),
+                ButtonAction::ToggleOutputs { a, b } => format!("toggle output {a} / {b}"),
+                ButtonAction::ToggleSinkSuspend { sink_name } => {
+                    format!("toggle suspend of {sink_name}")
+                }
+                ButtonAction::ToggleLeds => "toggle LEDs".to_string(),
+                ButtonAction::MoveAppToSink { matcher, sink_name } => {
+                    format!(
+                        "move {} to {sink_name}",
+                        AudioTarget::App {
+                            matcher: matcher.clone()
+                        }
+                    )
+                }
             });
-            if dial.is_some() || button.is_some() {
+            if cc.disabled {
+                mappings.push(MappingInfo {
+                    control: control_id.config_key(),
+                    dial: Some("disabled".into()),
+                    button: Some("disabled".into()),
+                });
+            } else if dial.is_some() || button.is_some() {
                 mappings.push(MappingInfo {
                     control: control_id.config_key(),
                     dial,
@@ -1045,80 +2477,522 @@ fn build_mapping_info(config: &Config) -> Vec<MappingInfo> {
     mappings
 }
 
-async fn send_initial_leds(hid_cmd_tx: &mpsc::Sender<HidCommand>, led_config: &LedConfig) {
-    use pcpaneld_core::hid::{LedSlot, LogoMode};
+/// Default color of the knob rings.
+const KNOB_LED: LedSlot = LedSlot::static_color(255, 255, 255);
 
-    let knob_leds = if led_config.knobs {
-        [LedSlot::static_color(255, 255, 255); 5]
-    } else {
-        [LedSlot::OFF; 5]
-    };
+/// Ring color of a `MuteDefaultInput` knob while the microphone is muted.
+const MIC_MUTED_LED: LedSlot = LedSlot::static_color(255, 0, 0);
 
-    let slider_leds = if led_config.sliders {
-        [LedSlot::static_color(0, 100, 255); 4]
-    } else {
-        [LedSlot::OFF; 4]
-    };
+/// Default color of the slider LED strips and labels.
+const SLIDER_LED: LedSlot = LedSlot::static_color(0, 100, 255);
 
-    let slider_label_leds = if led_config.slider_labels {
-        [LedSlot::static_color(0, 100, 255); 4]
-    } else {
-        [LedSlot::OFF; 4]
-    };
+/// Number of brightness steps a peak meter is quantized to. Keeps HID writes
+/// down when the level hovers around a value.
+const PEAK_METER_STEPS: f32 = 16.0;
 
-    send_hid(hid_cmd_tx, HidCommand::SetKnobLeds(knob_leds)).await;
-    send_hid(
-        hid_cmd_tx,
-        HidCommand::SetSliderLabelLeds(slider_label_leds),
-    )
-    .await;
-    send_hid(hid_cmd_tx, HidCommand::SetSliderLeds(slider_leds)).await;
+/// Number of brightness steps a knob level is quantized to, so small volume
+/// changes don't each cost a HID write.
+const KNOB_LEVEL_STEPS: f64 = 16.0;
 
-    let logo_cmd = if led_config.logo {
-        HidCommand::SetLogo {
-            mode: LogoMode::Static,
-            r: 255,
-            g: 255,
-            b: 255,
-            speed: 0,
-        }
-    } else {
-        HidCommand::SetLogo {
-            mode: LogoMode::Static,
-            r: 0,
-            g: 0,
-            b: 0,
-            speed: 0,
-        }
+/// Volume shown by a `Level` knob, 0.0-1.0: that of the target its dial
+/// currently drives, or of the loudest of its streams. Muted or missing
+/// targets show as 0.0. `None` for knobs without the effect.
+fn knob_level(control: &ControlConfig, state: &EngineState) -> Option<f64> {
+    if control.led_effect != Some(LedEffect::Level) {
+        return None;
+    }
+    let level = match resolve_first(
+        control.dial.as_ref()?.targets(),
+        &state.audio_state,
+        &state.focused_window,
+        &state.config.matching,
+    ) {
+        Some(ResolvedTarget::Sink(sink)) => (!sink.muted).then(|| sink.volume.get()),
+        Some(ResolvedTarget::Source(source)) => (!source.muted).then(|| source.volume.get()),
+        Some(ResolvedTarget::SinkInputs(inputs)) => inputs
+            .iter()
+            .filter(|si| !si.muted)
+            .map(|si| si.volume.get())
+            .reduce(f64::max),
+        None => None,
     };
-    send_hid(hid_cmd_tx, logo_cmd).await;
+    // PulseAudio volumes can go past 100%.
+    let level = level.unwrap_or(0.0).min(1.0);
+    Some((level * KNOB_LEVEL_STEPS).round() / KNOB_LEVEL_STEPS)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use pcpaneld_core::control::{AppMatcher, ControlConfig};
+/// [`knob_level`] of each knob.
+fn knob_levels(state: &EngineState) -> [Option<f64>; 5] {
+    std::array::from_fn(|i| {
+        state
+            .config
+            .get_control(ControlId::Knob(i as u8))
+            .and_then(|cc| knob_level(cc, state))
+    })
+}
 
-    /// Mock `/proc` reader for deterministic tests.
-    struct MockProc {
-        pgids: HashMap<u32, u32>,
-        ppids: HashMap<u32, u32>,
+/// Whether the default input is muted.
+fn mic_muted(state: &EngineState) -> bool {
+    find_default_source(&state.audio_state).is_some_and(|s| s.muted)
+}
+
+/// Rewrite the knob rings if a `Level` knob's volume or the microphone mute
+/// shown by a `MuteDefaultInput` knob changed.
+async fn update_knob_levels(state: &mut EngineState, hid_cmd_tx: &mpsc::Sender<HidCommand>) {
+    let levels = knob_levels(state);
+    let shows_mic_mute = (0..5).any(|i| {
+        state
+            .config
+            .get_control(ControlId::Knob(i))
+            .is_some_and(|cc| cc.button == Some(ButtonAction::MuteDefaultInput))
+    });
+    if levels.iter().all(Option::is_none) && !shows_mic_mute {
+        return;
+    }
+    let mut brightness = led_brightness(state);
+    if state.leds_idle {
+        brightness *= state.config.leds.idle_brightness_factor();
     }
+    let [knobs, ..] = led_commands(&state.config, &levels, mic_muted(state), brightness);
+    // Unchanged rings are skipped by `send_led`.
+    send_led(state, hid_cmd_tx, knobs).await;
+}
 
-    impl MockProc {
-        /// Empty mock — all lookups return `None`.
-        fn empty() -> Self {
-            Self {
-                pgids: HashMap::new(),
-                ppids: HashMap::new(),
-            }
-        }
+/// Source index whose peak level drives a `PeakMeter` control, if any.
+///
+/// Only device targets can be metered: the default output via its sink's
+/// monitor source, the default input directly.
+fn peak_source(control: &ControlConfig, audio_state: &AudioState) -> Option<u32> {
+    if control.led_effect != Some(LedEffect::PeakMeter) {
+        return None;
     }
+    // A chain is metered by its first target, the one it prefers.
+    match control.dial.as_ref()?.targets().first()? {
+        AudioTarget::DefaultOutput => find_default_sink(audio_state)?.monitor_source,
+        AudioTarget::DefaultInput => find_default_source(audio_state).map(|s| s.index),
+        AudioTarget::App { .. } | AudioTarget::FocusedApp => None,
+    }
+}
 
-    impl ProcInfo for MockProc {
-        fn parent_pid(&self, pid: u32) -> Option<u32> {
-            self.ppids.get(&pid).copied()
-        }
+/// Metered source index for each slider, `None` for sliders without a meter.
+fn slider_peak_sources(config: &Config, audio_state: &AudioState) -> [Option<u32>; 4] {
+    let mut sources = [None; 4];
+    for (slider, source) in sources.iter_mut().enumerate() {
+        *source = config
+            .get_control(ControlId::Slider(slider as u8))
+            .and_then(|cc| peak_source(cc, audio_state));
+    }
+    sources
+}
+
+/// How long the default devices stay metered after a `GetLevels`.
+const LEVELS_HOLD: Duration = Duration::from_secs(3);
+
+/// How long a `GetLevels` reply waits for meters that just opened.
+const LEVELS_WAIT: Duration = Duration::from_millis(500);
+
+/// Metered sources of the default output and input: the default sink's
+/// monitor and the default source.
+fn default_device_sources(audio_state: &AudioState) -> [Option<u32>; 2] {
+    [
+        find_default_sink(audio_state).and_then(|s| s.monitor_source),
+        find_default_source(audio_state).map(|s| s.index),
+    ]
+}
+
+/// Start and stop meters so the PA thread meters exactly the sources that
+/// peak-meter sliders and `GetLevels` clients need.
+async fn sync_peak_sources(state: &mut EngineState, audio_cmd_tx: &mpsc::Sender<AudioCommand>) {
+    let mut wanted: Vec<u32> = if state.config.leds.sliders {
+        slider_peak_sources(&state.config, &state.audio_state)
+            .into_iter()
+            .flatten()
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if state.levels_until.is_some() {
+        wanted.extend(
+            default_device_sources(&state.audio_state)
+                .into_iter()
+                .flatten(),
+        );
+    }
+    wanted.sort_unstable();
+    wanted.dedup();
+    if wanted == state.peak_sources {
+        return;
+    }
+    debug!("peak-metered sources: {wanted:?}");
+    for &index in &state.peak_sources {
+        if !wanted.contains(&index) {
+            send_audio(audio_cmd_tx, AudioCommand::StopMetering { index }).await;
+        }
+    }
+    for &index in &wanted {
+        if !state.peak_sources.contains(&index) {
+            send_audio(audio_cmd_tx, AudioCommand::StartMetering { index }).await;
+        }
+    }
+    state.source_peaks.retain(|index, _| wanted.contains(index));
+    state.peak_sources = wanted;
+}
+
+/// Close the meters kept open for `GetLevels` clients.
+async fn expire_levels(state: &mut EngineState, audio_cmd_tx: &mpsc::Sender<AudioCommand>) {
+    state.levels_until = None;
+    sync_peak_sources(state, audio_cmd_tx).await;
+}
+
+/// Latest levels of the default devices, as answered to `GetLevels`.
+fn levels(state: &EngineState) -> IpcResponse {
+    let [output, input] = default_device_sources(&state.audio_state)
+        .map(|source| source.and_then(|index| state.source_peaks.get(&index).copied()));
+    IpcResponse::Levels { output, input }
+}
+
+/// Whether every default device that exists has reported a level.
+fn levels_ready(state: &EngineState) -> bool {
+    default_device_sources(&state.audio_state)
+        .into_iter()
+        .flatten()
+        .all(|index| state.source_peaks.contains_key(&index))
+}
+
+/// Send the held `GetLevels` replies.
+fn answer_levels_waiters(state: &mut EngineState) {
+    state.levels_wait_until = None;
+    let response = levels(state);
+    for reply_tx in state.levels_waiters.drain(..) {
+        // Client may have disconnected; reply is best-effort.
+        let _ = reply_tx.send(response.clone());
+    }
+}
+
+/// Build the slider LED frame: metered sliders are scaled by their peak
+/// level, the rest keep their base color. Every slot is then gamma corrected.
+fn peak_meter_frame(
+    base: &[LedSlot; 4],
+    sources: &[Option<u32>; 4],
+    peaks: &[f32; 4],
+    gamma: f64,
+) -> [LedSlot; 4] {
+    let mut frame = *base;
+    for (slider, slot) in frame.iter_mut().enumerate() {
+        if sources[slider].is_some() {
+            let level = (peaks[slider] * PEAK_METER_STEPS).round() / PEAK_METER_STEPS;
+            *slot = slot.scaled(f64::from(level));
+        }
+        *slot = slot.gamma_corrected(gamma);
+    }
+    frame
+}
+
+async fn handle_peak(
+    index: u32,
+    level: f32,
+    state: &mut EngineState,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+) {
+    if !state.config.leds.sliders || state.leds_idle || leds_dark(state) {
+        return;
+    }
+    let sources = slider_peak_sources(&state.config, &state.audio_state);
+    for (slider, source) in sources.iter().enumerate() {
+        if *source == Some(index) {
+            state.slider_peaks[slider] = level;
+        }
+    }
+    let frame = peak_meter_frame(
+        &slider_leds(&state.config),
+        &sources,
+        &state.slider_peaks,
+        state.config.leds.gamma,
+    );
+    send_led(state, hid_cmd_tx, HidCommand::SetSliderLeds(frame)).await;
+}
+
+/// Deadline for the next idle dim, or `None` if idle dimming is disabled.
+fn idle_deadline(led_config: &LedConfig) -> Option<Instant> {
+    (led_config.idle_timeout_ms > 0)
+        .then(|| Instant::now() + Duration::from_millis(led_config.idle_timeout_ms))
+}
+
+/// Restart the idle timer after HID input, restoring full brightness if the
+/// LEDs were dimmed.
+async fn note_activity(state: &mut EngineState, hid_cmd_tx: &mpsc::Sender<HidCommand>) {
+    if state.leds_idle {
+        debug!("input received, restoring LED brightness");
+        refresh_leds(state, hid_cmd_tx).await;
+    } else {
+        state.idle_deadline = idle_deadline(&state.config.leds);
+    }
+}
+
+/// Send the configured LEDs at full brightness (or off, if toggled off) and
+/// restart the idle timer.
+async fn refresh_leds(state: &mut EngineState, hid_cmd_tx: &mpsc::Sender<HidCommand>) {
+    for cmd in led_commands(
+        &state.config,
+        &knob_levels(state),
+        mic_muted(state),
+        led_brightness(state),
+    ) {
+        send_led(state, hid_cmd_tx, cmd).await;
+    }
+    state.leds_idle = false;
+    state.idle_deadline = idle_deadline(&state.config.leds);
+}
+
+/// Time each frame of the startup animation stays on the knob rings.
+const LED_ANIMATION_FRAME: Duration = Duration::from_millis(60);
+
+/// Knob rings a lit ring takes to fade out behind the head of the sweep.
+const LED_ANIMATION_TAIL: usize = 3;
+
+/// Frames of the startup animation: a light sweeping from the first knob
+/// ring to the last, each ring fading out behind it, ending all dark.
+fn led_animation_frames(color: LedSlot) -> Vec<[LedSlot; 5]> {
+    (0..5 + LED_ANIMATION_TAIL)
+        .map(|head| {
+            std::array::from_fn(|i| {
+                if i > head {
+                    return LedSlot::OFF;
+                }
+                let fade = 1.0 - (head - i) as f64 / LED_ANIMATION_TAIL as f64;
+                color.scaled(fade)
+            })
+        })
+        .collect()
+}
+
+/// Play the startup animation on the knob rings if `[leds]
+/// startup_animation` is set and the rings are lit. Runs on its own task so
+/// the loop keeps handling input; the loop restores the configured LEDs
+/// once it finishes.
+fn start_led_animation(
+    state: &EngineState,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    let leds = &state.config.leds;
+    if !leds.startup_animation || !leds.knobs || leds_dark(state) {
+        return None;
+    }
+    let color = KNOB_LED.gamma_corrected(leds.gamma);
+    let frames = led_animation_frames(color);
+    let hid_cmd_tx = hid_cmd_tx.clone();
+    Some(tokio::spawn(async move {
+        for frame in frames {
+            send_hid(&hid_cmd_tx, HidCommand::SetKnobLeds(frame)).await;
+            tokio::time::sleep(LED_ANIMATION_FRAME).await;
+        }
+    }))
+}
+
+/// Wait for the startup animation to finish. Never completes without one.
+async fn led_animation_done(animation: &mut Option<tokio::task::JoinHandle<()>>) {
+    match animation {
+        Some(handle) => {
+            let _ = handle.await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Send an LED command unless its zone already shows exactly that. Rewriting
+/// an unchanged zone makes the panel flicker.
+async fn send_led(state: &mut EngineState, hid_cmd_tx: &mpsc::Sender<HidCommand>, cmd: HidCommand) {
+    let Some(zone) = led_zone(&cmd) else {
+        send_hid(hid_cmd_tx, cmd).await;
+        return;
+    };
+    if state.sent_leds[zone].as_ref() == Some(&cmd) {
+        return;
+    }
+    state.sent_leds[zone] = Some(cmd.clone());
+    send_hid(hid_cmd_tx, cmd).await;
+}
+
+/// The LEDs as last sent, per zone, for `GetLeds`.
+fn led_state(sent_leds: &[Option<HidCommand>]) -> LedState {
+    let mut leds = LedState::default();
+    for cmd in sent_leds.iter().flatten() {
+        match *cmd {
+            HidCommand::SetKnobLeds(slots) => leds.knobs = Some(slots),
+            HidCommand::SetSliderLabelLeds(slots) => leds.slider_labels = Some(slots),
+            HidCommand::SetSliderLeds(slots) => leds.sliders = Some(slots),
+            HidCommand::SetLogo {
+                mode,
+                r,
+                g,
+                b,
+                speed,
+            } => {
+                leds.logo = Some(LogoLed {
+                    mode,
+                    r,
+                    g,
+                    b,
+                    speed,
+                });
+            }
+            HidCommand::Init => {}
+        }
+    }
+    leds
+}
+
+/// Index of the LED zone `cmd` writes, in [`led_commands`] order.
+fn led_zone(cmd: &HidCommand) -> Option<usize> {
+    match cmd {
+        HidCommand::Init => None,
+        HidCommand::SetKnobLeds(_) => Some(0),
+        HidCommand::SetSliderLabelLeds(_) => Some(1),
+        HidCommand::SetSliderLeds(_) => Some(2),
+        HidCommand::SetLogo { .. } => Some(3),
+    }
+}
+
+/// Base color of one control's LEDs: its action color when
+/// `color_by_action` is on, otherwise the zone's uniform default.
+fn control_led(config: &Config, id: ControlId, default: LedSlot) -> LedSlot {
+    if config.get_control(id).is_some_and(|c| c.disabled) {
+        return LedSlot::OFF;
+    }
+    if !config.leds.color_by_action {
+        return default;
+    }
+    let Color(r, g, b) = config.leds.palette.color_for(config.get_control(id));
+    LedSlot::static_color(r, g, b)
+}
+
+/// Knob ring colors, scaled by `levels` for `Level` knobs and red on
+/// `MuteDefaultInput` knobs while `mic_muted`, or all off if the zone is
+/// disabled.
+fn knob_leds(config: &Config, levels: &[Option<f64>; 5], mic_muted: bool) -> [LedSlot; 5] {
+    if !config.leds.knobs {
+        return [LedSlot::OFF; 5];
+    }
+    std::array::from_fn(|i| {
+        let id = ControlId::Knob(i as u8);
+        let slot = control_led(config, id, KNOB_LED);
+        let shows_mic_mute = config
+            .get_control(id)
+            .is_some_and(|cc| !cc.disabled && cc.button == Some(ButtonAction::MuteDefaultInput));
+        if mic_muted && shows_mic_mute {
+            return MIC_MUTED_LED;
+        }
+        levels[i].map_or(slot, |level| slot.scaled(level))
+    })
+}
+
+/// Slider strip colors, or all off if the zone is disabled.
+fn slider_leds(config: &Config) -> [LedSlot; 4] {
+    if !config.leds.sliders {
+        return [LedSlot::OFF; 4];
+    }
+    std::array::from_fn(|i| control_led(config, ControlId::Slider(i as u8), SLIDER_LED))
+}
+
+/// Slider label colors (matching their slider), or all off if the zone is disabled.
+fn slider_label_leds(config: &Config) -> [LedSlot; 4] {
+    if !config.leds.slider_labels {
+        return [LedSlot::OFF; 4];
+    }
+    std::array::from_fn(|i| control_led(config, ControlId::Slider(i as u8), SLIDER_LED))
+}
+
+/// Build the commands for all LED zones, with every color scaled by
+/// `brightness` (1.0 = as configured), then gamma corrected. `knob_levels`
+/// come from [`knob_levels`], `mic_muted` from [`mic_muted`].
+fn led_commands(
+    config: &Config,
+    knob_levels: &[Option<f64>; 5],
+    mic_muted: bool,
+    brightness: f64,
+) -> [HidCommand; 4] {
+    use pcpaneld_core::hid::LogoMode;
+
+    let adjust = |slot: LedSlot| slot.scaled(brightness).gamma_corrected(config.leds.gamma);
+    let logo = adjust(if config.leds.logo {
+        LedSlot::static_color(255, 255, 255)
+    } else {
+        LedSlot::OFF
+    });
+
+    [
+        HidCommand::SetKnobLeds(knob_leds(config, knob_levels, mic_muted).map(adjust)),
+        HidCommand::SetSliderLabelLeds(slider_label_leds(config).map(adjust)),
+        HidCommand::SetSliderLeds(slider_leds(config).map(adjust)),
+        HidCommand::SetLogo {
+            mode: LogoMode::Static,
+            r: logo.r1,
+            g: logo.g1,
+            b: logo.b1,
+            speed: 0,
+        },
+    ]
+}
+
+/// Brightness before idle dimming: 0.0 while toggled off or dark for a
+/// screen lock, otherwise 1.0.
+fn led_brightness(state: &EngineState) -> f64 {
+    if leds_dark(state) {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+/// LEDs are off for now: toggled off, switched off by `[leds] off`, or the
+/// screen is locked and `[leds] off_on_lock` is set.
+fn leds_dark(state: &EngineState) -> bool {
+    state.leds_off
+        || state.config.leds.off
+        || (state.screen_locked && state.config.leds.off_on_lock)
+}
+
+/// Record a screen lock or unlock, switching the LEDs off or back on if
+/// `[leds] off_on_lock` is set.
+async fn set_screen_locked(
+    state: &mut EngineState,
+    locked: bool,
+    hid_cmd_tx: &mpsc::Sender<HidCommand>,
+) {
+    state.screen_locked = locked;
+    debug!("screen {}", if locked { "locked" } else { "unlocked" });
+    if state.config.leds.off_on_lock {
+        refresh_leds(state, hid_cmd_tx).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcpaneld_core::audio::PortInfo;
+    use pcpaneld_core::control::{ControlConfig, ControlPart};
+    use pcpaneld_core::hid::LogoMode;
+
+    /// Mock `/proc` reader for deterministic tests.
+    struct MockProc {
+        pgids: HashMap<u32, u32>,
+        ppids: HashMap<u32, u32>,
+    }
+
+    impl MockProc {
+        /// Empty mock — all lookups return `None`.
+        fn empty() -> Self {
+            Self {
+                pgids: HashMap::new(),
+                ppids: HashMap::new(),
+            }
+        }
+    }
+
+    impl ProcInfo for MockProc {
+        fn parent_pid(&self, pid: u32) -> Option<u32> {
+            self.ppids.get(&pid).copied()
+        }
 
         fn pgid(&self, pid: u32) -> Option<u32> {
             self.pgids.get(&pid).copied()
@@ -1145,6 +3019,8 @@ mod tests {
             binary: binary.map(String::from),
             flatpak_id: flatpak_id.map(String::from),
             pid: None,
+            cgroup: None,
+            media_name: None,
             sink_index: 0,
             volume: Volume::new(0.5),
             muted: false,
@@ -1174,16 +3050,52 @@ mod tests {
             Some("firefox"),
             Some("org.mozilla.firefox"),
         )];
-        let matched = find_focused_sink_inputs(&focused, &inputs, &MockProc::empty());
+        let matched = find_focused_sink_inputs(
+            &focused,
+            &inputs,
+            &MatchingConfig::default(),
+            &MockProc::empty(),
+        );
         assert_eq!(matched.len(), 1);
         assert_eq!(matched[0].index, 1);
     }
 
+    #[test]
+    fn focused_never_returns_ignored_binary() {
+        let focused = make_focused(None, Some("speech-dispatcher"), None);
+        let inputs = [
+            make_sink_input(1, "playback", Some("speech-dispatcher"), None),
+            make_sink_input(2, "speech-dispatcher", None, None),
+        ];
+        let matching = MatchingConfig {
+            ignore: vec!["Speech-Dispatcher".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            find_focused_sink_inputs(
+                &focused,
+                &inputs,
+                &MatchingConfig::default(),
+                &MockProc::empty()
+            )
+            .len(),
+            1
+        );
+        assert!(
+            find_focused_sink_inputs(&focused, &inputs, &matching, &MockProc::empty()).is_empty()
+        );
+    }
+
     #[test]
     fn focused_matches_resource_name_vs_binary() {
         let focused = make_focused(None, Some("firefox"), None);
         let inputs = [make_sink_input(1, "Firefox", Some("firefox"), None)];
-        let matched = find_focused_sink_inputs(&focused, &inputs, &MockProc::empty());
+        let matched = find_focused_sink_inputs(
+            &focused,
+            &inputs,
+            &MatchingConfig::default(),
+            &MockProc::empty(),
+        );
         assert_eq!(matched.len(), 1);
         assert_eq!(matched[0].index, 1);
     }
@@ -1193,7 +3105,12 @@ mod tests {
         // Native app where desktopFile matches binary name
         let focused = make_focused(Some("firefox"), None, None);
         let inputs = [make_sink_input(1, "Firefox", Some("firefox"), None)];
-        let matched = find_focused_sink_inputs(&focused, &inputs, &MockProc::empty());
+        let matched = find_focused_sink_inputs(
+            &focused,
+            &inputs,
+            &MatchingConfig::default(),
+            &MockProc::empty(),
+        );
         assert_eq!(matched.len(), 1);
         assert_eq!(matched[0].index, 1);
     }
@@ -1202,7 +3119,12 @@ mod tests {
     fn focused_matches_resource_class_vs_binary_fallback() {
         let focused = make_focused(None, None, Some("Firefox"));
         let inputs = [make_sink_input(1, "Firefox", Some("firefox"), None)];
-        let matched = find_focused_sink_inputs(&focused, &inputs, &MockProc::empty());
+        let matched = find_focused_sink_inputs(
+            &focused,
+            &inputs,
+            &MatchingConfig::default(),
+            &MockProc::empty(),
+        );
         assert_eq!(matched.len(), 1);
         assert_eq!(matched[0].index, 1);
     }
@@ -1211,7 +3133,12 @@ mod tests {
     fn focused_no_match_returns_empty() {
         let focused = make_focused(Some("org.gnome.Ptyxis"), Some("ptyxis"), Some("Ptyxis"));
         let inputs = [make_sink_input(1, "Firefox", Some("firefox"), None)];
-        let matched = find_focused_sink_inputs(&focused, &inputs, &MockProc::empty());
+        let matched = find_focused_sink_inputs(
+            &focused,
+            &inputs,
+            &MatchingConfig::default(),
+            &MockProc::empty(),
+        );
         assert!(matched.is_empty());
     }
 
@@ -1230,7 +3157,12 @@ mod tests {
             Some("firefox"),
             Some("org.mozilla.firefox"),
         )];
-        let matched = find_focused_sink_inputs(&focused, &inputs, &MockProc::empty());
+        let matched = find_focused_sink_inputs(
+            &focused,
+            &inputs,
+            &MatchingConfig::default(),
+            &MockProc::empty(),
+        );
         assert_eq!(matched.len(), 1);
     }
 
@@ -1245,7 +3177,13 @@ mod tests {
             Some("org.mozilla.firefox"),
         )];
         assert_eq!(
-            find_focused_sink_inputs(&focused, &inputs, &MockProc::empty()).len(),
+            find_focused_sink_inputs(
+                &focused,
+                &inputs,
+                &MatchingConfig::default(),
+                &MockProc::empty(),
+            )
+            .len(),
             1
         );
 
@@ -1253,7 +3191,13 @@ mod tests {
         let focused = make_focused(None, Some("Firefox"), None);
         let inputs = [make_sink_input(2, "Firefox", Some("firefox"), None)];
         assert_eq!(
-            find_focused_sink_inputs(&focused, &inputs, &MockProc::empty()).len(),
+            find_focused_sink_inputs(
+                &focused,
+                &inputs,
+                &MatchingConfig::default(),
+                &MockProc::empty(),
+            )
+            .len(),
             1
         );
 
@@ -1261,7 +3205,13 @@ mod tests {
         let focused = make_focused(Some("Org.Mozilla.Firefox"), None, None);
         let inputs = [make_sink_input(3, "Firefox", Some("firefox-bin"), None)];
         assert_eq!(
-            find_focused_sink_inputs(&focused, &inputs, &MockProc::empty()).len(),
+            find_focused_sink_inputs(
+                &focused,
+                &inputs,
+                &MatchingConfig::default(),
+                &MockProc::empty(),
+            )
+            .len(),
             1
         );
 
@@ -1269,7 +3219,13 @@ mod tests {
         let focused = make_focused(Some("Firefox"), None, None);
         let inputs = [make_sink_input(4, "Firefox", Some("firefox"), None)];
         assert_eq!(
-            find_focused_sink_inputs(&focused, &inputs, &MockProc::empty()).len(),
+            find_focused_sink_inputs(
+                &focused,
+                &inputs,
+                &MatchingConfig::default(),
+                &MockProc::empty(),
+            )
+            .len(),
             1
         );
 
@@ -1277,7 +3233,13 @@ mod tests {
         let focused = make_focused(None, None, Some("FIREFOX"));
         let inputs = [make_sink_input(5, "Firefox", Some("firefox"), None)];
         assert_eq!(
-            find_focused_sink_inputs(&focused, &inputs, &MockProc::empty()).len(),
+            find_focused_sink_inputs(
+                &focused,
+                &inputs,
+                &MatchingConfig::default(),
+                &MockProc::empty(),
+            )
+            .len(),
             1
         );
     }
@@ -1290,7 +3252,12 @@ mod tests {
             make_sink_input(1, "Firefox", Some("firefox"), None),
             make_sink_input(2, "Firefox - YouTube", Some("firefox"), None),
         ];
-        let matched = find_focused_sink_inputs(&focused, &inputs, &MockProc::empty());
+        let matched = find_focused_sink_inputs(
+            &focused,
+            &inputs,
+            &MatchingConfig::default(),
+            &MockProc::empty(),
+        );
         assert_eq!(matched.len(), 2);
     }
 
@@ -1460,6 +3427,59 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn focused_matches_via_grandchild() {
+        // Window PID 100 launched 150, which spawned the audio process 200.
+        let mock = MockProc {
+            pgids: HashMap::new(),
+            ppids: HashMap::from([(100, 10), (150, 100), (200, 150)]),
+        };
+        let focused_proc = CachedProcInfo::lookup(Some(100), &mock);
+        let mut focused = make_focused(Some("steam_app_12345"), None, None);
+        focused.pid = Some(100);
+        let mut si = make_sink_input(1, "Game Audio", Some("wine64-preloader"), None);
+        si.pid = Some(200);
+        assert!(sink_input_matches_focused(
+            &si,
+            &focused,
+            &focused_proc,
+            &mock
+        ));
+    }
+
+    #[test]
+    fn pid_ancestors_is_bounded_and_stops_at_init() {
+        // 10 → 9 → ... → 2 → 1: only MAX_ANCESTOR_DEPTH levels are walked.
+        let ppids = (2..=10).map(|p| (p, p - 1)).collect();
+        let mock = MockProc {
+            pgids: HashMap::new(),
+            ppids,
+        };
+        assert_eq!(pid_ancestors(10, &mock), vec![9, 8, 7, 6]);
+        assert_eq!(pid_ancestors(3, &mock), vec![2]);
+        assert!(pid_ancestors(42, &mock).is_empty());
+    }
+
+    #[test]
+    fn ancestor_beyond_depth_does_not_match() {
+        // Window PID 100 is five levels above the stream — too far to count.
+        let mock = MockProc {
+            pgids: HashMap::new(),
+            ppids: HashMap::from([(205, 204), (204, 203), (203, 202), (202, 201), (201, 100)]),
+        };
+        let focused_proc = CachedProcInfo::lookup(Some(100), &mock);
+        let mut focused = make_focused(Some("steam_app_12345"), None, None);
+        focused.pid = Some(100);
+        let mut si = make_sink_input(1, "Game Audio", Some("wine64-preloader"), None);
+        si.pid = Some(205);
+        assert!(!sink_input_matches_focused(
+            &si,
+            &focused,
+            &focused_proc,
+            &mock
+        ));
+    }
+
     #[test]
     fn pgid_zero_does_not_match() {
         // PGID 0 is special (kernel) — should not count as a match.
@@ -1605,9 +3625,35 @@ mod tests {
 
     // --- reapply_volumes_to_new_sink_inputs tests ---
 
+    #[test]
+    fn appeared_sink_inputs_catches_recycled_index() {
+        let mut firefox = make_sink_input(42, "Firefox", Some("firefox"), None);
+        firefox.pid = Some(100);
+        let previous = vec![firefox.clone()];
+
+        // Same stream, volume read back after we set it: not new.
+        let mut readback = firefox.clone();
+        readback.volume = Volume::new(0.2);
+        readback.media_name = Some("Next track".into());
+        assert!(appeared_sink_inputs(&previous, &[readback]).is_empty());
+
+        // Firefox's stream went away and Spotify's got its index.
+        let mut spotify = make_sink_input(42, "Spotify", Some("spotify"), None);
+        spotify.pid = Some(200);
+        let current = vec![spotify];
+        let appeared = appeared_sink_inputs(&previous, &current);
+        assert_eq!(appeared.len(), 1);
+        assert_eq!(appeared[0].name, "Spotify");
+
+        // Same app restarted, index reused by its new process.
+        let mut restarted = firefox.clone();
+        restarted.pid = Some(300);
+        assert_eq!(appeared_sink_inputs(&previous, &[restarted]).len(), 1);
+    }
+
     fn make_config_with_app_volume(analog_id: u8, matcher: AppMatcher) -> Config {
         let mut config = Config::default();
-        let control_id = ControlId::from_analog_id(analog_id).unwrap();
+        let control_id = ControlId::from_analog_id(analog_id, DeviceModel::Pro).unwrap();
         config.set_control(
             control_id,
             ControlConfig {
@@ -1615,6 +3661,11 @@ mod tests {
                     target: AudioTarget::App { matcher },
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         config
@@ -1622,12 +3673,17 @@ mod tests {
 
     fn make_config_with_target(analog_id: u8, target: AudioTarget) -> Config {
         let mut config = Config::default();
-        let control_id = ControlId::from_analog_id(analog_id).unwrap();
+        let control_id = ControlId::from_analog_id(analog_id, DeviceModel::Pro).unwrap();
         config.set_control(
             control_id,
             ControlConfig {
                 dial: Some(DialAction::Volume { target }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         config
@@ -1648,7 +3704,16 @@ mod tests {
             },
         );
 
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &None).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &None,
+        )
+        .await;
 
         let cmd = rx.try_recv().expect("expected a volume command");
         match cmd {
@@ -1680,7 +3745,16 @@ mod tests {
             },
         );
 
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &None).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &None,
+        )
+        .await;
 
         assert!(
             rx.try_recv().is_err(),
@@ -1702,7 +3776,16 @@ mod tests {
             },
         );
 
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &None).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &None,
+        )
+        .await;
 
         assert!(
             rx.try_recv().is_err(),
@@ -1720,7 +3803,16 @@ mod tests {
         let config = make_config_with_target(0, AudioTarget::FocusedApp);
         let focused = Some(make_focused(None, Some("firefox"), None));
 
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &focused).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &focused,
+        )
+        .await;
 
         let cmd = rx.try_recv().expect("expected a volume command");
         match cmd {
@@ -1737,6 +3829,108 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn focused_mute_brings_mixed_streams_into_step() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        state.focused_window = Some(make_focused(None, Some("firefox"), None));
+        let mut muted = make_sink_input(11, "Firefox", Some("firefox"), None);
+        muted.muted = true;
+        state.audio_state.sink_inputs =
+            vec![make_sink_input(10, "Firefox", Some("firefox"), None), muted];
+
+        send_mute_toggle(&[AudioTarget::FocusedApp], &state, &tx).await;
+
+        for expected_index in [10, 11] {
+            match rx.try_recv() {
+                Ok(AudioCommand::SinkInputMute { index, mute }) => {
+                    assert_eq!(index, expected_index);
+                    assert!(mute, "every stream should end up muted");
+                }
+                other => panic!("expected SinkInputMute, got {other:?}"),
+            }
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn app_mute_applies_one_state_to_every_stream() {
+        let target = AudioTarget::App {
+            matcher: AppMatcher {
+                binary: Some("firefox".into()),
+                ..Default::default()
+            },
+        };
+        // (starting mute states, state every stream should get)
+        let cases = [
+            ([false, false], true),
+            ([true, true], false),
+            ([true, false], true),
+        ];
+        for (start, expected) in cases {
+            let (tx, mut rx) = mpsc::channel(8);
+            let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+            state.audio_state.sink_inputs = start
+                .iter()
+                .enumerate()
+                .map(|(i, &muted)| {
+                    let mut si = make_sink_input(10 + i as u32, "Firefox", Some("firefox"), None);
+                    si.muted = muted;
+                    si
+                })
+                .collect();
+
+            send_mute_toggle(std::slice::from_ref(&target), &state, &tx).await;
+
+            let mut sent = Vec::new();
+            while let Ok(cmd) = rx.try_recv() {
+                match cmd {
+                    AudioCommand::SinkInputMute { index, mute } => sent.push((index, mute)),
+                    other => panic!("expected SinkInputMute, got {other:?}"),
+                }
+            }
+            assert_eq!(sent, [(10, expected), (11, expected)], "start {start:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn focus_then_audio_starts_uses_slider_position() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let config = make_config_with_target(0, AudioTarget::FocusedApp);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        // The slider was reported at 128, then its mapping was (re)loaded,
+        // which forgets the last volume it sent.
+        state.last_positions[0] = 128;
+        state.positions_seen[0] = true;
+        state.last_applied_volumes[0] = None;
+
+        // Firefox gets focus first; its stream shows up afterwards.
+        state.focused_window = Some(make_focused(None, Some("firefox"), None));
+        let si = make_sink_input(42, "Firefox", Some("firefox"), None);
+        reapply_volumes_to_new_sink_inputs(
+            &[&si],
+            &reapply_volumes(&state),
+            DeviceModel::Pro,
+            &state.config,
+            &AudioState::default(),
+            &tx,
+            &state.focused_window,
+        )
+        .await;
+
+        match rx.try_recv().expect("expected a volume command") {
+            AudioCommand::SinkInputVolume { index, volume, .. } => {
+                assert_eq!(index, 42);
+                assert_eq!(volume, VolumeCurve::new(1.0).hw_to_volume(128));
+            }
+            other => panic!("expected SinkInputVolume, got {other:?}"),
+        }
+
+        // A position never reported can't stand in for a volume.
+        state.positions_seen[0] = false;
+        assert_eq!(reapply_volumes(&state)[0], None);
+    }
+
     #[tokio::test]
     async fn reapply_focused_app_skips_when_not_focused() {
         let (tx, mut rx) = mpsc::channel(16);
@@ -1748,7 +3942,16 @@ mod tests {
         // Focused window is a different app
         let focused = Some(make_focused(None, Some("ptyxis"), None));
 
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &focused).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &focused,
+        )
+        .await;
 
         assert!(
             rx.try_recv().is_err(),
@@ -1773,6 +3976,11 @@ mod tests {
                     target: AudioTarget::DefaultOutput,
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         config.set_control(
@@ -1782,10 +3990,24 @@ mod tests {
                     target: AudioTarget::DefaultInput,
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &None).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &None,
+        )
+        .await;
 
         assert!(
             rx.try_recv().is_err(),
@@ -1807,7 +4029,16 @@ mod tests {
             },
         );
 
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &None).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &None,
+        )
+        .await;
 
         assert!(
             rx.try_recv().is_err(),
@@ -1839,6 +4070,11 @@ mod tests {
                     },
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         config.set_control(
@@ -1848,12 +4084,26 @@ mod tests {
                     target: AudioTarget::FocusedApp,
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
         // Firefox is focused, so both controls match the same sink-input
         let focused = Some(make_focused(None, Some("firefox"), None));
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &focused).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &focused,
+        )
+        .await;
 
         // Should get exactly one command with the App volume (0.3), not FocusedApp (0.8)
         let cmd = rx.try_recv().expect("expected a volume command");
@@ -1899,6 +4149,11 @@ mod tests {
                     },
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
         config.set_control(
@@ -1913,10 +4168,24 @@ mod tests {
                     },
                 }),
                 button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
             },
         );
 
-        reapply_volumes_to_new_sink_inputs(&new_inputs, &volumes, &config, &tx, &None).await;
+        reapply_volumes_to_new_sink_inputs(
+            &new_inputs,
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &AudioState::default(),
+            &tx,
+            &None,
+        )
+        .await;
 
         // Both have App priority (2). First processed (knob 0, analog_id=0) wins.
         let cmd = rx.try_recv().expect("expected a volume command");
@@ -1937,111 +4206,2450 @@ mod tests {
         );
     }
 
-    // --- engine integration test ---
+    // --- peak meter tests ---
 
-    /// End-to-end test: HID position change → signal pipeline → volume curve → audio command.
-    ///
-    /// Spawns the real engine loop, feeds it an audio state snapshot and HID position
-    /// change, then asserts the correct `AudioCommand::SinkInputVolume` emerges.
     #[tokio::test]
-    async fn engine_hid_position_produces_audio_command() {
-        use pcpaneld_core::audio::VolumeCurve;
+    async fn level_knob_follows_target_volume() {
+        let mut config = make_config_with_target(0, AudioTarget::DefaultOutput);
+        config.controls.get_mut("knob1").unwrap().led_effect = Some(LedEffect::Level);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        let (hid_cmd_tx, mut hid_cmd_rx) = mpsc::channel(8);
+
+        assert_eq!(knob_levels(&state), [Some(0.5), None, None, None, None]);
+        update_knob_levels(&mut state, &hid_cmd_tx).await;
+        match hid_cmd_rx.try_recv() {
+            Ok(HidCommand::SetKnobLeds(rings)) => {
+                assert_eq!(rings[0], KNOB_LED.scaled(0.5));
+                assert_eq!(rings[1], KNOB_LED);
+            }
+            other => panic!("expected SetKnobLeds, got {other:?}"),
+        }
 
-        let cancel = CancellationToken::new();
+        // Same volume in the next snapshot: no write.
+        update_knob_levels(&mut state, &hid_cmd_tx).await;
+        assert!(hid_cmd_rx.try_recv().is_err());
 
-        // --- Create ALL channel pairs for EngineChannels ---
-        let (hid_position_tx, hid_position_rx) = watch::channel([0u8; 9]);
-        let (_hid_button_tx, hid_button_rx) = mpsc::channel(4);
-        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(4);
-        let (audio_cmd_tx, mut audio_cmd_rx) = mpsc::channel(32);
-        let (audio_notify_tx, audio_notify_rx) = mpsc::channel(32);
-        let (_ipc_request_tx, ipc_request_rx) = mpsc::channel(4);
-        let (_tray_action_tx, tray_action_rx) = mpsc::channel(4);
-        let (_config_reload_tx, config_reload_rx) = mpsc::channel(4);
-        let (_focused_window_tx, focused_window_rx) =
-            watch::channel::<Option<FocusedWindowInfo>>(None);
-        let (_device_connected_tx, device_connected_rx) = watch::channel(false);
-        let (config_self_write_tx, _config_self_write_rx) = mpsc::channel(4);
+        state.audio_state.sinks[0].muted = true;
+        assert_eq!(knob_levels(&state)[0], Some(0.0));
+    }
 
-        // --- Build config with knob1 → app volume for "firefox" ---
-        let config = make_config_with_app_volume(
-            0,
-            AppMatcher {
-                binary: Some("firefox".into()),
+    #[tokio::test]
+    async fn mute_default_input_knob_turns_red_while_muted() {
+        let mut config = Config::default();
+        config.set_control(
+            ControlId::Knob(1),
+            ControlConfig {
+                dial: None,
+                button: Some(ButtonAction::MuteDefaultInput),
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
+            },
+        );
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        let (hid_cmd_tx, mut hid_cmd_rx) = mpsc::channel(8);
+
+        update_knob_levels(&mut state, &hid_cmd_tx).await;
+        match hid_cmd_rx.try_recv() {
+            Ok(HidCommand::SetKnobLeds(rings)) => assert_eq!(rings[1], KNOB_LED),
+            other => panic!("expected SetKnobLeds, got {other:?}"),
+        }
+
+        state.audio_state.sources[0].muted = true;
+        update_knob_levels(&mut state, &hid_cmd_tx).await;
+        match hid_cmd_rx.try_recv() {
+            Ok(HidCommand::SetKnobLeds(rings)) => {
+                assert_eq!(rings[0], KNOB_LED);
+                assert_eq!(rings[1], MIC_MUTED_LED);
+            }
+            other => panic!("expected SetKnobLeds, got {other:?}"),
+        }
+
+        // The button toggles the default source.
+        let (audio_cmd_tx, mut audio_cmd_rx) = mpsc::channel(8);
+        let (self_write_tx, _self_write_rx) = mpsc::channel(1);
+        run_button_action(
+            &ButtonAction::MuteDefaultInput,
+            &mut state,
+            &audio_cmd_tx,
+            &hid_cmd_tx,
+            &self_write_tx,
+        )
+        .await;
+        match audio_cmd_rx.try_recv() {
+            Ok(AudioCommand::SourceMute { index, mute }) => {
+                assert_eq!(index, 9);
+                assert!(!mute);
+            }
+            other => panic!("expected SourceMute, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn screen_lock_switches_leds_off_only_when_configured() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        let (hid_cmd_tx, mut hid_cmd_rx) = mpsc::channel(8);
+        refresh_leds(&mut state, &hid_cmd_tx).await;
+        while hid_cmd_rx.try_recv().is_ok() {}
+
+        set_screen_locked(&mut state, true, &hid_cmd_tx).await;
+        assert!(hid_cmd_rx.try_recv().is_err());
+        set_screen_locked(&mut state, false, &hid_cmd_tx).await;
+
+        state.config.leds.off_on_lock = true;
+        set_screen_locked(&mut state, true, &hid_cmd_tx).await;
+        assert_eq!(
+            drain_leds(&mut hid_cmd_rx),
+            led_bytes(&led_commands(&state.config, &[None; 5], false, 0.0))
+        );
+        set_screen_locked(&mut state, false, &hid_cmd_tx).await;
+        assert_eq!(
+            drain_leds(&mut hid_cmd_rx),
+            led_bytes(&led_commands(&state.config, &[None; 5], false, 1.0))
+        );
+    }
+
+    #[test]
+    fn volume_step_clamps_at_the_extremes() {
+        let mut state = EngineState::new(Config::default(), PathBuf::new());
+        let mut quiet = make_sink_input(1, "mpv", Some("mpv"), None);
+        quiet.volume = Volume::new(0.2);
+        let mut loud = make_sink_input(2, "mpv", Some("mpv"), None);
+        loud.volume = Volume::new(0.98);
+        state.audio_state = AudioState {
+            sinks: vec![SinkInfo {
+                index: 0,
+                name: "speakers".into(),
+                description: "Speakers".into(),
+                volume: Volume::new(0.02),
+                muted: false,
+                channels: 2,
+                monitor_source: None,
+                ports: Vec::new(),
+                active_port: None,
+                suspended: false,
+            }],
+            default_sink_name: Some("speakers".into()),
+            sink_inputs: vec![quiet, loud],
+            ..Default::default()
+        };
+        let output = [AudioTarget::DefaultOutput];
+        let mpv = [AudioTarget::App {
+            matcher: AppMatcher {
+                binary: Some("mpv".into()),
                 ..Default::default()
             },
+        }];
+        let step = |targets: &[AudioTarget], delta| {
+            stepped_volume(targets, delta, &state).map(Volume::get)
+        };
+
+        assert_eq!(step(&output, -0.05), Some(0.0));
+        assert!((step(&output, 0.05).unwrap() - 0.07).abs() < 1e-9);
+        // From the loudest stream
+        assert_eq!(step(&mpv, 0.05), Some(1.0));
+        assert!((step(&mpv, -0.08).unwrap() - 0.9).abs() < 1e-9);
+        assert_eq!(step(&output, f64::NAN), None);
+        assert_eq!(step(&[AudioTarget::DefaultInput], 0.05), None);
+    }
+
+    #[test]
+    fn focused_app_alone_on_output_resolves_to_sink() {
+        let mut audio_state = AudioState {
+            sinks: vec![SinkInfo {
+                index: 0,
+                name: "speakers".into(),
+                description: "Speakers".into(),
+                volume: Volume::new(0.5),
+                muted: false,
+                channels: 2,
+                monitor_source: None,
+                ports: Vec::new(),
+                active_port: None,
+                suspended: false,
+            }],
+            default_sink_name: Some("speakers".into()),
+            sink_inputs: vec![make_sink_input(1, "Firefox", Some("firefox"), None)],
+            ..Default::default()
+        };
+        let focused = Some(make_focused(None, Some("firefox"), None));
+        let mut matching = MatchingConfig {
+            focused_alone_controls_output: true,
+            ..Default::default()
+        };
+        let resolve = |audio_state: &AudioState, matching: &MatchingConfig| match resolve_target(
+            &AudioTarget::FocusedApp,
+            audio_state,
+            &focused,
+            matching,
+        ) {
+            Some(ResolvedTarget::Sink(sink)) => Some(sink.index),
+            Some(ResolvedTarget::SinkInputs(inputs)) => {
+                assert_eq!(inputs.len(), 1);
+                None
+            }
+            _ => panic!("focused_app resolved to neither the output nor a stream"),
+        };
+
+        // Only the focused app is playing: the dial drives the output.
+        assert_eq!(resolve(&audio_state, &matching), Some(0));
+
+        // A second app on the same output: back to the app's stream.
+        audio_state
+            .sink_inputs
+            .push(make_sink_input(2, "Spotify", Some("spotify"), None));
+        assert_eq!(resolve(&audio_state, &matching), None);
+
+        // Unless that app is ignored.
+        matching.ignore = vec!["spotify".into()];
+        assert_eq!(resolve(&audio_state, &matching), Some(0));
+
+        // Off by default.
+        assert_eq!(resolve(&audio_state, &MatchingConfig::default()), None);
+    }
+
+    fn make_peak_meter_config(slider: u8, target: AudioTarget) -> Config {
+        let mut config = Config::default();
+        config.set_control(
+            ControlId::Slider(slider),
+            ControlConfig {
+                dial: Some(DialAction::Volume { target }),
+                button: None,
+                led_effect: Some(LedEffect::PeakMeter),
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
+            },
         );
+        config
+    }
 
-        let config_path = PathBuf::from("/nonexistent/test-config.toml");
+    fn make_audio_state_with_devices() -> AudioState {
+        AudioState {
+            default_sink_name: Some("speakers".into()),
+            default_source_name: Some("mic".into()),
+            sinks: vec![SinkInfo {
+                index: 3,
+                name: "speakers".into(),
+                description: "Speakers".into(),
+                volume: Volume::new(0.5),
+                muted: false,
+                channels: 2,
+                monitor_source: Some(7),
+                ports: Vec::new(),
+                active_port: None,
+                suspended: false,
+            }],
+            sources: vec![SourceInfo {
+                index: 9,
+                name: "mic".into(),
+                description: "Microphone".into(),
+                volume: Volume::new(0.5),
+                muted: false,
+                channels: 1,
+            }],
+            sink_inputs: Vec::new(),
+        }
+    }
 
-        let channels = EngineChannels {
-            hid_position_rx,
-            hid_button_rx,
-            hid_cmd_tx,
-            audio_cmd_tx,
-            audio_notify_rx,
-            ipc_request_rx,
-            tray_action_rx,
-            config_reload_rx,
-            focused_window_rx,
-            device_connected_rx,
-            config_self_write_tx,
+    fn make_config_with_chain(analog_id: u8, targets: Vec<AudioTarget>) -> Config {
+        let mut config = Config::default();
+        config.set_control(
+            ControlId::from_analog_id(analog_id, DeviceModel::Pro).unwrap(),
+            ControlConfig {
+                dial: Some(DialAction::VolumeChain { targets }),
+                button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
+            },
+        );
+        config
+    }
+
+    #[tokio::test]
+    async fn volume_chain_falls_back_when_first_target_does_not_resolve() {
+        let config =
+            make_config_with_chain(0, vec![AudioTarget::FocusedApp, AudioTarget::DefaultOutput]);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        handle_position_change(0, 255, &state, &tx).await;
+
+        match rx.try_recv() {
+            Ok(AudioCommand::SinkVolume { index, .. }) => assert_eq!(index, 3),
+            other => panic!("expected SinkVolume for the default sink, got {other:?}"),
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn input_dials_use_mic_curve() {
+        let mut config = make_config_with_target(0, AudioTarget::DefaultInput);
+        config.set_control(
+            ControlId::from_analog_id(1, DeviceModel::Pro).unwrap(),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultOutput,
+                }),
+                ..ControlConfig::default()
+            },
+        );
+        config.signal.volume_exponent = 3.0;
+        config.signal.mic_volume_exponent = Some(1.0);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        handle_position_change(0, 128, &state, &tx).await;
+        match rx.try_recv() {
+            Ok(AudioCommand::SourceVolume { index, volume, .. }) => {
+                assert_eq!(index, 9);
+                assert_eq!(volume, VolumeCurve::new(1.0).hw_to_volume(128));
+            }
+            other => panic!("expected SourceVolume for the mic, got {other:?}"),
+        }
+
+        handle_position_change(1, 128, &state, &tx).await;
+        match rx.try_recv() {
+            Ok(AudioCommand::SinkVolume { index, volume, .. }) => {
+                assert_eq!(index, 3);
+                assert_eq!(volume, VolumeCurve::new(3.0).hw_to_volume(128));
+            }
+            other => panic!("expected SinkVolume for the speakers, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn volume_steps_snap_dial_volume() {
+        let mut config = make_config_with_target(0, AudioTarget::DefaultOutput);
+        config.signal.volume_steps = Some(20);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        // 130/255 is about 0.51; 0 and 255 stay at the endpoints.
+        for (value, expected) in [(130, 0.5), (0, 0.0), (255, 1.0)] {
+            handle_position_change(0, value, &state, &tx).await;
+            match rx.try_recv() {
+                Ok(AudioCommand::SinkVolume { volume, .. }) => {
+                    assert_eq!(volume.get(), expected, "value {value}");
+                }
+                other => panic!("expected SinkVolume, got {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn volume_chain_uses_first_resolving_target_only() {
+        let config = make_config_with_chain(
+            0,
+            vec![
+                AudioTarget::App {
+                    matcher: AppMatcher {
+                        binary: Some("firefox".into()),
+                        ..Default::default()
+                    },
+                },
+                AudioTarget::DefaultOutput,
+            ],
+        );
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        state
+            .audio_state
+            .sink_inputs
+            .push(make_sink_input(42, "Firefox", Some("firefox"), None));
+        let (tx, mut rx) = mpsc::channel(8);
+
+        handle_position_change(0, 255, &state, &tx).await;
+
+        match rx.try_recv() {
+            Ok(AudioCommand::SinkInputVolume { index, .. }) => assert_eq!(index, 42),
+            other => panic!("expected SinkInputVolume for firefox, got {other:?}"),
+        }
+        assert!(rx.try_recv().is_err(), "fallback must not also be driven");
+    }
+
+    #[tokio::test]
+    async fn reapply_volume_chain_follows_active_target() {
+        // Firefox resolves, so a new stream from the focused app (second in
+        // the chain) must not inherit the slider's volume.
+        let config = make_config_with_chain(
+            0,
+            vec![
+                AudioTarget::App {
+                    matcher: AppMatcher {
+                        binary: Some("firefox".into()),
+                        ..Default::default()
+                    },
+                },
+                AudioTarget::FocusedApp,
+            ],
+        );
+        let spotify = make_sink_input(2, "Spotify", Some("spotify"), None);
+        let audio = AudioState {
+            sink_inputs: vec![
+                make_sink_input(1, "Firefox", Some("firefox"), None),
+                spotify.clone(),
+            ],
+            ..Default::default()
         };
+        let focused = Some(make_focused(None, Some("spotify"), None));
+        let mut volumes: [Option<Volume>; 9] = [None; 9];
+        volumes[0] = Some(Volume::new(0.3));
+        let (tx, mut rx) = mpsc::channel(8);
+
+        reapply_volumes_to_new_sink_inputs(
+            &[&spotify],
+            &volumes,
+            DeviceModel::Pro,
+            &config,
+            &audio,
+            &tx,
+            &focused,
+        )
+        .await;
 
-        // --- Spawn engine ---
-        let engine_cancel = cancel.clone();
-        let engine_handle = tokio::spawn(async move {
-            run(config, config_path, channels, engine_cancel).await;
-        });
+        assert!(rx.try_recv().is_err());
+    }
 
-        // --- Seed audio state with a matching sink-input ---
-        let audio_state = AudioState {
-            sink_inputs: vec![make_sink_input(42, "Firefox", Some("firefox"), None)],
+    #[tokio::test]
+    async fn default_sink_change_reapplies_last_volume() {
+        let config = make_config_with_target(0, AudioTarget::DefaultOutput);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.last_applied_volumes[0] = Some(Volume::new(0.3));
+        state.last_default_sink = Some("headphones".into());
+        state.last_default_source = Some("mic".into());
+        state.audio_state = make_audio_state_with_devices();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        reapply_on_default_device_change(&mut state, false, &tx).await;
+
+        match rx.try_recv() {
+            Ok(AudioCommand::SinkVolume { index, volume, .. }) => {
+                assert_eq!(index, 3, "should target the new default sink");
+                assert!((volume.get() - 0.3).abs() < f64::EPSILON);
+            }
+            other => panic!("expected SinkVolume, got {other:?}"),
+        }
+        assert!(rx.try_recv().is_err());
+
+        // Same default in the next snapshot: nothing to do.
+        reapply_on_default_device_change(&mut state, false, &tx).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn default_source_change_reapplies_last_volume() {
+        let config = make_config_with_target(0, AudioTarget::DefaultInput);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.last_applied_volumes[0] = Some(Volume::new(0.6));
+        state.last_default_sink = Some("speakers".into());
+        state.last_default_source = Some("headset-mic".into());
+        state.audio_state = make_audio_state_with_devices();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        reapply_on_default_device_change(&mut state, false, &tx).await;
+
+        match rx.try_recv() {
+            Ok(AudioCommand::SourceVolume { index, volume, .. }) => {
+                assert_eq!(index, 9, "should target the new default source");
+                assert!((volume.get() - 0.6).abs() < f64::EPSILON);
+            }
+            other => panic!("expected SourceVolume, got {other:?}"),
+        }
+        assert!(rx.try_recv().is_err());
+        assert_eq!(state.last_default_source.as_deref(), Some("mic"));
+    }
+
+    #[tokio::test]
+    async fn pulse_reconnect_reapplies_cached_volumes() {
+        let matcher = AppMatcher {
+            binary: Some("firefox".into()),
             ..Default::default()
         };
-        audio_notify_tx
-            .send(AudioNotification::StateSnapshot(audio_state))
-            .await
+        let mut config = make_config_with_target(0, AudioTarget::DefaultOutput);
+        let app_control = make_config_with_app_volume(1, matcher)
+            .get_control(ControlId::Knob(1))
+            .cloned()
             .unwrap();
+        config.set_control(ControlId::Knob(1), app_control);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        let (audio_cmd_tx, mut audio_cmd_rx) = mpsc::channel(16);
+        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(16);
+
+        let mut audio = make_audio_state_with_devices();
+        audio.sink_inputs = vec![make_sink_input(42, "Firefox", Some("firefox"), None)];
+        for notification in [
+            AudioNotification::Connected,
+            AudioNotification::StateSnapshot(audio.clone()),
+        ] {
+            handle_audio_notification(notification, &mut state, &audio_cmd_tx, &hid_cmd_tx).await;
+        }
+        state.last_applied_volumes[0] = Some(Volume::new(0.3));
+        state.last_applied_volumes[1] = Some(Volume::new(0.4));
+        while audio_cmd_rx.try_recv().is_ok() {}
+
+        // The new session reuses index 42 for another app; Firefox is now 57.
+        audio.sink_inputs = vec![
+            make_sink_input(42, "Spotify", Some("spotify"), None),
+            make_sink_input(57, "Firefox", Some("firefox"), None),
+        ];
+        for notification in [
+            AudioNotification::Disconnected,
+            AudioNotification::Connected,
+            AudioNotification::StateSnapshot(audio),
+        ] {
+            handle_audio_notification(notification, &mut state, &audio_cmd_tx, &hid_cmd_tx).await;
+        }
 
-        // Wait for engine to process the snapshot. There's no observable side effect
-        // to poll for — the engine silently updates internal state.
-        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let mut sent = Vec::new();
+        while let Ok(cmd) = audio_cmd_rx.try_recv() {
+            sent.push(cmd);
+        }
+        assert_eq!(sent.len(), 2, "got {sent:?}");
+        assert!(sent.iter().any(|cmd| matches!(
+            cmd,
+            AudioCommand::SinkVolume { index: 3, volume, .. } if *volume == Volume::new(0.3)
+        )));
+        assert!(sent.iter().any(|cmd| matches!(
+            cmd,
+            AudioCommand::SinkInputVolume { index: 57, volume, .. } if *volume == Volume::new(0.4)
+        )));
+        assert!(!state.reapply_after_reconnect);
+    }
 
-        // --- Send HID position change for knob1 (analog_id 0) ---
-        // Signal pipeline with default knob params (window=3, delta=1, debounce=0)
-        // will let 128 through on first value since last_emitted is None.
-        let mut positions = [0u8; 9];
-        positions[0] = 128;
-        hid_position_tx.send(positions).unwrap();
+    #[tokio::test]
+    async fn default_sink_change_without_prior_volume_sends_nothing() {
+        let config = make_config_with_target(0, AudioTarget::DefaultOutput);
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        let (tx, mut rx) = mpsc::channel(8);
 
-        // --- Assert audio command with timeout ---
-        let cmd = tokio::time::timeout(std::time::Duration::from_secs(2), audio_cmd_rx.recv())
-            .await
-            .expect("timed out waiting for audio command")
-            .expect("audio channel closed unexpectedly");
+        reapply_on_default_device_change(&mut state, false, &tx).await;
 
-        let expected_volume = VolumeCurve::new(1.0).hw_to_volume(128);
-        match cmd {
-            AudioCommand::SinkInputVolume {
-                index,
-                volume,
-                channels,
-            } => {
-                assert_eq!(index, 42, "should target sink-input index 42");
-                assert!(
-                    (volume.get() - expected_volume.get()).abs() < f64::EPSILON,
-                    "volume mismatch: got {}, expected {}",
-                    volume.get(),
-                    expected_volume.get()
-                );
-                assert_eq!(channels, 2, "sink-input has 2 channels");
+        assert!(rx.try_recv().is_err());
+        assert_eq!(state.last_default_sink.as_deref(), Some("speakers"));
+    }
+
+    #[tokio::test]
+    async fn set_port_targets_named_sink() {
+        let mut audio = make_audio_state_with_devices();
+        audio.sinks[0].ports = vec![PortInfo {
+            name: "analog-output-headphones".into(),
+            description: "Headphones".into(),
+            available: true,
+        }];
+        let (tx, mut rx) = mpsc::channel(8);
+
+        send_set_port("speakers", "analog-output-headphones", &audio, &tx).await;
+
+        match rx.try_recv() {
+            Ok(AudioCommand::SetSinkPort { index, port }) => {
+                assert_eq!(index, 3);
+                assert_eq!(port, "analog-output-headphones");
+            }
+            other => panic!("expected SetSinkPort, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn set_port_skips_unknown_sink_or_port() {
+        let audio = make_audio_state_with_devices();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        send_set_port("missing", "analog-output-headphones", &audio, &tx).await;
+        send_set_port("speakers", "analog-output-headphones", &audio, &tx).await;
+
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn toggle_outputs_switches_to_the_other_sink() {
+        let mut audio = make_audio_state_with_devices();
+        let mut headphones = audio.sinks[0].clone();
+        headphones.index = 4;
+        headphones.name = "headphones".into();
+        audio.sinks.push(headphones);
+        let (tx, mut rx) = mpsc::channel(8);
+
+        for (default, expected) in [("speakers", "headphones"), ("headphones", "speakers")] {
+            audio.default_sink_name = Some(default.into());
+            send_toggle_outputs("speakers", "headphones", &audio, &tx).await;
+            match rx.try_recv() {
+                Ok(AudioCommand::SetDefaultSink { name }) => assert_eq!(name, expected),
+                other => panic!("expected SetDefaultSink, got {other:?}"),
             }
-            other => panic!("expected SinkInputVolume, got {other:?}"),
         }
 
-        // --- Clean up ---
-        cancel.cancel();
-        let _ = tokio::time::timeout(std::time::Duration::from_secs(1), engine_handle).await;
+        // Neither sink exists: nothing to switch to.
+        send_toggle_outputs("hdmi", "usb-headset", &audio, &tx).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn toggle_suspend_flips_named_sink_state() {
+        let mut audio = make_audio_state_with_devices();
+        let (tx, mut rx) = mpsc::channel(8);
+
+        send_toggle_suspend("speakers", &audio, &tx).await;
+        audio.sinks[0].suspended = true;
+        send_toggle_suspend("speakers", &audio, &tx).await;
+        send_toggle_suspend("missing", &audio, &tx).await;
+
+        for expected in [true, false] {
+            match rx.try_recv() {
+                Ok(AudioCommand::SuspendSink { index, suspend }) => {
+                    assert_eq!(index, 3);
+                    assert_eq!(suspend, expected);
+                }
+                other => panic!("expected SuspendSink, got {other:?}"),
+            }
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn move_app_moves_every_matching_input_not_already_there() {
+        let mut audio = make_audio_state_with_devices();
+        let mut already_there = make_sink_input(12, "Firefox", Some("firefox"), None);
+        already_there.sink_index = 3;
+        audio.sink_inputs = vec![
+            make_sink_input(10, "Firefox", Some("firefox"), None),
+            make_sink_input(11, "Firefox", Some("firefox"), None),
+            already_there,
+            make_sink_input(13, "Spotify", Some("spotify"), None),
+        ];
+        let matcher = AppMatcher {
+            binary: Some("firefox".into()),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(8);
+
+        send_move_app(&matcher, "speakers", &audio, &tx).await;
+
+        let mut moved = Vec::new();
+        while let Ok(cmd) = rx.try_recv() {
+            match cmd {
+                AudioCommand::MoveSinkInput { index, sink_index } => {
+                    assert_eq!(sink_index, 3);
+                    moved.push(index);
+                }
+                other => panic!("expected MoveSinkInput, got {other:?}"),
+            }
+        }
+        assert_eq!(moved, vec![10, 11]);
+    }
+
+    #[tokio::test]
+    async fn move_app_skips_missing_sink() {
+        let mut audio = make_audio_state_with_devices();
+        audio.sink_inputs = vec![make_sink_input(10, "Firefox", Some("firefox"), None)];
+        let matcher = AppMatcher {
+            binary: Some("firefox".into()),
+            ..Default::default()
+        };
+        let (tx, mut rx) = mpsc::channel(8);
+
+        send_move_app(&matcher, "missing", &audio, &tx).await;
+
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn peak_source_meters_default_sink_monitor_and_default_source() {
+        let audio = make_audio_state_with_devices();
+
+        let output = make_peak_meter_config(0, AudioTarget::DefaultOutput);
+        assert_eq!(
+            slider_peak_sources(&output, &audio),
+            [Some(7), None, None, None]
+        );
+
+        let input = make_peak_meter_config(2, AudioTarget::DefaultInput);
+        assert_eq!(
+            slider_peak_sources(&input, &audio),
+            [None, None, Some(9), None]
+        );
+    }
+
+    #[test]
+    fn peak_source_requires_opt_in_and_device_target() {
+        let audio = make_audio_state_with_devices();
+
+        // Same dial without the effect is not metered
+        let plain = make_config_with_target(
+            ControlId::Slider(0).to_analog_id(DeviceModel::Pro),
+            AudioTarget::DefaultOutput,
+        );
+        assert_eq!(slider_peak_sources(&plain, &audio), [None; 4]);
+
+        // App streams have no source to meter
+        let app = make_peak_meter_config(
+            0,
+            AudioTarget::App {
+                matcher: AppMatcher {
+                    binary: Some("firefox".into()),
+                    ..Default::default()
+                },
+            },
+        );
+        assert_eq!(slider_peak_sources(&app, &audio), [None; 4]);
+
+        // Default sink not present in the snapshot yet
+        let output = make_peak_meter_config(0, AudioTarget::DefaultOutput);
+        assert_eq!(
+            slider_peak_sources(&output, &AudioState::default()),
+            [None; 4]
+        );
+    }
+
+    #[test]
+    fn peak_meter_frame_scales_only_metered_sliders() {
+        let base = slider_leds(&Config::default());
+        let sources = [Some(7), None, None, None];
+        let silent = peak_meter_frame(&base, &sources, &[0.0, 1.0, 1.0, 1.0], 1.0);
+        let loud = peak_meter_frame(&base, &sources, &[1.0, 0.0, 0.0, 0.0], 1.0);
+
+        assert_eq!(silent[0], LedSlot::OFF);
+        assert_eq!(loud[0], SLIDER_LED);
+        // Unmetered sliders ignore levels entirely
+        assert_eq!(silent[1..], loud[1..]);
+        assert!(silent[1..].iter().all(|slot| *slot == SLIDER_LED));
+    }
+
+    #[test]
+    fn peak_meter_frame_is_monotonic_in_level() {
+        let base = slider_leds(&Config::default());
+        let sources = [Some(7); 4];
+        let mut prev = peak_meter_frame(&base, &sources, &[0.0; 4], 1.0)[0];
+        for step in 1..=100 {
+            let level = step as f32 / 100.0;
+            let slot = peak_meter_frame(&base, &sources, &[level; 4], 2.2)[0];
+            assert!(slot.b1 >= prev.b1, "brightness dropped at level {level}");
+            prev = slot;
+        }
+    }
+
+    #[test]
+    fn peak_meter_frame_respects_disabled_sliders() {
+        let mut config = Config::default();
+        config.leds.sliders = false;
+        let frame = peak_meter_frame(&slider_leds(&config), &[Some(7); 4], &[1.0; 4], 1.0);
+        assert!(frame.iter().all(|slot| *slot == LedSlot::OFF));
+    }
+
+    #[tokio::test]
+    async fn get_levels_meters_default_devices_until_expired() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, mut audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let response = handle_ipc_request(
+            IpcRequest::GetLevels,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        assert!(matches!(
+            response,
+            IpcResponse::Levels {
+                output: None,
+                input: None
+            }
+        ));
+        for expected in [7, 9] {
+            match audio_rx.try_recv() {
+                Ok(AudioCommand::StartMetering { index }) => assert_eq!(index, expected),
+                other => panic!("expected StartMetering for {expected}, got {other:?}"),
+            }
+        }
+        assert!(!levels_ready(&state));
+
+        state.source_peaks.insert(7, 0.5);
+        state.source_peaks.insert(9, 0.25);
+        assert!(levels_ready(&state));
+        // Polling again keeps the meters open without reopening them.
+        let response = handle_ipc_request(
+            IpcRequest::GetLevels,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        match response {
+            IpcResponse::Levels { output, input } => {
+                assert_eq!((output, input), (Some(0.5), Some(0.25)));
+            }
+            other => panic!("expected Levels, got {other:?}"),
+        }
+        assert!(audio_rx.try_recv().is_err());
+
+        expire_levels(&mut state, &audio_tx).await;
+        for expected in [7, 9] {
+            match audio_rx.try_recv() {
+                Ok(AudioCommand::StopMetering { index }) => assert_eq!(index, expected),
+                other => panic!("expected StopMetering for {expected}, got {other:?}"),
+            }
+        }
+        assert!(state.source_peaks.is_empty());
+        assert!(state.levels_until.is_none());
+    }
+
+    #[tokio::test]
+    async fn get_levels_reply_waits_for_the_meters() {
+        let cancel = CancellationToken::new();
+        let (_hid_position_tx, hid_position_rx) = watch::channel([0u8; 9]);
+        let (_hid_button_tx, hid_button_rx) = mpsc::channel(4);
+        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(64);
+        let (audio_cmd_tx, mut audio_cmd_rx) = mpsc::channel(32);
+        let (audio_notify_tx, audio_notify_rx) = mpsc::channel(32);
+        let (ipc_request_tx, ipc_request_rx) = mpsc::channel(4);
+        let (_tray_action_tx, tray_action_rx) = mpsc::channel(4);
+        let (_config_reload_tx, config_reload_rx) = mpsc::channel(4);
+        let (_state_dump_tx, state_dump_rx) = mpsc::channel(1);
+        let (_focused_window_tx, focused_window_rx) =
+            watch::channel::<Option<FocusedWindowInfo>>(None);
+        let (_device_connected_tx, device_connected_rx) = watch::channel(None);
+        let (config_self_write_tx, _config_self_write_rx) = mpsc::channel(4);
+        let (_screen_locked_tx, screen_locked_rx) = watch::channel(false);
+        let (_focus_tracking_tx, focus_tracking_rx) = watch::channel(false);
+        let channels = EngineChannels {
+            hid_position_rx,
+            hid_button_rx,
+            hid_cmd_tx,
+            audio_cmd_tx,
+            audio_notify_rx,
+            ipc_request_rx,
+            tray_action_rx,
+            config_reload_rx,
+            state_dump_rx,
+            focused_window_rx,
+            device_connected_rx,
+            config_self_write_tx,
+            screen_locked_rx,
+            focus_tracking_rx,
+            device_error_rx: watch::channel(None).1,
+        };
+        let engine_cancel = cancel.clone();
+        let engine_handle = tokio::spawn(async move {
+            let config_path = PathBuf::from("/nonexistent/test-config.toml");
+            run(
+                Config::default(),
+                config_path,
+                channels,
+                Arc::default(),
+                engine_cancel,
+            )
+            .await;
+        });
+        async fn request(tx: &mpsc::Sender<IpcMessage>, request: IpcRequest) -> IpcResponse {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            tx.send(IpcMessage { request, reply_tx }).await.unwrap();
+            tokio::time::timeout(Duration::from_secs(2), reply_rx)
+                .await
+                .expect("no reply")
+                .unwrap()
+        }
+
+        audio_notify_tx
+            .send(AudioNotification::StateSnapshot(
+                make_audio_state_with_devices(),
+            ))
+            .await
+            .unwrap();
+        // The engine's select! picks ready branches in random order, so wait
+        // for the snapshot to land before asking for levels.
+        while !matches!(
+            request(&ipc_request_tx, IpcRequest::ListOutputs).await,
+            IpcResponse::Outputs { outputs } if !outputs.is_empty()
+        ) {
+            tokio::task::yield_now().await;
+        }
+
+        let (reply_tx, mut reply_rx) = oneshot::channel();
+        let msg = IpcMessage {
+            request: IpcRequest::GetLevels,
+            reply_tx,
+        };
+        ipc_request_tx.send(msg).await.unwrap();
+        for expected in [7, 9] {
+            match tokio::time::timeout(Duration::from_secs(2), audio_cmd_rx.recv()).await {
+                Ok(Some(AudioCommand::StartMetering { index })) => assert_eq!(index, expected),
+                other => panic!("expected StartMetering for {expected}, got {other:?}"),
+            }
+        }
+        assert!(reply_rx.try_recv().is_err(), "answered before the meters");
+
+        for (index, peak) in [(7, 0.5), (9, 0.25)] {
+            let level = AudioNotification::Level { index, peak };
+            audio_notify_tx.send(level).await.unwrap();
+        }
+        match tokio::time::timeout(Duration::from_secs(2), reply_rx).await {
+            Ok(Ok(IpcResponse::Levels { output, input })) => {
+                assert_eq!((output, input), (Some(0.5), Some(0.25)));
+            }
+            other => panic!("expected Levels, got {other:?}"),
+        }
+
+        cancel.cancel();
+        engine_handle.await.unwrap();
+    }
+
+    // --- idle dimming tests ---
+
+    /// Flatten every color byte of an LED command sequence for comparison.
+    fn led_bytes(cmds: &[HidCommand]) -> Vec<u8> {
+        cmds.iter()
+            .flat_map(|cmd| cmd.encode()[2..].to_vec())
+            .collect()
+    }
+
+    fn drain_leds(rx: &mut mpsc::Receiver<HidCommand>) -> Vec<u8> {
+        let mut cmds = Vec::new();
+        while let Ok(cmd) = rx.try_recv() {
+            cmds.push(cmd);
+        }
+        led_bytes(&cmds)
+    }
+
+    #[tokio::test]
+    async fn toggle_leds_is_session_only_by_default() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        let (hid_cmd_tx, mut hid_cmd_rx) = mpsc::channel(8);
+        let (self_write_tx, mut self_write_rx) = mpsc::channel(4);
+
+        toggle_leds(&mut state, &hid_cmd_tx, &self_write_tx).await;
+        assert!(state.leds_off);
+        assert_eq!(
+            drain_leds(&mut hid_cmd_rx),
+            led_bytes(&led_commands(&state.config, &[None; 5], false, 0.0))
+        );
+        assert_eq!(state.config.leds, LedConfig::default());
+        assert!(
+            self_write_rx.try_recv().is_err(),
+            "config must not be saved"
+        );
+
+        toggle_leds(&mut state, &hid_cmd_tx, &self_write_tx).await;
+        assert!(!state.leds_off);
+        assert_eq!(
+            drain_leds(&mut hid_cmd_rx),
+            led_bytes(&led_commands(&state.config, &[None; 5], false, 1.0))
+        );
+    }
+
+    #[tokio::test]
+    async fn toggle_leds_persists_zone_flags_when_configured() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut config = Config::default();
+        config.leds.persist_toggle = true;
+        config.leds.logo = false;
+        let mut state = EngineState::new(config, path.clone());
+        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(8);
+        let (self_write_tx, mut self_write_rx) = mpsc::channel(4);
+
+        toggle_leds(&mut state, &hid_cmd_tx, &self_write_tx).await;
+        assert!(!state.leds_off);
+        assert!(state.config.leds.off);
+        assert!(leds_dark(&state));
+        assert_eq!(Config::load(&path).unwrap().leds, state.config.leds);
+        assert!(self_write_rx.try_recv().is_ok());
+
+        toggle_leds(&mut state, &hid_cmd_tx, &self_write_tx).await;
+        let leds = &state.config.leds;
+        assert!(!leds.off);
+        assert!(!leds_dark(&state));
+        // The zones the user switched off stay off.
+        assert!(leds.knobs && leds.sliders && leds.slider_labels && !leds.logo);
+        assert_eq!(Config::load(&path).unwrap().leds, *leds);
+    }
+
+    #[tokio::test]
+    async fn persisted_toggle_leds_keeps_running_config_when_save_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        // A regular file where the config directory should be makes the save fail.
+        let blocker = dir.path().join("not-a-dir");
+        std::fs::write(&blocker, "").unwrap();
+        let mut config = Config::default();
+        config.leds.persist_toggle = true;
+        let mut state = EngineState::new(config, blocker.join("config.toml"));
+        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(8);
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+
+        toggle_leds(&mut state, &hid_cmd_tx, &self_write_tx).await;
+        assert!(!state.config.leds.off);
+        assert!(!leds_dark(&state));
+    }
+
+    #[test]
+    fn idle_led_commands_never_brighter_than_full() {
+        let config = Config::default();
+        let full = led_bytes(&led_commands(&config, &[None; 5], false, 1.0));
+        for percent in [0u8, 10, 50, 99] {
+            let dimmed_leds = LedConfig {
+                idle_brightness: percent,
+                ..config.leds
+            };
+            let dimmed = led_bytes(&led_commands(
+                &config,
+                &[None; 5],
+                false,
+                dimmed_leds.idle_brightness_factor(),
+            ));
+            assert_eq!(full.len(), dimmed.len());
+            assert!(
+                full.iter().zip(&dimmed).all(|(f, d)| d <= f),
+                "idle_brightness={percent} produced a brighter LED"
+            );
+        }
+    }
+
+    #[test]
+    fn led_gamma_darkens_midtones() {
+        let mut config = Config::default();
+        config.leds.gamma = 2.2;
+        let linear = led_bytes(&led_commands(&Config::default(), &[None; 5], false, 0.5));
+        let corrected = led_bytes(&led_commands(&config, &[None; 5], false, 0.5));
+        assert!(linear.iter().zip(&corrected).all(|(l, c)| c <= l));
+        assert_ne!(linear, corrected);
+        // Full white stays full white
+        assert_eq!(
+            led_commands(&config, &[None; 5], false, 1.0)[0],
+            led_commands(&Config::default(), &[None; 5], false, 1.0)[0]
+        );
+
+        let base = slider_leds(&Config::default());
+        let half = peak_meter_frame(&base, &[Some(7); 4], &[0.5; 4], 2.2);
+        assert!(half[0].b1 < base[0].scaled(0.5).b1);
+    }
+
+    #[test]
+    fn idle_brightness_zero_turns_everything_dark() {
+        let cmds = led_commands(&Config::default(), &[None; 5], false, 0.0);
+        assert_eq!(cmds, HidCommand::all_off_sequence());
+    }
+
+    #[test]
+    fn led_animation_sweeps_across_the_rings_and_ends_dark() {
+        let color = LedSlot::static_color(200, 100, 0);
+        let frames = led_animation_frames(color);
+
+        assert_eq!(frames[0][0], color);
+        assert!(frames[0][1..].iter().all(|slot| *slot == LedSlot::OFF));
+        // Each ring lights at full color when the sweep reaches it...
+        for (i, frame) in frames.iter().take(5).enumerate() {
+            assert_eq!(frame[i], color, "frame {i}");
+        }
+        // ...and only dims after that.
+        for i in 0..5 {
+            let r: Vec<u8> = frames.iter().skip(i).map(|frame| frame[i].r1).collect();
+            assert!(r.windows(2).all(|w| w[1] <= w[0]), "ring {i}: {r:?}");
+        }
+        assert!(frames.last().unwrap().iter().all(|slot| slot.r1 == 0));
+    }
+
+    #[tokio::test]
+    async fn led_animation_only_when_enabled_and_lit() {
+        let (hid_tx, _hid_rx) = mpsc::channel(16);
+        let mut state = EngineState::new(Config::default(), PathBuf::new());
+        assert!(start_led_animation(&state, &hid_tx).is_none());
+
+        state.config.leds.startup_animation = true;
+        state.leds_off = true;
+        assert!(start_led_animation(&state, &hid_tx).is_none());
+
+        state.leds_off = false;
+        let animation = start_led_animation(&state, &hid_tx).unwrap();
+        animation.abort();
+    }
+
+    #[test]
+    fn idle_deadline_disabled_by_zero_timeout() {
+        assert!(idle_deadline(&LedConfig::default()).is_none());
+        let leds = LedConfig {
+            idle_timeout_ms: 5000,
+            ..LedConfig::default()
+        };
+        let deadline = idle_deadline(&leds).expect("timeout > 0 should arm the timer");
+        assert!(deadline > Instant::now());
+    }
+
+    // --- action color tests ---
+
+    #[test]
+    fn media_button_knob_gets_media_color() {
+        let mut config = Config::default();
+        config.leds.color_by_action = true;
+        config.set_control(
+            ControlId::Knob(3),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultOutput,
+                }),
+                button: Some(ButtonAction::Media {
+                    command: pcpaneld_core::control::MediaCommand::PlayPause,
+                }),
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
+            },
+        );
+
+        let Color(r, g, b) = config.leds.palette.media;
+        let knobs = knob_leds(&config, &[None; 5], false);
+        assert_eq!(knobs[3], LedSlot::static_color(r, g, b));
+        // Unmapped knobs are not colored like the media one
+        assert_ne!(knobs[0], knobs[3]);
+    }
+
+    #[test]
+    fn action_colors_follow_slider_to_its_label() {
+        let mut config = make_config_with_target(
+            ControlId::Slider(1).to_analog_id(DeviceModel::Pro),
+            AudioTarget::FocusedApp,
+        );
+        config.leds.color_by_action = true;
+
+        let Color(r, g, b) = config.leds.palette.focused;
+        let expected = LedSlot::static_color(r, g, b);
+        assert_eq!(slider_leds(&config)[1], expected);
+        assert_eq!(slider_label_leds(&config)[1], expected);
+    }
+
+    #[test]
+    fn action_colors_off_keeps_uniform_defaults() {
+        let config = make_config_with_target(0, AudioTarget::FocusedApp);
+        assert_eq!(knob_leds(&config, &[None; 5], false), [KNOB_LED; 5]);
+        assert_eq!(slider_leds(&config), [SLIDER_LED; 4]);
+    }
+
+    #[test]
+    fn disabled_control_is_inert_and_dark() {
+        let mut config = make_config_with_target(
+            ControlId::Slider(0).to_analog_id(DeviceModel::Pro),
+            AudioTarget::DefaultOutput,
+        );
+        config.set_control(
+            ControlId::Knob(1),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultInput,
+                }),
+                button: Some(ButtonAction::ToggleLeds),
+                disabled: true,
+                ..Default::default()
+            },
+        );
+        let mut state = EngineState::new(config, PathBuf::new());
+
+        assert!(is_disabled(1, DeviceModel::Pro, &state.config));
+        assert!(!is_disabled(5, DeviceModel::Pro, &state.config));
+        assert!(button_action(1, &state).is_none());
+        assert_eq!(knob_leds(&state.config, &[None; 5], false)[1], LedSlot::OFF);
+        assert_eq!(knob_leds(&state.config, &[None; 5], false)[0], KNOB_LED);
+
+        state.config.leds.color_by_action = true;
+        assert_eq!(knob_leds(&state.config, &[None; 5], false)[1], LedSlot::OFF);
+    }
+
+    #[test]
+    fn reverse_travel_flips_raw_position() {
+        let mut config = Config::default();
+        config.set_control(
+            ControlId::Slider(0),
+            ControlConfig {
+                reverse_travel: true,
+                ..ControlConfig::default()
+            },
+        );
+        let model = DeviceModel::Pro;
+        let slider = ControlId::Slider(0).to_analog_id(model);
+        let knob = ControlId::Knob(0).to_analog_id(model);
+
+        assert_eq!(physical_position(slider, 0, model, &config), 255);
+        assert_eq!(physical_position(slider, 255, model, &config), 0);
+        assert_eq!(physical_position(slider, 100, model, &config), 155);
+        // Unmapped and unreversed controls pass through.
+        assert_eq!(physical_position(knob, 100, model, &config), 100);
+    }
+
+    #[test]
+    fn detect_slam_returns_slam_action_on_double_hit() {
+        let mut config = Config::default();
+        config.set_control(
+            ControlId::Slider(0),
+            ControlConfig {
+                slam_action: Some(ButtonAction::ToggleLeds),
+                ..ControlConfig::default()
+            },
+        );
+        let mut state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        let slider = ControlId::Slider(0).to_analog_id(state.model);
+        let knob = ControlId::Knob(0).to_analog_id(state.model);
+
+        assert_eq!(detect_slam(slider, 255, &mut state), None);
+        assert_eq!(detect_slam(slider, 240, &mut state), None);
+        assert_eq!(
+            detect_slam(slider, 255, &mut state),
+            Some(ButtonAction::ToggleLeds)
+        );
+        // Controls without a slam action never fire.
+        for value in [0, 20, 0] {
+            assert_eq!(detect_slam(knob, value, &mut state), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn held_exec_button_repeats_until_released() {
+        let mut config = Config::default();
+        config.set_control(
+            ControlId::Knob(0),
+            ControlConfig {
+                button: Some(ButtonAction::Exec {
+                    command: "true".into(),
+                    repeat_ms: Some(100),
+                }),
+                ..Default::default()
+            },
+        );
+        let mut state = EngineState::new(config, PathBuf::new());
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let (hid_tx, _hid_rx) = mpsc::channel(4);
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let runs = |state: &EngineState| state.metrics.exec_run.load(Ordering::Relaxed);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        start_button_repeat(0, start, &mut state);
+        fire_button_repeats(at(50), &mut state, &audio_tx, &hid_tx, &self_write_tx).await;
+        assert_eq!(runs(&state), 0, "not due yet");
+        fire_button_repeats(at(100), &mut state, &audio_tx, &hid_tx, &self_write_tx).await;
+        fire_button_repeats(at(200), &mut state, &audio_tx, &hid_tx, &self_write_tx).await;
+        assert_eq!(runs(&state), 2);
+
+        // Release, as the engine loop does.
+        state.button_repeats.remove(&0);
+        fire_button_repeats(at(300), &mut state, &audio_tx, &hid_tx, &self_write_tx).await;
+        assert_eq!(runs(&state), 2);
+
+        // Buttons without `repeat_ms` fire only on press.
+        state.config.set_control(
+            ControlId::Knob(1),
+            ControlConfig {
+                button: Some(ButtonAction::Exec {
+                    command: "true".into(),
+                    repeat_ms: None,
+                }),
+                ..Default::default()
+            },
+        );
+        start_button_repeat(1, start, &mut state);
+        assert!(state.button_repeats.is_empty());
+    }
+
+    #[test]
+    fn controls_fighting_over_a_stream_are_noticed() {
+        let mut config = make_config_with_app_volume(
+            0,
+            AppMatcher {
+                binary: Some("firefox".into()),
+                ..Default::default()
+            },
+        );
+        config.set_control(
+            ControlId::Knob(1),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::FocusedApp,
+                }),
+                ..Default::default()
+            },
+        );
+        let mut state = EngineState::new(config, PathBuf::new());
+        state.audio_state.sink_inputs = vec![make_sink_input(42, "Firefox", Some("firefox"), None)];
+        state.focused_window = Some(make_focused(None, Some("firefox"), None));
+        let start = Instant::now();
+
+        // One control moving on its own isn't a conflict.
+        note_stream_writes(0, start, &mut state);
+        note_stream_writes(0, start + Duration::from_millis(100), &mut state);
+        assert!(state.warned_stream_conflicts.is_empty());
+
+        // The other control well after that isn't either.
+        let later = start + STREAM_CONFLICT_WINDOW * 2;
+        note_stream_writes(1, later, &mut state);
+        assert!(state.warned_stream_conflicts.is_empty());
+
+        // Both within the window is.
+        note_stream_writes(0, later + Duration::from_millis(500), &mut state);
+        assert_eq!(state.warned_stream_conflicts, HashSet::from([(0, 1)]));
+
+        // A mapping change allows a fresh warning.
+        let previous = state.config.clone();
+        reset_changed_controls(&mut state, &previous);
+        assert!(state.warned_stream_conflicts.is_empty());
+    }
+
+    /// Mute state lives in PulseAudio, not the engine, so a device
+    /// reconnect between two presses can't make the second one mute again.
+    #[tokio::test]
+    async fn mute_toggle_survives_device_reconnect() {
+        let cancel = CancellationToken::new();
+        let (_hid_position_tx, hid_position_rx) = watch::channel([0u8; 9]);
+        let (hid_button_tx, hid_button_rx) = mpsc::channel(4);
+        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(64);
+        let (audio_cmd_tx, mut audio_cmd_rx) = mpsc::channel(32);
+        let (audio_notify_tx, audio_notify_rx) = mpsc::channel(32);
+        let (ipc_request_tx, ipc_request_rx) = mpsc::channel(4);
+        let (_tray_action_tx, tray_action_rx) = mpsc::channel(4);
+        let (_config_reload_tx, config_reload_rx) = mpsc::channel(4);
+        let (_state_dump_tx, state_dump_rx) = mpsc::channel(1);
+        let (_focused_window_tx, focused_window_rx) =
+            watch::channel::<Option<FocusedWindowInfo>>(None);
+        let (_focus_tracking_tx, focus_tracking_rx) = watch::channel(false);
+        let (_screen_locked_tx, screen_locked_rx) = watch::channel(false);
+        let (device_connected_tx, device_connected_rx) = watch::channel(None);
+        let (config_self_write_tx, _config_self_write_rx) = mpsc::channel(4);
+
+        let matcher = AppMatcher {
+            binary: Some("firefox".into()),
+            ..Default::default()
+        };
+        let mut config = make_config_with_app_volume(0, matcher.clone());
+        config.controls.get_mut("knob1").unwrap().button = Some(ButtonAction::Mute {
+            target: AudioTarget::App { matcher },
+        });
+        let channels = EngineChannels {
+            hid_position_rx,
+            hid_button_rx,
+            hid_cmd_tx,
+            audio_cmd_tx,
+            audio_notify_rx,
+            ipc_request_rx,
+            tray_action_rx,
+            config_reload_rx,
+            state_dump_rx,
+            focused_window_rx,
+            focus_tracking_rx,
+            screen_locked_rx,
+            device_connected_rx,
+            device_error_rx: watch::channel(None).1,
+            config_self_write_tx,
+        };
+        let engine_cancel = cancel.clone();
+        let engine_handle = tokio::spawn(async move {
+            run(
+                config,
+                PathBuf::from("/nonexistent/test-config.toml"),
+                channels,
+                Arc::default(),
+                engine_cancel,
+            )
+            .await;
+        });
+
+        let snapshot = |muted: bool| {
+            let mut firefox = make_sink_input(42, "Firefox", Some("firefox"), None);
+            firefox.muted = muted;
+            AudioNotification::StateSnapshot(AudioState {
+                sink_inputs: vec![firefox],
+                ..Default::default()
+            })
+        };
+        let press = ButtonEvent {
+            button_id: 0,
+            pressed: true,
+        };
+        async fn next_mute(rx: &mut mpsc::Receiver<AudioCommand>) -> bool {
+            match tokio::time::timeout(Duration::from_secs(2), rx.recv()).await {
+                Ok(Some(AudioCommand::SinkInputMute { index: 42, mute })) => mute,
+                other => panic!("expected SinkInputMute for 42, got {other:?}"),
+            }
+        }
+        // The engine's select! picks ready branches in random order, so each
+        // step is confirmed over IPC before the press that depends on it.
+        async fn wait_for(
+            tx: &mpsc::Sender<IpcMessage>,
+            request: IpcRequest,
+            done: impl Fn(&IpcResponse) -> bool,
+        ) {
+            let poll = async {
+                loop {
+                    let (reply_tx, reply_rx) = oneshot::channel();
+                    let msg = IpcMessage {
+                        request: request.clone(),
+                        reply_tx,
+                    };
+                    tx.send(msg).await.unwrap();
+                    if done(&reply_rx.await.unwrap()) {
+                        return;
+                    }
+                    tokio::task::yield_now().await;
+                }
+            };
+            tokio::time::timeout(Duration::from_secs(2), poll)
+                .await
+                .unwrap_or_else(|_| panic!("engine never reached the state for {request:?}"));
+        }
+        let firefox_muted = |muted: bool| move |r: &IpcResponse| matches!(r, IpcResponse::Apps { apps } if apps.iter().any(|a| a.muted == muted));
+        let device_connected = |connected: bool| move |r: &IpcResponse| matches!(r, IpcResponse::DeviceInfo { device } if device.is_some() == connected);
+
+        device_connected_tx
+            .send(Some(connected_device(None, true)))
+            .unwrap();
+        wait_for(
+            &ipc_request_tx,
+            IpcRequest::GetDeviceInfo,
+            device_connected(true),
+        )
+        .await;
+        audio_notify_tx.send(snapshot(false)).await.unwrap();
+        wait_for(&ipc_request_tx, IpcRequest::ListApps, firefox_muted(false)).await;
+        hid_button_tx.send(press).await.unwrap();
+        assert!(next_mute(&mut audio_cmd_rx).await);
+        audio_notify_tx.send(snapshot(true)).await.unwrap();
+        wait_for(&ipc_request_tx, IpcRequest::ListApps, firefox_muted(true)).await;
+
+        device_connected_tx.send(None).unwrap();
+        wait_for(
+            &ipc_request_tx,
+            IpcRequest::GetDeviceInfo,
+            device_connected(false),
+        )
+        .await;
+        device_connected_tx
+            .send(Some(connected_device(None, true)))
+            .unwrap();
+        wait_for(
+            &ipc_request_tx,
+            IpcRequest::GetDeviceInfo,
+            device_connected(true),
+        )
+        .await;
+
+        hid_button_tx.send(press).await.unwrap();
+        assert!(
+            !next_mute(&mut audio_cmd_rx).await,
+            "second press after reconnect should unmute"
+        );
+
+        cancel.cancel();
+        engine_handle.await.unwrap();
+    }
+
+    // --- engine integration test ---
+
+    /// End-to-end test: HID position change → signal pipeline → volume curve → audio command.
+    ///
+    /// Spawns the real engine loop, feeds it an audio state snapshot and HID position
+    /// change, then asserts the correct `AudioCommand::SinkInputVolume` emerges.
+    #[tokio::test]
+    async fn engine_hid_position_produces_audio_command() {
+        use pcpaneld_core::audio::VolumeCurve;
+
+        let cancel = CancellationToken::new();
+
+        // --- Create ALL channel pairs for EngineChannels ---
+        let (hid_position_tx, hid_position_rx) = watch::channel([0u8; 9]);
+        let (_hid_button_tx, hid_button_rx) = mpsc::channel(4);
+        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(4);
+        let (audio_cmd_tx, mut audio_cmd_rx) = mpsc::channel(32);
+        let (audio_notify_tx, audio_notify_rx) = mpsc::channel(32);
+        let (_ipc_request_tx, ipc_request_rx) = mpsc::channel(4);
+        let (_tray_action_tx, tray_action_rx) = mpsc::channel(4);
+        let (_config_reload_tx, config_reload_rx) = mpsc::channel(4);
+        let (_state_dump_tx, state_dump_rx) = mpsc::channel(1);
+        let (_focused_window_tx, focused_window_rx) =
+            watch::channel::<Option<FocusedWindowInfo>>(None);
+        let (_focus_tracking_tx, focus_tracking_rx) = watch::channel(false);
+        let (_screen_locked_tx, screen_locked_rx) = watch::channel(false);
+        let (_device_connected_tx, device_connected_rx) = watch::channel(None);
+        let (config_self_write_tx, _config_self_write_rx) = mpsc::channel(4);
+
+        // --- Build config with knob1 → app volume for "firefox" ---
+        let config = make_config_with_app_volume(
+            0,
+            AppMatcher {
+                binary: Some("firefox".into()),
+                ..Default::default()
+            },
+        );
+
+        let config_path = PathBuf::from("/nonexistent/test-config.toml");
+
+        let channels = EngineChannels {
+            hid_position_rx,
+            hid_button_rx,
+            hid_cmd_tx,
+            audio_cmd_tx,
+            audio_notify_rx,
+            ipc_request_rx,
+            tray_action_rx,
+            config_reload_rx,
+            state_dump_rx,
+            focused_window_rx,
+            focus_tracking_rx,
+            screen_locked_rx,
+            device_connected_rx,
+            device_error_rx: watch::channel(None).1,
+            config_self_write_tx,
+        };
+
+        // --- Spawn engine ---
+        let engine_cancel = cancel.clone();
+        let metrics = Arc::new(Metrics::default());
+        let engine_metrics = Arc::clone(&metrics);
+        let engine_handle = tokio::spawn(async move {
+            run(config, config_path, channels, engine_metrics, engine_cancel).await;
+        });
+
+        // --- Seed audio state with a matching sink-input ---
+        let audio_state = AudioState {
+            sink_inputs: vec![make_sink_input(42, "Firefox", Some("firefox"), None)],
+            ..Default::default()
+        };
+        audio_notify_tx
+            .send(AudioNotification::StateSnapshot(audio_state))
+            .await
+            .unwrap();
+
+        // Wait for engine to process the snapshot. There's no observable side effect
+        // to poll for — the engine silently updates internal state.
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // --- Send HID position change for knob1 (analog_id 0) ---
+        // Signal pipeline with default knob params (window=3, delta=1, debounce=0)
+        // will let 128 through on first value since last_emitted is None.
+        let mut positions = [0u8; 9];
+        positions[0] = 128;
+        hid_position_tx.send(positions).unwrap();
+
+        // --- Assert audio command with timeout ---
+        let cmd = tokio::time::timeout(std::time::Duration::from_secs(2), audio_cmd_rx.recv())
+            .await
+            .expect("timed out waiting for audio command")
+            .expect("audio channel closed unexpectedly");
+
+        let expected_volume = VolumeCurve::new(1.0).hw_to_volume(128);
+        match cmd {
+            AudioCommand::SinkInputVolume {
+                index,
+                volume,
+                channels,
+            } => {
+                assert_eq!(index, 42, "should target sink-input index 42");
+                assert!(
+                    (volume.get() - expected_volume.get()).abs() < f64::EPSILON,
+                    "volume mismatch: got {}, expected {}",
+                    volume.get(),
+                    expected_volume.get()
+                );
+                assert_eq!(channels, 2, "sink-input has 2 channels");
+                assert_eq!(metrics.volume_commands.load(Ordering::Relaxed), 1);
+                assert!(metrics.events_processed.load(Ordering::Relaxed) >= 1);
+            }
+            other => panic!("expected SinkInputVolume, got {other:?}"),
+        }
+
+        // --- Clean up ---
+        cancel.cancel();
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(1), engine_handle).await;
+    }
+
+    #[tokio::test]
+    async fn reset_leds_drops_session_toggle() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(8);
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+        toggle_leds(&mut state, &hid_cmd_tx, &self_write_tx).await;
+        assert!(state.leds_off);
+
+        let response = handle_ipc_request(
+            IpcRequest::ResetLeds,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+
+        assert!(matches!(response, IpcResponse::Ok));
+        assert!(!state.leds_off);
+        assert_eq!(led_brightness(&state), 1.0);
+    }
+
+    #[tokio::test]
+    async fn config_dirty_compares_running_config_with_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        Config::default().save(&path).unwrap();
+        let mut state = EngineState::new(Config::default(), path.clone());
+        let (self_write_tx, _self_write_rx) = mpsc::channel(1);
+        let (audio_tx, _audio_rx) = mpsc::channel(1);
+        let cancel = CancellationToken::new();
+
+        let response = handle_ipc_request(
+            IpcRequest::ConfigDirty,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        assert!(matches!(
+            response,
+            IpcResponse::ConfigDirty { dirty: false, .. }
+        ));
+
+        std::fs::write(&path, "[leds]\nlogo = false\n").unwrap();
+        let response = handle_ipc_request(
+            IpcRequest::ConfigDirty,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        let IpcResponse::ConfigDirty { dirty, changes } = response else {
+            panic!("expected ConfigDirty, got {response:?}");
+        };
+        assert!(dirty);
+        assert_eq!(changes, vec!["leds"]);
+    }
+
+    #[test]
+    fn led_state_reports_each_sent_zone() {
+        let knobs = [LedSlot::static_color(255, 0, 0); 5];
+        let sent = [
+            Some(HidCommand::SetKnobLeds(knobs)),
+            None,
+            None,
+            Some(HidCommand::SetLogo {
+                mode: LogoMode::Static,
+                r: 1,
+                g: 2,
+                b: 3,
+                speed: 0,
+            }),
+        ];
+        let leds = led_state(&sent);
+        assert_eq!(leds.knobs, Some(knobs));
+        assert_eq!(leds.slider_labels, None);
+        assert_eq!(leds.sliders, None);
+        assert_eq!(
+            leds.logo,
+            Some(LogoLed {
+                mode: LogoMode::Static,
+                r: 1,
+                g: 2,
+                b: 3,
+                speed: 0,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn list_devices_hides_monitors_unless_asked() {
+        let mut state = EngineState::new(Config::default(), PathBuf::new());
+        let source = |index: u32, name: &str| SourceInfo {
+            index,
+            name: name.into(),
+            description: name.into(),
+            volume: Volume::new(0.5),
+            muted: false,
+            channels: 2,
+        };
+        state.audio_state.sources = vec![
+            source(1, "alsa_output.speakers.monitor"),
+            source(2, "alsa_input.mic"),
+        ];
+        let (self_write_tx, _self_write_rx) = mpsc::channel(1);
+        let (audio_tx, _audio_rx) = mpsc::channel(1);
+        let cancel = CancellationToken::new();
+
+        for (monitors, expected) in [(false, vec![2]), (true, vec![1, 2])] {
+            let response = handle_ipc_request(
+                IpcRequest::ListDevices { monitors },
+                &mut state,
+                &self_write_tx,
+                &audio_tx,
+                &cancel,
+            )
+            .await;
+            let IpcResponse::Devices { devices } = response else {
+                panic!("expected Devices, got {response:?}");
+            };
+            let indices: Vec<u32> = devices.iter().map(|d| d.index).collect();
+            assert_eq!(indices, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn get_focused_reports_window_and_matches() {
+        let mut state = EngineState::new(Config::default(), PathBuf::new());
+        let (self_write_tx, _self_write_rx) = mpsc::channel(1);
+        let (audio_tx, _audio_rx) = mpsc::channel(1);
+        let cancel = CancellationToken::new();
+
+        let response = handle_ipc_request(
+            IpcRequest::GetFocused,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        assert!(matches!(
+            response,
+            IpcResponse::Focused {
+                tracking: false,
+                window: None,
+                ref matches,
+            } if matches.is_empty()
+        ));
+
+        state.focus_tracking = true;
+        state.focused_window = Some(make_focused(None, Some("firefox"), None));
+        state.audio_state.sink_inputs = vec![
+            make_sink_input(1, "Firefox", Some("firefox"), None),
+            make_sink_input(2, "Spotify", Some("spotify"), None),
+        ];
+        let response = handle_ipc_request(
+            IpcRequest::GetFocused,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        let IpcResponse::Focused {
+            tracking,
+            window,
+            matches,
+        } = response
+        else {
+            panic!("expected Focused, got {response:?}");
+        };
+        assert!(tracking);
+        assert_eq!(window.unwrap().resource_name.as_deref(), Some("firefox"));
+        assert_eq!(matches.iter().map(|si| si.index).collect::<Vec<_>>(), [1]);
+    }
+
+    #[tokio::test]
+    async fn batch_applies_assigns_in_order_and_saves_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut state = EngineState::new(Config::default(), path.clone());
+        let (self_write_tx, mut self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let imported = make_config_with_target(1, AudioTarget::DefaultInput);
+        let request = IpcRequest::Batch {
+            requests: vec![
+                IpcRequest::SetConfig {
+                    toml: imported.to_toml().unwrap(),
+                },
+                IpcRequest::AssignDial {
+                    control: ControlId::Knob(0),
+                    action: DialAction::Volume {
+                        target: AudioTarget::DefaultOutput,
+                    },
+                },
+                IpcRequest::AssignButton {
+                    control: ControlId::Knob(0),
+                    action: ButtonAction::Mute {
+                        target: AudioTarget::DefaultOutput,
+                    },
+                },
+                IpcRequest::Batch { requests: vec![] },
+            ],
+        };
+        let response =
+            handle_ipc_request(request, &mut state, &self_write_tx, &audio_tx, &cancel).await;
+
+        let IpcResponse::Batch { responses } = response else {
+            panic!("expected Batch, got {response:?}");
+        };
+        assert!(matches!(responses[0], IpcResponse::Ok));
+        assert!(matches!(responses[1], IpcResponse::Ok));
+        assert!(matches!(responses[2], IpcResponse::Ok));
+        assert!(matches!(
+            responses[3],
+            IpcResponse::Error {
+                code: ErrorCode::InvalidRequest,
+                ..
+            }
+        ));
+        let knob = state.config.get_control(ControlId::Knob(0)).unwrap();
+        assert!(knob.dial.is_some() && knob.button.is_some());
+        assert!(state.config.get_control(ControlId::Knob(1)).is_some());
+        assert_eq!(Config::load(&path).unwrap(), state.config);
+        assert!(self_write_rx.try_recv().is_ok());
+        assert!(self_write_rx.try_recv().is_err(), "saved more than once");
+    }
+
+    #[tokio::test]
+    async fn batch_keeps_running_config_when_save_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        // A regular file where the config directory should be makes the save fail.
+        let blocker = dir.path().join("not-a-dir");
+        std::fs::write(&blocker, "").unwrap();
+        let original = make_config_with_target(1, AudioTarget::DefaultOutput);
+        let mut state = EngineState::new(original.clone(), blocker.join("config.toml"));
+        let (self_write_tx, mut self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let assign = IpcRequest::AssignDial {
+            control: ControlId::Knob(0),
+            action: DialAction::Volume {
+                target: AudioTarget::DefaultInput,
+            },
+        };
+        let response = handle_ipc_request(
+            assign.clone(),
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        assert!(matches!(
+            response,
+            IpcResponse::Error {
+                code: ErrorCode::ConfigIo,
+                ..
+            }
+        ));
+        assert_eq!(state.config, original);
+
+        let request = IpcRequest::Batch {
+            requests: vec![
+                IpcRequest::SetConfig {
+                    toml: Config::default().to_toml().unwrap(),
+                },
+                assign,
+                IpcRequest::GetConfig,
+            ],
+        };
+        let response =
+            handle_ipc_request(request, &mut state, &self_write_tx, &audio_tx, &cancel).await;
+
+        let IpcResponse::Batch { responses } = response else {
+            panic!("expected Batch, got {response:?}");
+        };
+        for response in &responses[..2] {
+            assert!(matches!(
+                response,
+                IpcResponse::Error {
+                    code: ErrorCode::ConfigIo,
+                    ..
+                }
+            ));
+        }
+        // Requests after a change see it, even though it is never saved.
+        let IpcResponse::Config { toml } = &responses[2] else {
+            panic!("expected Config, got {:?}", responses[2]);
+        };
+        let seen = Config::from_toml(toml).unwrap();
+        assert!(seen.get_control(ControlId::Knob(0)).is_some());
+        assert!(seen.get_control(ControlId::Knob(1)).is_none());
+        assert_eq!(state.config, original);
+        assert!(self_write_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn assign_button_to_slider_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut state = EngineState::new(Config::default(), path.clone());
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let assign = || IpcRequest::AssignButton {
+            control: ControlId::Slider(0),
+            action: ButtonAction::Media {
+                command: pcpaneld_core::control::MediaCommand::PlayPause,
+            },
+        };
+        let response =
+            handle_ipc_request(assign(), &mut state, &self_write_tx, &audio_tx, &cancel).await;
+        assert!(matches!(
+            response,
+            IpcResponse::Error {
+                code: ErrorCode::InvalidRequest,
+                ..
+            }
+        ));
+
+        let request = IpcRequest::Batch {
+            requests: vec![assign()],
+        };
+        let response =
+            handle_ipc_request(request, &mut state, &self_write_tx, &audio_tx, &cancel).await;
+        let IpcResponse::Batch { responses } = response else {
+            panic!("expected Batch, got {response:?}");
+        };
+        assert!(matches!(responses[0], IpcResponse::Error { .. }));
+        assert!(state.config.get_control(ControlId::Slider(0)).is_none());
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn unassign_part_keeps_the_other_half() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut state = EngineState::new(Config::default(), path.clone());
+        state.config.controls.insert(
+            "knob1".into(),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultOutput,
+                }),
+                button: Some(ButtonAction::Mute {
+                    target: AudioTarget::DefaultOutput,
+                }),
+                ..Default::default()
+            },
+        );
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let unassign = |part| IpcRequest::Unassign {
+            control: ControlId::Knob(0),
+            part: Some(part),
+        };
+        let response = handle_ipc_request(
+            unassign(ControlPart::Button),
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        assert!(matches!(response, IpcResponse::Ok));
+        let knob = state.config.get_control(ControlId::Knob(0)).unwrap();
+        assert!(knob.dial.is_some());
+        assert!(knob.button.is_none());
+
+        let response = handle_ipc_request(
+            unassign(ControlPart::Dial),
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        assert!(matches!(response, IpcResponse::Ok));
+        assert!(state.config.get_control(ControlId::Knob(0)).is_none());
+        assert_eq!(Config::load(&path).unwrap(), state.config);
+    }
+
+    #[tokio::test]
+    async fn set_config_replaces_and_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut state = EngineState::new(Config::default(), path.clone());
+        let (self_write_tx, mut self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let imported = make_config_with_target(0, AudioTarget::DefaultInput);
+        let request = IpcRequest::SetConfig {
+            toml: imported.to_toml().unwrap(),
+        };
+        let response =
+            handle_ipc_request(request, &mut state, &self_write_tx, &audio_tx, &cancel).await;
+
+        assert!(matches!(response, IpcResponse::Ok));
+        assert_eq!(state.config, imported);
+        assert_eq!(Config::load(&path).unwrap(), imported);
+        assert!(self_write_rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn set_config_rejects_invalid_toml_without_touching_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let original = make_config_with_target(1, AudioTarget::DefaultOutput);
+        let mut state = EngineState::new(original.clone(), path.clone());
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let request = IpcRequest::SetConfig {
+            toml: "[controls.knob1\ndial = 3".into(),
+        };
+        let response =
+            handle_ipc_request(request, &mut state, &self_write_tx, &audio_tx, &cancel).await;
+
+        match response {
+            IpcResponse::Error { code, message } => {
+                assert_eq!(code, ErrorCode::InvalidConfig);
+                assert!(message.starts_with("invalid config:"));
+            }
+            other => panic!("expected Error, got {other:?}"),
+        }
+        assert_eq!(state.config, original);
+        assert!(!path.exists(), "rejected config must not be written");
+    }
+
+    #[tokio::test]
+    async fn set_config_keeps_previous_config_when_save_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        // A regular file where the config directory should be makes the save fail.
+        let blocker = dir.path().join("not-a-dir");
+        std::fs::write(&blocker, "").unwrap();
+        let original = make_config_with_target(1, AudioTarget::DefaultOutput);
+        let mut state = EngineState::new(original.clone(), blocker.join("config.toml"));
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let request = IpcRequest::SetConfig {
+            toml: Config::default().to_toml().unwrap(),
+        };
+        let response =
+            handle_ipc_request(request, &mut state, &self_write_tx, &audio_tx, &cancel).await;
+
+        assert!(matches!(
+            response,
+            IpcResponse::Error {
+                code: ErrorCode::ConfigIo,
+                ..
+            }
+        ));
+        assert_eq!(state.config, original);
+    }
+
+    #[test]
+    fn resolve_device_by_index_name_or_unique_substring() {
+        let devices = [
+            (3, "alsa_output.pci.analog-stereo", "Built-in Audio"),
+            (5, "alsa_output.usb-headset", "USB Headset"),
+        ];
+
+        assert_eq!(
+            resolve_device("5", devices, "output"),
+            Ok("alsa_output.usb-headset")
+        );
+        assert_eq!(
+            resolve_device("alsa_output.pci.analog-stereo", devices, "output"),
+            Ok("alsa_output.pci.analog-stereo")
+        );
+        assert_eq!(
+            resolve_device("headset", devices, "output"),
+            Ok("alsa_output.usb-headset")
+        );
+        assert_eq!(
+            resolve_device("BUILT-IN", devices, "output"),
+            Ok("alsa_output.pci.analog-stereo")
+        );
+    }
+
+    #[test]
+    fn resolve_device_rejects_missing_and_ambiguous() {
+        let devices = [
+            (3, "alsa_output.pci.analog-stereo", "Built-in Audio"),
+            (5, "alsa_output.usb-headset", "USB Headset"),
+        ];
+
+        assert_eq!(
+            resolve_device("hdmi", devices, "output"),
+            Err((
+                ErrorCode::DeviceNotFound,
+                "no output device matches 'hdmi'".into()
+            ))
+        );
+        assert_eq!(
+            resolve_device("alsa_output", devices, "output"),
+            Err((
+                ErrorCode::AmbiguousDevice,
+                "'alsa_output' matches several output devices: Built-in Audio, USB Headset".into()
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn set_default_sink_sends_resolved_name() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        state.audio_state = make_audio_state_with_devices();
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, mut audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let request = IpcRequest::SetDefaultSink {
+            device: "speak".into(),
+        };
+        let response =
+            handle_ipc_request(request, &mut state, &self_write_tx, &audio_tx, &cancel).await;
+
+        assert!(matches!(response, IpcResponse::Ok));
+        match audio_rx.try_recv() {
+            Ok(AudioCommand::SetDefaultSink { name }) => assert_eq!(name, "speakers"),
+            other => panic!("expected SetDefaultSink, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_device_info_reports_connected_device() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+
+        let response = handle_ipc_request(
+            IpcRequest::GetDeviceInfo,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        assert!(matches!(response, IpcResponse::DeviceInfo { device: None }));
+
+        let info = HardwareInfo {
+            model: DeviceModel::Mini,
+            serial: Some("ABC123".into()),
+            firmware_version: Some("1.0".into()),
+        };
+        state.device = Some(info.clone());
+        let response = handle_ipc_request(
+            IpcRequest::GetDeviceInfo,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        match response {
+            IpcResponse::DeviceInfo { device } => assert_eq!(device, Some(info)),
+            other => panic!("expected DeviceInfo, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_status_reports_runtime_stats() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+        let (audio_tx, _audio_rx) = mpsc::channel(4);
+        let cancel = CancellationToken::new();
+        for _ in 0..3 {
+            Metrics::incr(&state.metrics.events_processed);
+            Metrics::incr(&state.metrics.pulse_connections);
+        }
+
+        let response = handle_ipc_request(
+            IpcRequest::GetStatus,
+            &mut state,
+            &self_write_tx,
+            &audio_tx,
+            &cancel,
+        )
+        .await;
+        match response {
+            IpcResponse::Status { stats, .. } => {
+                assert_eq!(stats.events_processed, 3);
+                assert_eq!(stats.pulse_reconnects, 2);
+            }
+            other => panic!("expected Status, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_status_reports_device_error_while_disconnected() {
+        async fn status(state: &mut EngineState) -> DeviceStatus {
+            let (self_write_tx, _self_write_rx) = mpsc::channel(4);
+            let (audio_tx, _audio_rx) = mpsc::channel(4);
+            let cancel = CancellationToken::new();
+            let request = IpcRequest::GetStatus;
+            match handle_ipc_request(request, state, &self_write_tx, &audio_tx, &cancel).await {
+                IpcResponse::Status { device, .. } => device,
+                other => panic!("expected Status, got {other:?}"),
+            }
+        }
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        let (device_error_tx, device_error_rx) = watch::channel(None);
+        state.device_error_rx = device_error_rx;
+        device_error_tx.send_replace(Some("failed to open device: permission denied".into()));
+
+        let device = status(&mut state).await;
+        assert!(!device.connected);
+        assert_eq!(
+            device.error.as_deref(),
+            Some("failed to open device: permission denied")
+        );
+
+        // A stale error isn't reported once a device is connected.
+        state.device = Some(connected_device(None, true).info);
+        assert_eq!(status(&mut state).await.error, None);
+    }
+
+    fn connected_device(serial: Option<&str>, only_device: bool) -> ConnectedDevice {
+        ConnectedDevice {
+            info: HardwareInfo {
+                model: DeviceModel::Pro,
+                serial: serial.map(str::to_string),
+                firmware_version: None,
+            },
+            only_device,
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_lock_serial_persists_sole_device_serial() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut config = Config::default();
+        config.device.auto_lock = true;
+        let mut state = EngineState::new(config, path.clone());
+        let (self_write_tx, mut self_write_rx) = mpsc::channel(4);
+
+        auto_lock_serial(
+            &mut state,
+            &connected_device(Some("ABC123"), true),
+            &self_write_tx,
+        )
+        .await;
+
+        assert_eq!(state.config.device.serial.as_deref(), Some("ABC123"));
+        let saved = Config::load(&path).unwrap();
+        assert_eq!(saved.device.serial.as_deref(), Some("ABC123"));
+        assert!(saved.device.auto_lock);
+        assert!(self_write_rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn auto_lock_serial_skips_when_not_applicable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let (self_write_tx, mut self_write_rx) = mpsc::channel(4);
+
+        // Opt-in only.
+        let mut state = EngineState::new(Config::default(), path.clone());
+        auto_lock_serial(
+            &mut state,
+            &connected_device(Some("ABC123"), true),
+            &self_write_tx,
+        )
+        .await;
+        assert_eq!(state.config.device.serial, None);
+
+        // Several devices present, or no serial to lock to.
+        state.config.device.auto_lock = true;
+        for device in [
+            connected_device(Some("ABC123"), false),
+            connected_device(None, true),
+        ] {
+            auto_lock_serial(&mut state, &device, &self_write_tx).await;
+            assert_eq!(state.config.device.serial, None);
+        }
+
+        // An existing lock is never overwritten.
+        state.config.device.serial = Some("OLD".into());
+        auto_lock_serial(
+            &mut state,
+            &connected_device(Some("ABC123"), true),
+            &self_write_tx,
+        )
+        .await;
+        assert_eq!(state.config.device.serial.as_deref(), Some("OLD"));
+
+        assert!(!path.exists());
+        assert!(self_write_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn apply_config_resets_derived_state_and_leds() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        state.last_applied_volumes[0] = Some(Volume::new(0.5));
+        state.config.signal.volume_exponent = 3.0;
+        let (hid_cmd_tx, mut hid_cmd_rx) = mpsc::channel(8);
+        let (audio_cmd_tx, _audio_cmd_rx) = mpsc::channel(8);
+
+        apply_config(&mut state, &Config::default(), &hid_cmd_tx, &audio_cmd_tx).await;
+
+        assert_eq!(state.last_applied_volumes, [None; 9]);
+        let expected = VolumeCurve::new(3.0).hw_to_volume(128);
+        assert_eq!(state.volume_curve.hw_to_volume(128), expected);
+        let mut sent = 0;
+        while hid_cmd_rx.try_recv().is_ok() {
+            sent += 1;
+        }
+        assert_eq!(sent, 4, "knobs, slider labels, sliders and logo");
+    }
+
+    #[tokio::test]
+    async fn clear_on_reload_sends_all_off_first() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        state.config.leds.clear_on_reload = true;
+        let (hid_cmd_tx, mut hid_cmd_rx) = mpsc::channel(16);
+        let (audio_cmd_tx, _audio_cmd_rx) = mpsc::channel(8);
+        refresh_leds(&mut state, &hid_cmd_tx).await;
+        while hid_cmd_rx.try_recv().is_ok() {}
+
+        // Even a reload that changes nothing clears, then rewrites every zone.
+        apply_config(&mut state, &Config::default(), &hid_cmd_tx, &audio_cmd_tx).await;
+        let mut expected = HidCommand::all_off_sequence().to_vec();
+        expected.extend(led_commands(&state.config, &[None; 5], false, 1.0));
+        assert_eq!(drain_leds(&mut hid_cmd_rx), led_bytes(&expected));
+    }
+
+    #[tokio::test]
+    async fn unchanged_leds_are_not_resent() {
+        let mut state = EngineState::new(Config::default(), PathBuf::from("/nonexistent"));
+        let (hid_cmd_tx, mut hid_cmd_rx) = mpsc::channel(8);
+        let (audio_cmd_tx, _audio_cmd_rx) = mpsc::channel(8);
+        refresh_leds(&mut state, &hid_cmd_tx).await;
+        while hid_cmd_rx.try_recv().is_ok() {}
+
+        // A reload that changes nothing LED-related writes nothing.
+        apply_config(&mut state, &Config::default(), &hid_cmd_tx, &audio_cmd_tx).await;
+        assert!(hid_cmd_rx.try_recv().is_err());
+
+        // Only the zone that changed is rewritten.
+        state.config.leds.logo = false;
+        apply_config(&mut state, &Config::default(), &hid_cmd_tx, &audio_cmd_tx).await;
+        assert!(matches!(
+            hid_cmd_rx.try_recv(),
+            Ok(HidCommand::SetLogo {
+                r: 0,
+                g: 0,
+                b: 0,
+                ..
+            })
+        ));
+        assert!(hid_cmd_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn apply_config_keeps_unchanged_controls() {
+        let previous = make_config_with_target(0, AudioTarget::DefaultOutput);
+        let mut state = EngineState::new(previous.clone(), PathBuf::from("/nonexistent"));
+        state.last_applied_volumes[0] = Some(Volume::new(0.5));
+        state.last_applied_volumes[1] = Some(Volume::new(0.5));
+        let pipeline = state.pipelines.get_mut(&0).unwrap();
+        assert_eq!(pipeline.process(100), Some(100));
+        state.pipelines.get_mut(&1).unwrap().process(100);
+
+        state.config.set_control(
+            ControlId::Knob(1),
+            ControlConfig {
+                dial: Some(DialAction::Volume {
+                    target: AudioTarget::DefaultInput,
+                }),
+                button: None,
+                led_effect: None,
+                reverse_travel: false,
+                curve_points: Vec::new(),
+                slam_action: None,
+                disabled: false,
+            },
+        );
+        let (hid_cmd_tx, _hid_cmd_rx) = mpsc::channel(8);
+        let (audio_cmd_tx, _audio_cmd_rx) = mpsc::channel(8);
+        apply_config(&mut state, &previous, &hid_cmd_tx, &audio_cmd_tx).await;
+
+        // knob1 is untouched: its history suppresses a repeated value
+        assert_eq!(state.last_applied_volumes[0], Some(Volume::new(0.5)));
+        assert_eq!(state.pipelines.get_mut(&0).unwrap().process(100), None);
+        // knob2 was remapped: fresh pipeline, no cached volume
+        assert_eq!(state.last_applied_volumes[1], None);
+        assert_eq!(state.pipelines.get_mut(&1).unwrap().process(100), Some(100));
+
+        // Changing knob signal parameters resets every knob
+        let previous = state.config.clone();
+        state.config.signal.knob_delta_threshold = 5;
+        apply_config(&mut state, &previous, &hid_cmd_tx, &audio_cmd_tx).await;
+        assert_eq!(state.pipelines.get_mut(&0).unwrap().process(100), Some(100));
+        assert_eq!(state.last_applied_volumes[0], Some(Volume::new(0.5)));
+    }
+
+    #[tokio::test]
+    async fn execute_command_drops_beyond_concurrency_limit() {
+        let mut config = Config::default();
+        config.exec.max_concurrent = 1;
+        let state = EngineState::new(config, PathBuf::from("/nonexistent"));
+        let exec = state.config.exec;
+
+        // The first command holds the only permit until its task completes,
+        // which can't happen before the second call.
+        assert!(execute_command("true", &state.exec_semaphore, &exec));
+        assert!(!execute_command("true", &state.exec_semaphore, &exec));
+        assert_eq!(state.exec_semaphore.available_permits(), 0);
+    }
+
+    #[tokio::test]
+    async fn execute_command_captures_more_than_a_pipe_buffer() {
+        let mut config = Config::default();
+        config.exec.max_concurrent = 1;
+        config.exec.capture_output = true;
+        let state = EngineState::new(config, PathBuf::from("/nonexistent"));
+
+        // Well past the 64 KiB pipe buffer, on both streams: the command only
+        // exits (and frees its permit) if the output is drained as it runs.
+        let command = "head -c 200000 /dev/zero; head -c 200000 /dev/zero >&2";
+        assert!(execute_command(
+            command,
+            &state.exec_semaphore,
+            &state.config.exec
+        ));
+        let permit =
+            tokio::time::timeout(Duration::from_secs(10), state.exec_semaphore.acquire()).await;
+        assert!(permit.is_ok(), "exec command never finished");
+    }
+
+    #[test]
+    fn state_dump_reports_device_volumes_and_mappings() {
+        let mut state = EngineState::new(
+            make_config_with_target(0, AudioTarget::DefaultOutput),
+            PathBuf::from("/nonexistent"),
+        );
+        state.last_applied_volumes[0] = Some(Volume::new(0.5));
+        let dump = state_dump(&state);
+        assert!(dump.contains("device: disconnected"), "got:\n{dump}");
+        assert!(dump.contains("pulse_connected: false"), "got:\n{dump}");
+        assert!(
+            dump.contains("last_applied_volumes: [0.500, -,"),
+            "got:\n{dump}"
+        );
+        assert!(
+            dump.contains("knob1: dial: volume default-output"),
+            "got:\n{dump}"
+        );
+
+        state.device = Some(HardwareInfo {
+            model: DeviceModel::Pro,
+            serial: Some("ABC123".into()),
+            firmware_version: None,
+        });
+        let dump = state_dump(&state);
+        assert!(
+            dump.contains("serial: ABC123, firmware: unknown"),
+            "got:\n{dump}"
+        );
     }
 }
@@ -1,4 +1,4 @@
-use pcpaneld_core::hid::{HidError, PRODUCT_ID, REPORT_SIZE, VENDOR_ID};
+use pcpaneld_core::hid::{is_known_device, HidError, REPORT_SIZE, VENDOR_ID};
 
 /// Abstraction over HID device I/O for testability.
 ///
@@ -15,22 +15,37 @@ pub trait HidTransport: Send + 'static {
 
     /// Get the device serial number, if available.
     fn get_serial(&self) -> Option<String>;
+
+    /// USB product ID of the opened device, which identifies the model.
+    fn product_id(&self) -> u16;
+
+    /// Read a feature report. `buf[0]` holds the Report ID to request; on
+    /// success the report is written back with the Report ID first.
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError>;
 }
 
 /// Real HID transport using hidapi.
 pub struct HidApiTransport {
     device: hidapi::HidDevice,
     serial: Option<String>,
+    product_id: u16,
 }
 
 impl HidApiTransport {
-    /// Open a PCPanel Pro device. If `serial` is Some, open that specific device.
+    /// Open the first known PCPanel device in `api`'s device list, whatever
+    /// its model. If `serial` is Some, open that specific device.
     pub fn open(api: &hidapi::HidApi, serial: Option<&str>) -> Result<Self, HidError> {
-        let device = match serial {
-            Some(s) => api.open_serial(VENDOR_ID, PRODUCT_ID, s),
-            None => api.open(VENDOR_ID, PRODUCT_ID),
-        }
-        .map_err(|e| HidError::Io(e.to_string()))?;
+        let info = api
+            .device_list()
+            .find(|d| {
+                is_known_device(d.vendor_id(), d.product_id())
+                    && serial.is_none_or(|s| d.serial_number() == Some(s))
+            })
+            .ok_or(HidError::DeviceNotFound { vid: VENDOR_ID })?;
+        let product_id = info.product_id();
+        let device = info
+            .open_device(api)
+            .map_err(|e| HidError::from_open_error(e.to_string()))?;
 
         // Set non-blocking mode off (we use read_timeout for controlled blocking)
         device
@@ -39,10 +54,29 @@ impl HidApiTransport {
 
         let serial = device.get_serial_number_string().ok().flatten();
 
-        Ok(HidApiTransport { device, serial })
+        Ok(HidApiTransport {
+            device,
+            serial,
+            product_id,
+        })
     }
 }
 
+/// Number of known PCPanel devices in `api`'s device list.
+///
+/// hidapi lists one entry per HID interface, so entries are deduplicated by
+/// product ID and serial.
+pub fn known_device_count(api: &hidapi::HidApi) -> usize {
+    let mut devices: Vec<(u16, Option<&str>)> = api
+        .device_list()
+        .filter(|d| is_known_device(d.vendor_id(), d.product_id()))
+        .map(|d| (d.product_id(), d.serial_number()))
+        .collect();
+    devices.sort_unstable();
+    devices.dedup();
+    devices.len()
+}
+
 impl HidTransport for HidApiTransport {
     fn read_timeout(&self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, HidError> {
         self.device
@@ -79,6 +113,16 @@ impl HidTransport for HidApiTransport {
     fn get_serial(&self) -> Option<String> {
         self.serial.clone()
     }
+
+    fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        self.device
+            .get_feature_report(buf)
+            .map_err(|e| HidError::Io(e.to_string()))
+    }
 }
 
 /// Mock HID transport for testing. Replays scripted read responses.
@@ -87,6 +131,8 @@ pub struct MockHidTransport {
     reads: std::sync::Mutex<std::collections::VecDeque<Result<Vec<u8>, HidError>>>,
     writes: std::sync::Mutex<Vec<Vec<u8>>>,
     serial: Option<String>,
+    product_id: u16,
+    feature_report: Option<Vec<u8>>,
 }
 
 #[cfg(test)]
@@ -96,6 +142,8 @@ impl MockHidTransport {
             reads: std::sync::Mutex::new(std::collections::VecDeque::new()),
             writes: std::sync::Mutex::new(Vec::new()),
             serial: None,
+            product_id: pcpaneld_core::hid::PRO_PRODUCT_ID,
+            feature_report: None,
         }
     }
 
@@ -104,6 +152,18 @@ impl MockHidTransport {
         self
     }
 
+    pub fn with_product_id(mut self, product_id: u16) -> Self {
+        self.product_id = product_id;
+        self
+    }
+
+    /// Answer feature report requests with `report` (Report ID included).
+    /// Without one, requests fail like on a device that doesn't support them.
+    pub fn with_feature_report(mut self, report: Vec<u8>) -> Self {
+        self.feature_report = Some(report);
+        self
+    }
+
     /// Queue a successful read response.
     pub fn queue_read(&self, data: Vec<u8>) {
         self.reads.lock().unwrap().push_back(Ok(data));
@@ -154,6 +214,20 @@ impl HidTransport for MockHidTransport {
     fn get_serial(&self) -> Option<String> {
         self.serial.clone()
     }
+
+    fn product_id(&self) -> u16 {
+        self.product_id
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> Result<usize, HidError> {
+        let report = self
+            .feature_report
+            .as_ref()
+            .ok_or_else(|| HidError::Io("feature report not supported".into()))?;
+        let len = report.len().min(buf.len());
+        buf[..len].copy_from_slice(&report[..len]);
+        Ok(len)
+    }
 }
 
 #[cfg(test)]
@@ -2,12 +2,18 @@ use std::os::fd::AsRawFd;
 use std::sync::mpsc as std_mpsc;
 use std::time::{Duration, Instant};
 
-use pcpaneld_core::hid::{HidCommand, HidEvent, PRODUCT_ID, VENDOR_ID};
+use pcpaneld_core::config::DeviceConfig;
+use pcpaneld_core::control::DeviceModel;
+use pcpaneld_core::hid::{
+    model_for_product_id, parse_firmware_version, udev_rule, HidCommand, HidError, HidEvent,
+    FIRMWARE_REPORT_ID, REPORT_SIZE, VENDOR_ID,
+};
+use pcpaneld_core::ipc::HardwareInfo;
 use tokio::sync::{mpsc, watch};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
-use crate::hid::{HidApiTransport, HidTransport};
+use crate::hid::{known_device_count, HidApiTransport, HidTransport};
 
 /// Button event sent from HID thread to engine.
 #[derive(Debug, Clone, Copy)]
@@ -16,6 +22,14 @@ pub struct ButtonEvent {
     pub pressed: bool,
 }
 
+/// A device that finished init, sent from HID thread to engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectedDevice {
+    pub info: HardwareInfo,
+    /// No other PCPanel was present when this one was opened.
+    pub only_device: bool,
+}
+
 /// Device hotplug events from udev monitor.
 #[derive(Debug, Clone, Copy)]
 pub enum DeviceEvent {
@@ -27,24 +41,36 @@ pub enum DeviceEvent {
 ///
 /// Manages device lifecycle: open -> init -> read loop -> reconnect on disconnect.
 /// Uses udev events for instant reconnection instead of polling.
+///
+/// `device_error_tx` carries the error keeping the device disconnected, if
+/// any, for `GetStatus`. A device that is listed but won't open, or that
+/// fails mid-session without being unplugged, counts; plain absence doesn't.
+#[allow(clippy::too_many_arguments)]
 pub fn run(
-    config_serial: Option<String>,
+    device_config: DeviceConfig,
     position_tx: watch::Sender<[u8; 9]>,
     button_tx: mpsc::Sender<ButtonEvent>,
     mut cmd_rx: mpsc::Receiver<HidCommand>,
     device_event_rx: std_mpsc::Receiver<DeviceEvent>,
-    device_connected_tx: watch::Sender<bool>,
+    device_connected_tx: watch::Sender<Option<ConnectedDevice>>,
+    device_error_tx: watch::Sender<Option<String>>,
     cancel: CancellationToken,
 ) {
     let mut api = match hidapi::HidApi::new() {
         Ok(api) => api,
         Err(e) => {
             error!("failed to initialize HID API: {e}");
+            report_error(
+                &device_error_tx,
+                Some(format!("failed to initialize HID API: {e}")),
+            );
             return;
         }
     };
 
     let mut positions = [0u8; 9];
+    // The permission hint is logged once, not on every retry.
+    let mut permission_hinted = false;
 
     loop {
         if cancel.is_cancelled() {
@@ -52,16 +78,37 @@ pub fn run(
         }
 
         // Try to open device
-        let transport = match HidApiTransport::open(&api, config_serial.as_deref()) {
+        let transport = match HidApiTransport::open(&api, device_config.serial.as_deref()) {
             Ok(t) => {
                 info!(
                     "HID device connected (serial: {})",
                     t.get_serial().as_deref().unwrap_or("unknown")
                 );
+                report_error(&device_error_tx, None);
+                permission_hinted = false;
                 t
             }
             Err(e) => {
-                debug!("device not found: {e}");
+                let error = match e {
+                    HidError::DeviceNotFound { .. } => {
+                        debug!("device not found: {e}");
+                        None
+                    }
+                    HidError::PermissionDenied(_) => {
+                        if !permission_hinted {
+                            warn!("{}", permission_hint(&e));
+                            permission_hinted = true;
+                        }
+                        Some(format!(
+                            "{e} (is the udev rule installed? The daemon log has details)"
+                        ))
+                    }
+                    _ => {
+                        debug!("failed to open device: {e}");
+                        Some(format!("failed to open device: {e}"))
+                    }
+                };
+                report_error(&device_error_tx, error);
                 wait_for_device(&device_event_rx, &cancel);
                 if let Err(e) = api.refresh_devices() {
                     warn!("failed to refresh HID device list: {e}");
@@ -70,9 +117,13 @@ pub fn run(
             }
         };
 
+        let only_device = known_device_count(&api) == 1;
+
         // Run the device session
-        run_device_session(
+        let error = run_device_session(
             &transport,
+            &device_config,
+            only_device,
             &position_tx,
             &button_tx,
             &mut cmd_rx,
@@ -82,14 +133,47 @@ pub fn run(
         );
 
         // run_device_session returned = device disconnected or errored
-        let _ = device_connected_tx.send(false);
+        let _ = device_connected_tx.send(None);
         info!("HID device disconnected");
         positions = [0u8; 9];
+        // An unplug also ends the session with an I/O error; it only counts
+        // as a device error if the device is still there.
+        if let Err(e) = api.refresh_devices() {
+            warn!("failed to refresh HID device list: {e}");
+        }
+        report_error(
+            &device_error_tx,
+            error.filter(|_| known_device_count(&api) > 0),
+        );
     }
 
     info!("HID thread exiting");
 }
 
+/// What to do about a device that is present but can't be opened.
+fn permission_hint(e: &HidError) -> String {
+    format!(
+        "{e}\n\
+         The PCPanel is plugged in, but this user has no access to its hidraw device. \
+         Install this udev rule as /etc/udev/rules.d/70-pcpanel.rules:\n  {}\n\
+         then run `sudo udevadm control --reload-rules && sudo udevadm trigger` \
+         and replug the device.",
+        udev_rule()
+    )
+}
+
+/// Publish the current device error, waking the engine only on a change.
+fn report_error(device_error_tx: &watch::Sender<Option<String>>, error: Option<String>) {
+    device_error_tx.send_if_modified(|current| {
+        if *current == error {
+            false
+        } else {
+            *current = error;
+            true
+        }
+    });
+}
+
 /// Wait for a udev device event or timeout for fallback polling.
 fn wait_for_device(device_event_rx: &std_mpsc::Receiver<DeviceEvent>, cancel: &CancellationToken) {
     match device_event_rx.recv_timeout(Duration::from_secs(5)) {
@@ -110,35 +194,51 @@ fn wait_for_device(device_event_rx: &std_mpsc::Receiver<DeviceEvent>, cancel: &C
     }
 }
 
-/// Run a single device session: init, drain, read loop.
+/// Run a single device session: init, drain, read loop. Returns the error
+/// that ended it, or `None` if it ended on shutdown or because the engine
+/// went away.
+#[allow(clippy::too_many_arguments)]
 fn run_device_session(
     transport: &dyn HidTransport,
+    device_config: &DeviceConfig,
+    only_device: bool,
     position_tx: &watch::Sender<[u8; 9]>,
     button_tx: &mpsc::Sender<ButtonEvent>,
     cmd_rx: &mut mpsc::Receiver<HidCommand>,
     positions: &mut [u8; 9],
-    device_connected_tx: &watch::Sender<bool>,
+    device_connected_tx: &watch::Sender<Option<ConnectedDevice>>,
     cancel: &CancellationToken,
-) {
+) -> Option<String> {
     // Send init command
     let init_payload = HidCommand::Init.encode();
     if let Err(e) = transport.write(&init_payload) {
         error!("failed to send init command: {e}");
-        return;
+        return Some(format!("failed to send init command: {e}"));
     }
 
     // Drain stale position reports (timeout-based)
-    drain_stale_reports(transport);
+    drain_stale_reports(
+        transport,
+        Duration::from_millis(device_config.drain_ms),
+        device_config.drain_read_timeout_ms,
+    );
 
     // Signal that the device is connected and ready
-    let _ = device_connected_tx.send(true);
+    let _ = device_connected_tx.send(Some(ConnectedDevice {
+        info: HardwareInfo {
+            model: detect_model(transport),
+            serial: transport.get_serial(),
+            firmware_version: query_firmware_version(transport),
+        },
+        only_device,
+    }));
 
     // Main read loop
     let mut buf = [0u8; 64];
     loop {
         if cancel.is_cancelled() {
             send_all_off(transport);
-            return;
+            return None;
         }
 
         // Check for outgoing commands (non-blocking)
@@ -146,12 +246,11 @@ fn run_device_session(
             let payload = cmd.encode();
             if let Err(e) = transport.write(&payload) {
                 warn!("failed to write HID command: {e}");
-                return;
+                return Some(format!("failed to write HID command: {e}"));
             }
         }
 
-        // Read with 100ms timeout
-        match transport.read_timeout(&mut buf, 100) {
+        match transport.read_timeout(&mut buf, device_config.read_timeout_ms()) {
             Ok(0) => continue,
             Ok(n) => match HidEvent::parse(&buf[..n]) {
                 Ok(HidEvent::Position { control_id, value }) => {
@@ -171,7 +270,7 @@ fn run_device_session(
                     let event = ButtonEvent { button_id, pressed };
                     if let Err(e) = button_tx.blocking_send(event) {
                         warn!("failed to send button event: {e}");
-                        return;
+                        return None;
                     }
                 }
                 Err(e) => {
@@ -180,27 +279,66 @@ fn run_device_session(
             },
             Err(e) => {
                 warn!("HID read error: {e}");
-                return;
+                return Some(format!("HID read error: {e}"));
             }
         }
     }
 }
 
+/// Determine the device model from its USB product ID.
+///
+/// Unknown IDs fall back to the Pro layout, which is a superset of the others.
+fn detect_model(transport: &dyn HidTransport) -> DeviceModel {
+    let product_id = transport.product_id();
+    match model_for_product_id(product_id) {
+        Some(model) => {
+            info!("detected {model} (product ID {product_id:#06x})");
+            model
+        }
+        None => {
+            warn!("unknown product ID {product_id:#06x}, assuming PCPanel Pro layout");
+            DeviceModel::Pro
+        }
+    }
+}
+
+/// Ask the device for its firmware version.
+///
+/// Not every firmware answers the query; failures are logged and yield `None`.
+fn query_firmware_version(transport: &dyn HidTransport) -> Option<String> {
+    let mut buf = [0u8; REPORT_SIZE + 1];
+    buf[0] = FIRMWARE_REPORT_ID;
+    let version = match transport.get_feature_report(&mut buf) {
+        Ok(n) => parse_firmware_version(&buf[..n]),
+        Err(e) => {
+            debug!("firmware version query failed: {e}");
+            return None;
+        }
+    };
+    match &version {
+        Some(v) => info!("firmware version {v}"),
+        None => debug!("device did not report a firmware version"),
+    }
+    version
+}
+
 /// Drain stale position reports after init.
 ///
 /// The device sends a burst of position reports in response to the init command.
 /// These reflect the current physical positions but would cause false "changed"
-/// events if fed into the engine, so we discard them here.
-fn drain_stale_reports(transport: &dyn HidTransport) {
+/// events if fed into the engine, so we discard them here: for up to `window`,
+/// or until no report arrives within `read_timeout_ms`.
+fn drain_stale_reports(transport: &dyn HidTransport, window: Duration, read_timeout_ms: u64) {
     let start = Instant::now();
+    let read_timeout_ms = i32::try_from(read_timeout_ms).unwrap_or(i32::MAX);
     let mut buf = [0u8; 64];
     let mut count = 0u32;
 
     loop {
-        if start.elapsed() > Duration::from_millis(500) {
+        if start.elapsed() > window {
             break;
         }
-        match transport.read_timeout(&mut buf, 50) {
+        match transport.read_timeout(&mut buf, read_timeout_ms) {
             Ok(0) => break,
             Ok(_) => {
                 count += 1;
@@ -274,30 +412,30 @@ pub fn run_udev_monitor(event_tx: std_mpsc::SyncSender<DeviceEvent>, cancel: Can
         if let Some(event) = socket.iter().next() {
             // Walk up two levels in the device tree: hidraw → hid → usb_device,
             // where VID/PID attributes live.
-            let is_pcpanel = event
-                .parent()
-                .and_then(|p| p.parent())
-                .map(|usb| {
-                    let vid = usb
-                        .attribute_value("idVendor")
-                        .and_then(|v| v.to_str())
-                        .and_then(|v| u16::from_str_radix(v, 16).ok());
-                    let pid = usb
-                        .attribute_value("idProduct")
-                        .and_then(|v| v.to_str())
-                        .and_then(|v| u16::from_str_radix(v, 16).ok());
-                    vid == Some(VENDOR_ID) && pid == Some(PRODUCT_ID)
-                })
-                .unwrap_or(false);
-
-            if is_pcpanel {
+            let model = event.parent().and_then(|p| p.parent()).and_then(|usb| {
+                let vid = usb
+                    .attribute_value("idVendor")
+                    .and_then(|v| v.to_str())
+                    .and_then(|v| u16::from_str_radix(v, 16).ok())?;
+                let pid = usb
+                    .attribute_value("idProduct")
+                    .and_then(|v| v.to_str())
+                    .and_then(|v| u16::from_str_radix(v, 16).ok())?;
+                if vid == VENDOR_ID {
+                    model_for_product_id(pid)
+                } else {
+                    None
+                }
+            });
+
+            if let Some(model) = model {
                 let dev_event = match event.event_type() {
                     udev::EventType::Add => Some(DeviceEvent::Added),
                     udev::EventType::Remove => Some(DeviceEvent::Removed),
                     _ => None,
                 };
                 if let Some(evt) = dev_event {
-                    debug!("udev: PCPanel Pro {evt:?}");
+                    debug!("udev: {model} {evt:?}");
                     let _ = event_tx.try_send(evt);
                 }
             }
@@ -320,11 +458,152 @@ mod tests {
         }
         mock.queue_timeout();
 
-        drain_stale_reports(&mock);
+        drain_stale_reports(&mock, Duration::from_millis(500), 50);
         let mut buf = [0u8; 64];
         assert_eq!(mock.read_timeout(&mut buf, 50).unwrap(), 0);
     }
 
+    /// A device that never stops sending position reports.
+    struct DribblingTransport {
+        timeouts: std::sync::Mutex<Vec<i32>>,
+    }
+
+    impl HidTransport for DribblingTransport {
+        fn read_timeout(&self, buf: &mut [u8], timeout_ms: i32) -> Result<usize, HidError> {
+            self.timeouts.lock().unwrap().push(timeout_ms);
+            std::thread::sleep(Duration::from_millis(2));
+            buf[..3].copy_from_slice(&[0x01, 0x00, 0x40]);
+            Ok(3)
+        }
+        fn write(&self, data: &[u8]) -> Result<usize, HidError> {
+            Ok(data.len())
+        }
+        fn get_serial(&self) -> Option<String> {
+            None
+        }
+        fn product_id(&self) -> u16 {
+            0
+        }
+        fn get_feature_report(&self, _buf: &mut [u8]) -> Result<usize, HidError> {
+            Err(HidError::Io("unsupported".into()))
+        }
+    }
+
+    #[test]
+    fn drain_stale_reports_honors_custom_window() {
+        let transport = DribblingTransport {
+            timeouts: std::sync::Mutex::new(Vec::new()),
+        };
+        let start = Instant::now();
+        drain_stale_reports(&transport, Duration::from_millis(40), 120);
+        let elapsed = start.elapsed();
+
+        assert!(
+            elapsed >= Duration::from_millis(40),
+            "stopped early: {elapsed:?}"
+        );
+        assert!(
+            elapsed < Duration::from_millis(400),
+            "ran too long: {elapsed:?}"
+        );
+        let timeouts = transport.timeouts.lock().unwrap();
+        assert!(timeouts.len() > 1);
+        assert!(timeouts.iter().all(|&t| t == 120));
+    }
+
+    #[test]
+    fn detect_model_from_product_id() {
+        use pcpaneld_core::hid::MINI_PRODUCT_ID;
+
+        let mock = MockHidTransport::new();
+        assert_eq!(detect_model(&mock), DeviceModel::Pro);
+        let mock = MockHidTransport::new().with_product_id(MINI_PRODUCT_ID);
+        assert_eq!(detect_model(&mock), DeviceModel::Mini);
+        let mock = MockHidTransport::new().with_product_id(0xFFFF);
+        assert_eq!(detect_model(&mock), DeviceModel::Pro);
+    }
+
+    #[test]
+    fn query_firmware_version_reads_feature_report() {
+        let mut report = vec![FIRMWARE_REPORT_ID];
+        report.extend_from_slice(b"2.1\0");
+        let mock = MockHidTransport::new().with_feature_report(report);
+        assert_eq!(query_firmware_version(&mock).as_deref(), Some("2.1"));
+    }
+
+    #[test]
+    fn query_firmware_version_degrades_when_unsupported() {
+        let mock = MockHidTransport::new();
+        assert_eq!(query_firmware_version(&mock), None);
+    }
+
+    #[test]
+    fn device_session_returns_the_error_that_ended_it() {
+        let mock = MockHidTransport::new();
+        mock.queue_timeout();
+        mock.queue_read_error("device gone");
+        let device_config = DeviceConfig {
+            drain_ms: 0,
+            ..DeviceConfig::default()
+        };
+        let (position_tx, _position_rx) = watch::channel([0u8; 9]);
+        let (button_tx, _button_rx) = mpsc::channel(4);
+        let (_cmd_tx, mut cmd_rx) = mpsc::channel(4);
+        let (device_connected_tx, device_connected_rx) = watch::channel(None);
+
+        let error = run_device_session(
+            &mock,
+            &device_config,
+            true,
+            &position_tx,
+            &button_tx,
+            &mut cmd_rx,
+            &mut [0u8; 9],
+            &device_connected_tx,
+            &CancellationToken::new(),
+        );
+        let error = error.expect("a read error ends the session with an error");
+        assert!(error.contains("device gone"), "{error}");
+        assert!(device_connected_rx.borrow().is_some());
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let error = run_device_session(
+            &MockHidTransport::new(),
+            &device_config,
+            true,
+            &position_tx,
+            &button_tx,
+            &mut cmd_rx,
+            &mut [0u8; 9],
+            &device_connected_tx,
+            &cancel,
+        );
+        assert_eq!(error, None);
+    }
+
+    #[test]
+    fn permission_hint_names_the_udev_rule() {
+        let hint = permission_hint(&HidError::PermissionDenied("Permission denied".into()));
+        assert!(hint.contains(&udev_rule()), "{hint}");
+        assert!(hint.contains("70-pcpanel.rules"), "{hint}");
+    }
+
+    #[test]
+    fn report_error_only_wakes_on_change() {
+        let (tx, mut rx) = watch::channel(None);
+        report_error(&tx, None);
+        assert!(!rx.has_changed().unwrap());
+        report_error(&tx, Some("failed to open device".into()));
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(
+            rx.borrow_and_update().as_deref(),
+            Some("failed to open device")
+        );
+        report_error(&tx, Some("failed to open device".into()));
+        assert!(!rx.has_changed().unwrap());
+    }
+
     #[test]
     fn send_all_off_writes_led_clear() {
         let mock = MockHidTransport::new();
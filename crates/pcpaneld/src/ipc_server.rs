@@ -1,7 +1,13 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::fs::OpenOptionsExt;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use anyhow::{Context, Result};
-use pcpaneld_core::ipc::{self, IpcRequest, IpcResponse};
+use pcpaneld_core::ipc::{self, ErrorCode, IpcEndpoint, IpcRequest, IpcResponse};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::{mpsc, oneshot};
@@ -10,6 +16,89 @@ use tracing::{debug, info, warn};
 
 use crate::engine::IpcMessage;
 
+/// Requests per second a user's connections may sustain together. Far above
+/// what CLI commands send, even `levels --meter` alongside others, so only a
+/// runaway client hits it.
+const REQUESTS_PER_SEC: f64 = 50.0;
+
+/// Requests a user may send back to back before the rate applies.
+const REQUEST_BURST: f64 = 50.0;
+
+/// Token bucket per peer uid, so one client can't fill the engine's request
+/// channel and starve HID and audio handling.
+struct RateLimiter {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    fn new(now: Instant) -> Self {
+        Self {
+            tokens: REQUEST_BURST,
+            last: now,
+        }
+    }
+
+    /// Take a token for a request arriving at `now`; `false` if none is left.
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * REQUESTS_PER_SEC).min(REQUEST_BURST);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The rate limiters of every connection, keyed by peer uid (`None` if the
+/// kernel can't tell). The CLI opens a connection per command, so a limiter
+/// per connection would never fill up.
+#[derive(Clone, Default)]
+struct RateLimiters(Arc<Mutex<HashMap<Option<u32>, RateLimiter>>>);
+
+impl RateLimiters {
+    /// Take a token from `uid`'s bucket for a request arriving at `now`.
+    fn try_acquire(&self, uid: Option<u32>, now: Instant) -> bool {
+        let mut limiters = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        limiters
+            .entry(uid)
+            .or_insert_with(|| RateLimiter::new(now))
+            .try_acquire(now)
+    }
+}
+
+/// Take an exclusive lock on `<socket>.lock`, held until the returned file is
+/// dropped.
+///
+/// Two daemons starting at once could both find the socket stale and both
+/// bind; the lock lets only one of them past the stale-socket check. The
+/// file itself is left behind, as removing it would reopen the race.
+pub fn lock_socket(path: &Path) -> Result<File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_path = PathBuf::from(lock_path);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .mode(0o600)
+        .open(&lock_path)
+        .with_context(|| format!("failed to open lock file {}", lock_path.display()))?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => anyhow::bail!(
+            "another pcpaneld instance is already running (lock {} is held)",
+            lock_path.display()
+        ),
+        Err(TryLockError::Error(e)) => {
+            Err(e).with_context(|| format!("failed to lock {}", lock_path.display()))
+        }
+    }
+}
+
 /// Check for and clean up a stale socket file.
 ///
 /// If a socket file exists, try connecting. If connection succeeds, another
@@ -38,24 +127,80 @@ pub async fn cleanup_stale_socket(path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Bind the listener for `endpoint`.
+fn bind(endpoint: &IpcEndpoint) -> Result<UnixListener> {
+    match endpoint {
+        IpcEndpoint::Path(path) => {
+            // Set restrictive umask before binding.
+            // Safety: libc::umask() is process-global state, but this runs at startup
+            // before any file-creating threads are active, so no race is possible.
+            let old_umask = unsafe { libc::umask(0o077) };
+
+            let listener = UnixListener::bind(path).map_err(|e| bind_error(path, e));
+
+            // Restore umask
+            unsafe { libc::umask(old_umask) };
+            listener
+        }
+        IpcEndpoint::Abstract(name) => {
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            let listener = match std::os::unix::net::UnixListener::bind_addr(&addr) {
+                Ok(listener) => listener,
+                Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => anyhow::bail!(
+                    "another pcpaneld instance is already running (socket {endpoint} is active)"
+                ),
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("failed to bind IPC socket at {endpoint}"))
+                }
+            };
+            listener.set_nonblocking(true)?;
+            Ok(UnixListener::from_std(listener)?)
+        }
+    }
+}
+
+/// Name the directory when it's why binding failed; the bare io error
+/// ("No such file or directory") doesn't say which path is wrong.
+fn bind_error(path: &Path, e: std::io::Error) -> anyhow::Error {
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let problem = if !dir.is_dir() {
+        "does not exist"
+    } else if !ipc::is_writable_dir(dir) {
+        "is not writable"
+    } else {
+        return anyhow::Error::new(e)
+            .context(format!("failed to bind IPC socket at {}", path.display()));
+    };
+    anyhow::anyhow!(
+        "failed to bind IPC socket at {}: directory {} {problem} ({e}). \
+         Set XDG_RUNTIME_DIR or pass --socket to use another location",
+        path.display(),
+        dir.display()
+    )
+}
+
+/// Whether `stream` comes from our own user. Abstract sockets have no file
+/// permissions, so this is their only access control.
+fn is_same_user(stream: &UnixStream) -> bool {
+    let uid = unsafe { libc::getuid() };
+    stream.peer_cred().is_ok_and(|cred| cred.uid() == uid)
+}
+
 /// Run the IPC server on a Unix socket.
 pub async fn run(
-    socket_path: PathBuf,
+    endpoint: IpcEndpoint,
     request_tx: mpsc::Sender<IpcMessage>,
     cancel: CancellationToken,
 ) -> Result<()> {
-    // Set restrictive umask before binding.
-    // Safety: libc::umask() is process-global state, but this runs at startup
-    // before any file-creating threads are active, so no race is possible.
-    let old_umask = unsafe { libc::umask(0o077) };
-
-    let listener = UnixListener::bind(&socket_path)
-        .with_context(|| format!("failed to bind IPC socket at {}", socket_path.display()))?;
+    let listener = bind(&endpoint)?;
+    let check_peer = matches!(endpoint, IpcEndpoint::Abstract(_));
+    let limiters = RateLimiters::default();
 
-    // Restore umask
-    unsafe { libc::umask(old_umask) };
-
-    info!("IPC server listening on {}", socket_path.display());
+    info!("IPC server listening on {endpoint}");
 
     loop {
         tokio::select! {
@@ -65,10 +210,15 @@ pub async fn run(
             result = listener.accept() => {
                 match result {
                     Ok((stream, _addr)) => {
+                        if check_peer && !is_same_user(&stream) {
+                            warn!("rejected IPC connection from another user");
+                            continue;
+                        }
                         let tx = request_tx.clone();
+                        let limiters = limiters.clone();
                         let client_cancel = cancel.clone();
                         tokio::spawn(async move {
-                            if let Err(e) = handle_client(stream, tx, client_cancel).await {
+                            if let Err(e) = handle_client(stream, tx, limiters, client_cancel).await {
                                 debug!("IPC client error: {e}");
                             }
                         });
@@ -82,7 +232,9 @@ pub async fn run(
     }
 
     // Clean up socket on exit
-    let _ = tokio::fs::remove_file(&socket_path).await;
+    if let IpcEndpoint::Path(path) = &endpoint {
+        let _ = tokio::fs::remove_file(path).await;
+    }
     info!("IPC server stopped");
     Ok(())
 }
@@ -90,8 +242,10 @@ pub async fn run(
 async fn handle_client(
     mut stream: UnixStream,
     request_tx: mpsc::Sender<IpcMessage>,
+    limiters: RateLimiters,
     cancel: CancellationToken,
 ) -> Result<()> {
+    let uid = stream.peer_cred().ok().map(|cred| cred.uid());
     loop {
         tokio::select! {
             _ = cancel.cancelled() => {
@@ -99,7 +253,23 @@ async fn handle_client(
             }
             result = read_request(&mut stream) => {
                 match result {
-                    Ok(Some(request)) => {
+                    Ok(Some(_)) if !limiters.try_acquire(uid, Instant::now()) => {
+                        let resp = IpcResponse::Error {
+                            code: ErrorCode::RateLimited,
+                            message: "too many requests, slow down".into(),
+                        };
+                        write_response(&mut stream, &resp).await?;
+                    }
+                    Ok(Some(Err(e))) => {
+                        // Answer instead of hanging up, so a client newer than
+                        // the daemon learns why its request went nowhere.
+                        let resp = IpcResponse::Error {
+                            code: ErrorCode::InvalidRequest,
+                            message: format!("invalid request: {e}"),
+                        };
+                        write_response(&mut stream, &resp).await?;
+                    }
+                    Ok(Some(Ok(request))) => {
                         let (reply_tx, reply_rx) = oneshot::channel();
                         let msg = IpcMessage { request, reply_tx };
 
@@ -113,6 +283,7 @@ async fn handle_client(
                             }
                             Err(_) => {
                                 let resp = IpcResponse::Error {
+                                    code: ErrorCode::ShuttingDown,
                                     message: "daemon shutting down".into(),
                                 };
                                 write_response(&mut stream, &resp).await?;
@@ -133,7 +304,11 @@ async fn handle_client(
     }
 }
 
-async fn read_request(stream: &mut UnixStream) -> Result<Option<IpcRequest>> {
+/// Read one framed request. `Ok(None)` means the client hung up; a payload
+/// that isn't a valid request is returned as the inner error.
+async fn read_request(
+    stream: &mut UnixStream,
+) -> Result<Option<Result<IpcRequest, serde_json::Error>>> {
     let mut len_buf = [0u8; 4];
     match stream.read_exact(&mut len_buf).await {
         Ok(_) => {}
@@ -148,10 +323,7 @@ async fn read_request(stream: &mut UnixStream) -> Result<Option<IpcRequest>> {
     let mut payload = vec![0u8; len as usize];
     stream.read_exact(&mut payload).await?;
 
-    let request: IpcRequest =
-        serde_json::from_slice(&payload).with_context(|| "failed to parse IPC request")?;
-
-    Ok(Some(request))
+    Ok(Some(serde_json::from_slice(&payload)))
 }
 
 async fn write_response(stream: &mut UnixStream, response: &IpcResponse) -> Result<()> {
@@ -160,3 +332,119 @@ async fn write_response(stream: &mut UnixStream, response: &IpcResponse) -> Resu
     stream.flush().await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn rate_limiter_rejects_burst_beyond_cap() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(start);
+        let accepted = (0..50).filter(|_| limiter.try_acquire(start)).count();
+        assert_eq!(accepted, REQUEST_BURST as usize);
+        assert!(!limiter.try_acquire(start));
+
+        // Tokens come back with time.
+        assert!(limiter.try_acquire(start + Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn bind_error_names_missing_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        let path = missing.join("pcpaneld.sock");
+        let e = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let message = bind_error(&path, e).to_string();
+        assert!(
+            message.contains(&format!("directory {} does not exist", missing.display())),
+            "got: {message}"
+        );
+    }
+
+    #[test]
+    fn concurrent_startups_get_one_socket_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("pcpaneld.sock");
+        let barrier = std::sync::Barrier::new(8);
+        let locks: Vec<Result<File>> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    s.spawn(|| {
+                        barrier.wait();
+                        lock_socket(&socket)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(locks.iter().filter(|l| l.is_ok()).count(), 1);
+        let err = locks.iter().find_map(|l| l.as_ref().err()).unwrap();
+        assert!(err.to_string().contains("already running"), "got: {err}");
+
+        // The next daemon gets it once the running one is gone.
+        drop(locks);
+        assert!(lock_socket(&socket).is_ok());
+    }
+
+    /// Send `request` on a connection of its own, like the CLI does.
+    async fn request_on_new_connection(path: &Path, request: &IpcRequest) -> IpcResponse {
+        let mut stream = UnixStream::connect(path).await.unwrap();
+        stream
+            .write_all(&ipc::encode_request(request).unwrap())
+            .await
+            .unwrap();
+        let mut len_buf = [0u8; 4];
+        stream.read_exact(&mut len_buf).await.unwrap();
+        let mut payload = vec![0u8; ipc::read_length_prefix(&len_buf).unwrap() as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    #[tokio::test]
+    async fn rate_limit_applies_across_connections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pcpaneld.sock");
+        let (request_tx, mut request_rx) = mpsc::channel::<IpcMessage>(8);
+        let cancel = CancellationToken::new();
+        tokio::spawn(run(
+            IpcEndpoint::Path(path.clone()),
+            request_tx,
+            cancel.clone(),
+        ));
+        tokio::spawn(async move {
+            while let Some(msg) = request_rx.recv().await {
+                let _ = msg.reply_tx.send(IpcResponse::Ok);
+            }
+        });
+        while !path.exists() {
+            tokio::task::yield_now().await;
+        }
+
+        let mut limited = 0;
+        for _ in 0..3 * REQUEST_BURST as usize {
+            match request_on_new_connection(&path, &IpcRequest::GetConfig).await {
+                IpcResponse::Ok => {}
+                IpcResponse::Error {
+                    code: ErrorCode::RateLimited,
+                    ..
+                } => limited += 1,
+                other => panic!("unexpected response {other:?}"),
+            }
+        }
+        assert!(limited > 0, "no request was rate limited");
+        cancel.cancel();
+    }
+
+    #[test]
+    fn rate_limiter_passes_normal_cadence() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(start);
+        for i in 0..500 {
+            let now = start + Duration::from_millis(100) * i;
+            assert!(limiter.try_acquire(now), "request {i} rejected");
+        }
+    }
+}
@@ -94,9 +94,15 @@ sendWindowInfo(workspace.activeWindow);
 /// 3. Loads the script via `org.kde.kwin.Scripting`
 /// 4. The script calls back via D-Bus on every window focus change
 ///
+/// Once the script is loaded, `tracking_tx` is set to true.
+///
 /// Graceful degradation: if any step fails, logs a warning and awaits
 /// cancellation. `FocusedApp` targets will silently do nothing.
-pub async fn run(focused_tx: watch::Sender<Option<FocusedWindowInfo>>, cancel: CancellationToken) {
+pub async fn run(
+    focused_tx: watch::Sender<Option<FocusedWindowInfo>>,
+    tracking_tx: watch::Sender<bool>,
+    cancel: CancellationToken,
+) {
     // Step 1: Register D-Bus service BEFORE loading the KWin script.
     // The script fires windowActivated immediately on load, so the service
     // must be ready to receive calls.
@@ -146,6 +152,7 @@ pub async fn run(focused_tx: watch::Sender<Option<FocusedWindowInfo>>, cancel: C
     }
 
     info!("KWin focused window tracking active");
+    let _ = tracking_tx.send(true);
 
     // Keep the D-Bus service alive until cancellation.
     cancel.cancelled().await;
@@ -172,7 +179,7 @@ async fn setup_dbus_service(
 }
 
 fn script_path() -> PathBuf {
-    pcpaneld_core::ipc::xdg_runtime_dir().join("pcpaneld-kwin.js")
+    pcpaneld_core::ipc::usable_runtime_dir().join("pcpaneld-kwin.js")
 }
 
 fn write_kwin_script() -> Result<PathBuf, std::io::Error> {
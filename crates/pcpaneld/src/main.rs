@@ -6,12 +6,33 @@ mod hid;
 mod hid_thread;
 mod ipc_server;
 mod kwin;
+mod metrics;
 mod mpris;
+mod pidfile;
 mod pulse;
+mod screen_lock;
 mod signal;
 mod tray;
 
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+
+/// Control names accepted on the command line, offered by shell completion.
+const CONTROL_NAMES: [&str; 9] = [
+    "knob1", "knob2", "knob3", "knob4", "knob5", "slider1", "slider2", "slider3", "slider4",
+];
+
+/// `assign` action types, offered by shell completion.
+const ACTIONS: [&str; 7] = [
+    "volume",
+    "mute",
+    "volume-step",
+    "media",
+    "exec",
+    "port",
+    "move",
+];
 
 #[derive(Parser)]
 #[command(
@@ -20,6 +41,14 @@ use clap::{Parser, Subcommand};
     about = "PCPanel Pro daemon and control tool"
 )]
 struct Cli {
+    /// IPC socket path, to run several daemons side by side [default:
+    /// $PCPANELD_SOCKET, else $XDG_RUNTIME_DIR/pcpaneld.sock]
+    #[arg(long, global = true, value_name = "PATH")]
+    socket: Option<PathBuf>,
+    /// Config file, for the daemon and for commands that read it [default:
+    /// $XDG_CONFIG_HOME/pcpaneld/config.toml]
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -31,41 +60,191 @@ pub enum Commands {
         /// Log level (trace, debug, info, warn, error)
         #[arg(long, env = "PCPANELD_LOG_LEVEL", default_value = "info")]
         log_level: String,
+        /// Don't show a tray icon, overriding `[daemon] tray`
+        #[arg(long)]
+        no_tray: bool,
     },
     /// Show device, audio, and mapping info
     Info,
     /// List running audio applications
-    Apps,
+    Apps {
+        /// Order the list instead of keeping PulseAudio's order
+        #[arg(long, value_enum)]
+        sort: Option<AppSort>,
+        /// Only list apps whose name, binary, Flatpak ID, or scope contains
+        /// this (case-insensitive)
+        #[arg(long, value_name = "TEXT")]
+        filter: Option<String>,
+        /// Add a column naming the controls mapped to each app
+        #[arg(long)]
+        mappings: bool,
+    },
+    /// Show the focused window and the audio apps it matches
+    Focused,
     /// List audio devices (outputs and inputs)
-    Devices,
+    Devices {
+        /// Also list the monitor of each output, which records what it plays
+        #[arg(long)]
+        monitors: bool,
+    },
+    /// Show the peak levels of the default output and input
+    Levels {
+        /// Keep redrawing the levels until interrupted
+        #[arg(long)]
+        meter: bool,
+    },
+    /// List output devices with their ports (speakers, headphones, ...)
+    Ports,
+    /// Set the default output or input device
+    SetDefault {
+        #[command(subcommand)]
+        command: SetDefaultCommands,
+    },
     /// Assign an action to a control
     Assign {
-        /// Control name (knob1-knob5, slider1-slider4)
-        control: String,
-        /// Action type (volume, mute, media, exec)
-        action: String,
+        /// Control name, or several separated by commas (knob1,knob2)
+        #[arg(
+            required_unless_present = "interactive",
+            value_parser = CONTROL_NAMES,
+            value_delimiter = ',',
+            num_args = 1,
+            action = clap::ArgAction::Set
+        )]
+        control: Vec<String>,
+        /// Action type
+        #[arg(required_unless_present = "interactive", value_parser = ACTIONS)]
+        action: Option<String>,
         /// Target or value
-        value: String,
-        /// Match by binary name (volume/mute only)
+        #[arg(required_unless_present = "interactive")]
+        value: Option<String>,
+        /// Match by binary name (volume/mute/volume-step/move only)
         #[arg(long)]
         binary: Option<String>,
-        /// Match by application name (volume/mute only)
+        /// Match by application name (volume/mute/volume-step/move only)
         #[arg(long)]
         name: Option<String>,
-        /// Match by Flatpak ID (volume/mute only)
+        /// Match by Flatpak ID (volume/mute/volume-step/move only)
         #[arg(long)]
         flatpak_id: Option<String>,
+        /// Match by systemd scope unit, as shown by `apps` (volume/mute/volume-step/move only)
+        #[arg(long)]
+        cgroup: Option<String>,
+        /// Match by the stream's media name, e.g. "Loopback" (volume/mute/volume-step/move only)
+        #[arg(long)]
+        media_name: Option<String>,
+        /// Output device name, as shown by `ports` (port only)
+        #[arg(long)]
+        sink: Option<String>,
+        /// Pick the control, action, and target from menus instead
+        #[arg(short, long, conflicts_with_all = ["control", "action", "value"])]
+        interactive: bool,
+        /// Print how the config would change instead of changing it
+        #[arg(long, conflicts_with = "interactive")]
+        dry_run: bool,
+    },
+    /// Make a knob set a target's volume and its button mute it
+    AssignKnob {
+        /// Knob name
+        #[arg(value_parser = CONTROL_NAMES)]
+        control: String,
+        /// Target: default-output, default-input, app, or focused
+        target: String,
+        /// Match by binary name (app target only)
+        #[arg(long)]
+        binary: Option<String>,
+        /// Match by application name (app target only)
+        #[arg(long)]
+        name: Option<String>,
+        /// Match by Flatpak ID (app target only)
+        #[arg(long)]
+        flatpak_id: Option<String>,
+        /// Match by systemd scope unit, as shown by `apps` (app target only)
+        #[arg(long)]
+        cgroup: Option<String>,
+        /// Match by the stream's media name, e.g. "Loopback" (app target only)
+        #[arg(long)]
+        media_name: Option<String>,
     },
     /// Remove a control assignment
     Unassign {
-        /// Control name (knob1-knob5, slider1-slider4)
+        /// Control name
+        #[arg(value_parser = CONTROL_NAMES)]
         control: String,
+        /// Remove only the dial mapping
+        #[arg(long, conflicts_with = "button")]
+        dial: bool,
+        /// Remove only the button mapping
+        #[arg(long)]
+        button: bool,
+        /// Print how the config would change instead of changing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write the daemon's active config to a file
+    Export {
+        /// Destination file
+        path: PathBuf,
+    },
+    /// Replace the daemon's config with one read from a file
+    Import {
+        /// TOML config file previously written by `export`
+        path: PathBuf,
     },
     /// Configuration commands
     Config {
         #[command(subcommand)]
         command: ConfigCommands,
     },
+    /// LED commands
+    Leds {
+        #[command(subcommand)]
+        command: LedsCommands,
+    },
+    /// Preview how hardware positions map to volume
+    Curve {
+        /// Show this control's `curve_points` instead of the [signal] curve
+        #[arg(value_parser = CONTROL_NAMES)]
+        control: Option<String>,
+    },
+    /// Print the udev rule that gives your user access to the device
+    UdevRule,
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions {
+        /// Shell to generate completions for
+        shell: clap_complete::Shell,
+    },
+}
+
+/// Sort order for `apps --sort`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AppSort {
+    /// Alphabetically by application name
+    Name,
+    /// Loudest first
+    Volume,
+    /// By stream index, oldest first
+    Index,
+}
+
+#[derive(Subcommand)]
+pub enum SetDefaultCommands {
+    /// Set the default output device
+    Output {
+        /// Device index (from `devices`) or part of its name or description
+        device: String,
+    },
+    /// Set the default input device
+    Input {
+        /// Device index (from `devices`) or part of its name or description
+        device: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum LedsCommands {
+    /// Resend the configured LED colors, undoing a session-only toggle
+    Reset,
 }
 
 #[derive(Subcommand)]
@@ -76,6 +255,11 @@ pub enum ConfigCommands {
     Reload,
     /// Print config directory path
     Dir,
+    /// Check a config file for mappings that can't work as intended
+    Validate {
+        /// Config file to check (defaults to the active config file)
+        path: Option<PathBuf>,
+    },
 }
 
 fn main() {
@@ -83,7 +267,6 @@ fn main() {
 
     let result = match cli.command {
         None => {
-            use clap::CommandFactory;
             let _ = Cli::command().print_help();
             println!();
             std::process::exit(0);
@@ -91,12 +274,23 @@ fn main() {
         // Daemon builds its own multi-thread runtime (needs spawn_blocking for
         // std::thread joins). CLI commands only need a single-threaded runtime
         // for one IPC round-trip.
-        Some(Commands::Daemon { log_level }) => daemon::run(&log_level),
+        Some(Commands::Daemon { log_level, no_tray }) => {
+            daemon::run(&log_level, no_tray, cli.config, cli.socket)
+        }
+        Some(Commands::Completions { shell }) => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "pcpaneld",
+                &mut std::io::stdout(),
+            );
+            Ok(())
+        }
         Some(cmd) => tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()
             .expect("failed to create tokio runtime")
-            .block_on(cli::run(cmd)),
+            .block_on(cli::run(cmd, cli.config, cli.socket)),
     };
 
     if let Err(e) = result {
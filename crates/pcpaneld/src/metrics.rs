@@ -0,0 +1,249 @@
+//! Runtime counters, optionally served in Prometheus text format.
+//!
+//! The engine increments these atomics as it works; with the `metrics` cargo
+//! feature and `[metrics] listen` set, a small HTTP server exposes them at
+//! `/metrics`. Atomics rather than a channel because a scrape must never wait
+//! on the engine loop.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Counters and gauges shared between the engine and the metrics server.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Knob/slider position changes and button events received from the device.
+    pub events_processed: AtomicU64,
+    /// Volume commands sent to PulseAudio.
+    pub volume_commands: AtomicU64,
+    /// Whether a device is currently connected.
+    pub device_connected: AtomicBool,
+    /// Successful PulseAudio connections, including the first.
+    pub pulse_connections: AtomicU64,
+    /// Exec button commands started.
+    pub exec_run: AtomicU64,
+    /// Exec button commands dropped at the concurrency limit.
+    pub exec_dropped: AtomicU64,
+}
+
+impl Metrics {
+    /// Add one to `counter`.
+    pub fn incr(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// PulseAudio connections after the first.
+    pub fn pulse_reconnects(&self) -> u64 {
+        self.pulse_connections
+            .load(Ordering::Relaxed)
+            .saturating_sub(1)
+    }
+
+    /// Render every metric in the Prometheus text exposition format.
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub fn render(&self) -> String {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let metrics = [
+            (
+                "pcpaneld_events_processed_total",
+                "counter",
+                "Position and button events received from the device.",
+                load(&self.events_processed),
+            ),
+            (
+                "pcpaneld_volume_commands_total",
+                "counter",
+                "Volume commands sent to PulseAudio.",
+                load(&self.volume_commands),
+            ),
+            (
+                "pcpaneld_device_connected",
+                "gauge",
+                "1 while a PCPanel is connected.",
+                u64::from(self.device_connected.load(Ordering::Relaxed)),
+            ),
+            (
+                "pcpaneld_pulse_reconnects_total",
+                "counter",
+                "PulseAudio reconnections after the first connection.",
+                self.pulse_reconnects(),
+            ),
+            (
+                "pcpaneld_exec_commands_run_total",
+                "counter",
+                "Exec button commands started.",
+                load(&self.exec_run),
+            ),
+            (
+                "pcpaneld_exec_commands_dropped_total",
+                "counter",
+                "Exec button commands dropped at the concurrency limit.",
+                load(&self.exec_dropped),
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            let _ = writeln!(out, "{name} {value}");
+        }
+        out
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub use server::serve;
+
+#[cfg(feature = "metrics")]
+mod server {
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_util::sync::CancellationToken;
+    use tracing::{debug, info, warn};
+
+    use super::Metrics;
+
+    /// Largest request head accepted. Scrapers send a few hundred bytes.
+    const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+    /// Time a client gets to send its request.
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Serve `GET /metrics` on `addr` until cancelled.
+    pub async fn serve(addr: SocketAddr, metrics: Arc<Metrics>, cancel: CancellationToken) {
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("failed to bind metrics server on {addr}: {e}");
+                return;
+            }
+        };
+        info!("serving metrics on http://{addr}/metrics");
+
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let metrics = Arc::clone(&metrics);
+                        tokio::spawn(async move {
+                            if let Err(e) = handle_connection(stream, &metrics).await {
+                                debug!("metrics request from {peer} failed: {e}");
+                            }
+                        });
+                    }
+                    Err(e) => warn!("failed to accept metrics connection: {e}"),
+                },
+            }
+        }
+    }
+
+    /// Answer a single HTTP/1.x request and close the connection.
+    async fn handle_connection<S>(mut stream: S, metrics: &Metrics) -> std::io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut buf = vec![0u8; MAX_REQUEST_SIZE];
+        let mut len = 0;
+        let head_complete = |buf: &[u8]| buf.windows(4).any(|w| w == b"\r\n\r\n");
+        let read_head = async {
+            while !head_complete(&buf[..len]) && len < buf.len() {
+                let n = stream.read(&mut buf[len..]).await?;
+                if n == 0 {
+                    break;
+                }
+                len += n;
+            }
+            Ok::<_, std::io::Error>(())
+        };
+        tokio::time::timeout(REQUEST_TIMEOUT, read_head)
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+
+        let head = String::from_utf8_lossy(&buf[..len]);
+        let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+        let (status, body) = match (request_line.next(), request_line.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+            (Some("GET"), Some(_)) => ("404 Not Found", "not found\n".to_string()),
+            _ => ("400 Bad Request", "bad request\n".to_string()),
+        };
+        let response = format!(
+            "HTTP/1.1 {status}\r\n\
+             Content-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        async fn request(raw: &str, metrics: &Metrics) -> String {
+            let (mut client, server) = tokio::io::duplex(MAX_REQUEST_SIZE * 2);
+            client.write_all(raw.as_bytes()).await.unwrap();
+            handle_connection(server, metrics).await.unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            response
+        }
+
+        #[tokio::test]
+        async fn serves_metrics_and_rejects_other_paths() {
+            let metrics = Metrics::default();
+            Metrics::incr(&metrics.volume_commands);
+
+            let response = request("GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n", &metrics).await;
+            assert!(
+                response.starts_with("HTTP/1.1 200 OK\r\n"),
+                "got: {response}"
+            );
+            assert!(response.contains("\npcpaneld_volume_commands_total 1\n"));
+
+            let response = request("GET / HTTP/1.1\r\n\r\n", &metrics).await;
+            assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+            let response = request("POST /metrics HTTP/1.1\r\n\r\n", &metrics).await;
+            assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_reports_every_metric() {
+        let metrics = Metrics::default();
+        Metrics::incr(&metrics.events_processed);
+        Metrics::incr(&metrics.events_processed);
+        Metrics::incr(&metrics.exec_dropped);
+        metrics.device_connected.store(true, Ordering::Relaxed);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE pcpaneld_events_processed_total counter\n"));
+        assert!(text.contains("\npcpaneld_events_processed_total 2\n"));
+        assert!(text.contains("\npcpaneld_device_connected 1\n"));
+        assert!(text.contains("\npcpaneld_exec_commands_dropped_total 1\n"));
+        assert!(text.contains("\npcpaneld_pulse_reconnects_total 0\n"));
+    }
+
+    #[test]
+    fn first_pulse_connection_is_not_a_reconnect() {
+        let metrics = Metrics::default();
+        assert_eq!(metrics.pulse_reconnects(), 0);
+        Metrics::incr(&metrics.pulse_connections);
+        assert_eq!(metrics.pulse_reconnects(), 0);
+        Metrics::incr(&metrics.pulse_connections);
+        Metrics::incr(&metrics.pulse_connections);
+        assert_eq!(metrics.pulse_reconnects(), 2);
+    }
+}
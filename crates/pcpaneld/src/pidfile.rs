@@ -0,0 +1,74 @@
+//! Optional PID file (`[daemon] pidfile`) for init scripts and monitoring.
+//!
+//! The socket, not this file, decides whether another daemon is running; a
+//! leftover PID file from a crash is replaced rather than refused.
+
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Write our PID to `path`, via a temporary file and rename so readers never
+/// see a partial write.
+pub fn write(path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp_path = tmp_path(path);
+    std::fs::write(&tmp_path, format!("{}\n", std::process::id()))?;
+    std::fs::rename(&tmp_path, path)
+}
+
+/// The PID recorded in `path`, if that process is still running.
+pub fn live_pid(path: &Path) -> Option<u32> {
+    let pid = read_pid(path)?;
+    Path::new(&format!("/proc/{pid}")).exists().then_some(pid)
+}
+
+/// Remove `path` if it still names this process, so a file written by a
+/// newer daemon is left alone.
+pub fn remove(path: &Path) -> io::Result<()> {
+    if read_pid(path) == Some(std::process::id()) {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_then_remove_own_pidfile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run/pcpaneld.pid");
+
+        write(&path).unwrap();
+        assert_eq!(live_pid(&path), Some(std::process::id()));
+        assert!(!tmp_path(&path).exists());
+
+        remove(&path).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn stale_pidfile_is_not_live_and_not_ours() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pcpaneld.pid");
+        // PIDs are capped well below u32::MAX, so no process has this one.
+        std::fs::write(&path, format!("{}\n", u32::MAX)).unwrap();
+
+        assert_eq!(live_pid(&path), None);
+        remove(&path).unwrap();
+        assert!(path.exists(), "another process's pidfile must be kept");
+    }
+}
@@ -1,13 +1,21 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender};
+use std::time::{Duration, Instant};
 
 use libpulse_binding as pulse;
 use libpulse_binding::callbacks::ListResult;
 use libpulse_binding::context::subscribe::{Facility, InterestMaskSet};
 use libpulse_binding::context::{Context, FlagSet as CtxFlagSet, State as CtxState};
+use libpulse_binding::def::{BufferAttr, PortAvailable};
 use libpulse_binding::mainloop::threaded::Mainloop;
 use libpulse_binding::proplist::Proplist;
-use pcpaneld_core::audio::{AudioState, SinkInfo, SinkInputInfo, SourceInfo, Volume};
+use libpulse_binding::sample::{Format, Spec};
+use libpulse_binding::stream::{FlagSet as StreamFlagSet, PeekResult, Stream};
+use pcpaneld_core::audio::{
+    cgroup_scope, AudioState, PortInfo, SinkInfo, SinkInputInfo, SourceInfo, Volume,
+};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
@@ -42,6 +50,34 @@ pub enum AudioCommand {
         index: u32,
         mute: bool,
     },
+    SetSinkPort {
+        index: u32,
+        port: String,
+    },
+    SuspendSink {
+        index: u32,
+        suspend: bool,
+    },
+    MoveSinkInput {
+        index: u32,
+        sink_index: u32,
+    },
+    SetDefaultSink {
+        name: String,
+    },
+    SetDefaultSource {
+        name: String,
+    },
+    /// Open a peak-detecting record stream on a source, such as a sink's
+    /// monitor. Its levels come back as [`AudioNotification::Level`]. A
+    /// no-op if the source is already metered.
+    StartMetering {
+        index: u32,
+    },
+    /// Close the meter opened by `StartMetering`.
+    StopMetering {
+        index: u32,
+    },
 }
 
 /// Notifications from the PA thread to the engine.
@@ -50,17 +86,125 @@ pub enum AudioNotification {
     Connected,
     Disconnected,
     StateSnapshot(AudioState),
+    /// Peak level (0.0-1.0) of a metered source since the previous `Level`
+    /// for the same source. Replaceable: dropped if the channel is full.
+    Level {
+        index: u32,
+        peak: f32,
+    },
 }
 
+/// Peak samples per second requested from the server. With `PEAK_DETECT` the
+/// server computes the peak of each period, so a meter stream carries a few
+/// dozen floats per second instead of raw audio.
+const PEAK_RATE: u32 = 25;
+
+/// Minimum interval between `Level` notifications for a single source.
+/// Slightly shorter than the `PEAK_RATE` period so each delivered peak gets
+/// through while bursts are still coalesced.
+const PEAK_INTERVAL: Duration = Duration::from_millis(30);
+
+/// Why the PA thread woke up.
+#[derive(Debug)]
+enum Wake {
+    /// A command forwarded from the engine.
+    Command(AudioCommand),
+    /// Something on the PA side needs a look: a subscribed event, a context
+    /// state change, or fresh peak data.
+    Pulse,
+    /// The engine dropped its command sender; the daemon is shutting down.
+    Closed,
+}
+
+/// Bound of the PA thread's wake channel. Commands are forwarded with a
+/// blocking `send`, so a stalled PA thread back-pressures the engine as the
+/// command channel did before. `Pulse` wakes use `try_send`: when the channel
+/// is full a wake is already pending and dropping another loses nothing.
+const WAKE_CAPACITY: usize = 64;
+
+/// Quiet period after a PA event before snapshotting, so a burst of events
+/// (an app opening its streams, a device switch) yields a single snapshot.
+const SNAPSHOT_QUIET: Duration = Duration::from_millis(30);
+
+/// Upper bound on how long a continuous stream of events can defer a snapshot.
+const SNAPSHOT_MAX_DELAY: Duration = Duration::from_millis(150);
+
+/// A pending snapshot. Set by the subscribe callback, taken when the
+/// snapshot queries start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Dirty {
+    /// First event of the current burst.
+    first: Instant,
+    /// When the snapshot should run.
+    due: Instant,
+    /// Events coalesced into this snapshot, for logging.
+    events: u32,
+}
+
+impl Dirty {
+    /// A snapshot that runs right away (session start).
+    fn immediate(now: Instant) -> Self {
+        Self {
+            first: now,
+            due: now,
+            events: 0,
+        }
+    }
+
+    /// Record a PA event at `now`. Pushes the snapshot back to `now +
+    /// SNAPSHOT_QUIET`, but never past `SNAPSHOT_MAX_DELAY` after the first
+    /// event, and never once it is already due.
+    fn note_event(dirty: &mut Option<Dirty>, now: Instant) {
+        match dirty {
+            Some(d) => {
+                d.events += 1;
+                if d.due > now {
+                    d.due = (now + SNAPSHOT_QUIET).min(d.first + SNAPSHOT_MAX_DELAY);
+                }
+            }
+            None => {
+                *dirty = Some(Dirty {
+                    first: now,
+                    due: now + SNAPSHOT_QUIET,
+                    events: 1,
+                });
+            }
+        }
+    }
+}
+
+/// Longest the PA thread blocks without a wake, so cancellation is noticed
+/// even if the engine never closes the command channel.
+const IDLE_CHECK: Duration = Duration::from_secs(1);
+
 /// Main PulseAudio thread function.
 ///
 /// Runs the PA threaded mainloop, subscribes to events, and communicates
-/// with the engine via channels.
+/// with the engine via channels. Between bursts of work the thread blocks on
+/// a wake channel fed by PA callbacks and by a small forwarder thread that
+/// moves engine commands over, instead of polling on a timer.
 pub fn run(
     mut cmd_rx: mpsc::Receiver<AudioCommand>,
     notify_tx: mpsc::Sender<AudioNotification>,
     cancel: CancellationToken,
 ) {
+    let (wake_tx, wake_rx) = std::sync::mpsc::sync_channel(WAKE_CAPACITY);
+    let forward_tx = wake_tx.clone();
+    let forwarder = std::thread::Builder::new()
+        .name("pulse-cmd".into())
+        .spawn(move || {
+            while let Some(cmd) = cmd_rx.blocking_recv() {
+                if forward_tx.send(Wake::Command(cmd)).is_err() {
+                    return;
+                }
+            }
+            let _ = forward_tx.send(Wake::Closed);
+        });
+    if let Err(e) = forwarder {
+        warn!("failed to spawn PA command forwarder: {e}");
+        return;
+    }
+
     let mut retry_delay_ms: u64 = 1000;
     loop {
         if cancel.is_cancelled() {
@@ -68,7 +212,7 @@ pub fn run(
         }
 
         let session_start = std::time::Instant::now();
-        match run_session(&mut cmd_rx, &notify_tx, &cancel) {
+        match run_session(&wake_tx, &wake_rx, &notify_tx, &cancel) {
             Ok(()) => {
                 break;
             }
@@ -92,7 +236,8 @@ pub fn run(
 }
 
 fn run_session(
-    cmd_rx: &mut mpsc::Receiver<AudioCommand>,
+    wake_tx: &SyncSender<Wake>,
+    wake_rx: &Receiver<Wake>,
     notify_tx: &mpsc::Sender<AudioNotification>,
     cancel: &CancellationToken,
 ) -> anyhow::Result<()> {
@@ -153,11 +298,19 @@ fn run_session(
     info!("PulseAudio connected");
     let _ = notify_tx.blocking_send(AudioNotification::Connected);
 
+    // Peak meter streams, keyed by source index. Only touched under the PA lock.
+    let mut meters: HashMap<u32, PeakMeter> = HashMap::new();
+
     // Set up subscribe callback for change notifications.
-    // The dirty flag is accessed from our thread (under PA lock) and from the
+    // The dirty state is accessed from our thread (under PA lock) and from the
     // mainloop thread's subscribe callback (which runs with the lock held).
-    let dirty = Rc::new(RefCell::new(true)); // Start dirty to get initial snapshot
+    // Start dirty so the initial snapshot is taken immediately.
+    let dirty = Rc::new(RefCell::new(Some(Dirty::immediate(Instant::now()))));
     let dirty_for_cb = dirty.clone();
+    // A snapshot's queries are outstanding; the next one waits for it.
+    let in_flight = Rc::new(RefCell::new(false));
+    let wake_for_cb = wake_tx.clone();
+    let wake_for_state = wake_tx.clone();
 
     mainloop.borrow_mut().lock();
     {
@@ -167,9 +320,14 @@ fn run_session(
                 Facility::Sink | Facility::Source | Facility::SinkInput | Facility::Server,
             ) = facility
             {
-                *dirty_for_cb.borrow_mut() = true;
+                Dirty::note_event(&mut dirty_for_cb.borrow_mut(), Instant::now());
+                let _ = wake_for_cb.try_send(Wake::Pulse);
             }
         })));
+        // Wake on context failure so a dead server is noticed right away.
+        ctx.set_state_callback(Some(Box::new(move || {
+            let _ = wake_for_state.try_send(Wake::Pulse);
+        })));
 
         ctx.subscribe(
             InterestMaskSet::SINK
@@ -181,6 +339,8 @@ fn run_session(
     }
     mainloop.borrow_mut().unlock();
 
+    // Wake received while blocked, handled at the top of the next iteration.
+    let mut next_wake = None;
     loop {
         if cancel.is_cancelled() {
             break;
@@ -197,22 +357,39 @@ fn run_session(
             return Err(anyhow::anyhow!("PA context disconnected"));
         }
 
-        // Process audio commands (non-blocking)
-        let mut had_work = false;
-        while let Ok(cmd) = cmd_rx.try_recv() {
-            execute_command(&context, cmd);
-            had_work = true;
+        // Process everything that is already queued (non-blocking)
+        let mut closed = false;
+        let pending = next_wake
+            .take()
+            .into_iter()
+            .chain(std::iter::from_fn(|| wake_rx.try_recv().ok()));
+        for wake in pending {
+            match wake {
+                Wake::Command(cmd) => execute_command(&context, &mut meters, wake_tx, cmd),
+                Wake::Pulse => {}
+                Wake::Closed => closed = true,
+            }
         }
 
-        // If dirty, start a new snapshot
-        if *dirty.borrow() {
-            had_work = true;
-            *dirty.borrow_mut() = false;
+        flush_peaks(&mut meters, notify_tx);
+
+        // Start a new snapshot once the burst has settled and the previous
+        // snapshot has completed
+        let now = Instant::now();
+        let due = dirty
+            .borrow()
+            .filter(|d| d.due <= now && !*in_flight.borrow());
+        if let Some(d) = due {
+            *dirty.borrow_mut() = None;
+            *in_flight.borrow_mut() = true;
             let ctx = SnapshotCtx {
                 state: Rc::new(RefCell::new(AudioState::default())),
                 pending: Rc::new(RefCell::new(4u32)), // 4 queries
                 notify: notify_tx.clone(),
                 dirty: dirty.clone(),
+                in_flight: in_flight.clone(),
+                wake: wake_tx.clone(),
+                events: d.events,
             };
 
             // Query server info (for default sink/source names)
@@ -251,6 +428,31 @@ fn run_session(
                                 volume: Volume::new(vol),
                                 muted: info.mute,
                                 channels: info.volume.len(),
+                                monitor_source: (info.monitor_source != pulse::def::INVALID_INDEX)
+                                    .then_some(info.monitor_source),
+                                ports: info
+                                    .ports
+                                    .iter()
+                                    .map(|port| PortInfo {
+                                        name: port
+                                            .name
+                                            .as_ref()
+                                            .map(|s| s.to_string())
+                                            .unwrap_or_default(),
+                                        description: port
+                                            .description
+                                            .as_ref()
+                                            .map(|s| s.to_string())
+                                            .unwrap_or_default(),
+                                        available: port.available != PortAvailable::No,
+                                    })
+                                    .collect(),
+                                active_port: info
+                                    .active_port
+                                    .as_ref()
+                                    .and_then(|port| port.name.as_ref())
+                                    .map(|s| s.to_string()),
+                                suspended: info.state == pulse::def::SinkState::Suspended,
                             });
                         } else if let ListResult::End = result {
                             ctx.complete();
@@ -304,6 +506,8 @@ fn run_session(
                                 .proplist
                                 .get_str("application.process.id")
                                 .and_then(|s| s.parse::<u32>().ok());
+                            let cgroup = pid.and_then(read_cgroup_scope);
+                            let media_name = info.proplist.get_str("media.name");
                             let name = info
                                 .name
                                 .as_ref()
@@ -317,6 +521,8 @@ fn run_session(
                                 binary,
                                 flatpak_id,
                                 pid,
+                                cgroup,
+                                media_name,
                                 sink_index: info.sink,
                                 volume: Volume::new(vol),
                                 muted: info.mute,
@@ -331,14 +537,38 @@ fn run_session(
 
         mainloop.borrow_mut().unlock();
 
-        // Sleep briefly to avoid busy-spinning (lock is NOT held during sleep).
-        // Use a shorter interval when actively processing commands/events.
-        let sleep_ms = if had_work { 20 } else { 100 };
-        std::thread::sleep(std::time::Duration::from_millis(sleep_ms));
+        if closed {
+            break;
+        }
+
+        // Block until there is something to do (lock is NOT held while waiting).
+        // A pending snapshot bounds the wait; an in-flight one wakes us when
+        // it completes.
+        let timeout = match *dirty.borrow() {
+            Some(d) if !*in_flight.borrow() => d
+                .due
+                .saturating_duration_since(Instant::now())
+                .min(IDLE_CHECK),
+            _ => IDLE_CHECK,
+        };
+        match wake_rx.recv_timeout(timeout) {
+            Ok(wake) => next_wake = Some(wake),
+            Err(RecvTimeoutError::Timeout) => {}
+            // Unreachable while `run` holds a sender, but don't spin if it happens.
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
     }
 
     // Clean shutdown
     mainloop.borrow_mut().lock();
+    {
+        let mut ctx = context.borrow_mut();
+        ctx.set_subscribe_callback(None);
+        ctx.set_state_callback(None);
+    }
+    for (_, meter) in meters.drain() {
+        meter.close();
+    }
     context.borrow_mut().disconnect();
     mainloop.borrow_mut().unlock();
     mainloop.borrow_mut().stop();
@@ -354,7 +584,10 @@ struct SnapshotCtx {
     state: Rc<RefCell<AudioState>>,
     pending: Rc<RefCell<u32>>,
     notify: mpsc::Sender<AudioNotification>,
-    dirty: Rc<RefCell<bool>>,
+    dirty: Rc<RefCell<Option<Dirty>>>,
+    in_flight: Rc<RefCell<bool>>,
+    wake: SyncSender<Wake>,
+    events: u32,
 }
 
 impl SnapshotCtx {
@@ -364,18 +597,22 @@ impl SnapshotCtx {
         if *p == 0 {
             let snapshot = self.state.borrow().clone();
             debug!(
-                "PA snapshot: {} sinks, {} sources, {} inputs",
+                "PA snapshot: {} sinks, {} sources, {} inputs ({} events coalesced)",
                 snapshot.sinks.len(),
                 snapshot.sources.len(),
                 snapshot.sink_inputs.len(),
+                self.events,
             );
             let _ = self
                 .notify
                 .blocking_send(AudioNotification::StateSnapshot(snapshot));
+            *self.in_flight.borrow_mut() = false;
 
-            // If dirty flag was set during query, we'll re-snapshot on next iteration
-            if *self.dirty.borrow() {
-                debug!("dirty flag set during snapshot, will re-query");
+            // Events that arrived during the queries were held back; let the
+            // PA thread schedule the next snapshot.
+            if self.dirty.borrow().is_some() {
+                debug!("PA events during snapshot, will re-query");
+                let _ = self.wake.try_send(Wake::Pulse);
             }
         }
     }
@@ -388,8 +625,164 @@ fn make_channel_volumes(volume: Volume, channels: u8) -> pulse::volume::ChannelV
     cv
 }
 
+/// A record stream on a source that reports its peak level.
+struct PeakMeter {
+    stream: Rc<RefCell<Stream>>,
+    /// Highest level seen since the last notification, written by the read callback.
+    peak: Rc<RefCell<Option<f32>>>,
+    last_sent: Instant,
+}
+
+impl PeakMeter {
+    /// Open a peak-detecting record stream on the source with the given index.
+    /// Each delivered peak wakes the PA thread through `wake_tx`.
+    /// Caller must hold the mainloop lock.
+    fn open(
+        context: &Rc<RefCell<Context>>,
+        index: u32,
+        wake_tx: SyncSender<Wake>,
+    ) -> anyhow::Result<Self> {
+        let spec = Spec {
+            format: Format::F32le,
+            rate: PEAK_RATE,
+            channels: 1,
+        };
+        let stream = Stream::new(&mut context.borrow_mut(), "Peak meter", &spec, None)
+            .ok_or_else(|| anyhow::anyhow!("failed to create PA stream"))?;
+        let stream = Rc::new(RefCell::new(stream));
+        let peak: Rc<RefCell<Option<f32>>> = Rc::new(RefCell::new(None));
+
+        {
+            // Weak reference: the stream owns this callback, so a strong one
+            // would form a cycle and leak the stream.
+            let weak = Rc::downgrade(&stream);
+            let peak = peak.clone();
+            stream
+                .borrow_mut()
+                .set_read_callback(Some(Box::new(move |_len| {
+                    let Some(stream) = weak.upgrade() else {
+                        return;
+                    };
+                    let mut stream = stream.borrow_mut();
+                    loop {
+                        let level = match stream.peek() {
+                            Ok(PeekResult::Data(data)) => max_sample(data),
+                            Ok(PeekResult::Hole(_)) => None,
+                            Ok(PeekResult::Empty) | Err(_) => break,
+                        };
+                        let _ = stream.discard();
+                        if let Some(level) = level {
+                            let mut peak = peak.borrow_mut();
+                            *peak = Some(peak.map_or(level, |p| p.max(level)));
+                        }
+                    }
+                    if peak.borrow().is_some() {
+                        let _ = wake_tx.try_send(Wake::Pulse);
+                    }
+                })));
+        }
+
+        // One float per fragment so the server delivers each peak promptly.
+        let attr = BufferAttr {
+            maxlength: u32::MAX,
+            tlength: u32::MAX,
+            prebuf: u32::MAX,
+            minreq: u32::MAX,
+            fragsize: std::mem::size_of::<f32>() as u32,
+        };
+        stream
+            .borrow_mut()
+            .connect_record(
+                Some(&index.to_string()),
+                Some(&attr),
+                StreamFlagSet::PEAK_DETECT
+                    | StreamFlagSet::ADJUST_LATENCY
+                    | StreamFlagSet::DONT_MOVE,
+            )
+            .map_err(|e| anyhow::anyhow!("PA record connect failed: {e}"))?;
+
+        Ok(Self {
+            stream,
+            peak,
+            last_sent: Instant::now(),
+        })
+    }
+
+    /// Disconnect the stream. Caller must hold the mainloop lock.
+    fn close(self) {
+        let mut stream = self.stream.borrow_mut();
+        stream.set_read_callback(None);
+        let _ = stream.disconnect();
+    }
+}
+
+/// systemd scope unit of a sink-input's process. `None` if the process has
+/// already exited or isn't in an app scope.
+fn read_cgroup_scope(pid: u32) -> Option<String> {
+    let contents = std::fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    cgroup_scope(&contents).map(str::to_owned)
+}
+
+/// Largest absolute sample in a buffer of little-endian `f32` samples.
+fn max_sample(data: &[u8]) -> Option<f32> {
+    data.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).abs())
+        .filter(|v| v.is_finite())
+        .reduce(f32::max)
+        .map(|v| v.min(1.0))
+}
+
+/// Open a meter on a source unless it already has one. Caller must hold the
+/// mainloop lock.
+fn start_metering(
+    context: &Rc<RefCell<Context>>,
+    meters: &mut HashMap<u32, PeakMeter>,
+    wake_tx: &SyncSender<Wake>,
+    index: u32,
+) {
+    if meters.contains_key(&index) {
+        return;
+    }
+    match PeakMeter::open(context, index, wake_tx.clone()) {
+        Ok(meter) => {
+            debug!("opened peak meter on source {index}");
+            meters.insert(index, meter);
+        }
+        Err(e) => warn!("failed to open peak meter on source {index}: {e}"),
+    }
+}
+
+/// Close a source's meter, if it has one. Caller must hold the mainloop lock.
+fn stop_metering(meters: &mut HashMap<u32, PeakMeter>, index: u32) {
+    if let Some(meter) = meters.remove(&index) {
+        debug!("closing peak meter on source {index}");
+        meter.close();
+    }
+}
+
+/// Send the accumulated peak of each meter, at most once per `PEAK_INTERVAL`.
+fn flush_peaks(meters: &mut HashMap<u32, PeakMeter>, notify_tx: &mpsc::Sender<AudioNotification>) {
+    for (&index, meter) in meters.iter_mut() {
+        if meter.last_sent.elapsed() < PEAK_INTERVAL {
+            continue;
+        }
+        let Some(peak) = meter.peak.borrow_mut().take() else {
+            continue;
+        };
+        meter.last_sent = Instant::now();
+        // Levels are replaceable: if the engine is behind, dropping one is
+        // better than blocking the PA thread.
+        let _ = notify_tx.try_send(AudioNotification::Level { index, peak });
+    }
+}
+
 /// Execute a PA command. Caller must hold the mainloop lock.
-fn execute_command(context: &Rc<RefCell<Context>>, cmd: AudioCommand) {
+fn execute_command(
+    context: &Rc<RefCell<Context>>,
+    meters: &mut HashMap<u32, PeakMeter>,
+    wake_tx: &SyncSender<Wake>,
+    cmd: AudioCommand,
+) {
     let mut introspect = context.borrow().introspect();
 
     match cmd {
@@ -426,9 +819,92 @@ fn execute_command(context: &Rc<RefCell<Context>>, cmd: AudioCommand) {
         AudioCommand::SinkInputMute { index, mute } => {
             introspect.set_sink_input_mute(index, mute, None);
         }
+        AudioCommand::SetSinkPort { index, port } => {
+            introspect.set_sink_port_by_index(index, &port, None);
+        }
+        AudioCommand::SuspendSink { index, suspend } => {
+            introspect.suspend_sink_by_index(index, suspend, None);
+        }
+        AudioCommand::MoveSinkInput { index, sink_index } => {
+            introspect.move_sink_input_by_index(index, sink_index, None);
+        }
+        AudioCommand::SetDefaultSink { name } => {
+            context.borrow_mut().set_default_sink(&name, |_| {});
+        }
+        AudioCommand::SetDefaultSource { name } => {
+            context.borrow_mut().set_default_source(&name, |_| {});
+        }
+        AudioCommand::StartMetering { index } => {
+            start_metering(context, meters, wake_tx, index);
+        }
+        AudioCommand::StopMetering { index } => stop_metering(meters, index),
     }
 }
 
 fn volume_to_pa(volume: Volume) -> pulse::volume::Volume {
     pulse::volume::Volume::from(pulse::volume::VolumeLinear(volume.get()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Count the snapshots the PA thread takes for events at the given
+    /// offsets (ms), assuming it wakes on every event and at every due time
+    /// and that snapshots complete instantly.
+    fn snapshots_for(event_offsets_ms: &[u64]) -> u32 {
+        let start = Instant::now();
+        let mut dirty = None;
+        let mut snapshots = 0;
+        let mut events = event_offsets_ms.iter().peekable();
+        loop {
+            let next_event = events.peek().map(|&&ms| start + Duration::from_millis(ms));
+            let next_due = dirty.map(|d: Dirty| d.due);
+            match (next_event, next_due) {
+                (Some(e), Some(d)) if d < e => {
+                    dirty = None;
+                    snapshots += 1;
+                }
+                (Some(e), _) => {
+                    Dirty::note_event(&mut dirty, e);
+                    events.next();
+                }
+                (None, Some(_)) => {
+                    dirty = None;
+                    snapshots += 1;
+                }
+                (None, None) => return snapshots,
+            }
+        }
+    }
+
+    #[test]
+    fn burst_of_events_yields_one_snapshot() {
+        // App startup: 12 events within ~60ms. The old 20ms poll took a
+        // snapshot on nearly every tick of the burst (4 here).
+        let burst: Vec<u64> = (0..12).map(|i| i * 5).collect();
+        assert_eq!(snapshots_for(&burst), 1);
+    }
+
+    #[test]
+    fn continuous_events_still_snapshot_at_max_delay() {
+        // One event every 10ms for a second never goes quiet; snapshots are
+        // forced every SNAPSHOT_MAX_DELAY instead of being deferred forever.
+        let stream: Vec<u64> = (0..100).map(|i| i * 10).collect();
+        let count = snapshots_for(&stream);
+        assert!((6..=8).contains(&count), "got {count} snapshots");
+    }
+
+    #[test]
+    fn separate_events_each_get_a_snapshot() {
+        assert_eq!(snapshots_for(&[0, 500, 1000]), 3);
+    }
+
+    #[test]
+    fn initial_snapshot_is_not_deferred_by_events() {
+        let now = Instant::now();
+        let mut dirty = Some(Dirty::immediate(now));
+        Dirty::note_event(&mut dirty, now + Duration::from_millis(1));
+        assert_eq!(dirty.map(|d| d.due), Some(now));
+    }
+}
@@ -0,0 +1,78 @@
+use std::pin::Pin;
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+use zbus::export::futures_core::Stream;
+
+/// Interfaces whose `ActiveChanged(bool)` signal reports the screen locker.
+/// KDE and most other desktops implement the freedesktop one, GNOME its own.
+const SCREENSAVER_IFACES: [&str; 2] = ["org.freedesktop.ScreenSaver", "org.gnome.ScreenSaver"];
+
+/// Tracks whether the session's screen is locked by listening for the
+/// screensaver's `ActiveChanged` signal on the session bus.
+///
+/// Graceful degradation: without a session bus, logs and awaits
+/// cancellation, and `locked_tx` stays `false`. A desktop that never emits
+/// the signal has the same effect.
+pub async fn run(locked_tx: watch::Sender<bool>, cancel: CancellationToken) {
+    let mut stream = match subscribe().await {
+        Ok(s) => s,
+        Err(e) => {
+            info!("screen lock tracking unavailable: {e}");
+            cancel.cancelled().await;
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            msg = next_message(&mut stream) => {
+                let Some(msg) = msg else {
+                    debug!("screen lock signal stream ended");
+                    cancel.cancelled().await;
+                    break;
+                };
+                let Ok(msg) = msg else { continue };
+                let header = msg.header();
+                let known = header
+                    .interface()
+                    .is_some_and(|iface| SCREENSAVER_IFACES.contains(&iface.as_str()));
+                if !known {
+                    continue;
+                }
+                match msg.body().deserialize::<bool>() {
+                    Ok(locked) => {
+                        // Some desktops emit the signal on several object paths.
+                        locked_tx.send_if_modified(|current| {
+                            let changed = *current != locked;
+                            *current = locked;
+                            changed
+                        });
+                    }
+                    Err(e) => debug!("malformed ActiveChanged signal: {e}"),
+                }
+            }
+        }
+    }
+}
+
+/// Next message of `stream`, without pulling in a futures crate for
+/// `StreamExt`.
+async fn next_message(
+    stream: &mut zbus::MessageStream,
+) -> Option<Result<zbus::Message, zbus::Error>> {
+    std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+async fn subscribe() -> Result<zbus::MessageStream, zbus::Error> {
+    let conn = zbus::Connection::session().await?;
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .member("ActiveChanged")?
+        .build();
+    let stream = zbus::MessageStream::for_match_rule(rule, &conn, None).await?;
+    debug!("listening for screen lock changes");
+    Ok(stream)
+}
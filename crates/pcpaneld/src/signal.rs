@@ -1,5 +1,5 @@
 use std::collections::VecDeque;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Per-control signal processing pipeline.
 ///
@@ -111,10 +111,44 @@ impl SignalPipeline {
     }
 }
 
+/// Detects a slam gesture on one control: its processed value arriving at
+/// the same endpoint (0 or 255) twice within a window, e.g. flicking a slider
+/// to the bottom, nudging it up, and flicking it down again.
+///
+/// Normal travel to an end arrives there once. Only a second arrival soon
+/// after counts, so resting at the end or slowly returning to it does not.
+#[derive(Debug, Default)]
+pub struct SlamDetector {
+    /// Last value fed in, so only arrivals at an endpoint count.
+    last_value: Option<u8>,
+    /// Endpoint and time of the last arrival that may start a slam.
+    last_hit: Option<(u8, Instant)>,
+}
+
+impl SlamDetector {
+    /// Feed an emitted pipeline value. Returns true when it completes a slam.
+    pub fn process_at(&mut self, value: u8, now: Instant, window: Duration) -> bool {
+        let previous = self.last_value.replace(value);
+        if (value != 0 && value != 255) || previous == Some(value) {
+            return false;
+        }
+        match self.last_hit {
+            Some((hit, at)) if hit == value && now.duration_since(at) <= window => {
+                // A third hit starts a new gesture rather than firing again.
+                self.last_hit = None;
+                true
+            }
+            _ => {
+                self.last_hit = Some((value, now));
+                false
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Duration;
 
     #[test]
     fn first_value_always_passes() {
@@ -282,4 +316,47 @@ mod tests {
         let result = p.process(0);
         assert_eq!(result, Some(0));
     }
+
+    const SLAM_WINDOW: Duration = Duration::from_millis(400);
+
+    #[test]
+    fn slam_fires_on_quick_second_hit_of_same_end() {
+        let mut d = SlamDetector::default();
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        assert!(!d.process_at(128, at(0), SLAM_WINDOW));
+        assert!(!d.process_at(0, at(50), SLAM_WINDOW));
+        assert!(!d.process_at(30, at(150), SLAM_WINDOW));
+        assert!(d.process_at(0, at(250), SLAM_WINDOW));
+    }
+
+    #[test]
+    fn slam_ignores_single_travel_and_resting_at_end() {
+        let mut d = SlamDetector::default();
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        assert!(!d.process_at(40, at(0), SLAM_WINDOW));
+        assert!(!d.process_at(0, at(100), SLAM_WINDOW));
+        // Still at the bottom: not a new hit.
+        assert!(!d.process_at(0, at(200), SLAM_WINDOW));
+        // Back to the bottom, but too late.
+        assert!(!d.process_at(20, at(300), SLAM_WINDOW));
+        assert!(!d.process_at(0, at(600), SLAM_WINDOW));
+    }
+
+    #[test]
+    fn slam_needs_the_same_end_twice() {
+        let mut d = SlamDetector::default();
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        assert!(!d.process_at(0, at(0), SLAM_WINDOW));
+        assert!(!d.process_at(255, at(100), SLAM_WINDOW));
+        assert!(!d.process_at(0, at(200), SLAM_WINDOW));
+        // The next bottom hit pairs with the one at 200ms and fires; a third
+        // hit right after starts over.
+        assert!(!d.process_at(10, at(250), SLAM_WINDOW));
+        assert!(d.process_at(0, at(300), SLAM_WINDOW));
+        assert!(!d.process_at(10, at(320), SLAM_WINDOW));
+        assert!(!d.process_at(0, at(340), SLAM_WINDOW));
+    }
 }
@@ -3,7 +3,7 @@ use std::time::Duration;
 use ksni::TrayMethods;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
-use tracing::warn;
+use tracing::{debug, warn};
 
 /// Actions from the system tray to the engine.
 #[derive(Debug, Clone)]
@@ -13,6 +13,8 @@ pub enum TrayAction {
 
 struct PcPanelTray {
     action_tx: mpsc::Sender<TrayAction>,
+    /// Quit from the top-level item instead of a "Confirm" submenu.
+    direct_quit: bool,
 }
 
 impl ksni::Tray for PcPanelTray {
@@ -29,30 +31,61 @@ impl ksni::Tray for PcPanelTray {
     }
 
     fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
-        vec![ksni::MenuItem::Standard(ksni::menu::StandardItem {
+        let quit = |label: &str| {
+            ksni::MenuItem::Standard(ksni::menu::StandardItem {
+                label: label.into(),
+                activate: Box::new(|tray: &mut Self| {
+                    // ksni callbacks run on a background thread — use blocking_send
+                    // since we can't .await in a sync context.
+                    let _ = tray.action_tx.blocking_send(TrayAction::Quit);
+                }),
+                ..Default::default()
+            })
+        };
+        if self.direct_quit {
+            return vec![quit("Quit")];
+        }
+        // A stray click on a crowded tray shouldn't stop volume control, so
+        // quitting takes a second click in a submenu.
+        vec![ksni::MenuItem::SubMenu(ksni::menu::SubMenu {
             label: "Quit".into(),
-            activate: Box::new(|tray: &mut Self| {
-                // ksni callbacks run on a background thread — use blocking_send
-                // since we can't .await in a sync context.
-                let _ = tray.action_tx.blocking_send(TrayAction::Quit);
-            }),
+            submenu: vec![quit("Confirm")],
             ..Default::default()
         })]
     }
+
+    fn watcher_online(&self) {
+        debug!("StatusNotifierWatcher appeared, registering the tray icon");
+    }
+
+    fn watcher_offline(&self, reason: ksni::OfflineReason) -> bool {
+        // Keep waiting: the watcher may still start, e.g. when the
+        // AppIndicator extension is enabled or the shell restarts.
+        debug!("no StatusNotifierWatcher, tray icon hidden until one starts: {reason:?}");
+        true
+    }
 }
 
 /// Run the system tray.
 ///
 /// Uses ksni for SNI (StatusNotifierItem) registration on KDE/Wayland.
-pub async fn run(action_tx: mpsc::Sender<TrayAction>, cancel: CancellationToken) {
+/// Without a StatusNotifierWatcher on the bus (GNOME without the
+/// AppIndicator extension, bare window managers) the tray waits for one
+/// instead of failing, and registers when it appears.
+pub async fn run(
+    action_tx: mpsc::Sender<TrayAction>,
+    direct_quit: bool,
+    cancel: CancellationToken,
+) {
     // spawn() consumes self, so reconstruct PcPanelTray on each retry.
     // Sender::clone() is cheap (Arc increment).
     let handle = 'retry: {
         for attempt in 1..=5u64 {
             let tray = PcPanelTray {
                 action_tx: action_tx.clone(),
+                direct_quit,
             };
-            match tray.spawn().await {
+            match tray.assume_sni_available(true).spawn().await {
                 Ok(handle) => break 'retry handle,
                 Err(e) => {
                     if attempt < 5 {
@@ -97,6 +97,7 @@ Wants=pipewire-pulse.service
 
 [Service]
 ExecStart=%h/.local/bin/pcpaneld daemon
+ExecReload=/bin/kill -HUP $MAINPID
 Restart=on-failure
 RestartSec=2
 
@@ -108,13 +109,18 @@ systemctl --user daemon-reload
 
 # --- Install udev rule ---
 
-UDEV_RULE='KERNEL=="hidraw*", ATTRS{idVendor}=="0483", ATTRS{idProduct}=="a3c5", MODE="0660", TAG+="uaccess"'
+UDEV_RULE='KERNEL=="hidraw*", ATTRS{idVendor}=="0483", ATTRS{idProduct}=="a3c4|a3c5", MODE="0660", TAG+="uaccess"'
 
-if [ -f "$UDEV_RULE_PATH" ]; then
+# An older install's rule may lack a product ID, so only an identical one counts.
+if [ -f "$UDEV_RULE_PATH" ] && [ "$(cat "$UDEV_RULE_PATH")" = "$UDEV_RULE" ]; then
     ok "udev rule already installed at $UDEV_RULE_PATH"
 else
     printf "\n"
-    printf "%bThe PCPanel needs a udev rule for device access.%b\n" "$BOLD" "$RESET"
+    if [ -f "$UDEV_RULE_PATH" ]; then
+        printf "%b%s differs from the rule this version needs and will be replaced.%b\n" "$BOLD" "$UDEV_RULE_PATH" "$RESET"
+    else
+        printf "%bThe PCPanel needs a udev rule for device access.%b\n" "$BOLD" "$RESET"
+    fi
     printf "This will run: sudo install -Dm644 ... %s\n" "$UDEV_RULE_PATH"
     printf "\n"
     read -rp "Install udev rule now? [y/N] " answer
@@ -41,6 +41,12 @@ echo "[udevadm] $*"
 STUB
 chmod +x /usr/local/bin/udevadm
 
+# --- Rule from an older install, without the Mini's product ID ---
+
+mkdir -p /etc/udev/rules.d
+echo 'KERNEL=="hidraw*", ATTRS{idVendor}=="0483", ATTRS{idProduct}=="a3c5", MODE="0660", TAG+="uaccess"' \
+    > /etc/udev/rules.d/70-pcpanel.rules
+
 # --- Run installer (answer "y" to udev prompt) ---
 
 if [ -n "$VERSION" ]; then
@@ -80,6 +86,7 @@ echo ""
 echo "--- Service file ---"
 check "service file exists" "[ -f ~/.config/systemd/user/pcpaneld.service ]"
 check "ExecStart points to ~/.local/bin" "grep -q 'ExecStart=%h/.local/bin/pcpaneld daemon' ~/.config/systemd/user/pcpaneld.service"
+check "reloads with SIGHUP" "grep -q 'ExecReload=/bin/kill -HUP' ~/.config/systemd/user/pcpaneld.service"
 check "has Restart=on-failure" "grep -q 'Restart=on-failure' ~/.config/systemd/user/pcpaneld.service"
 check "has WantedBy=default.target" "grep -q 'WantedBy=default.target' ~/.config/systemd/user/pcpaneld.service"
 check "orders after pipewire-pulse" "grep -q 'After=pipewire-pulse.service' ~/.config/systemd/user/pcpaneld.service"
@@ -89,7 +96,8 @@ echo ""
 echo "--- udev rule ---"
 check "udev rule exists" "[ -f /etc/udev/rules.d/70-pcpanel.rules ]"
 check "rule has correct vendor ID" "grep -q '0483' /etc/udev/rules.d/70-pcpanel.rules"
-check "rule has correct product ID" "grep -q 'a3c5' /etc/udev/rules.d/70-pcpanel.rules"
+check "rule has Pro product ID" "grep -q 'a3c5' /etc/udev/rules.d/70-pcpanel.rules"
+check "rule has Mini product ID" "grep -q 'a3c4' /etc/udev/rules.d/70-pcpanel.rules"
 check "rule has uaccess tag" "grep -q 'uaccess' /etc/udev/rules.d/70-pcpanel.rules"
 
 echo ""